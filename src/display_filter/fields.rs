@@ -0,0 +1,2556 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use pnet::util::MacAddr;
+
+use crate::parser::wrapers::{
+    DnsService, ParsedPacket, SerializableArpPacket, SerializableDhcpOptionData,
+    SerializableDhcpPacket, SerializableDhcpv6OptionData, SerializableDhcpv6Packet,
+    SerializableDnsData, SerializableDnsPacket, SerializableGenevePacket, SerializableGrePacket,
+    SerializableHttpHeader, SerializableHttpRequestPacket, SerializableHttpResponsePacket,
+    SerializableIcmpBody, SerializableIcmpQuote, SerializableIcmpv6Body, SerializableIpv4Packet,
+    SerializableIpv6ExtensionData, SerializableIpv6ExtensionHeader, SerializableIpv6Packet,
+    SerializableMldRecord, SerializableMplsPacket, SerializableNdpOption,
+    SerializableNdpOptionData, SerializablePacket, SerializableTcpPacket,
+    SerializableTlsCertificate, SerializableTlsClientHello, SerializableTlsContent,
+    SerializableTlsHandshake, SerializableTlsPacket, SerializableTlsServerHello,
+    SerializableUdpPacket, SerializableVlanPacket, SerializableVxlanPacket,
+};
+
+/// Type of the values a field produces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    /// Present when the protocol was decoded, only usable on its own
+    Protocol,
+    Bool,
+    Integer,
+    Ipv4,
+    Ipv6,
+    Mac,
+    Text,
+}
+
+/// A value produced by a field, or a literal it is compared with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Integer(u64),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Mac(MacAddr),
+    Text(String),
+}
+
+/// A named field of a decoded packet
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub field_type: FieldType,
+    pub description: &'static str,
+    extract: fn(&ParsedPacket, &mut Vec<Value>),
+}
+
+impl Field {
+    /// Get every value of this field in `packet`, empty if the field is absent
+    pub fn values(&self, packet: &ParsedPacket) -> Vec<Value> {
+        let mut values = Vec::new();
+        (self.extract)(packet, &mut values);
+        values
+    }
+}
+
+/// Look a field up by its filter name
+pub fn field(name: &str) -> Option<&'static Field> {
+    FIELDS.iter().find(|field| field.name == name)
+}
+
+/// Get every field known to the display filter
+pub fn fields() -> &'static [Field] {
+    FIELDS
+}
+
+/// Extract the number from strings formatted like `"Tcp (6)"`
+fn trailing_number(text: &str) -> Option<u64> {
+    let start = text.rfind('(')?;
+    text[start + 1..].trim_end_matches(')').parse().ok()
+}
+
+fn ethernet_addresses(packet: &ParsedPacket) -> Option<(MacAddr, MacAddr)> {
+    match packet.get_link_layer_packet() {
+        Some(SerializablePacket::EthernetPacket(ethernet)) => {
+            Some((ethernet.source, ethernet.destination))
+        }
+        _ => None,
+    }
+}
+
+fn ethertype(packet: &ParsedPacket) -> Option<&str> {
+    match packet.get_link_layer_packet() {
+        Some(SerializablePacket::EthernetPacket(ethernet)) => Some(&ethernet.ethertype),
+        _ => None,
+    }
+}
+
+/// Get the VLAN tags of a frame, outermost first
+fn vlans(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableVlanPacket> {
+    packet.find_all()
+}
+
+/// Get the MPLS label stack of a frame, top label first
+fn mpls_labels(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableMplsPacket> {
+    packet.find_all()
+}
+
+fn gre(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableGrePacket> {
+    packet.find_all()
+}
+
+fn vxlan(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableVxlanPacket> {
+    packet.find_all()
+}
+
+fn geneve(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableGenevePacket> {
+    packet.find_all()
+}
+
+fn ipv4(packet: &ParsedPacket) -> Option<&SerializableIpv4Packet> {
+    match packet.get_network_layer_packet() {
+        Some(SerializablePacket::Ipv4Packet(ipv4)) => Some(ipv4),
+        _ => None,
+    }
+}
+
+fn ipv6(packet: &ParsedPacket) -> Option<&SerializableIpv6Packet> {
+    match packet.get_network_layer_packet() {
+        Some(SerializablePacket::Ipv6Packet(ipv6)) => Some(ipv6),
+        _ => None,
+    }
+}
+
+/// Get the IPv6 extension headers of type `header_type`
+fn ipv6_extensions(
+    packet: &ParsedPacket,
+    header_type: u8,
+) -> impl Iterator<Item = &SerializableIpv6ExtensionHeader> {
+    ipv6(packet)
+        .into_iter()
+        .flat_map(|ip| ip.extension_headers.iter())
+        .filter(move |header| header.header_type == header_type)
+}
+
+/// Get offset, more fragments flag and identification of IPv6 fragment headers
+fn ipv6_fragments(packet: &ParsedPacket) -> impl Iterator<Item = (u16, bool, u32)> + '_ {
+    ipv6_extensions(packet, 44).filter_map(|header| match header.data {
+        SerializableIpv6ExtensionData::Fragment {
+            offset,
+            more_fragments,
+            identification,
+        } => Some((offset, more_fragments, identification)),
+        _ => None,
+    })
+}
+
+/// Get SPI of the IPv6 authentication (51) or ESP (50) headers
+fn ipsec_spis(packet: &ParsedPacket, header_type: u8) -> impl Iterator<Item = u32> + '_ {
+    ipv6_extensions(packet, header_type).filter_map(|header| match header.data {
+        SerializableIpv6ExtensionData::Authentication { spi, .. }
+        | SerializableIpv6ExtensionData::Esp { spi, .. } => Some(spi),
+        _ => None,
+    })
+}
+
+fn arp(packet: &ParsedPacket) -> Option<&SerializableArpPacket> {
+    match packet.get_network_layer_packet() {
+        Some(SerializablePacket::ArpPacket(arp)) => Some(arp),
+        _ => None,
+    }
+}
+
+fn tcp(packet: &ParsedPacket) -> Option<&SerializableTcpPacket> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::TcpPacket(tcp)) => Some(tcp),
+        _ => None,
+    }
+}
+
+fn udp(packet: &ParsedPacket) -> Option<&SerializableUdpPacket> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::UdpPacket(udp)) => Some(udp),
+        _ => None,
+    }
+}
+
+/// ICMP header values, identifier and sequence number are only present on echo, timestamp and
+/// address mask messages
+struct Icmp {
+    icmp_type: u64,
+    code: u8,
+    identifier: Option<(u16, u16)>,
+}
+
+fn icmp(packet: &ParsedPacket) -> Option<Icmp> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::IcmpPacket(icmp)) => Some(Icmp {
+            icmp_type: trailing_number(&icmp.icmp_type)?,
+            code: icmp.icmp_code,
+            identifier: match icmp.body {
+                SerializableIcmpBody::Timestamp {
+                    identifier,
+                    sequence_number,
+                    ..
+                }
+                | SerializableIcmpBody::AddressMask {
+                    identifier,
+                    sequence_number,
+                    ..
+                } => Some((identifier, sequence_number)),
+                _ => None,
+            },
+        }),
+        Some(SerializablePacket::EchoRequestPacket(echo)) => Some(Icmp {
+            icmp_type: echo.icmp_type as u64,
+            code: echo.icmp_code,
+            identifier: Some((echo.identifier, echo.sequence_number)),
+        }),
+        Some(SerializablePacket::EchoReplyPacket(echo)) => Some(Icmp {
+            icmp_type: echo.icmp_type as u64,
+            code: echo.icmp_code,
+            identifier: Some((echo.identifier, echo.sequence_number)),
+        }),
+        _ => None,
+    }
+}
+
+/// Request an echo reply answers, as filled in by `ping::PingTracker`
+struct EchoReply {
+    response_to: Option<usize>,
+    time: Option<Duration>,
+    duplicate: bool,
+}
+
+fn icmp_echo_reply(packet: &ParsedPacket) -> Option<EchoReply> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::EchoReplyPacket(echo)) => Some(EchoReply {
+            response_to: echo.response_to,
+            time: echo.time,
+            duplicate: echo.duplicate,
+        }),
+        _ => None,
+    }
+}
+
+fn icmpv6_echo_reply(packet: &ParsedPacket) -> Option<EchoReply> {
+    match icmpv6_body(packet)? {
+        SerializableIcmpv6Body::EchoReply {
+            response_to,
+            time,
+            duplicate,
+            ..
+        } => Some(EchoReply {
+            response_to: *response_to,
+            time: *time,
+            duplicate: *duplicate,
+        }),
+        _ => None,
+    }
+}
+
+/// Get the ICMP message other than an echo
+fn icmp_body(packet: &ParsedPacket) -> Option<&SerializableIcmpBody> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::IcmpPacket(icmp)) => Some(&icmp.body),
+        _ => None,
+    }
+}
+
+/// Get the datagram quoted by an ICMP error
+fn icmp_original(packet: &ParsedPacket) -> Option<&SerializableIcmpQuote> {
+    icmp_body(packet)?.original()
+}
+
+fn is_icmpv6(packet: &ParsedPacket) -> bool {
+    matches!(
+        packet.get_transport_layer_packet(),
+        Some(SerializablePacket::Icmpv6Packet(_))
+    )
+}
+
+fn icmpv6_body(packet: &ParsedPacket) -> Option<&SerializableIcmpv6Body> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::Icmpv6Packet(icmpv6)) => Some(&icmpv6.body),
+        _ => None,
+    }
+}
+
+/// Get the Neighbor Discovery options of an ICMPv6 message
+fn ndp_options(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableNdpOption> {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::Icmpv6Packet(icmpv6)) => icmpv6.options().iter(),
+        _ => [].iter(),
+    }
+}
+
+/// Get the flags of a router advertisement
+fn router_advertisement_flags(packet: &ParsedPacket) -> Option<u8> {
+    match icmpv6_body(packet)? {
+        SerializableIcmpv6Body::RouterAdvertisement { flags, .. } => Some(*flags),
+        _ => None,
+    }
+}
+
+/// Get the flags of a neighbor advertisement
+fn neighbor_advertisement_flags(packet: &ParsedPacket) -> Option<u8> {
+    match icmpv6_body(packet)? {
+        SerializableIcmpv6Body::NeighborAdvertisement { flags, .. } => Some(*flags),
+        _ => None,
+    }
+}
+
+fn mld_records(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableMldRecord> {
+    match icmpv6_body(packet) {
+        Some(SerializableIcmpv6Body::MulticastListenerReportV2 { records }) => records.iter(),
+        _ => [].iter(),
+    }
+}
+
+fn dns(packet: &ParsedPacket) -> Option<&SerializableDnsPacket> {
+    packet.find()
+}
+
+fn is_dns_service(packet: &ParsedPacket, service: DnsService) -> bool {
+    dns(packet).is_some_and(|dns| dns.service == service)
+}
+
+/// Get the data of every resource record, answers first
+fn dns_data(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableDnsData> {
+    dns(packet)
+        .into_iter()
+        .flat_map(|dns| dns.records())
+        .map(|record| &record.data)
+}
+
+fn http_requests(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableHttpRequestPacket> {
+    packet.find_all()
+}
+
+fn http_responses(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableHttpResponsePacket> {
+    packet.find_all()
+}
+
+/// Get the values of header `name` in every HTTP message of the packet
+fn http_headers<'a>(packet: &'a ParsedPacket, name: &'a str) -> impl Iterator<Item = &'a str> {
+    let requests = http_requests(packet).map(|request| &request.headers);
+    let responses = http_responses(packet).map(|response| &response.headers);
+    requests
+        .chain(responses)
+        .flatten()
+        .filter(move |header: &&SerializableHttpHeader| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value.as_str())
+}
+
+fn dhcp(packet: &ParsedPacket) -> Option<&SerializableDhcpPacket> {
+    packet.find()
+}
+
+/// Get every address of DHCP option `code`
+fn dhcp_addresses(packet: &ParsedPacket, code: u8) -> impl Iterator<Item = Value> + '_ {
+    dhcp(packet)
+        .and_then(|dhcp| match dhcp.option(code)? {
+            SerializableDhcpOptionData::Addresses(addresses) => Some(addresses),
+            _ => None,
+        })
+        .into_iter()
+        .flatten()
+        .map(|&address| Value::Ipv4(address))
+}
+
+/// Get a DHCPv6 message and the messages relayed in it
+fn dhcpv6_messages(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableDhcpv6Packet> {
+    std::iter::successors(
+        packet.find::<SerializableDhcpv6Packet>(),
+        |message| match message.option(9)? {
+            SerializableDhcpv6OptionData::RelayMessage(relayed) => Some(relayed),
+            _ => None,
+        },
+    )
+}
+
+/// Get the leases of every DHCPv6 message with a prefix length of 128, or of less for
+/// delegated prefixes
+fn dhcpv6_leases(packet: &ParsedPacket, addresses: bool) -> impl Iterator<Item = Value> + '_ {
+    dhcpv6_messages(packet)
+        .flat_map(|message| message.leases())
+        .filter(move |(_, prefix_length, _)| (*prefix_length == 128) == addresses)
+        .map(|(address, _, _)| Value::Ipv6(address))
+}
+
+fn tls(packet: &ParsedPacket) -> Option<&SerializableTlsPacket> {
+    packet.find()
+}
+
+fn tls_handshakes(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableTlsHandshake> {
+    tls(packet).into_iter().flat_map(|tls| tls.handshakes())
+}
+
+fn client_hellos(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableTlsClientHello> {
+    tls_handshakes(packet).filter_map(|message| match message {
+        SerializableTlsHandshake::ClientHello(hello) => Some(hello),
+        _ => None,
+    })
+}
+
+fn server_hellos(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableTlsServerHello> {
+    tls_handshakes(packet).filter_map(|message| match message {
+        SerializableTlsHandshake::ServerHello(hello) => Some(hello),
+        _ => None,
+    })
+}
+
+fn certificates(packet: &ParsedPacket) -> impl Iterator<Item = &SerializableTlsCertificate> {
+    tls_handshakes(packet)
+        .filter_map(|message| match message {
+            SerializableTlsHandshake::Certificate(certificates) => Some(certificates),
+            _ => None,
+        })
+        .flatten()
+}
+
+fn tcp_flag(packet: &ParsedPacket, values: &mut Vec<Value>, mask: u16) {
+    if let Some(tcp) = tcp(packet) {
+        values.push(Value::Bool(tcp.flags & mask != 0));
+    }
+}
+
+static FIELDS: &[Field] = &[
+    Field {
+        name: "frame.number",
+        field_type: FieldType::Integer,
+        description: "Frame number",
+        extract: |p, v| v.push(Value::Integer(p.get_id() as u64)),
+    },
+    Field {
+        name: "frame.len",
+        field_type: FieldType::Integer,
+        description: "Frame length on the wire",
+        extract: |p, v| v.push(Value::Integer(p.get_length() as u64)),
+    },
+    Field {
+        name: "frame.interface_name",
+        field_type: FieldType::Text,
+        description: "Capture interface name",
+        extract: |p, v| v.extend(p.get_interface_name().map(|n| Value::Text(n.to_string()))),
+    },
+    Field {
+        name: "frame.comment",
+        field_type: FieldType::Text,
+        description: "Packet comment",
+        extract: |p, v| v.extend(p.get_comments().iter().cloned().map(Value::Text)),
+    },
+    Field {
+        name: "eth",
+        field_type: FieldType::Protocol,
+        description: "Ethernet",
+        extract: |p, v| v.extend(ethernet_addresses(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "eth.src",
+        field_type: FieldType::Mac,
+        description: "Source MAC address",
+        extract: |p, v| v.extend(ethernet_addresses(p).map(|(src, _)| Value::Mac(src))),
+    },
+    Field {
+        name: "eth.dst",
+        field_type: FieldType::Mac,
+        description: "Destination MAC address",
+        extract: |p, v| v.extend(ethernet_addresses(p).map(|(_, dst)| Value::Mac(dst))),
+    },
+    Field {
+        name: "eth.addr",
+        field_type: FieldType::Mac,
+        description: "Source or destination MAC address",
+        extract: |p, v| {
+            if let Some((src, dst)) = ethernet_addresses(p) {
+                v.extend([Value::Mac(src), Value::Mac(dst)]);
+            }
+        },
+    },
+    Field {
+        name: "eth.type",
+        field_type: FieldType::Text,
+        description: "EtherType name",
+        extract: |p, v| v.extend(ethertype(p).map(|t| Value::Text(t.to_string()))),
+    },
+    Field {
+        name: "vlan",
+        field_type: FieldType::Protocol,
+        description: "802.1Q Virtual LAN",
+        extract: |p, v| v.extend(vlans(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "vlan.id",
+        field_type: FieldType::Integer,
+        description: "VLAN identifier",
+        extract: |p, v| v.extend(vlans(p).map(|t| Value::Integer(t.vlan_id as u64))),
+    },
+    Field {
+        name: "vlan.priority",
+        field_type: FieldType::Integer,
+        description: "VLAN priority code point",
+        extract: |p, v| v.extend(vlans(p).map(|t| Value::Integer(t.priority as u64))),
+    },
+    Field {
+        name: "vlan.dei",
+        field_type: FieldType::Bool,
+        description: "VLAN drop eligible indicator",
+        extract: |p, v| v.extend(vlans(p).map(|t| Value::Bool(t.drop_eligible))),
+    },
+    Field {
+        name: "vlan.etype",
+        field_type: FieldType::Text,
+        description: "EtherType inside the VLAN tag",
+        extract: |p, v| v.extend(vlans(p).map(|t| Value::Text(t.ethertype.clone()))),
+    },
+    Field {
+        name: "mpls",
+        field_type: FieldType::Protocol,
+        description: "MultiProtocol Label Switching",
+        extract: |p, v| v.extend(mpls_labels(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "mpls.label",
+        field_type: FieldType::Integer,
+        description: "MPLS label",
+        extract: |p, v| v.extend(mpls_labels(p).map(|m| Value::Integer(m.label as u64))),
+    },
+    Field {
+        name: "mpls.exp",
+        field_type: FieldType::Integer,
+        description: "MPLS traffic class",
+        extract: |p, v| v.extend(mpls_labels(p).map(|m| Value::Integer(m.traffic_class as u64))),
+    },
+    Field {
+        name: "mpls.bottom",
+        field_type: FieldType::Bool,
+        description: "MPLS bottom of label stack",
+        extract: |p, v| v.extend(mpls_labels(p).map(|m| Value::Bool(m.bottom_of_stack))),
+    },
+    Field {
+        name: "mpls.ttl",
+        field_type: FieldType::Integer,
+        description: "MPLS time to live",
+        extract: |p, v| v.extend(mpls_labels(p).map(|m| Value::Integer(m.ttl as u64))),
+    },
+    Field {
+        name: "arp",
+        field_type: FieldType::Protocol,
+        description: "Address Resolution Protocol",
+        extract: |p, v| v.extend(arp(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "arp.opcode",
+        field_type: FieldType::Integer,
+        description: "ARP operation",
+        extract: |p, v| {
+            v.extend(
+                arp(p)
+                    .and_then(|a| trailing_number(&a.operation))
+                    .map(Value::Integer),
+            )
+        },
+    },
+    Field {
+        name: "arp.src.hw_mac",
+        field_type: FieldType::Mac,
+        description: "ARP sender MAC address",
+        extract: |p, v| v.extend(arp(p).map(|a| Value::Mac(a.sender_hw_addr))),
+    },
+    Field {
+        name: "arp.src.proto_ipv4",
+        field_type: FieldType::Ipv4,
+        description: "ARP sender IPv4 address",
+        extract: |p, v| v.extend(arp(p).map(|a| Value::Ipv4(a.sender_proto_addr))),
+    },
+    Field {
+        name: "arp.dst.hw_mac",
+        field_type: FieldType::Mac,
+        description: "ARP target MAC address",
+        extract: |p, v| v.extend(arp(p).map(|a| Value::Mac(a.target_hw_addr))),
+    },
+    Field {
+        name: "arp.dst.proto_ipv4",
+        field_type: FieldType::Ipv4,
+        description: "ARP target IPv4 address",
+        extract: |p, v| v.extend(arp(p).map(|a| Value::Ipv4(a.target_proto_addr))),
+    },
+    Field {
+        name: "ip",
+        field_type: FieldType::Protocol,
+        description: "Internet Protocol version 4",
+        extract: |p, v| v.extend(ipv4(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ip.version",
+        field_type: FieldType::Integer,
+        description: "IPv4 version",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.version as u64))),
+    },
+    Field {
+        name: "ip.hdr_len",
+        field_type: FieldType::Integer,
+        description: "IPv4 header length in 32-bit words",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.header_length as u64))),
+    },
+    Field {
+        name: "ip.dsfield.dscp",
+        field_type: FieldType::Integer,
+        description: "Differentiated services codepoint",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.dscp as u64))),
+    },
+    Field {
+        name: "ip.dsfield.ecn",
+        field_type: FieldType::Integer,
+        description: "Explicit congestion notification",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.ecn as u64))),
+    },
+    Field {
+        name: "ip.len",
+        field_type: FieldType::Integer,
+        description: "IPv4 total length",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.total_length as u64))),
+    },
+    Field {
+        name: "ip.id",
+        field_type: FieldType::Integer,
+        description: "IPv4 identification",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.identification as u64))),
+    },
+    Field {
+        name: "ip.flags",
+        field_type: FieldType::Integer,
+        description: "IPv4 flags",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.flags as u64))),
+    },
+    Field {
+        name: "ip.flags.df",
+        field_type: FieldType::Bool,
+        description: "Don't fragment",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Bool(ip.flags & 0b010 != 0))),
+    },
+    Field {
+        name: "ip.flags.mf",
+        field_type: FieldType::Bool,
+        description: "More fragments",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Bool(ip.flags & 0b001 != 0))),
+    },
+    Field {
+        name: "ip.frag_offset",
+        field_type: FieldType::Integer,
+        description: "IPv4 fragment offset",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.fragment_offset as u64))),
+    },
+    Field {
+        name: "ip.fragment",
+        field_type: FieldType::Integer,
+        description: "Frame holding a fragment of the reassembled IPv4 datagram",
+        extract: |p, v| {
+            if ipv4(p).is_some() {
+                v.extend(
+                    p.get_fragments()
+                        .iter()
+                        .map(|&frame| Value::Integer(frame as u64)),
+                )
+            }
+        },
+    },
+    Field {
+        name: "ip.ttl",
+        field_type: FieldType::Integer,
+        description: "Time to live",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.ttl as u64))),
+    },
+    Field {
+        name: "ip.proto",
+        field_type: FieldType::Integer,
+        description: "IPv4 payload protocol",
+        extract: |p, v| {
+            v.extend(
+                ipv4(p)
+                    .and_then(|ip| trailing_number(&ip.next_level_protocol))
+                    .map(Value::Integer),
+            )
+        },
+    },
+    Field {
+        name: "ip.checksum",
+        field_type: FieldType::Integer,
+        description: "IPv4 header checksum",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Integer(ip.checksum as u64))),
+    },
+    Field {
+        name: "ip.src",
+        field_type: FieldType::Ipv4,
+        description: "IPv4 source address",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Ipv4(ip.source))),
+    },
+    Field {
+        name: "ip.dst",
+        field_type: FieldType::Ipv4,
+        description: "IPv4 destination address",
+        extract: |p, v| v.extend(ipv4(p).map(|ip| Value::Ipv4(ip.destination))),
+    },
+    Field {
+        name: "ip.addr",
+        field_type: FieldType::Ipv4,
+        description: "IPv4 source or destination address",
+        extract: |p, v| {
+            if let Some(ip) = ipv4(p) {
+                v.extend([Value::Ipv4(ip.source), Value::Ipv4(ip.destination)]);
+            }
+        },
+    },
+    Field {
+        name: "ipv6",
+        field_type: FieldType::Protocol,
+        description: "Internet Protocol version 6",
+        extract: |p, v| v.extend(ipv6(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ipv6.tclass",
+        field_type: FieldType::Integer,
+        description: "IPv6 traffic class",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Integer(ip.traffic_class as u64))),
+    },
+    Field {
+        name: "ipv6.flow",
+        field_type: FieldType::Integer,
+        description: "IPv6 flow label",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Integer(ip.flow_label as u64))),
+    },
+    Field {
+        name: "ipv6.plen",
+        field_type: FieldType::Integer,
+        description: "IPv6 payload length",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Integer(ip.payload_length as u64))),
+    },
+    Field {
+        name: "ipv6.nxt",
+        field_type: FieldType::Integer,
+        description: "IPv6 next header",
+        extract: |p, v| {
+            v.extend(
+                ipv6(p)
+                    .and_then(|ip| trailing_number(&ip.next_header))
+                    .map(Value::Integer),
+            )
+        },
+    },
+    Field {
+        name: "ipv6.hlim",
+        field_type: FieldType::Integer,
+        description: "IPv6 hop limit",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Integer(ip.hop_limit as u64))),
+    },
+    Field {
+        name: "ipv6.src",
+        field_type: FieldType::Ipv6,
+        description: "IPv6 source address",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Ipv6(ip.source))),
+    },
+    Field {
+        name: "ipv6.dst",
+        field_type: FieldType::Ipv6,
+        description: "IPv6 destination address",
+        extract: |p, v| v.extend(ipv6(p).map(|ip| Value::Ipv6(ip.destination))),
+    },
+    Field {
+        name: "ipv6.addr",
+        field_type: FieldType::Ipv6,
+        description: "IPv6 source or destination address",
+        extract: |p, v| {
+            if let Some(ip) = ipv6(p) {
+                v.extend([Value::Ipv6(ip.source), Value::Ipv6(ip.destination)]);
+            }
+        },
+    },
+    Field {
+        name: "ipv6.hopopts",
+        field_type: FieldType::Protocol,
+        description: "IPv6 Hop-by-Hop Options header",
+        extract: |p, v| v.extend(ipv6_extensions(p, 0).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ipv6.routing",
+        field_type: FieldType::Protocol,
+        description: "IPv6 Routing header",
+        extract: |p, v| v.extend(ipv6_extensions(p, 43).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ipv6.dstopts",
+        field_type: FieldType::Protocol,
+        description: "IPv6 Destination Options header",
+        extract: |p, v| v.extend(ipv6_extensions(p, 60).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ipv6.fraghdr",
+        field_type: FieldType::Protocol,
+        description: "IPv6 Fragment header",
+        extract: |p, v| v.extend(ipv6_extensions(p, 44).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ipv6.fraghdr.offset",
+        field_type: FieldType::Integer,
+        description: "IPv6 fragment offset in bytes",
+        extract: |p, v| {
+            v.extend(ipv6_fragments(p).map(|(offset, _, _)| Value::Integer(offset as u64)))
+        },
+    },
+    Field {
+        name: "ipv6.fraghdr.more",
+        field_type: FieldType::Bool,
+        description: "More IPv6 fragments follow",
+        extract: |p, v| v.extend(ipv6_fragments(p).map(|(_, more, _)| Value::Bool(more))),
+    },
+    Field {
+        name: "ipv6.fraghdr.ident",
+        field_type: FieldType::Integer,
+        description: "IPv6 fragment identification",
+        extract: |p, v| v.extend(ipv6_fragments(p).map(|(_, _, id)| Value::Integer(id as u64))),
+    },
+    Field {
+        name: "ipv6.fragment",
+        field_type: FieldType::Integer,
+        description: "Frame holding a fragment of the reassembled IPv6 datagram",
+        extract: |p, v| {
+            if ipv6(p).is_some() {
+                v.extend(
+                    p.get_fragments()
+                        .iter()
+                        .map(|&frame| Value::Integer(frame as u64)),
+                )
+            }
+        },
+    },
+    Field {
+        name: "ah",
+        field_type: FieldType::Protocol,
+        description: "IPsec Authentication Header",
+        extract: |p, v| v.extend(ipv6_extensions(p, 51).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "ah.spi",
+        field_type: FieldType::Integer,
+        description: "AH security parameters index",
+        extract: |p, v| v.extend(ipsec_spis(p, 51).map(|spi| Value::Integer(spi as u64))),
+    },
+    Field {
+        name: "esp",
+        field_type: FieldType::Protocol,
+        description: "IPsec Encapsulating Security Payload",
+        extract: |p, v| v.extend(ipv6_extensions(p, 50).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "esp.spi",
+        field_type: FieldType::Integer,
+        description: "ESP security parameters index",
+        extract: |p, v| v.extend(ipsec_spis(p, 50).map(|spi| Value::Integer(spi as u64))),
+    },
+    Field {
+        name: "tcp",
+        field_type: FieldType::Protocol,
+        description: "Transmission Control Protocol",
+        extract: |p, v| v.extend(tcp(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "tcp.srcport",
+        field_type: FieldType::Integer,
+        description: "TCP source port",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.source as u64))),
+    },
+    Field {
+        name: "tcp.dstport",
+        field_type: FieldType::Integer,
+        description: "TCP destination port",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.destination as u64))),
+    },
+    Field {
+        name: "tcp.port",
+        field_type: FieldType::Integer,
+        description: "TCP source or destination port",
+        extract: |p, v| {
+            if let Some(t) = tcp(p) {
+                v.extend([
+                    Value::Integer(t.source as u64),
+                    Value::Integer(t.destination as u64),
+                ]);
+            }
+        },
+    },
+    Field {
+        name: "tcp.seq",
+        field_type: FieldType::Integer,
+        description: "TCP sequence number",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.sequence as u64))),
+    },
+    Field {
+        name: "tcp.ack",
+        field_type: FieldType::Integer,
+        description: "TCP acknowledgement number",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.acknowledgement as u64))),
+    },
+    Field {
+        name: "tcp.hdr_len",
+        field_type: FieldType::Integer,
+        description: "TCP header length in bytes",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.data_offset as u64 * 4))),
+    },
+    Field {
+        name: "tcp.flags",
+        field_type: FieldType::Integer,
+        description: "TCP flags",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.flags as u64))),
+    },
+    Field {
+        name: "tcp.flags.fin",
+        field_type: FieldType::Bool,
+        description: "TCP FIN flag",
+        extract: |p, v| tcp_flag(p, v, 0x01),
+    },
+    Field {
+        name: "tcp.flags.syn",
+        field_type: FieldType::Bool,
+        description: "TCP SYN flag",
+        extract: |p, v| tcp_flag(p, v, 0x02),
+    },
+    Field {
+        name: "tcp.flags.reset",
+        field_type: FieldType::Bool,
+        description: "TCP RST flag",
+        extract: |p, v| tcp_flag(p, v, 0x04),
+    },
+    Field {
+        name: "tcp.flags.push",
+        field_type: FieldType::Bool,
+        description: "TCP PSH flag",
+        extract: |p, v| tcp_flag(p, v, 0x08),
+    },
+    Field {
+        name: "tcp.flags.ack",
+        field_type: FieldType::Bool,
+        description: "TCP ACK flag",
+        extract: |p, v| tcp_flag(p, v, 0x10),
+    },
+    Field {
+        name: "tcp.flags.urg",
+        field_type: FieldType::Bool,
+        description: "TCP URG flag",
+        extract: |p, v| tcp_flag(p, v, 0x20),
+    },
+    Field {
+        name: "tcp.flags.ece",
+        field_type: FieldType::Bool,
+        description: "TCP ECE flag",
+        extract: |p, v| tcp_flag(p, v, 0x40),
+    },
+    Field {
+        name: "tcp.flags.cwr",
+        field_type: FieldType::Bool,
+        description: "TCP CWR flag",
+        extract: |p, v| tcp_flag(p, v, 0x80),
+    },
+    Field {
+        name: "tcp.window_size",
+        field_type: FieldType::Integer,
+        description: "TCP window",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.window as u64))),
+    },
+    Field {
+        name: "tcp.checksum",
+        field_type: FieldType::Integer,
+        description: "TCP checksum",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.checksum as u64))),
+    },
+    Field {
+        name: "tcp.urgent_pointer",
+        field_type: FieldType::Integer,
+        description: "TCP urgent pointer",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.urgent_ptr as u64))),
+    },
+    Field {
+        name: "tcp.len",
+        field_type: FieldType::Integer,
+        description: "TCP payload length",
+        extract: |p, v| v.extend(tcp(p).map(|t| Value::Integer(t.length as u64))),
+    },
+    Field {
+        name: "udp",
+        field_type: FieldType::Protocol,
+        description: "User Datagram Protocol",
+        extract: |p, v| v.extend(udp(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "udp.srcport",
+        field_type: FieldType::Integer,
+        description: "UDP source port",
+        extract: |p, v| v.extend(udp(p).map(|u| Value::Integer(u.source as u64))),
+    },
+    Field {
+        name: "udp.dstport",
+        field_type: FieldType::Integer,
+        description: "UDP destination port",
+        extract: |p, v| v.extend(udp(p).map(|u| Value::Integer(u.destination as u64))),
+    },
+    Field {
+        name: "udp.port",
+        field_type: FieldType::Integer,
+        description: "UDP source or destination port",
+        extract: |p, v| {
+            if let Some(u) = udp(p) {
+                v.extend([
+                    Value::Integer(u.source as u64),
+                    Value::Integer(u.destination as u64),
+                ]);
+            }
+        },
+    },
+    Field {
+        name: "udp.length",
+        field_type: FieldType::Integer,
+        description: "UDP length",
+        extract: |p, v| v.extend(udp(p).map(|u| Value::Integer(u.length as u64))),
+    },
+    Field {
+        name: "udp.checksum",
+        field_type: FieldType::Integer,
+        description: "UDP checksum",
+        extract: |p, v| v.extend(udp(p).map(|u| Value::Integer(u.checksum as u64))),
+    },
+    Field {
+        name: "gre",
+        field_type: FieldType::Protocol,
+        description: "Generic Routing Encapsulation",
+        extract: |p, v| v.extend(gre(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "gre.proto",
+        field_type: FieldType::Integer,
+        description: "EtherType carried in GRE",
+        extract: |p, v| v.extend(gre(p).map(|g| Value::Integer(g.protocol_type as u64))),
+    },
+    Field {
+        name: "gre.key",
+        field_type: FieldType::Integer,
+        description: "GRE key",
+        extract: |p, v| {
+            v.extend(
+                gre(p)
+                    .filter_map(|g| g.key)
+                    .map(|k| Value::Integer(k as u64)),
+            )
+        },
+    },
+    Field {
+        name: "gre.seq",
+        field_type: FieldType::Integer,
+        description: "GRE sequence number",
+        extract: |p, v| {
+            v.extend(
+                gre(p)
+                    .filter_map(|g| g.sequence)
+                    .map(|s| Value::Integer(s as u64)),
+            )
+        },
+    },
+    Field {
+        name: "vxlan",
+        field_type: FieldType::Protocol,
+        description: "Virtual eXtensible LAN",
+        extract: |p, v| v.extend(vxlan(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "vxlan.vni",
+        field_type: FieldType::Integer,
+        description: "VXLAN network identifier",
+        extract: |p, v| v.extend(vxlan(p).map(|x| Value::Integer(x.vni as u64))),
+    },
+    Field {
+        name: "geneve",
+        field_type: FieldType::Protocol,
+        description: "Generic Network Virtualization Encapsulation",
+        extract: |p, v| v.extend(geneve(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "geneve.vni",
+        field_type: FieldType::Integer,
+        description: "Geneve virtual network identifier",
+        extract: |p, v| v.extend(geneve(p).map(|g| Value::Integer(g.vni as u64))),
+    },
+    Field {
+        name: "geneve.proto",
+        field_type: FieldType::Integer,
+        description: "EtherType carried in Geneve",
+        extract: |p, v| v.extend(geneve(p).map(|g| Value::Integer(g.protocol_type as u64))),
+    },
+    Field {
+        name: "geneve.option.class",
+        field_type: FieldType::Integer,
+        description: "Geneve option class",
+        extract: |p, v| {
+            v.extend(
+                geneve(p)
+                    .flat_map(|g| &g.options)
+                    .map(|o| Value::Integer(o.class as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp",
+        field_type: FieldType::Protocol,
+        description: "Internet Control Message Protocol",
+        extract: |p, v| v.extend(icmp(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "icmp.type",
+        field_type: FieldType::Integer,
+        description: "ICMP type",
+        extract: |p, v| v.extend(icmp(p).map(|icmp| Value::Integer(icmp.icmp_type))),
+    },
+    Field {
+        name: "icmp.code",
+        field_type: FieldType::Integer,
+        description: "ICMP code",
+        extract: |p, v| v.extend(icmp(p).map(|icmp| Value::Integer(icmp.code as u64))),
+    },
+    Field {
+        name: "icmp.ident",
+        field_type: FieldType::Integer,
+        description: "ICMP identifier of echo, timestamp and address mask messages",
+        extract: |p, v| {
+            v.extend(
+                icmp(p)
+                    .and_then(|icmp| icmp.identifier)
+                    .map(|(id, _)| Value::Integer(id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.seq",
+        field_type: FieldType::Integer,
+        description: "ICMP sequence number of echo, timestamp and address mask messages",
+        extract: |p, v| {
+            v.extend(
+                icmp(p)
+                    .and_then(|icmp| icmp.identifier)
+                    .map(|(_, seq)| Value::Integer(seq as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.resp_to",
+        field_type: FieldType::Integer,
+        description: "Frame number of the echo request an echo reply answers",
+        extract: |p, v| {
+            v.extend(
+                icmp_echo_reply(p)
+                    .and_then(|echo| echo.response_to)
+                    .map(|id| Value::Integer(id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.resptime",
+        field_type: FieldType::Integer,
+        description: "Milliseconds between an echo request and its reply",
+        extract: |p, v| {
+            v.extend(
+                icmp_echo_reply(p)
+                    .and_then(|echo| echo.time)
+                    .map(|time| Value::Integer(time.as_millis() as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.duplicate",
+        field_type: FieldType::Bool,
+        description: "Echo reply to a request answered already",
+        extract: |p, v| v.extend(icmp_echo_reply(p).map(|echo| Value::Bool(echo.duplicate))),
+    },
+    Field {
+        name: "icmp.no_resp",
+        field_type: FieldType::Bool,
+        description: "Echo request got no reply in time",
+        extract: |p, v| {
+            if let Some(SerializablePacket::EchoRequestPacket(echo)) =
+                p.get_transport_layer_packet()
+            {
+                v.push(Value::Bool(echo.unanswered));
+            }
+        },
+    },
+    Field {
+        name: "icmp.mtu",
+        field_type: FieldType::Integer,
+        description: "MTU of the next hop, when fragmentation was needed",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::DestinationUnreachable {
+                next_hop_mtu: Some(mtu),
+                ..
+            }) = icmp_body(p)
+            {
+                v.push(Value::Integer(mtu as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.redir_gw",
+        field_type: FieldType::Ipv4,
+        description: "Gateway an ICMP redirect points to",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::Redirect { gateway, .. }) = icmp_body(p) {
+                v.push(Value::Ipv4(gateway));
+            }
+        },
+    },
+    Field {
+        name: "icmp.pointer",
+        field_type: FieldType::Integer,
+        description: "Offset of the faulty byte of a parameter problem",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::ParameterProblem { pointer, .. }) = icmp_body(p) {
+                v.push(Value::Integer(pointer as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.lifetime",
+        field_type: FieldType::Integer,
+        description: "Router advertisement lifetime in seconds",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::RouterAdvertisement { lifetime, .. }) = icmp_body(p)
+            {
+                v.push(Value::Integer(lifetime as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.router_address",
+        field_type: FieldType::Ipv4,
+        description: "Router address of a router advertisement",
+        extract: |p, v| {
+            if let Some(SerializableIcmpBody::RouterAdvertisement { entries, .. }) = icmp_body(p) {
+                v.extend(entries.iter().map(|entry| Value::Ipv4(entry.address)));
+            }
+        },
+    },
+    Field {
+        name: "icmp.originate_timestamp",
+        field_type: FieldType::Integer,
+        description: "ICMP originate timestamp, milliseconds since midnight UT",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::Timestamp { originate, .. }) = icmp_body(p) {
+                v.push(Value::Integer(originate as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.receive_timestamp",
+        field_type: FieldType::Integer,
+        description: "ICMP receive timestamp, milliseconds since midnight UT",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::Timestamp { receive, .. }) = icmp_body(p) {
+                v.push(Value::Integer(receive as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.transmit_timestamp",
+        field_type: FieldType::Integer,
+        description: "ICMP transmit timestamp, milliseconds since midnight UT",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::Timestamp { transmit, .. }) = icmp_body(p) {
+                v.push(Value::Integer(transmit as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmp.address_mask",
+        field_type: FieldType::Ipv4,
+        description: "Subnet mask of an ICMP address mask message",
+        extract: |p, v| {
+            if let Some(&SerializableIcmpBody::AddressMask { mask, .. }) = icmp_body(p) {
+                v.push(Value::Ipv4(mask));
+            }
+        },
+    },
+    Field {
+        name: "icmp.original_in",
+        field_type: FieldType::Integer,
+        description: "Packet carrying the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(
+                icmp_original(p)
+                    .and_then(|original| original.original_in)
+                    .map(|id| Value::Integer(id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.original.src",
+        field_type: FieldType::Ipv4,
+        description: "Source address of the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(icmp_original(p).map(|original| Value::Ipv4(original.ipv4.source)))
+        },
+    },
+    Field {
+        name: "icmp.original.dst",
+        field_type: FieldType::Ipv4,
+        description: "Destination address of the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(icmp_original(p).map(|original| Value::Ipv4(original.ipv4.destination)))
+        },
+    },
+    Field {
+        name: "icmp.original.proto",
+        field_type: FieldType::Integer,
+        description: "IP protocol of the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(
+                icmp_original(p)
+                    .and_then(|original| trailing_number(&original.ipv4.next_level_protocol))
+                    .map(Value::Integer),
+            )
+        },
+    },
+    Field {
+        name: "icmp.original.srcport",
+        field_type: FieldType::Integer,
+        description: "TCP or UDP source port of the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(
+                icmp_original(p)
+                    .and_then(SerializableIcmpQuote::ports)
+                    .map(|(source, _)| Value::Integer(source as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmp.original.dstport",
+        field_type: FieldType::Integer,
+        description: "TCP or UDP destination port of the datagram an ICMP error was sent about",
+        extract: |p, v| {
+            v.extend(
+                icmp_original(p)
+                    .and_then(SerializableIcmpQuote::ports)
+                    .map(|(_, destination)| Value::Integer(destination as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmpv6",
+        field_type: FieldType::Protocol,
+        description: "Internet Control Message Protocol for IPv6",
+        extract: |p, v| {
+            if is_icmpv6(p) {
+                v.push(Value::Bool(true));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.type",
+        field_type: FieldType::Integer,
+        description: "ICMPv6 type",
+        extract: |p, v| {
+            if let Some(SerializablePacket::Icmpv6Packet(icmp)) = p.get_transport_layer_packet() {
+                v.extend(trailing_number(&icmp.icmpv6_type).map(Value::Integer));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.code",
+        field_type: FieldType::Integer,
+        description: "ICMPv6 code",
+        extract: |p, v| {
+            if let Some(SerializablePacket::Icmpv6Packet(icmp)) = p.get_transport_layer_packet() {
+                v.push(Value::Integer(icmp.icmpv6_code as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.echo.identifier",
+        field_type: FieldType::Integer,
+        description: "ICMPv6 echo identifier",
+        extract: |p, v| {
+            if let Some(
+                SerializableIcmpv6Body::EchoRequest { identifier, .. }
+                | SerializableIcmpv6Body::EchoReply { identifier, .. },
+            ) = icmpv6_body(p)
+            {
+                v.push(Value::Integer(*identifier as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.echo.sequence_number",
+        field_type: FieldType::Integer,
+        description: "ICMPv6 echo sequence number",
+        extract: |p, v| {
+            if let Some(
+                SerializableIcmpv6Body::EchoRequest {
+                    sequence_number, ..
+                }
+                | SerializableIcmpv6Body::EchoReply {
+                    sequence_number, ..
+                },
+            ) = icmpv6_body(p)
+            {
+                v.push(Value::Integer(*sequence_number as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.resp_to",
+        field_type: FieldType::Integer,
+        description: "Frame number of the echo request an ICMPv6 echo reply answers",
+        extract: |p, v| {
+            v.extend(
+                icmpv6_echo_reply(p)
+                    .and_then(|echo| echo.response_to)
+                    .map(|id| Value::Integer(id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmpv6.resptime",
+        field_type: FieldType::Integer,
+        description: "Milliseconds between an ICMPv6 echo request and its reply",
+        extract: |p, v| {
+            v.extend(
+                icmpv6_echo_reply(p)
+                    .and_then(|echo| echo.time)
+                    .map(|time| Value::Integer(time.as_millis() as u64)),
+            )
+        },
+    },
+    Field {
+        name: "icmpv6.duplicate",
+        field_type: FieldType::Bool,
+        description: "ICMPv6 echo reply to a request answered already",
+        extract: |p, v| v.extend(icmpv6_echo_reply(p).map(|echo| Value::Bool(echo.duplicate))),
+    },
+    Field {
+        name: "icmpv6.no_resp",
+        field_type: FieldType::Bool,
+        description: "ICMPv6 echo request got no reply in time",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::EchoRequest { unanswered, .. }) = icmpv6_body(p) {
+                v.push(Value::Bool(*unanswered));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.cur_hop_limit",
+        field_type: FieldType::Integer,
+        description: "Hop limit advertised by a router",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::RouterAdvertisement { hop_limit, .. }) =
+                icmpv6_body(p)
+            {
+                v.push(Value::Integer(*hop_limit as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.flag.m",
+        field_type: FieldType::Bool,
+        description: "Router advertisement managed address configuration flag",
+        extract: |p, v| {
+            v.extend(router_advertisement_flags(p).map(|flags| Value::Bool(flags & 0x80 != 0)))
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.flag.o",
+        field_type: FieldType::Bool,
+        description: "Router advertisement other configuration flag",
+        extract: |p, v| {
+            v.extend(router_advertisement_flags(p).map(|flags| Value::Bool(flags & 0x40 != 0)))
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.router_lifetime",
+        field_type: FieldType::Integer,
+        description: "Seconds a router is a default router for, 0 when it is not one",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::RouterAdvertisement { lifetime, .. }) =
+                icmpv6_body(p)
+            {
+                v.push(Value::Integer(*lifetime as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.reachable_time",
+        field_type: FieldType::Integer,
+        description: "Milliseconds a neighbor is considered reachable for",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::RouterAdvertisement { reachable_time, .. }) =
+                icmpv6_body(p)
+            {
+                v.push(Value::Integer(*reachable_time as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ra.retrans_timer",
+        field_type: FieldType::Integer,
+        description: "Milliseconds between retransmitted neighbor solicitations",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::RouterAdvertisement {
+                retransmit_timer, ..
+            }) = icmpv6_body(p)
+            {
+                v.push(Value::Integer(*retransmit_timer as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.ns.target_address",
+        field_type: FieldType::Ipv6,
+        description: "Address a neighbor solicitation resolves",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::NeighborSolicitation { target, .. }) =
+                icmpv6_body(p)
+            {
+                v.push(Value::Ipv6(*target));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.na.target_address",
+        field_type: FieldType::Ipv6,
+        description: "Address a neighbor advertisement is for",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::NeighborAdvertisement { target, .. }) =
+                icmpv6_body(p)
+            {
+                v.push(Value::Ipv6(*target));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.na.flag.r",
+        field_type: FieldType::Bool,
+        description: "Neighbor advertisement router flag",
+        extract: |p, v| {
+            v.extend(neighbor_advertisement_flags(p).map(|flags| Value::Bool(flags & 0x80 != 0)))
+        },
+    },
+    Field {
+        name: "icmpv6.nd.na.flag.s",
+        field_type: FieldType::Bool,
+        description: "Neighbor advertisement solicited flag",
+        extract: |p, v| {
+            v.extend(neighbor_advertisement_flags(p).map(|flags| Value::Bool(flags & 0x40 != 0)))
+        },
+    },
+    Field {
+        name: "icmpv6.nd.na.flag.o",
+        field_type: FieldType::Bool,
+        description: "Neighbor advertisement override flag",
+        extract: |p, v| {
+            v.extend(neighbor_advertisement_flags(p).map(|flags| Value::Bool(flags & 0x20 != 0)))
+        },
+    },
+    Field {
+        name: "icmpv6.nd.rd.target_address",
+        field_type: FieldType::Ipv6,
+        description: "Better first hop a redirect points to",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::Redirect { target, .. }) = icmpv6_body(p) {
+                v.push(Value::Ipv6(*target));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.nd.rd.destination_address",
+        field_type: FieldType::Ipv6,
+        description: "Destination a redirect is for",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::Redirect { destination, .. }) = icmpv6_body(p) {
+                v.push(Value::Ipv6(*destination));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.opt.type",
+        field_type: FieldType::Integer,
+        description: "Neighbor Discovery option type",
+        extract: |p, v| {
+            v.extend(ndp_options(p).map(|option| Value::Integer(option.option_type as u64)))
+        },
+    },
+    Field {
+        name: "icmpv6.opt.linkaddr",
+        field_type: FieldType::Mac,
+        description: "Source or target link-layer address option",
+        extract: |p, v| {
+            v.extend(ndp_options(p).filter_map(|option| match option.data {
+                SerializableNdpOptionData::LinkLayerAddress(address) => Some(Value::Mac(address)),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "icmpv6.opt.prefix",
+        field_type: FieldType::Ipv6,
+        description: "Prefix advertised in a prefix information option",
+        extract: |p, v| {
+            v.extend(ndp_options(p).filter_map(|option| match option.data {
+                SerializableNdpOptionData::PrefixInformation { prefix, .. } => {
+                    Some(Value::Ipv6(prefix))
+                }
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "icmpv6.opt.prefix.length",
+        field_type: FieldType::Integer,
+        description: "Length of a prefix information option's prefix",
+        extract: |p, v| {
+            v.extend(ndp_options(p).filter_map(|option| match option.data {
+                SerializableNdpOptionData::PrefixInformation { prefix_length, .. } => {
+                    Some(Value::Integer(prefix_length as u64))
+                }
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "icmpv6.opt.mtu",
+        field_type: FieldType::Integer,
+        description: "Link MTU option",
+        extract: |p, v| {
+            v.extend(ndp_options(p).filter_map(|option| match option.data {
+                SerializableNdpOptionData::Mtu(mtu) => Some(Value::Integer(mtu as u64)),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "icmpv6.opt.rdnss",
+        field_type: FieldType::Ipv6,
+        description: "Recursive DNS server option address",
+        extract: |p, v| {
+            for option in ndp_options(p) {
+                if let SerializableNdpOptionData::Rdnss { servers, .. } = &option.data {
+                    v.extend(servers.iter().copied().map(Value::Ipv6));
+                }
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.opt.dnssl",
+        field_type: FieldType::Text,
+        description: "DNS search list option domain",
+        extract: |p, v| {
+            for option in ndp_options(p) {
+                if let SerializableNdpOptionData::Dnssl { domains, .. } = &option.data {
+                    v.extend(domains.iter().cloned().map(Value::Text));
+                }
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.mld.maximum_response_delay",
+        field_type: FieldType::Integer,
+        description: "Milliseconds listeners may wait before answering a query",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::MulticastListenerQuery {
+                maximum_response_delay,
+                ..
+            }) = icmpv6_body(p)
+            {
+                v.push(Value::Integer(*maximum_response_delay as u64));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.mld.multicast_address",
+        field_type: FieldType::Ipv6,
+        description: "Multicast address of an MLDv1 message or MLDv2 query",
+        extract: |p, v| {
+            if let Some(
+                SerializableIcmpv6Body::MulticastListenerQuery { address, .. }
+                | SerializableIcmpv6Body::MulticastListenerReport { address }
+                | SerializableIcmpv6Body::MulticastListenerDone { address },
+            ) = icmpv6_body(p)
+            {
+                v.push(Value::Ipv6(*address));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.mld.source_address",
+        field_type: FieldType::Ipv6,
+        description: "Source an MLDv2 query is specific to",
+        extract: |p, v| {
+            if let Some(SerializableIcmpv6Body::MulticastListenerQuery { v2: Some(v2), .. }) =
+                icmpv6_body(p)
+            {
+                v.extend(v2.sources.iter().copied().map(Value::Ipv6));
+            }
+        },
+    },
+    Field {
+        name: "icmpv6.mldr.mar.multicast_address",
+        field_type: FieldType::Ipv6,
+        description: "Multicast address of an MLDv2 report record",
+        extract: |p, v| v.extend(mld_records(p).map(|record| Value::Ipv6(record.address))),
+    },
+    Field {
+        name: "icmpv6.mldr.mar.record_type",
+        field_type: FieldType::Integer,
+        description: "Type of an MLDv2 report record, e.g. 4 for change to exclude mode",
+        extract: |p, v| {
+            v.extend(mld_records(p).map(|record| Value::Integer(record.record_type as u64)))
+        },
+    },
+    Field {
+        name: "dns",
+        field_type: FieldType::Protocol,
+        description: "Domain Name System, including mDNS and LLMNR",
+        extract: |p, v| v.extend(dns(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "mdns",
+        field_type: FieldType::Protocol,
+        description: "Multicast Domain Name System",
+        extract: |p, v| {
+            if is_dns_service(p, DnsService::Mdns) {
+                v.push(Value::Bool(true));
+            }
+        },
+    },
+    Field {
+        name: "llmnr",
+        field_type: FieldType::Protocol,
+        description: "Link-local Multicast Name Resolution",
+        extract: |p, v| {
+            if is_dns_service(p, DnsService::Llmnr) {
+                v.push(Value::Bool(true));
+            }
+        },
+    },
+    Field {
+        name: "dns.id",
+        field_type: FieldType::Integer,
+        description: "DNS transaction ID",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Integer(dns.id as u64))),
+    },
+    Field {
+        name: "dns.flags.response",
+        field_type: FieldType::Bool,
+        description: "DNS message is a response",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Bool(dns.is_response()))),
+    },
+    Field {
+        name: "dns.flags.opcode",
+        field_type: FieldType::Integer,
+        description: "DNS operation code",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Integer(dns.opcode() as u64))),
+    },
+    Field {
+        name: "dns.flags.truncated",
+        field_type: FieldType::Bool,
+        description: "DNS message is truncated",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Bool(dns.flags & 0x0200 != 0))),
+    },
+    Field {
+        name: "dns.flags.rcode",
+        field_type: FieldType::Integer,
+        description: "DNS response code",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .filter(|dns| dns.is_response())
+                    .map(|dns| Value::Integer(dns.rcode() as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dns.count.queries",
+        field_type: FieldType::Integer,
+        description: "Number of DNS questions",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Integer(dns.questions.len() as u64))),
+    },
+    Field {
+        name: "dns.count.answers",
+        field_type: FieldType::Integer,
+        description: "Number of DNS answer records",
+        extract: |p, v| v.extend(dns(p).map(|dns| Value::Integer(dns.answers.len() as u64))),
+    },
+    Field {
+        name: "dns.qry.name",
+        field_type: FieldType::Text,
+        description: "Name a DNS question asks about",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .into_iter()
+                    .flat_map(|dns| &dns.questions)
+                    .map(|question| Value::Text(question.name.clone())),
+            )
+        },
+    },
+    Field {
+        name: "dns.qry.type",
+        field_type: FieldType::Integer,
+        description: "Record type a DNS question asks for",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .into_iter()
+                    .flat_map(|dns| &dns.questions)
+                    .map(|question| Value::Integer(question.record_type as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dns.resp.name",
+        field_type: FieldType::Text,
+        description: "Owner name of a DNS resource record",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .into_iter()
+                    .flat_map(|dns| dns.records())
+                    .map(|record| Value::Text(record.name.clone())),
+            )
+        },
+    },
+    Field {
+        name: "dns.resp.type",
+        field_type: FieldType::Integer,
+        description: "Type of a DNS resource record",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .into_iter()
+                    .flat_map(|dns| dns.records())
+                    .map(|record| Value::Integer(record.record_type as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dns.resp.ttl",
+        field_type: FieldType::Integer,
+        description: "Time to live of a DNS resource record",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .into_iter()
+                    .flat_map(|dns| dns.records())
+                    .map(|record| Value::Integer(record.ttl as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dns.a",
+        field_type: FieldType::Ipv4,
+        description: "Address of a DNS A record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::A(address) => Some(Value::Ipv4(*address)),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.aaaa",
+        field_type: FieldType::Ipv6,
+        description: "Address of a DNS AAAA record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Aaaa(address) => Some(Value::Ipv6(*address)),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.cname",
+        field_type: FieldType::Text,
+        description: "Canonical name of a DNS CNAME record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Cname(name) => Some(Value::Text(name.clone())),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.ns",
+        field_type: FieldType::Text,
+        description: "Name server of a DNS NS record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Ns(name) => Some(Value::Text(name.clone())),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.ptr.domain_name",
+        field_type: FieldType::Text,
+        description: "Domain name of a DNS PTR record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Ptr(name) => Some(Value::Text(name.clone())),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.mx.mail_exchange",
+        field_type: FieldType::Text,
+        description: "Mail exchange of a DNS MX record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Mx { exchange, .. } => Some(Value::Text(exchange.clone())),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.txt",
+        field_type: FieldType::Text,
+        description: "String of a DNS TXT record",
+        extract: |p, v| {
+            v.extend(
+                dns_data(p)
+                    .filter_map(|data| match data {
+                        SerializableDnsData::Txt(strings) => Some(strings),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|string| Value::Text(string.clone())),
+            )
+        },
+    },
+    Field {
+        name: "dns.srv.port",
+        field_type: FieldType::Integer,
+        description: "Port of a DNS SRV record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Srv { port, .. } => Some(Value::Integer(*port as u64)),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.srv.target",
+        field_type: FieldType::Text,
+        description: "Target host of a DNS SRV record",
+        extract: |p, v| {
+            v.extend(dns_data(p).filter_map(|data| match data {
+                SerializableDnsData::Srv { target, .. } => Some(Value::Text(target.clone())),
+                _ => None,
+            }))
+        },
+    },
+    Field {
+        name: "dns.response_to",
+        field_type: FieldType::Integer,
+        description: "Frame number of the query a DNS response answers",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .and_then(|dns| dns.response_to)
+                    .map(|id| Value::Integer(id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dns.unanswered",
+        field_type: FieldType::Bool,
+        description: "DNS query got no response in time",
+        extract: |p, v| {
+            v.extend(
+                dns(p)
+                    .filter(|dns| !dns.is_response())
+                    .map(|dns| Value::Bool(dns.unanswered)),
+            )
+        },
+    },
+    Field {
+        name: "http",
+        field_type: FieldType::Protocol,
+        description: "Hypertext Transfer Protocol",
+        extract: |p, v| {
+            if http_requests(p).next().is_some() || http_responses(p).next().is_some() {
+                v.push(Value::Bool(true));
+            }
+        },
+    },
+    Field {
+        name: "http.request",
+        field_type: FieldType::Protocol,
+        description: "Packet ends an HTTP request",
+        extract: |p, v| v.extend(http_requests(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "http.response",
+        field_type: FieldType::Protocol,
+        description: "Packet ends an HTTP response",
+        extract: |p, v| v.extend(http_responses(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "http.request.method",
+        field_type: FieldType::Text,
+        description: "HTTP request method, e.g. GET",
+        extract: |p, v| {
+            v.extend(http_requests(p).map(|request| Value::Text(request.method.clone())))
+        },
+    },
+    Field {
+        name: "http.request.uri",
+        field_type: FieldType::Text,
+        description: "HTTP request target",
+        extract: |p, v| v.extend(http_requests(p).map(|request| Value::Text(request.uri.clone()))),
+    },
+    Field {
+        name: "http.request.version",
+        field_type: FieldType::Text,
+        description: "HTTP version of a request",
+        extract: |p, v| {
+            v.extend(http_requests(p).map(|request| Value::Text(request.version.clone())))
+        },
+    },
+    Field {
+        name: "http.response.code",
+        field_type: FieldType::Integer,
+        description: "HTTP response status code",
+        extract: |p, v| {
+            v.extend(http_responses(p).map(|response| Value::Integer(response.status as u64)))
+        },
+    },
+    Field {
+        name: "http.response.phrase",
+        field_type: FieldType::Text,
+        description: "HTTP response reason phrase",
+        extract: |p, v| {
+            v.extend(http_responses(p).map(|response| Value::Text(response.reason.clone())))
+        },
+    },
+    Field {
+        name: "http.host",
+        field_type: FieldType::Text,
+        description: "HTTP Host header",
+        extract: |p, v| {
+            v.extend(http_headers(p, "Host").map(|value| Value::Text(value.to_string())))
+        },
+    },
+    Field {
+        name: "http.user_agent",
+        field_type: FieldType::Text,
+        description: "HTTP User-Agent header",
+        extract: |p, v| {
+            v.extend(http_headers(p, "User-Agent").map(|value| Value::Text(value.to_string())))
+        },
+    },
+    Field {
+        name: "http.server",
+        field_type: FieldType::Text,
+        description: "HTTP Server header",
+        extract: |p, v| {
+            v.extend(http_headers(p, "Server").map(|value| Value::Text(value.to_string())))
+        },
+    },
+    Field {
+        name: "http.content_type",
+        field_type: FieldType::Text,
+        description: "HTTP Content-Type header",
+        extract: |p, v| {
+            v.extend(http_headers(p, "Content-Type").map(|value| Value::Text(value.to_string())))
+        },
+    },
+    Field {
+        name: "http.content_length",
+        field_type: FieldType::Integer,
+        description: "Body length in bytes, after removing the chunked coding",
+        extract: |p, v| {
+            let requests = http_requests(p).map(|request| &request.body);
+            let responses = http_responses(p).map(|response| &response.body);
+            v.extend(
+                requests
+                    .chain(responses)
+                    .map(|body| Value::Integer(body.length as u64)),
+            )
+        },
+    },
+    Field {
+        name: "http.request_in",
+        field_type: FieldType::Integer,
+        description: "Frame number of the request an HTTP response answers",
+        extract: |p, v| {
+            v.extend(
+                http_responses(p)
+                    .filter_map(|response| response.request.as_ref())
+                    .map(|request| Value::Integer(request.packet as u64)),
+            )
+        },
+    },
+    Field {
+        name: "http.time",
+        field_type: FieldType::Integer,
+        description: "Milliseconds between an HTTP request and its response",
+        extract: |p, v| {
+            v.extend(
+                http_responses(p)
+                    .filter_map(|response| response.request.as_ref())
+                    .map(|request| Value::Integer(request.time.as_millis() as u64)),
+            )
+        },
+    },
+    Field {
+        name: "tls",
+        field_type: FieldType::Protocol,
+        description: "Transport Layer Security",
+        extract: |p, v| v.extend(tls(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "tls.record.version",
+        field_type: FieldType::Integer,
+        description: "Protocol version of a TLS record",
+        extract: |p, v| {
+            v.extend(
+                tls(p)
+                    .into_iter()
+                    .flat_map(|tls| &tls.records)
+                    .map(|record| Value::Integer(record.version as u64)),
+            )
+        },
+    },
+    Field {
+        name: "tls.record.content_type",
+        field_type: FieldType::Integer,
+        description: "Content type of a TLS record, e.g. 22 for handshakes",
+        extract: |p, v| {
+            v.extend(
+                tls(p)
+                    .into_iter()
+                    .flat_map(|tls| &tls.records)
+                    .map(|record| Value::Integer(record.content_type as u64)),
+            )
+        },
+    },
+    Field {
+        name: "tls.handshake.type",
+        field_type: FieldType::Integer,
+        description: "Type of a TLS handshake message, e.g. 1 for client hellos",
+        extract: |p, v| {
+            v.extend(tls_handshakes(p).map(|message| {
+                Value::Integer(match message {
+                    SerializableTlsHandshake::ClientHello(_) => 1,
+                    SerializableTlsHandshake::ServerHello(_) => 2,
+                    SerializableTlsHandshake::Certificate(_) => 11,
+                    SerializableTlsHandshake::Other { handshake_type, .. } => {
+                        *handshake_type as u64
+                    }
+                })
+            }))
+        },
+    },
+    Field {
+        name: "tls.handshake.version",
+        field_type: FieldType::Integer,
+        description: "Version field of a TLS client or server hello",
+        extract: |p, v| {
+            v.extend(client_hellos(p).map(|hello| Value::Integer(hello.version as u64)));
+            v.extend(server_hellos(p).map(|hello| Value::Integer(hello.version as u64)));
+        },
+    },
+    Field {
+        name: "tls.handshake.extensions.supported_version",
+        field_type: FieldType::Integer,
+        description: "TLS version picked by a server hello",
+        extract: |p, v| {
+            v.extend(server_hellos(p).map(|hello| Value::Integer(hello.selected_version() as u64)))
+        },
+    },
+    Field {
+        name: "tls.handshake.ciphersuite",
+        field_type: FieldType::Integer,
+        description: "Cipher suite offered by a client or picked by a server",
+        extract: |p, v| {
+            v.extend(
+                client_hellos(p)
+                    .flat_map(|hello| &hello.cipher_suites)
+                    .map(|&suite| Value::Integer(suite as u64)),
+            );
+            v.extend(server_hellos(p).map(|hello| Value::Integer(hello.cipher_suite as u64)));
+        },
+    },
+    Field {
+        name: "tls.handshake.extension.type",
+        field_type: FieldType::Integer,
+        description: "Type of an extension of a TLS hello",
+        extract: |p, v| {
+            let client = client_hellos(p).flat_map(|hello| &hello.extensions);
+            let server = server_hellos(p).flat_map(|hello| &hello.extensions);
+            v.extend(
+                client
+                    .chain(server)
+                    .map(|extension| Value::Integer(extension.extension_type as u64)),
+            )
+        },
+    },
+    Field {
+        name: "tls.handshake.extensions_server_name",
+        field_type: FieldType::Text,
+        description: "Server name indication of a TLS client hello",
+        extract: |p, v| {
+            v.extend(
+                client_hellos(p)
+                    .filter_map(|hello| hello.server_name())
+                    .map(|name| Value::Text(name.to_string())),
+            )
+        },
+    },
+    Field {
+        name: "tls.handshake.extensions_alpn_str",
+        field_type: FieldType::Text,
+        description: "Application protocol offered by a client or picked by a server",
+        extract: |p, v| {
+            v.extend(
+                client_hellos(p)
+                    .flat_map(|hello| hello.alpn())
+                    .map(|protocol| Value::Text(protocol.clone())),
+            );
+            v.extend(
+                server_hellos(p)
+                    .filter_map(|hello| hello.alpn())
+                    .map(|protocol| Value::Text(protocol.to_string())),
+            );
+        },
+    },
+    Field {
+        name: "tls.handshake.ja3_full",
+        field_type: FieldType::Text,
+        description: "JA3 fingerprint of a TLS client hello before hashing",
+        extract: |p, v| v.extend(client_hellos(p).map(|hello| Value::Text(hello.ja3_full.clone()))),
+    },
+    Field {
+        name: "tls.handshake.ja3",
+        field_type: FieldType::Text,
+        description: "JA3 fingerprint of a TLS client hello",
+        extract: |p, v| v.extend(client_hellos(p).map(|hello| Value::Text(hello.ja3.clone()))),
+    },
+    Field {
+        name: "tls.handshake.ja3s_full",
+        field_type: FieldType::Text,
+        description: "JA3S fingerprint of a TLS server hello before hashing",
+        extract: |p, v| {
+            v.extend(server_hellos(p).map(|hello| Value::Text(hello.ja3s_full.clone())))
+        },
+    },
+    Field {
+        name: "tls.handshake.ja3s",
+        field_type: FieldType::Text,
+        description: "JA3S fingerprint of a TLS server hello",
+        extract: |p, v| v.extend(server_hellos(p).map(|hello| Value::Text(hello.ja3s.clone()))),
+    },
+    Field {
+        name: "tls.handshake.ja4",
+        field_type: FieldType::Text,
+        description: "JA4 fingerprint of a TLS client hello",
+        extract: |p, v| v.extend(client_hellos(p).map(|hello| Value::Text(hello.ja4.clone()))),
+    },
+    Field {
+        name: "tls.handshake.certificate.subject",
+        field_type: FieldType::Text,
+        description: "Subject of a certificate in a TLS chain",
+        extract: |p, v| {
+            v.extend(certificates(p).map(|certificate| Value::Text(certificate.subject.clone())))
+        },
+    },
+    Field {
+        name: "tls.handshake.certificate.issuer",
+        field_type: FieldType::Text,
+        description: "Issuer of a certificate in a TLS chain",
+        extract: |p, v| {
+            v.extend(certificates(p).map(|certificate| Value::Text(certificate.issuer.clone())))
+        },
+    },
+    Field {
+        name: "tls.handshake.certificate.alt_names",
+        field_type: FieldType::Text,
+        description: "DNS name a certificate in a TLS chain is valid for",
+        extract: |p, v| {
+            v.extend(
+                certificates(p)
+                    .flat_map(|certificate| &certificate.alt_names)
+                    .map(|name| Value::Text(name.clone())),
+            )
+        },
+    },
+    Field {
+        name: "tls.alert_message.desc",
+        field_type: FieldType::Integer,
+        description: "Description of a TLS alert, e.g. 40 for handshake failures",
+        extract: |p, v| {
+            v.extend(
+                tls(p)
+                    .into_iter()
+                    .flat_map(|tls| &tls.records)
+                    .filter_map(|record| match record.content {
+                        SerializableTlsContent::Alert { description, .. } => Some(description),
+                        _ => None,
+                    })
+                    .map(|description| Value::Integer(description as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcp",
+        field_type: FieldType::Protocol,
+        description: "Dynamic Host Configuration Protocol, including BOOTP",
+        extract: |p, v| v.extend(dhcp(p).map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "dhcp.type",
+        field_type: FieldType::Integer,
+        description: "BOOTP message op, 1 for requests and 2 for replies",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Integer(dhcp.op as u64))),
+    },
+    Field {
+        name: "dhcp.id",
+        field_type: FieldType::Integer,
+        description: "DHCP transaction ID",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Integer(dhcp.transaction_id as u64))),
+    },
+    Field {
+        name: "dhcp.hw.mac_addr",
+        field_type: FieldType::Mac,
+        description: "DHCP client hardware address",
+        extract: |p, v| v.extend(dhcp(p).and_then(|dhcp| dhcp.client_mac()).map(Value::Mac)),
+    },
+    Field {
+        name: "dhcp.ip.client",
+        field_type: FieldType::Ipv4,
+        description: "Address of a DHCP client that already has one",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Ipv4(dhcp.client_address))),
+    },
+    Field {
+        name: "dhcp.ip.your",
+        field_type: FieldType::Ipv4,
+        description: "Address a DHCP server offers or assigns to the client",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Ipv4(dhcp.your_address))),
+    },
+    Field {
+        name: "dhcp.ip.server",
+        field_type: FieldType::Ipv4,
+        description: "Next server of the boot process",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Ipv4(dhcp.server_address))),
+    },
+    Field {
+        name: "dhcp.ip.relay",
+        field_type: FieldType::Ipv4,
+        description: "Address of the relay agent a DHCP message went through",
+        extract: |p, v| v.extend(dhcp(p).map(|dhcp| Value::Ipv4(dhcp.relay_address))),
+    },
+    Field {
+        name: "dhcp.option.type",
+        field_type: FieldType::Integer,
+        description: "Code of an option in a DHCP message",
+        extract: |p, v| {
+            v.extend(
+                dhcp(p)
+                    .into_iter()
+                    .flat_map(|dhcp| &dhcp.options)
+                    .map(|option| Value::Integer(option.code as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcp.option.dhcp",
+        field_type: FieldType::Integer,
+        description: "DHCP message type, e.g. 1 for Discover and 5 for ACK",
+        extract: |p, v| {
+            v.extend(
+                dhcp(p)
+                    .and_then(|dhcp| dhcp.message_type())
+                    .map(|message_type| Value::Integer(message_type as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcp.option.requested_ip_address",
+        field_type: FieldType::Ipv4,
+        description: "Address a DHCP client asks for",
+        extract: |p, v| v.extend(dhcp_addresses(p, 50)),
+    },
+    Field {
+        name: "dhcp.option.dhcp_server_id",
+        field_type: FieldType::Ipv4,
+        description: "Identifier of the DHCP server a message is from or meant for",
+        extract: |p, v| v.extend(dhcp_addresses(p, 54)),
+    },
+    Field {
+        name: "dhcp.option.router",
+        field_type: FieldType::Ipv4,
+        description: "Router handed out by a DHCP server",
+        extract: |p, v| v.extend(dhcp_addresses(p, 3)),
+    },
+    Field {
+        name: "dhcp.option.domain_name_server",
+        field_type: FieldType::Ipv4,
+        description: "DNS server handed out by a DHCP server",
+        extract: |p, v| v.extend(dhcp_addresses(p, 6)),
+    },
+    Field {
+        name: "dhcp.option.ip_address_lease_time",
+        field_type: FieldType::Integer,
+        description: "DHCP lease time in seconds",
+        extract: |p, v| {
+            v.extend(
+                dhcp(p)
+                    .and_then(|dhcp| dhcp.lease_time())
+                    .map(|seconds| Value::Integer(seconds as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcp.option.hostname",
+        field_type: FieldType::Text,
+        description: "Host name of a DHCP client",
+        extract: |p, v| {
+            v.extend(
+                dhcp(p)
+                    .and_then(|dhcp| dhcp.text_option(12))
+                    .map(|hostname| Value::Text(hostname.to_string())),
+            )
+        },
+    },
+    Field {
+        name: "dhcp.option.vendor_class_id",
+        field_type: FieldType::Text,
+        description: "Vendor class of a DHCP client, e.g. MSFT 5.0",
+        extract: |p, v| {
+            v.extend(
+                dhcp(p)
+                    .and_then(|dhcp| dhcp.text_option(60))
+                    .map(|vendor| Value::Text(vendor.to_string())),
+            )
+        },
+    },
+    Field {
+        name: "dhcpv6",
+        field_type: FieldType::Protocol,
+        description: "DHCPv6",
+        extract: |p, v| v.extend(dhcpv6_messages(p).next().map(|_| Value::Bool(true))),
+    },
+    Field {
+        name: "dhcpv6.msgtype",
+        field_type: FieldType::Integer,
+        description: "DHCPv6 message type, of relay messages and the messages they carry",
+        extract: |p, v| {
+            v.extend(dhcpv6_messages(p).map(|message| Value::Integer(message.message_type as u64)))
+        },
+    },
+    Field {
+        name: "dhcpv6.xid",
+        field_type: FieldType::Integer,
+        description: "DHCPv6 transaction ID",
+        extract: |p, v| {
+            v.extend(
+                dhcpv6_messages(p)
+                    .filter(|message| message.relay.is_none())
+                    .map(|message| Value::Integer(message.transaction_id as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcpv6.duid",
+        field_type: FieldType::Text,
+        description: "DUID of a DHCPv6 client, as hex",
+        extract: |p, v| {
+            v.extend(dhcpv6_messages(p).filter_map(|message| {
+                let duid = message.client_id()?;
+                Some(Value::Text(
+                    duid.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                ))
+            }))
+        },
+    },
+    Field {
+        name: "dhcpv6.option.type",
+        field_type: FieldType::Integer,
+        description: "Code of an option in a DHCPv6 message",
+        extract: |p, v| {
+            v.extend(
+                dhcpv6_messages(p)
+                    .flat_map(|message| &message.options)
+                    .map(|option| Value::Integer(option.code as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcpv6.iaaddr.ip",
+        field_type: FieldType::Ipv6,
+        description: "Address of a DHCPv6 identity association",
+        extract: |p, v| v.extend(dhcpv6_leases(p, true)),
+    },
+    Field {
+        name: "dhcpv6.iaprefix.pref_addr",
+        field_type: FieldType::Ipv6,
+        description: "Prefix delegated by DHCPv6",
+        extract: |p, v| v.extend(dhcpv6_leases(p, false)),
+    },
+    Field {
+        name: "dhcpv6.status_code",
+        field_type: FieldType::Integer,
+        description: "DHCPv6 status code other than success, e.g. 2 for NoAddrsAvail",
+        extract: |p, v| {
+            v.extend(
+                dhcpv6_messages(p)
+                    .filter_map(|message| message.error_status())
+                    .map(|status| Value::Integer(status as u64)),
+            )
+        },
+    },
+    Field {
+        name: "dhcpv6.dns_server",
+        field_type: FieldType::Ipv6,
+        description: "DNS server handed out by a DHCPv6 server",
+        extract: |p, v| {
+            v.extend(
+                dhcpv6_messages(p)
+                    .filter_map(|message| match message.option(23)? {
+                        SerializableDhcpv6OptionData::Addresses(addresses) => Some(addresses),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|&address| Value::Ipv6(address)),
+            )
+        },
+    },
+    Field {
+        name: "dhcpv6.domain",
+        field_type: FieldType::Text,
+        description: "Domain of a DHCPv6 domain search list",
+        extract: |p, v| {
+            v.extend(
+                dhcpv6_messages(p)
+                    .filter_map(|message| match message.option(24)? {
+                        SerializableDhcpv6OptionData::DomainList(names) => Some(names),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|name| Value::Text(name.clone())),
+            )
+        },
+    },
+];
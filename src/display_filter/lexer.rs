@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use super::DisplayFilterError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Field name or unquoted literal such as `10.0.0.0/8`, `443` or `aa:bb:cc:dd:ee:ff`
+    Word(String),
+    /// Double-quoted string literal
+    Text(String),
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+    In,
+    And,
+    Or,
+    Not,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    Comma,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._:/-".contains(c)
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, DisplayFilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let two = source.get(start..start + 2).unwrap_or("");
+        let (kind, length) = match (c, two) {
+            (_, "==") => (TokenKind::Equal, 2),
+            (_, "!=") => (TokenKind::NotEqual, 2),
+            (_, "<=") => (TokenKind::LessOrEqual, 2),
+            (_, ">=") => (TokenKind::GreaterOrEqual, 2),
+            (_, "&&") => (TokenKind::And, 2),
+            (_, "||") => (TokenKind::Or, 2),
+            ('<', _) => (TokenKind::Less, 1),
+            ('>', _) => (TokenKind::Greater, 1),
+            ('!', _) => (TokenKind::Not, 1),
+            ('(', _) => (TokenKind::OpenParen, 1),
+            (')', _) => (TokenKind::CloseParen, 1),
+            ('{', _) => (TokenKind::OpenBrace, 1),
+            ('}', _) => (TokenKind::CloseBrace, 1),
+            (',', _) => (TokenKind::Comma, 1),
+            ('"', _) => {
+                chars.next();
+                let mut text = String::new();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            end = Some(i + 1);
+                            break;
+                        }
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                text.push(escaped);
+                            }
+                        }
+                        c => text.push(c),
+                    }
+                }
+                let end = end.ok_or_else(|| {
+                    DisplayFilterError::new("unterminated string", start..source.len())
+                })?;
+                tokens.push(Token {
+                    kind: TokenKind::Text(text),
+                    span: start..end,
+                });
+                continue;
+            }
+            (c, _) if is_word_char(c) => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let word = &source[start..end];
+                let kind = match word {
+                    "eq" => TokenKind::Equal,
+                    "ne" => TokenKind::NotEqual,
+                    "lt" => TokenKind::Less,
+                    "le" => TokenKind::LessOrEqual,
+                    "gt" => TokenKind::Greater,
+                    "ge" => TokenKind::GreaterOrEqual,
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    "contains" => TokenKind::Contains,
+                    "in" => TokenKind::In,
+                    _ => TokenKind::Word(word.to_string()),
+                };
+                tokens.push(Token {
+                    kind,
+                    span: start..end,
+                });
+                continue;
+            }
+            (c, _) => {
+                return Err(DisplayFilterError::new(
+                    format!("unexpected character '{}'", c),
+                    start..start + c.len_utf8(),
+                ))
+            }
+        };
+
+        for _ in 0..length {
+            chars.next();
+        }
+        tokens.push(Token {
+            kind,
+            span: start..start + length,
+        });
+    }
+
+    Ok(tokens)
+}
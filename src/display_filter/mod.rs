@@ -0,0 +1,315 @@
+//! Wireshark-like display filters over decoded packets, e.g.
+//! `ip.src == 10.0.0.0/8 && tcp.flags.syn && !arp`
+
+mod fields;
+mod lexer;
+mod parser;
+
+pub use fields::{field, fields, Field, FieldType, Value};
+
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::Range,
+};
+
+use pnet::util::MacAddr;
+use thiserror::Error;
+
+use crate::parser::wrapers::ParsedPacket;
+
+/// Syntax or type error in a display filter, with the byte range of the offending text
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct DisplayFilterError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl DisplayFilterError {
+    pub(crate) fn new<S: Into<String>>(message: S, span: Range<usize>) -> Self {
+        DisplayFilterError {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+}
+
+/// A type-checked literal a field is compared with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+    Bool(bool),
+    Integer(u64),
+    Ipv4Network(Ipv4Addr, u8),
+    Ipv6Network(Ipv6Addr, u8),
+    Mac(MacAddr),
+    Text(String),
+}
+
+/// A type-checked display filter expression
+#[derive(Debug)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    /// A field on its own: protocols and non-boolean fields test presence, booleans test truth
+    Field(&'static Field),
+    Compare(&'static Field, Comparison, Literal),
+    In(&'static Field, Vec<Literal>),
+}
+
+/// A compiled display filter
+#[derive(Debug)]
+pub struct DisplayFilter {
+    source: String,
+    expression: Expression,
+}
+
+impl DisplayFilter {
+    /// Parse and type-check a filter
+    pub fn parse(source: &str) -> Result<Self, DisplayFilterError> {
+        let tokens = lexer::tokenize(source)?;
+        let expression = parser::parse(&tokens, source.len())?;
+
+        Ok(DisplayFilter {
+            source: source.to_string(),
+            expression,
+        })
+    }
+
+    /// Get the filter as written by the user
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the type-checked expression
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Whether `packet` passes the filter
+    pub fn matches(&self, packet: &ParsedPacket) -> bool {
+        evaluate(&self.expression, packet)
+    }
+}
+
+fn evaluate(expression: &Expression, packet: &ParsedPacket) -> bool {
+    match expression {
+        Expression::And(left, right) => evaluate(left, packet) && evaluate(right, packet),
+        Expression::Or(left, right) => evaluate(left, packet) || evaluate(right, packet),
+        Expression::Not(inner) => !evaluate(inner, packet),
+        Expression::Field(field) => {
+            let values = field.values(packet);
+            match field.field_type {
+                FieldType::Bool => values.contains(&Value::Bool(true)),
+                _ => !values.is_empty(),
+            }
+        }
+        // "Not equal" holds when no occurrence equals, so `ip.addr != x` excludes x both ways
+        Expression::Compare(field, Comparison::NotEqual, literal) => !field
+            .values(packet)
+            .iter()
+            .any(|value| compare(value, Comparison::Equal, literal)),
+        Expression::Compare(field, comparison, literal) => field
+            .values(packet)
+            .iter()
+            .any(|value| compare(value, *comparison, literal)),
+        Expression::In(field, literals) => field.values(packet).iter().any(|value| {
+            literals
+                .iter()
+                .any(|literal| compare(value, Comparison::Equal, literal))
+        }),
+    }
+}
+
+fn compare(value: &Value, comparison: Comparison, literal: &Literal) -> bool {
+    match (value, literal) {
+        (Value::Integer(value), Literal::Integer(literal)) => match comparison {
+            Comparison::Equal => value == literal,
+            Comparison::NotEqual => value != literal,
+            Comparison::Less => value < literal,
+            Comparison::LessOrEqual => value <= literal,
+            Comparison::Greater => value > literal,
+            Comparison::GreaterOrEqual => value >= literal,
+            Comparison::Contains => false,
+        },
+        (Value::Bool(value), Literal::Bool(literal)) => match comparison {
+            Comparison::Equal => value == literal,
+            Comparison::NotEqual => value != literal,
+            _ => false,
+        },
+        (Value::Ipv4(value), Literal::Ipv4Network(network, prefix)) => {
+            let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+            let inside = u32::from(*value) & mask == u32::from(*network) & mask;
+            match comparison {
+                Comparison::Equal => inside,
+                Comparison::NotEqual => !inside,
+                _ => false,
+            }
+        }
+        (Value::Ipv6(value), Literal::Ipv6Network(network, prefix)) => {
+            let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+            let inside = u128::from(*value) & mask == u128::from(*network) & mask;
+            match comparison {
+                Comparison::Equal => inside,
+                Comparison::NotEqual => !inside,
+                _ => false,
+            }
+        }
+        (Value::Mac(value), Literal::Mac(literal)) => match comparison {
+            Comparison::Equal => value == literal,
+            Comparison::NotEqual => value != literal,
+            _ => false,
+        },
+        (Value::Text(value), Literal::Text(literal)) => match comparison {
+            Comparison::Equal => value == literal,
+            Comparison::NotEqual => value != literal,
+            Comparison::Contains => value.contains(literal.as_str()),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::ethernet::EthernetPacket;
+
+    use super::*;
+    use crate::parser::{defrag::Defragmenter, Parser};
+
+    /// Ethernet, IPv4 and a TCP SYN from 10.0.0.7:51514 to 93.184.216.34:443
+    const SYN: [u8; 54] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00, 0x45,
+        0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x07,
+        0x5d, 0xb8, 0xd8, 0x22, 0xc9, 0x3a, 0x01, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x50, 0x02, 0xfa, 0xf0, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn packet() -> ParsedPacket {
+        let ethernet = EthernetPacket::new(&SYN).unwrap();
+        let mut packet = Parser::parse_ethernet_frame(&ethernet, 0, &mut Defragmenter::new());
+        packet.set_interface_name(Some("eth0".to_string()));
+        packet
+    }
+
+    fn matches(filter: &str) -> bool {
+        DisplayFilter::parse(filter).unwrap().matches(&packet())
+    }
+
+    fn error(filter: &str) -> (String, Range<usize>) {
+        let err = DisplayFilter::parse(filter).unwrap_err();
+        (err.message, err.span)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let filter = DisplayFilter::parse("arp || tcp && !udp").unwrap();
+        match filter.expression() {
+            Expression::Or(left, right) => {
+                assert!(matches!(**left, Expression::Field(field) if field.name == "arp"));
+                let Expression::And(_, not) = &**right else {
+                    panic!("parsed as {:?}", right);
+                };
+                assert!(matches!(**not, Expression::Not(_)));
+            }
+            expression => panic!("parsed as {:?}", expression),
+        }
+        assert!(matches!(
+            DisplayFilter::parse("(arp || tcp) && udp").unwrap().expression(),
+            Expression::And(left, _) if matches!(**left, Expression::Or(..))
+        ));
+    }
+
+    #[test]
+    fn matches_fields_by_type() {
+        assert!(matches("tcp"));
+        assert!(!matches("arp || udp"));
+        assert!(matches("tcp.flags.syn && !tcp.flags.ack"));
+        assert!(matches("tcp.flags.syn == 1 && tcp.flags.ack == false"));
+        assert!(matches("tcp.dstport == 443 && tcp.srcport > 50000"));
+        assert!(matches("tcp.port == 0x1bb"));
+        assert!(matches("tcp.port in {80, 443 8080}"));
+        assert!(!matches("tcp.port in {80 8080}"));
+        assert!(matches("ip.src == 10.0.0.0/8 && ip.dst == 93.184.216.34"));
+        assert!(!matches("ip.src == 10.1.0.0/16"));
+        assert!(matches(
+            "eth.src == 66:77:88:99:aa:bb && eth.dst == 00-11-22-33-44-55"
+        ));
+        assert!(matches("frame.interface_name contains \"eth\""));
+        assert!(!matches("frame.interface_name == wlan0"));
+        // Fields the packet lacks never equal anything
+        assert!(!matches("udp.port == 443"));
+        assert!(matches("udp.port != 443"));
+    }
+
+    #[test]
+    fn not_equal_excludes_every_occurrence() {
+        assert!(!matches("ip.addr != 10.0.0.7"));
+        assert!(!matches("ip.addr != 93.184.216.34"));
+        assert!(matches("ip.addr != 10.0.0.8"));
+        assert!(matches("ip.addr == 10.0.0.7 && ip.addr == 93.184.216.34"));
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        assert_eq!(error(""), ("empty filter".to_string(), 0..0));
+        assert_eq!(error("tcpp"), ("unknown field 'tcpp'".to_string(), 0..4));
+        assert_eq!(
+            error("tcp.port =="),
+            ("expected a value".to_string(), 11..11)
+        );
+        assert_eq!(error("(tcp || udp"), ("unclosed '('".to_string(), 0..1));
+        assert_eq!(
+            error("tcp udp"),
+            ("expected '&&' or '||'".to_string(), 4..7)
+        );
+        assert_eq!(
+            error("tcp.port == http"),
+            (
+                "'http' is not an integer (tcp.port is an integer)".to_string(),
+                12..16
+            )
+        );
+        assert_eq!(error("ip.src == 10.0.0.0/33").1, 10..21);
+        assert_eq!(error("ip.src contains 10").1, 7..15);
+        assert_eq!(error("tcp == 1").1, 4..6);
+        assert_eq!(error("tcp.port in {80").1, 12..13);
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let packet = packet();
+        let nested = |depth| format!("{}tcp", "!".repeat(depth));
+        assert!(DisplayFilter::parse(&nested(256)).unwrap().matches(&packet));
+        assert_eq!(
+            error(&nested(257)),
+            ("filter nested too deeply".to_string(), 256..257)
+        );
+
+        let chained = |length| vec!["tcp"; length].join(" && ");
+        assert!(DisplayFilter::parse(&chained(257))
+            .unwrap()
+            .matches(&packet));
+        assert!(DisplayFilter::parse(&chained(258)).is_err());
+
+        // Deep enough to overflow the stack without the limit
+        for source in [
+            nested(100_000),
+            format!("{}tcp{}", "(".repeat(100_000), ")".repeat(100_000)),
+            vec!["tcp"; 100_000].join(" || "),
+        ] {
+            assert_eq!(error(&source).0, "filter nested too deeply");
+        }
+    }
+}
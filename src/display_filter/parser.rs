@@ -0,0 +1,299 @@
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::Range,
+};
+
+use pnet::util::MacAddr;
+
+use super::{
+    fields::{field, Field, FieldType},
+    lexer::{Token, TokenKind},
+    Comparison, DisplayFilterError, Expression, Literal,
+};
+
+/// Depth of operators a filter may nest, counting each `&&`/`||` of a chain as one level as
+/// the expression tree does. Parsing and evaluation recurse once per level.
+const MAX_NESTING: usize = 256;
+
+/// Parse and type-check a token stream
+pub fn parse(tokens: &[Token], source_length: usize) -> Result<Expression, DisplayFilterError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        source_length,
+        depth: 0,
+    };
+    if tokens.is_empty() {
+        return Err(DisplayFilterError::new("empty filter", 0..source_length));
+    }
+
+    let expression = parser.or()?;
+    if let Some(token) = parser.peek() {
+        return Err(DisplayFilterError::new(
+            "expected '&&' or '||'",
+            token.span.clone(),
+        ));
+    }
+    Ok(expression)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    source_length: usize,
+    /// Operators enclosing the expression being parsed
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().is_some_and(|token| &token.kind == kind) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Span just past the end of the input, for errors about missing tokens
+    fn end(&self) -> Range<usize> {
+        self.source_length..self.source_length
+    }
+
+    /// Go one operator deeper past the token just accepted, failing past `MAX_NESTING`
+    fn deeper(&mut self) -> Result<(), DisplayFilterError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            let span = self.tokens[self.position - 1].span.clone();
+            return Err(DisplayFilterError::new("filter nested too deeply", span));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<Expression, DisplayFilterError> {
+        let depth = self.depth;
+        let mut left = self.and()?;
+        while self.accept(&TokenKind::Or) {
+            self.deeper()?;
+            left = Expression::Or(Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, DisplayFilterError> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+        while self.accept(&TokenKind::And) {
+            self.deeper()?;
+            left = Expression::And(Box::new(left), Box::new(self.unary()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, DisplayFilterError> {
+        let depth = self.depth;
+        if self.accept(&TokenKind::Not) {
+            self.deeper()?;
+            let inner = self.unary()?;
+            self.depth = depth;
+            return Ok(Expression::Not(Box::new(inner)));
+        }
+
+        let open = self.peek().map(|token| token.span.clone());
+        if self.accept(&TokenKind::OpenParen) {
+            self.deeper()?;
+            let expression = self.or()?;
+            if !self.accept(&TokenKind::CloseParen) {
+                return Err(DisplayFilterError::new(
+                    "unclosed '('",
+                    open.unwrap_or_else(|| self.end()),
+                ));
+            }
+            self.depth = depth;
+            return Ok(expression);
+        }
+
+        self.test()
+    }
+
+    fn test(&mut self) -> Result<Expression, DisplayFilterError> {
+        let token = self
+            .next()
+            .ok_or_else(|| DisplayFilterError::new("expected a field", self.end()))?;
+        let field = match &token.kind {
+            TokenKind::Word(name) => field(name).ok_or_else(|| {
+                DisplayFilterError::new(format!("unknown field '{}'", name), token.span.clone())
+            })?,
+            _ => {
+                return Err(DisplayFilterError::new(
+                    "expected a field",
+                    token.span.clone(),
+                ))
+            }
+        };
+
+        let operator = match self.peek() {
+            Some(operator) => operator,
+            None => return Ok(Expression::Field(field)),
+        };
+        let comparison = match operator.kind {
+            TokenKind::Equal => Comparison::Equal,
+            TokenKind::NotEqual => Comparison::NotEqual,
+            TokenKind::Less => Comparison::Less,
+            TokenKind::LessOrEqual => Comparison::LessOrEqual,
+            TokenKind::Greater => Comparison::Greater,
+            TokenKind::GreaterOrEqual => Comparison::GreaterOrEqual,
+            TokenKind::Contains => Comparison::Contains,
+            TokenKind::In => {
+                self.position += 1;
+                return self.set(field);
+            }
+            _ => return Ok(Expression::Field(field)),
+        };
+        self.position += 1;
+
+        check_operator(field, comparison, operator.span.clone())?;
+        let literal = self.literal(field)?;
+        Ok(Expression::Compare(field, comparison, literal))
+    }
+
+    /// Membership in a set such as `{80 443 8080}`, commas are optional
+    fn set(&mut self, field: &'static Field) -> Result<Expression, DisplayFilterError> {
+        let open = self
+            .peek()
+            .map_or_else(|| self.end(), |token| token.span.clone());
+        if !self.accept(&TokenKind::OpenBrace) {
+            return Err(DisplayFilterError::new("expected '{'", open));
+        }
+        check_operator(field, Comparison::Equal, open.clone())?;
+
+        let mut literals = Vec::new();
+        while !self.accept(&TokenKind::CloseBrace) {
+            if self.peek().is_none() {
+                return Err(DisplayFilterError::new("unclosed '{'", open));
+            }
+            if !self.accept(&TokenKind::Comma) {
+                literals.push(self.literal(field)?);
+            }
+        }
+        Ok(Expression::In(field, literals))
+    }
+
+    fn literal(&mut self, field: &'static Field) -> Result<Literal, DisplayFilterError> {
+        let token = self
+            .next()
+            .ok_or_else(|| DisplayFilterError::new("expected a value", self.end()))?;
+        let text = match &token.kind {
+            TokenKind::Word(text) | TokenKind::Text(text) => text,
+            _ => {
+                return Err(DisplayFilterError::new(
+                    "expected a value",
+                    token.span.clone(),
+                ))
+            }
+        };
+        let invalid = |expected: &str| {
+            DisplayFilterError::new(
+                format!(
+                    "'{}' is not {} ({} is {})",
+                    text, expected, field.name, expected
+                ),
+                token.span.clone(),
+            )
+        };
+
+        match field.field_type {
+            FieldType::Bool => match text.as_str() {
+                "1" | "true" => Ok(Literal::Bool(true)),
+                "0" | "false" => Ok(Literal::Bool(false)),
+                _ => Err(invalid("a boolean")),
+            },
+            FieldType::Integer => parse_integer(text)
+                .map(Literal::Integer)
+                .ok_or_else(|| invalid("an integer")),
+            FieldType::Ipv4 => {
+                let (address, prefix) =
+                    split_prefix(text, 32).ok_or_else(|| invalid("an IPv4 network"))?;
+                address
+                    .parse::<Ipv4Addr>()
+                    .map(|address| Literal::Ipv4Network(address, prefix))
+                    .map_err(|_| invalid("an IPv4 address"))
+            }
+            FieldType::Ipv6 => {
+                let (address, prefix) =
+                    split_prefix(text, 128).ok_or_else(|| invalid("an IPv6 network"))?;
+                address
+                    .parse::<Ipv6Addr>()
+                    .map(|address| Literal::Ipv6Network(address, prefix))
+                    .map_err(|_| invalid("an IPv6 address"))
+            }
+            FieldType::Mac => text
+                .replace('-', ":")
+                .parse::<MacAddr>()
+                .map(Literal::Mac)
+                .map_err(|_| invalid("a MAC address")),
+            FieldType::Text => Ok(Literal::Text(text.clone())),
+            FieldType::Protocol => Err(invalid("comparable")),
+        }
+    }
+}
+
+/// Reject operators that make no sense for the field's type
+fn check_operator(
+    field: &Field,
+    comparison: Comparison,
+    span: Range<usize>,
+) -> Result<(), DisplayFilterError> {
+    let allowed = match field.field_type {
+        FieldType::Protocol => false,
+        FieldType::Integer => comparison != Comparison::Contains,
+        FieldType::Text => matches!(
+            comparison,
+            Comparison::Equal | Comparison::NotEqual | Comparison::Contains
+        ),
+        FieldType::Bool | FieldType::Ipv4 | FieldType::Ipv6 | FieldType::Mac => {
+            matches!(comparison, Comparison::Equal | Comparison::NotEqual)
+        }
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(DisplayFilterError::new(
+            format!(
+                "operator not supported by {} ({:?})",
+                field.name, field.field_type
+            ),
+            span,
+        ))
+    }
+}
+
+fn parse_integer(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Split `address/prefix`, defaulting to a whole-address prefix
+fn split_prefix(text: &str, max_prefix: u8) -> Option<(&str, u8)> {
+    match text.split_once('/') {
+        Some((address, prefix)) => {
+            let prefix = prefix.parse().ok().filter(|&prefix| prefix <= max_prefix)?;
+            Some((address, prefix))
+        }
+        None => Some((text, max_prefix)),
+    }
+}
@@ -0,0 +1,204 @@
+//! "Follow TCP stream" view, the reassembled conversation of one connection
+
+use netui::parser::reassembly::{Direction, StreamChunk, StreamState, TcpStream};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Bytes shown on each line in hex mode
+const BYTES_PER_LINE: usize = 16;
+
+pub struct FollowView {
+    stream: usize,
+    /// Show a hex dump instead of the bytes as text
+    hex: bool,
+    /// First visible line
+    scroll: usize,
+}
+
+impl FollowView {
+    pub fn new(stream: usize) -> Self {
+        FollowView {
+            stream,
+            hex: false,
+            scroll: 0,
+        }
+    }
+
+    /// Get index of the stream shown
+    pub fn stream(&self) -> usize {
+        self.stream
+    }
+
+    pub fn toggle_hex(&mut self) {
+        self.hex = !self.hex;
+        self.scroll = 0;
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll += lines;
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, stream: &TcpStream) {
+        let chunks = Layout::default()
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(area);
+
+        // Borders take two lines, only the lines fitting in between are built
+        let height = chunks[0].height.saturating_sub(2) as usize;
+        let total: usize = stream
+            .get_chunks()
+            .iter()
+            .map(|chunk| line_count(chunk, self.hex))
+            .sum();
+        self.scroll = self.scroll.min(total.saturating_sub(height));
+        let lines = visible_lines(stream, self.hex, self.scroll, height);
+
+        let state = match stream.get_state() {
+            StreamState::Open => "open",
+            StreamState::HalfClosed => "half-closed",
+            StreamState::Closed => "closed",
+            StreamState::Reset => "reset",
+        };
+        let title = format!(
+            "Follow TCP stream {}: {} \u{21c4} {} [{}] (h hex/text, Esc close)",
+            stream.get_index(),
+            stream.get_client(),
+            stream.get_server(),
+            state
+        );
+        let view = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(view, chunks[0]);
+
+        let status = Spans::from(vec![
+            Span::styled(
+                flow_status(stream, Direction::ToServer, "client"),
+                style(Direction::ToServer),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                flow_status(stream, Direction::ToClient, "server"),
+                style(Direction::ToClient),
+            ),
+        ]);
+        f.render_widget(Paragraph::new(status), chunks[1]);
+    }
+}
+
+/// Client data in red and server data in blue, as other analyzers do
+fn style(direction: Direction) -> Style {
+    match direction {
+        Direction::ToServer => Style::default().fg(Color::Red),
+        Direction::ToClient => Style::default().fg(Color::Blue),
+    }
+}
+
+fn flow_status(stream: &TcpStream, direction: Direction, side: &str) -> String {
+    let mut status = format!("{}: {} bytes", side, stream.get_bytes(direction));
+    let retransmitted = stream.get_retransmitted(direction);
+    if retransmitted > 0 {
+        status += &format!(", {} retransmitted", retransmitted);
+    }
+    let out_of_order = stream.get_out_of_order(direction);
+    if out_of_order > 0 {
+        status += &format!(", {} segments out of order", out_of_order);
+    }
+    let pending = stream.get_pending(direction);
+    if pending > 0 {
+        status += &format!(", {} bytes after a gap", pending);
+    }
+    let missing = stream.get_missing(direction);
+    if missing > 0 {
+        status += &format!(", {} bytes missing", missing);
+    }
+    status
+}
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Split a chunk into text lines at line feeds, every chunk starts on a new line
+fn text_split(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    data.strip_suffix(b"\n")
+        .unwrap_or(data)
+        .split(|&byte| byte == b'\n')
+}
+
+/// Get the number of lines `chunk` takes up
+fn line_count(chunk: &StreamChunk, hex: bool) -> usize {
+    if hex {
+        chunk.data.len().div_ceil(BYTES_PER_LINE)
+    } else {
+        text_split(&chunk.data).count()
+    }
+}
+
+/// Build the `height` lines after the first `skip` ones
+///
+/// In hex mode offsets count the bytes of each direction separately, server lines are
+/// indented.
+fn visible_lines(
+    stream: &TcpStream,
+    hex: bool,
+    mut skip: usize,
+    height: usize,
+) -> Vec<Spans<'static>> {
+    let mut lines = Vec::with_capacity(height);
+    let mut offsets = [0usize; 2];
+    for chunk in stream.get_chunks() {
+        if lines.len() == height {
+            break;
+        }
+        let (offset, indent) = match chunk.direction {
+            Direction::ToServer => (&mut offsets[0], ""),
+            Direction::ToClient => (&mut offsets[1], "    "),
+        };
+        let count = line_count(chunk, hex);
+        if skip >= count {
+            skip -= count;
+            *offset += chunk.data.len();
+            continue;
+        }
+
+        let remaining = height - lines.len();
+        let style = style(chunk.direction);
+        if hex {
+            let rows = chunk.data.chunks(BYTES_PER_LINE).enumerate();
+            for (row, bytes) in rows.skip(skip).take(remaining) {
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                let ascii: String = bytes.iter().map(|&byte| printable(byte)).collect();
+                let text = format!(
+                    "{}{:08x}  {:<48} {}",
+                    indent,
+                    *offset + row * BYTES_PER_LINE,
+                    hex.join(" "),
+                    ascii
+                );
+                lines.push(Spans::from(Span::styled(text, style)));
+            }
+        } else {
+            for line in text_split(&chunk.data).skip(skip).take(remaining) {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let text: String = line.iter().map(|&byte| printable(byte)).collect();
+                lines.push(Spans::from(Span::styled(text, style)));
+            }
+        }
+        *offset += chunk.data.len();
+        skip = 0;
+    }
+    lines
+}
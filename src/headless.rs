@@ -0,0 +1,190 @@
+//! Running without the interface, decoded packets are printed as they are captured
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "serde")]
+use netui::parser::export::NdjsonWriter;
+use netui::{
+    display_filter::DisplayFilter,
+    parser::{defrag::Defragmenter, wrapers::ParsedPacket, Parser, ParserError},
+};
+
+use crate::trackers::Trackers;
+
+/// How each packet is printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    /// One line per packet, with the columns of the packet list
+    Summary,
+    /// Every layer of every packet with its fields
+    Verbose,
+    /// One JSON object per packet and line
+    #[cfg(feature = "serde")]
+    Ndjson,
+}
+
+/// When to stop reading packets, whichever limit is reached first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Stop after this many packets were printed
+    pub count: Option<usize>,
+    /// Stop once packets span this much capture time, or this much time passed on idle links
+    pub duration: Option<Duration>,
+}
+
+/// Decode every frame of `parser` and print those passing `display_filter` to `path`, or to
+/// stdout
+pub fn run(
+    mut parser: Parser,
+    output: Output,
+    display_filter: Option<DisplayFilter>,
+    limits: Limits,
+    path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let out: Box<dyn Write> = match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut printer = Printer::new(output, out);
+    let mut defragmenter = Defragmenter::new();
+    // Responses are printed with their latency, HTTP messages and TLS records with the packet
+    // carrying their last byte, ICMP errors with the packet carrying the datagram they quote,
+    // queries and echo requests before knowing their fate. DHCP exchanges are followed the same
+    // way, only the lease view sums them up, each message is printed as decoded
+    let mut trackers = Trackers::new();
+    let mut id = 0;
+    let mut printed = 0;
+    let is_live = parser.source().is_live();
+    let started = Instant::now();
+    let mut first_timestamp = None;
+
+    let result = loop {
+        // Live captures stop on the clock, idle links would never deliver a late packet
+        let timed_out = is_live
+            && limits
+                .duration
+                .is_some_and(|limit| started.elapsed() >= limit);
+        if limits.count.is_some_and(|count| printed >= count) || timed_out {
+            break printer.flush();
+        }
+        let frame = match parser.next() {
+            Ok(Some(frame)) => frame,
+            // Live reads time out on idle links, files are done
+            Ok(None) if is_live => continue,
+            Ok(None) => break printer.flush(),
+            // Packets read before the damage are printed, the error is reported on exit
+            Err(err) => break printer.flush().and(Err(err)),
+        };
+        let first_timestamp = *first_timestamp.get_or_insert(frame.timestamp);
+        if limits
+            .duration
+            .is_some_and(|limit| frame.timestamp.saturating_sub(first_timestamp) >= limit)
+        {
+            break printer.flush();
+        }
+        let mut packet = Parser::parse_frame(&frame, id, &mut defragmenter);
+        id += 1;
+        trackers.process(&mut packet);
+        // Unanswered queries and requests were printed already, they only need forgetting
+        trackers.expire(packet.get_timestamp());
+        trackers.prune(packet.get_timestamp());
+
+        if display_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(&packet))
+        {
+            continue;
+        }
+        let written = printer.print(&packet, first_timestamp);
+        // Live packets are seen as they arrive, without waiting for the buffer to fill up
+        let written = if is_live {
+            written.and_then(|_| printer.flush())
+        } else {
+            written
+        };
+        if let Err(err) = written {
+            break Err(err);
+        }
+        printed += 1;
+    };
+
+    match result {
+        // The reader went away, e.g. `netui -r file --no-tui | head`
+        Err(ParserError::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+enum Printer {
+    Text {
+        output: Output,
+        out: Box<dyn Write>,
+    },
+    #[cfg(feature = "serde")]
+    Ndjson(NdjsonWriter<Box<dyn Write>>),
+}
+
+impl Printer {
+    fn new(output: Output, out: Box<dyn Write>) -> Self {
+        match output {
+            #[cfg(feature = "serde")]
+            Output::Ndjson => Printer::Ndjson(NdjsonWriter::new(out)),
+            output => Printer::Text { output, out },
+        }
+    }
+
+    fn print(
+        &mut self,
+        packet: &ParsedPacket,
+        first_timestamp: Duration,
+    ) -> Result<(), ParserError> {
+        match self {
+            Printer::Text { output, out } => {
+                let summary = packet.summary();
+                let time = packet.get_timestamp().saturating_sub(first_timestamp);
+                let vlan = match summary.vlan.as_str() {
+                    "" => String::new(),
+                    vlan => format!(" VLAN {}", vlan),
+                };
+                writeln!(
+                    out,
+                    "{:>5} {:>11.6} {} → {}{} {} {} {}",
+                    packet.get_id(),
+                    time.as_secs_f64(),
+                    summary.source,
+                    summary.destination,
+                    vlan,
+                    summary.protocol,
+                    packet.get_length(),
+                    summary.info
+                )?;
+                if *output == Output::Verbose {
+                    for layer in packet.details() {
+                        writeln!(out, "  {}", layer.title)?;
+                        for field in layer.fields {
+                            writeln!(out, "      {}: {}", field.name, field.value)?;
+                        }
+                    }
+                    writeln!(out)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "serde")]
+            Printer::Ndjson(writer) => writer.write_packet(packet),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), ParserError> {
+        match self {
+            Printer::Text { out, .. } => Ok(out.flush()?),
+            #[cfg(feature = "serde")]
+            Printer::Ndjson(writer) => writer.flush(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Hex and ASCII dump of the selected packet's bytes
+
+use std::ops::Range;
+
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Bytes shown on each line
+const BYTES_PER_LINE: usize = 16;
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Draw `data` as offset, hex and ASCII columns, with the bytes in `highlight` marked
+///
+/// The dump is scrolled so the start of the highlighted bytes is always visible.
+pub fn render<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    data: &[u8],
+    highlight: Option<Range<usize>>,
+) {
+    let marked = Style::default()
+        .fg(Color::Black)
+        .bg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let is_marked = |i: usize| highlight.as_ref().is_some_and(|range| range.contains(&i));
+
+    let lines: Vec<Spans> = data
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(line, bytes)| {
+            let start = line * BYTES_PER_LINE;
+            let mut spans = vec![Span::styled(
+                format!("{:04x}  ", start),
+                Style::default().fg(Color::DarkGray),
+            )];
+            for (i, byte) in bytes.iter().enumerate() {
+                let style = if is_marked(start + i) {
+                    marked
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!("{:02x}", byte), style));
+                // Keep the gap between two marked bytes marked so fields read as one block
+                let gap = if i + 1 == BYTES_PER_LINE / 2 {
+                    "  "
+                } else {
+                    " "
+                };
+                if is_marked(start + i) && is_marked(start + i + 1) && i + 1 < bytes.len() {
+                    spans.push(Span::styled(gap, marked));
+                } else {
+                    spans.push(Span::raw(gap));
+                }
+            }
+            // Pad a short last line so its ASCII column lines up
+            let missing = BYTES_PER_LINE - bytes.len();
+            let padding = missing * 3 + usize::from(bytes.len() < BYTES_PER_LINE / 2);
+            spans.push(Span::raw(" ".repeat(padding + 1)));
+            for (i, &byte) in bytes.iter().enumerate() {
+                let style = if is_marked(start + i) {
+                    marked
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(printable(byte).to_string(), style));
+            }
+            Spans::from(spans)
+        })
+        .collect();
+
+    // Borders take two lines
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = highlight
+        .map(|range| range.start / BYTES_PER_LINE)
+        .filter(|&line| line >= height)
+        .map_or(0, |line| line + 1 - height);
+
+    let title = format!("Bytes ({} captured)", data.len());
+    let dump = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((scroll as u16, 0));
+    f.render_widget(dump, area);
+}
@@ -0,0 +1,143 @@
+//! DHCP lease view, one row per exchange of a client with DHCP or DHCPv6 servers
+
+use std::time::Duration;
+
+use netui::parser::{
+    dhcp::{DhcpVersion, Lease, LeaseState},
+    wrapers::format_seconds,
+};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+#[derive(Default)]
+pub struct LeaseView {
+    state: TableState,
+}
+
+impl LeaseView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get index of the selected exchange
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn next(&mut self, count: usize) {
+        if count > 0 {
+            let next = self.state.selected().map_or(0, |i| (i + 1).min(count - 1));
+            self.state.select(Some(next));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |i| i.saturating_sub(1));
+        self.state.select(Some(previous));
+    }
+
+    pub fn render<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        leases: &[Lease],
+        first_timestamp: Duration,
+    ) {
+        if let Some(selected) = self.state.selected() {
+            self.state
+                .select(leases.len().checked_sub(1).map(|last| selected.min(last)));
+        }
+        let rows: Vec<Row> = leases
+            .iter()
+            .map(|lease| {
+                let version = match lease.version {
+                    DhcpVersion::V4 => "DHCP",
+                    DhcpVersion::V6 => "DHCPv6",
+                };
+                let mut address = lease.address.map(|address| address.to_string());
+                if let Some((prefix, length)) = lease.prefix {
+                    let prefix = format!("{}/{}", prefix, length);
+                    address = Some(match address {
+                        Some(address) => format!("{}, {}", address, prefix),
+                        None => prefix,
+                    });
+                }
+                let started = lease.started.saturating_sub(first_timestamp);
+                let packets = match (lease.packets.first(), lease.packets.last()) {
+                    (Some(first), Some(last)) if first != last => {
+                        format!("#{}-#{} ({})", first, last, lease.packets.len())
+                    }
+                    (Some(first), _) => format!("#{}", first),
+                    _ => String::new(),
+                };
+                Row::new(vec![
+                    Cell::from(version),
+                    Cell::from(lease.client.clone()),
+                    Cell::from(lease.hostname.clone().unwrap_or_default()),
+                    Cell::from(lease.state.name()),
+                    Cell::from(address.unwrap_or_default()),
+                    Cell::from(
+                        lease
+                            .server
+                            .map(|server| server.to_string())
+                            .unwrap_or_default(),
+                    ),
+                    Cell::from(lease.lease_time.map(format_seconds).unwrap_or_default()),
+                    Cell::from(format!("{:.6}", started.as_secs_f64())),
+                    Cell::from(milliseconds(lease.offer_time)),
+                    Cell::from(milliseconds(lease.reply_time)),
+                    Cell::from(packets),
+                ])
+                .style(style(lease.state))
+            })
+            .collect();
+
+        let header = Row::new(vec![
+            "Version", "Client", "Host", "State", "Address", "Server", "Lease", "Started", "Offer",
+            "Reply", "Packets",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let title = format!(
+            "DHCP leases [{} exchanges] (Enter go to packet, Esc close)",
+            leases.len()
+        );
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&[
+                Constraint::Length(7),
+                Constraint::Length(28),
+                Constraint::Length(16),
+                Constraint::Length(10),
+                Constraint::Length(28),
+                Constraint::Length(26),
+                Constraint::Length(18),
+                Constraint::Length(12),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Percentage(100),
+            ])
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+/// Completed exchanges in green, failed ones in red
+fn style(state: LeaseState) -> Style {
+    match state {
+        LeaseState::Bound => Style::default().fg(Color::Green),
+        LeaseState::Rejected | LeaseState::Declined => Style::default().fg(Color::Red),
+        _ => Style::default(),
+    }
+}
+
+fn milliseconds(time: Option<Duration>) -> String {
+    time.map(|time| format!("{:.1} ms", time.as_secs_f64() * 1000.0))
+        .unwrap_or_default()
+}
@@ -0,0 +1,2 @@
+pub mod display_filter;
+pub mod parser;
@@ -1,40 +1,70 @@
-mod parser;
+mod follow;
+mod headless;
+mod hexdump;
+mod leases;
+mod picker;
+mod pingers;
+mod trackers;
+mod tree;
 
+use clap::Parser as _;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use parser::Parser;
-use pnet::{
-    packet::{ethernet::EthernetPacket, PacketSize},
-    util::MacAddr,
+use follow::FollowView;
+use headless::{Limits, Output};
+use leases::LeaseView;
+use netui::{
+    display_filter::{DisplayFilter, DisplayFilterError},
+    parser::{
+        capture::{CaptureFilter, FileFormat, Rotation},
+        dns, ping,
+        worker::{CaptureWorker, Overflow},
+        wrapers::{LayerDetails, ParsedPacket},
+        Parser,
+    },
 };
+use pingers::PingerView;
 use std::{
     error::Error,
-    fmt::format,
     io,
-    time::{Duration, Instant},
+    ops::Range,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+use trackers::Trackers;
+use tree::DetailTree;
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Corner, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame, Terminal,
 };
 
-struct StatefulList<T> {
-    state: ListState,
+/// Packets the capture thread may get ahead of the UI by
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Lines scrolled by Page Up/Down in the follow view
+const FOLLOW_PAGE: usize = 20;
+
+/// Selection and scrolling over a list too long to turn into widgets on every frame
+struct StatefulTable<T> {
     items: Vec<T>,
+    selected: Option<usize>,
+    /// First visible item
+    offset: usize,
 }
 
-impl<T> StatefulList<T> {
-    fn with_items(items: Vec<T>) -> StatefulList<T> {
-        StatefulList {
-            state: ListState::default(),
+impl<T> StatefulTable<T> {
+    fn with_items(items: Vec<T>) -> StatefulTable<T> {
+        StatefulTable {
             items,
+            selected: None,
+            offset: 0,
         }
     }
 
@@ -42,8 +72,30 @@ impl<T> StatefulList<T> {
         self.items.push(item);
     }
 
+    /// Insert an item before `index`, keeping the same item selected
+    fn insert(&mut self, index: usize, item: T) {
+        self.items.insert(index, item);
+        match self.selected.as_mut() {
+            Some(selected) if *selected >= index => *selected += 1,
+            _ => {}
+        }
+    }
+
+    /// Remove the item at `index`, keeping the same item selected
+    fn remove(&mut self, index: usize) {
+        self.items.remove(index);
+        match self.selected.as_mut() {
+            Some(selected) if *selected > index => *selected -= 1,
+            Some(selected) if *selected == index => self.selected = None,
+            _ => {}
+        }
+    }
+
     fn next(&mut self) {
-        let i = match self.state.selected() {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.selected {
             Some(i) => {
                 if i >= self.items.len() - 1 {
                     0
@@ -51,13 +103,16 @@ impl<T> StatefulList<T> {
                     i + 1
                 }
             }
-            None => 0,
+            None => self.offset,
         };
-        self.state.select(Some(i));
+        self.selected = Some(i);
     }
 
     fn previous(&mut self) {
-        let i = match self.state.selected() {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.selected {
             Some(i) => {
                 if i == 0 {
                     self.items.len() - 1
@@ -65,49 +120,540 @@ impl<T> StatefulList<T> {
                     i - 1
                 }
             }
-            None => 0,
+            None => self.offset,
         };
-        self.state.select(Some(i));
+        self.selected = Some(i);
     }
 
     fn unselect(&mut self) {
-        self.state.select(None);
+        self.selected = None;
+    }
+
+    /// Get the items shown in `height` rows, following the newest items while nothing is selected
+    fn visible(&mut self, height: usize) -> Range<usize> {
+        if height == 0 {
+            return 0..0;
+        }
+        match self.selected {
+            None => self.offset = self.items.len().saturating_sub(height),
+            Some(selected) if selected < self.offset => self.offset = selected,
+            Some(selected) if selected >= self.offset + height => {
+                self.offset = selected + 1 - height
+            }
+            Some(_) => {}
+        }
+        self.offset..(self.offset + height).min(self.items.len())
     }
 }
 
-/// This struct holds the current state of the app. In particular, it has the `items` field which is a wrapper
-/// around `ListState`. Keeping track of the items state let us render the associated widget with its state
-/// and have access to features such as natural scrolling.
+/// This struct holds the current state of the app. In particular, it has the `items` field which keeps
+/// the selection and scroll offset of the packet list, so only the visible rows are rendered.
 ///
 /// Check the event handling at the bottom to see how to change the state on incoming events.
 /// Check the drawing logic for items on how to specify the highlighting style for selected items.
-struct App<'a> {
-    parser: Parser,
-    items: StatefulList<(MacAddr, usize)>,
-    events: Vec<(&'a str, &'a str)>,
+struct App {
+    worker: CaptureWorker,
+    /// Every packet captured so far, `items` holds the indices of those passing the display filter
+    packets: Vec<ParsedPacket>,
+    items: StatefulTable<usize>,
+    display_filter: Option<DisplayFilter>,
+    filter_input: FilterInput,
+    focus: Focus,
+    tree: DetailTree,
+    /// TCP connections, DNS queries, DHCP exchanges and the like, from all packets
+    trackers: Trackers,
+    /// Stream being followed, shown instead of the packet list
+    follow: Option<FollowView>,
+    /// Lease view, shown instead of the packet list
+    leases: Option<LeaseView>,
+    /// Pinger view, shown instead of the packet list
+    pingers: Option<PingerView>,
+    /// Order the packet list by VLAN ID, then by arrival
+    group_by_vlan: bool,
+    recording: RecordingConfig,
+    /// Whether recording was switched on, the worker reports how it is going
+    is_recording: bool,
+}
+
+/// Pane the arrow keys move in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Focus {
+    Packets,
+    Details,
 }
 
-impl<'a> App<'a> {
-    fn new() -> App<'a> {
-        let parser = Parser::new("wlp4s0".to_string()).unwrap();
+/// Display filter being typed into the filter bar
+#[derive(Default)]
+struct FilterInput {
+    editing: bool,
+    text: String,
+    /// Cursor position in characters
+    cursor: usize,
+    /// Result of parsing `text`, refreshed on every keystroke
+    error: Option<DisplayFilterError>,
+}
+
+impl FilterInput {
+    fn byte_offset(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, c: char) {
+        let offset = self.byte_offset(self.cursor);
+        self.text.insert(offset, c);
+        self.cursor += 1;
+        self.validate();
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let offset = self.byte_offset(self.cursor);
+            self.text.remove(offset);
+            self.validate();
+        }
+    }
+
+    fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    /// An empty filter is valid and shows every packet
+    fn validate(&mut self) {
+        self.error = match self.text.trim() {
+            "" => None,
+            text => DisplayFilter::parse(text).err().map(|mut err| {
+                let leading = self.text.len() - self.text.trim_start().len();
+                err.span = err.span.start + leading..err.span.end + leading;
+                err
+            }),
+        };
+    }
+}
+
+/// Where captured frames go while recording is switched on
+struct RecordingConfig {
+    path: Option<PathBuf>,
+    format: Option<FileFormat>,
+    rotation: Rotation,
+}
+
+impl App {
+    fn new(worker: CaptureWorker, recording: RecordingConfig) -> App {
         App {
-            parser,
-            items: StatefulList::with_items(vec![]),
-            events: vec![],
+            worker,
+            packets: vec![],
+            items: StatefulTable::with_items(vec![]),
+            display_filter: None,
+            filter_input: FilterInput::default(),
+            focus: Focus::Packets,
+            tree: DetailTree::default(),
+            trackers: Trackers::new(),
+            follow: None,
+            leases: None,
+            pingers: None,
+            group_by_vlan: false,
+            recording,
+            is_recording: false,
+        }
+    }
+
+    /// Start recording into a new file, or stop the running recording
+    fn toggle_recording(&mut self) {
+        if self.is_recording {
+            self.worker.stop_recording();
+            self.is_recording = false;
+            return;
         }
+
+        let path = self.recording.path.clone().unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            PathBuf::from(format!("netui_{}.pcapng", now.as_secs()))
+        });
+        let format = self
+            .recording
+            .format
+            .unwrap_or_else(|| FileFormat::from_path(&path));
+
+        self.worker
+            .start_recording(path, format, self.recording.rotation);
+        self.is_recording = true;
     }
 
-    /// Rotate through the event list.
-    /// This only exists to simulate some kind of "progress"
+    /// Start typing a new display filter, starting from the applied one
+    fn edit_filter(&mut self) {
+        self.filter_input.editing = true;
+        self.filter_input.cursor = self.filter_input.text.chars().count();
+    }
+
+    /// Apply the typed filter and re-filter the whole packet buffer, invalid filters are kept for editing
+    fn apply_filter(&mut self) {
+        let text = self.filter_input.text.trim();
+        let filter = match text {
+            "" => None,
+            text => match DisplayFilter::parse(text) {
+                Ok(filter) => Some(filter),
+                Err(_) => return,
+            },
+        };
+        self.display_filter = filter;
+        self.filter_input.editing = false;
+
+        self.items.items = self
+            .packets
+            .iter()
+            .enumerate()
+            .filter(|(_, packet)| self.passes_filter(packet))
+            .map(|(i, _)| i)
+            .collect();
+        self.items.unselect();
+        self.sort_items();
+    }
+
+    /// Switch between listing packets in arrival order and grouped by VLAN ID
+    fn toggle_vlan_grouping(&mut self) {
+        self.group_by_vlan = !self.group_by_vlan;
+        let selected = self
+            .items
+            .selected
+            .map(|selected| self.items.items[selected]);
+        self.sort_items();
+        self.items.selected =
+            selected.and_then(|selected| self.items.items.iter().position(|&i| i == selected));
+    }
+
+    /// Put the packet list in the current order, untagged packets come before tagged ones
+    fn sort_items(&mut self) {
+        if self.group_by_vlan {
+            let packets = &self.packets;
+            self.items
+                .items
+                .sort_by_cached_key(|&i| (packets[i].get_vlan_ids(), i));
+        } else {
+            self.items.items.sort_unstable();
+        }
+    }
+
+    /// Stop editing and go back to the applied filter
+    fn cancel_filter(&mut self) {
+        self.filter_input.editing = false;
+        self.filter_input.text = self
+            .display_filter
+            .as_ref()
+            .map_or_else(String::new, |filter| filter.source().to_string());
+        self.filter_input.validate();
+    }
+
+    fn passes_filter(&self, packet: &ParsedPacket) -> bool {
+        self.display_filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(packet))
+    }
+
+    /// Get the packet selected in the packet list
+    fn selected_packet(&self) -> Option<&ParsedPacket> {
+        self.items
+            .selected
+            .map(|selected| &self.packets[self.items.items[selected]])
+    }
+
+    /// Get the protocol tree of the selected packet
+    fn selected_details(&self) -> Vec<LayerDetails> {
+        self.selected_packet()
+            .map(ParsedPacket::details)
+            .unwrap_or_default()
+    }
+
+    /// Show the reassembled stream of the selected TCP packet
+    fn follow_stream(&mut self) {
+        let stream = self
+            .selected_packet()
+            .and_then(|packet| self.trackers.streams.stream_of(packet))
+            .map(|stream| stream.get_index());
+        if let Some(stream) = stream {
+            self.follow = Some(FollowView::new(stream));
+        }
+    }
+
+    /// Select the last packet of the exchange picked in the lease view, and go back to the list
+    fn show_lease_packet(&mut self) {
+        let id = self
+            .leases
+            .as_ref()
+            .and_then(LeaseView::selected)
+            .and_then(|selected| self.trackers.dhcp.leases().get(selected))
+            .and_then(|lease| lease.packets.last().copied());
+        self.leases = None;
+        if let Some(id) = id {
+            self.select_packet(id);
+        }
+    }
+
+    /// Select the last packet of the session picked in the pinger view, and go back to the list
+    fn show_pinger_packet(&mut self) {
+        let sessions = self.trackers.ping.sessions();
+        let id = self
+            .pingers
+            .as_ref()
+            .and_then(|pingers| pingers.selected(sessions))
+            .map(|selected| sessions[selected].last_packet);
+        self.pingers = None;
+        if let Some(id) = id {
+            self.select_packet(id);
+        }
+    }
+
+    /// Select the packet with id `id` in the packet list
+    fn select_packet(&mut self, id: usize) {
+        let Ok(index) = self.packets.binary_search_by_key(&id, ParsedPacket::get_id) else {
+            return;
+        };
+        // Packets hidden by the display filter can't be selected
+        if let Some(position) = self.items.items.iter().position(|&i| i == index) {
+            self.items.selected = Some(position);
+            self.focus = Focus::Packets;
+        }
+    }
+
+    fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Packets => Focus::Details,
+            Focus::Details => Focus::Packets,
+        };
+    }
+
+    /// Take in everything the capture thread dissected since the last frame
     fn on_tick(&mut self) {
-        if let Some(item) = self.parser.next() {
-            let packet = EthernetPacket::new(item).unwrap();
-            self.items.push((packet.get_source(), packet.packet_size()));
+        // Checked first, packets sent before the capture thread finished are all drained below
+        let finished = self.worker.is_finished();
+        for mut packet in self.worker.drain() {
+            self.trackers.process(&mut packet);
+            if self.passes_filter(&packet) {
+                if self.group_by_vlan {
+                    // Packets arrive in order, so the new one goes last in its group
+                    let vlan_ids = packet.get_vlan_ids();
+                    let index = self
+                        .items
+                        .items
+                        .partition_point(|&i| self.packets[i].get_vlan_ids() <= vlan_ids);
+                    self.items.insert(index, self.packets.len());
+                } else {
+                    self.items.push(self.packets.len());
+                }
+            }
+            self.packets.push(packet);
+        }
+
+        // Once a file is read to the end, no response is coming for the queries left
+        let now = if finished {
+            Duration::MAX
+        } else {
+            self.packets
+                .last()
+                .map_or(Duration::ZERO, ParsedPacket::get_timestamp)
+        };
+        let unanswered = self.trackers.expire(now);
+        for id in unanswered.dns {
+            // Packets dropped by the capture thread leave gaps in the ids
+            if let Ok(index) = self.packets.binary_search_by_key(&id, ParsedPacket::get_id) {
+                dns::mark_unanswered(&mut self.packets[index]);
+                self.relist(index);
+            }
+        }
+        for id in unanswered.ping {
+            if let Ok(index) = self.packets.binary_search_by_key(&id, ParsedPacket::get_id) {
+                ping::mark_unanswered(&mut self.packets[index]);
+                self.relist(index);
+            }
+        }
+        // Sessions that ended stay listed once the file is read, only capture time ages them
+        if let Some(last) = self.packets.last() {
+            self.trackers.prune(last.get_timestamp());
+        }
+    }
+
+    /// Add or remove a packet from the list after it changed, e.g. `dns.unanswered` matches now
+    fn relist(&mut self, index: usize) {
+        let packets = &self.packets;
+        let position = if self.group_by_vlan {
+            let key = (packets[index].get_vlan_ids(), index);
+            self.items
+                .items
+                .partition_point(|&i| (packets[i].get_vlan_ids(), i) < key)
+        } else {
+            self.items.items.partition_point(|&i| i < index)
+        };
+        let listed = self.items.items.get(position) == Some(&index);
+        match (self.passes_filter(&self.packets[index]), listed) {
+            (true, false) => self.items.insert(position, index),
+            (false, true) => self.items.remove(position),
+            _ => {}
+        }
+    }
+}
+
+/// Terminal network traffic analyzer
+#[derive(clap::Parser)]
+#[command(version, about)]
+struct Args {
+    /// Capture from this interface, an interface picker is shown when neither this nor --read is given
+    #[arg(short, long, conflicts_with = "read")]
+    interface: Option<String>,
+    /// List network interfaces and exit
+    #[arg(long)]
+    list_interfaces: bool,
+    /// Replay a pcap or pcapng capture file instead of capturing live
+    #[arg(short, long, value_name = "FILE")]
+    read: Option<PathBuf>,
+    /// Only capture frames matching a tcpdump-style expression, e.g. "tcp port 443 and host 10.0.0.1"
+    #[arg(short = 'f', long, value_name = "EXPRESSION")]
+    capture_filter: Option<String>,
+    /// Record captured frames into this file from the start, `r` toggles recording at runtime
+    #[arg(short, long, value_name = "FILE")]
+    write: Option<PathBuf>,
+    /// Recording file format (pcap or pcapng), guessed from the file extension by default
+    #[arg(short = 'F', long, value_parser = parse_file_format)]
+    format: Option<FileFormat>,
+    /// Continue recording into a new file once the current one reaches this many megabytes
+    #[arg(short = 'C', long, value_name = "MB")]
+    file_size: Option<u64>,
+    /// Continue recording into a new file after this many seconds
+    #[arg(short = 'G', long, value_name = "SECONDS")]
+    rotate_seconds: Option<u64>,
+    /// Only show packets matching a display filter, e.g. "tcp.port == 443 && ip.ttl < 64"
+    #[arg(short = 'Y', long, value_name = "FILTER")]
+    display_filter: Option<String>,
+    /// Print a summary line per packet instead of showing the interface
+    #[arg(long, conflicts_with = "write")]
+    no_tui: bool,
+    /// Print every layer of each packet with its fields, implies --no-tui
+    #[arg(short, long, conflicts_with = "write")]
+    verbose: bool,
+    /// Write decoded packets in this format (ndjson) instead of showing the interface
+    #[cfg(feature = "serde")]
+    #[arg(short, long, value_parser = parse_output_format, conflicts_with_all = ["write", "verbose"])]
+    output: Option<Output>,
+    /// Print packets into this file instead of stdout, without the interface
+    #[arg(long, value_name = "FILE")]
+    output_file: Option<PathBuf>,
+    /// Stop after printing this many packets, without the interface
+    #[arg(short = 'c', long, value_name = "COUNT")]
+    count: Option<usize>,
+    /// Stop after this many seconds of capture, without the interface
+    #[arg(short = 'a', long, value_name = "SECONDS")]
+    duration: Option<u64>,
+}
+
+impl Args {
+    /// Whether packets are printed instead of shown in the interface
+    fn output(&self) -> Option<Output> {
+        #[cfg(feature = "serde")]
+        if self.output.is_some() {
+            return self.output;
+        }
+        if self.verbose {
+            Some(Output::Verbose)
+        } else if self.no_tui {
+            Some(Output::Summary)
+        } else {
+            None
         }
     }
 }
 
+#[cfg(feature = "serde")]
+fn parse_output_format(format: &str) -> Result<Output, String> {
+    match format {
+        "ndjson" => Ok(Output::Ndjson),
+        other => Err(format!("unknown output format {}", other)),
+    }
+}
+
+fn parse_file_format(format: &str) -> Result<FileFormat, String> {
+    match format {
+        "pcap" => Ok(FileFormat::Pcap),
+        "pcapng" => Ok(FileFormat::Pcapng),
+        other => Err(format!("unknown capture file format {}", other)),
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    if args.list_interfaces {
+        for interface in Parser::interfaces() {
+            println!(
+                "{:<16} {:<18} {:<22} {}",
+                interface.name,
+                picker::mac(&interface),
+                picker::flags(&interface),
+                picker::addresses(&interface)
+            );
+        }
+        return Ok(());
+    }
+
+    // Everything that can fail on bad arguments is checked before the terminal is taken over
+    let capture_filter = args
+        .capture_filter
+        .as_deref()
+        .map(CaptureFilter::parse)
+        .transpose()?;
+    let output = args.output();
+    let parser = match (&args.read, args.interface) {
+        (Some(path), _) => Some(Parser::from_file(path)?),
+        (None, Some(interface)) => Some(Parser::new(interface)?),
+        (None, None) => None,
+    };
+
+    let display_filter = args
+        .display_filter
+        .as_deref()
+        .map(DisplayFilter::parse)
+        .transpose()?;
+
+    if let Some(output) = output {
+        let mut parser = parser.ok_or("printing packets needs --read or --interface")?;
+        if let Some(filter) = capture_filter {
+            parser.set_capture_filter(filter)?;
+        }
+        let limits = Limits {
+            count: args.count,
+            duration: args.duration.map(Duration::from_secs),
+        };
+        return headless::run(
+            parser,
+            output,
+            display_filter,
+            limits,
+            args.output_file.as_deref(),
+        );
+    }
+    if args.output_file.is_some() || args.count.is_some() || args.duration.is_some() {
+        return Err(
+            "--output-file, --count and --duration only apply with --no-tui, --verbose or --output"
+                .into(),
+        );
+    }
+
+    let recording = RecordingConfig {
+        path: args.write,
+        format: args.format,
+        rotation: Rotation {
+            max_size: args.file_size.map(|mb| mb * 1_000_000),
+            max_duration: args.rotate_seconds.map(Duration::from_secs),
+        },
+    };
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -115,10 +661,13 @@ fn main() -> Result<(), Box<dyn Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let tick_rate = Duration::from_millis(250);
-    let app = App::new();
-    let res = run_app(&mut terminal, app, tick_rate);
+    let res = run(
+        &mut terminal,
+        parser,
+        capture_filter,
+        display_filter,
+        recording,
+    );
 
     // restore terminal
     disable_raw_mode()?;
@@ -129,9 +678,48 @@ fn main() -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{:?}", err)
+    res
+}
+
+/// Pick an interface when no capture source was given, then run the capture screen
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    parser: Option<Parser>,
+    capture_filter: Option<CaptureFilter>,
+    display_filter: Option<DisplayFilter>,
+    recording: RecordingConfig,
+) -> Result<(), Box<dyn Error>> {
+    let mut parser = match parser {
+        Some(parser) => parser,
+        None => match picker::pick_interface(terminal)? {
+            Some(interface) => Parser::new(interface.name)?,
+            None => return Ok(()),
+        },
+    };
+    if let Some(filter) = capture_filter {
+        parser.set_capture_filter(filter)?;
+    }
+
+    // Capture on its own thread, replays wait for the UI while live captures drop what it cannot keep up with
+    let overflow = if parser.source().is_live() {
+        Overflow::Drop
+    } else {
+        Overflow::Block
+    };
+    let worker = CaptureWorker::spawn(parser, CHANNEL_CAPACITY, overflow);
+
+    // create app and run it
+    let tick_rate = Duration::from_millis(50);
+    let record_now = recording.path.is_some();
+    let mut app = App::new(worker, recording);
+    if let Some(filter) = display_filter {
+        app.filter_input.text = filter.source().to_string();
+        app.display_filter = Some(filter);
+    }
+    if record_now {
+        app.toggle_recording();
     }
+    run_app(terminal, app, tick_rate)?;
 
     Ok(())
 }
@@ -143,6 +731,12 @@ fn run_app<B: Backend>(
 ) -> io::Result<()> {
     let mut last_tick = Instant::now();
     loop {
+        // Ticks come before keys, the key branches below `continue` and held keys would
+        // otherwise keep the capture thread's packets waiting
+        if last_tick.elapsed() >= tick_rate {
+            app.on_tick();
+            last_tick = Instant::now();
+        }
         terminal.draw(|f| ui(f, &mut app))?;
 
         let timeout = tick_rate
@@ -150,43 +744,244 @@ fn run_app<B: Backend>(
             .unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
+                if app.filter_input.editing {
+                    match key.code {
+                        KeyCode::Enter => app.apply_filter(),
+                        KeyCode::Esc => app.cancel_filter(),
+                        KeyCode::Backspace => app.filter_input.backspace(),
+                        KeyCode::Left => app.filter_input.left(),
+                        KeyCode::Right => app.filter_input.right(),
+                        KeyCode::Char(c) => app.filter_input.insert(c),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(follow) = app.follow.as_mut() {
+                    match key.code {
+                        KeyCode::Char('q') => return Ok(()),
+                        KeyCode::Esc | KeyCode::Char('f') => app.follow = None,
+                        KeyCode::Char('h') => follow.toggle_hex(),
+                        KeyCode::Down => follow.scroll_down(1),
+                        KeyCode::Up => follow.scroll_up(1),
+                        KeyCode::PageDown => follow.scroll_down(FOLLOW_PAGE),
+                        KeyCode::PageUp => follow.scroll_up(FOLLOW_PAGE),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(leases) = app.leases.as_mut() {
+                    match key.code {
+                        KeyCode::Char('q') => return Ok(()),
+                        KeyCode::Esc | KeyCode::Char('l') => app.leases = None,
+                        KeyCode::Down => leases.next(app.trackers.dhcp.leases().len()),
+                        KeyCode::Up => leases.previous(),
+                        KeyCode::Enter => app.show_lease_packet(),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(pingers) = app.pingers.as_mut() {
+                    match key.code {
+                        KeyCode::Char('q') => return Ok(()),
+                        KeyCode::Esc | KeyCode::Char('p') => app.pingers = None,
+                        KeyCode::Down => pingers.next(app.trackers.ping.sessions()),
+                        KeyCode::Up => pingers.previous(app.trackers.ping.sessions()),
+                        KeyCode::Enter => app.show_pinger_packet(),
+                        _ => {}
+                    }
+                    continue;
+                }
                 match key.code {
                     KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Left => app.items.unselect(),
-                    KeyCode::Down => app.items.next(),
-                    KeyCode::Up => app.items.previous(),
+                    KeyCode::Char('f') => app.follow_stream(),
+                    KeyCode::Char('l') => app.leases = Some(LeaseView::new()),
+                    KeyCode::Char('p') => app.pingers = Some(PingerView::new()),
+                    KeyCode::Char('r') => app.toggle_recording(),
+                    KeyCode::Char('/') => app.edit_filter(),
+                    KeyCode::Char('v') => app.toggle_vlan_grouping(),
+                    KeyCode::Tab => app.toggle_focus(),
+                    _ => {}
+                }
+                match (app.focus, key.code) {
+                    (Focus::Packets, KeyCode::Left) => app.items.unselect(),
+                    (Focus::Packets, KeyCode::Down) => app.items.next(),
+                    (Focus::Packets, KeyCode::Up) => app.items.previous(),
+                    (Focus::Details, KeyCode::Down) => {
+                        let layers = app.selected_details();
+                        app.tree.next(&layers);
+                    }
+                    (Focus::Details, KeyCode::Up) => app.tree.previous(),
+                    (Focus::Details, KeyCode::Right) => {
+                        let layers = app.selected_details();
+                        app.tree.expand(&layers);
+                    }
+                    (Focus::Details, KeyCode::Left) => {
+                        let layers = app.selected_details();
+                        app.tree.collapse(&layers);
+                    }
+                    (Focus::Details, KeyCode::Enter | KeyCode::Char(' ')) => {
+                        let layers = app.selected_details();
+                        app.tree.toggle(&layers);
+                    }
                     _ => {}
                 }
             }
         }
-        if last_tick.elapsed() >= tick_rate {
-            app.on_tick();
-            last_tick = Instant::now();
-        }
     }
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    // Create two chunks with equal horizontal screen space
+    // Leave a line at the bottom for the filter bar
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+        .split(f.size());
+
+    if let Some(follow) = app.follow.as_mut() {
+        if let Some(stream) = app.trackers.streams.get_stream(follow.stream()) {
+            follow.render(f, rows[0], stream);
+            render_filter_bar(f, app, rows[1]);
+            return;
+        }
+    }
+    if let Some(leases) = app.leases.as_mut() {
+        let first_timestamp = app
+            .packets
+            .first()
+            .map(|packet| packet.get_timestamp())
+            .unwrap_or_default();
+        leases.render(f, rows[0], app.trackers.dhcp.leases(), first_timestamp);
+        render_filter_bar(f, app, rows[1]);
+        return;
+    }
+    if let Some(pingers) = app.pingers.as_mut() {
+        let first_timestamp = app
+            .packets
+            .first()
+            .map(|packet| packet.get_timestamp())
+            .unwrap_or_default();
+        let now = app
+            .packets
+            .last()
+            .map(|packet| packet.get_timestamp())
+            .unwrap_or_default();
+        pingers.render(
+            f,
+            rows[0],
+            app.trackers.ping.sessions(),
+            first_timestamp,
+            now,
+        );
+        render_filter_bar(f, app, rows[1]);
+        return;
+    }
+
+    // Packet list on top, the rest below it
     let chunks = Layout::default()
-        .direction(Direction::Horizontal)
+        .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(f.size());
+        .split(rows[0]);
 
-    // Iterate through all elements in the `items` app and append some debug text to it.
-    let items: Vec<ListItem> = app
-        .items
+    let stats = app.worker.stats();
+    let title = format!(
+        "Packets [{} captured, {} dropped, {} queued{}]",
+        stats.captured,
+        stats.dropped,
+        stats.queued,
+        match app.worker.error() {
+            Some(err) => format!(", stopped: {}", err),
+            None if app.worker.is_finished() => ", done".to_string(),
+            None => String::new(),
+        }
+    );
+    let recording = app.worker.recording();
+    let title = match (&recording.path, &recording.error) {
+        (Some(path), _) => format!(
+            "{} [REC {} - {} frames]",
+            title,
+            path.display(),
+            recording.frames
+        ),
+        (None, Some(err)) => format!("{} [recording failed: {}]", title, err),
+        (None, None) => title,
+    };
+    let title = match &app.display_filter {
+        Some(_) => format!(
+            "{} [{}/{} shown]",
+            title,
+            app.items.items.len(),
+            app.packets.len()
+        ),
+        None => title,
+    };
+    let title = if app.group_by_vlan {
+        format!("{} [grouped by VLAN]", title)
+    } else {
+        title
+    };
+
+    // Only the visible rows are summarized, borders and header take three lines
+    let visible = app
         .items
+        .visible(chunks[0].height.saturating_sub(3) as usize);
+    let first_timestamp = app
+        .packets
+        .first()
+        .map(|packet| packet.get_timestamp())
+        .unwrap_or_default();
+    let packet_rows: Vec<Row> = app.items.items[visible.clone()]
         .iter()
-        .map(|(addr, size)| {
-            let mut lines = vec![Spans::from(format!("{} --- {}", addr.to_string(), size))];
-            ListItem::new(lines).style(Style::default().fg(Color::Black).bg(Color::White))
+        .map(|&i| {
+            let packet = &app.packets[i];
+            let summary = packet.summary();
+            let time = packet.get_timestamp().saturating_sub(first_timestamp);
+            Row::new(vec![
+                Cell::from(packet.get_id().to_string()),
+                Cell::from(format!("{:.6}", time.as_secs_f64())),
+                Cell::from(summary.source),
+                Cell::from(summary.destination),
+                Cell::from(summary.vlan),
+                Cell::from(summary.protocol),
+                Cell::from(packet.get_length().to_string()),
+                Cell::from(summary.info),
+            ])
         })
         .collect();
 
-    // Create a List from all list items and highlight the currently selected one
-    let items = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("List"))
+    let header = Row::new(vec![
+        "No.",
+        "Time",
+        "Source",
+        "Destination",
+        "VLAN",
+        "Protocol",
+        "Length",
+        "Info",
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let table = Table::new(packet_rows)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(if app.focus == Focus::Packets {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                })
+                .title(title),
+        )
+        .widths(&[
+            Constraint::Length(7),
+            Constraint::Length(12),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Percentage(100),
+        ])
         .highlight_style(
             Style::default()
                 .bg(Color::LightGreen)
@@ -194,51 +989,76 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         )
         .highlight_symbol(">> ");
 
-    // We can now render the item list
-    f.render_stateful_widget(items, chunks[0], &mut app.items.state);
+    let mut state = TableState::default();
+    state.select(app.items.selected.map(|selected| selected - visible.start));
+    f.render_stateful_widget(table, chunks[0], &mut state);
 
-    // Let's do the same for the events.
-    // The event list doesn't have any state and only displays the current state of the list.
-    let events: Vec<ListItem> = app
-        .events
-        .iter()
-        .rev()
-        .map(|&(event, level)| {
-            // Colorcode the level depending on its type
-            let s = match level {
-                "CRITICAL" => Style::default().fg(Color::Red),
-                "ERROR" => Style::default().fg(Color::Magenta),
-                "WARNING" => Style::default().fg(Color::Yellow),
-                "INFO" => Style::default().fg(Color::Blue),
-                _ => Style::default(),
+    // Protocol tree next to the bytes it was decoded from, the dump is 72 columns wide
+    let details = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(74)].as_ref())
+        .split(chunks[1]);
+
+    let layers = app.selected_details();
+    app.tree
+        .render(f, details[0], &layers, app.focus == Focus::Details);
+
+    let highlight = match app.focus {
+        Focus::Details => app.tree.selected_range(&layers),
+        Focus::Packets => None,
+    };
+    let data = app
+        .selected_packet()
+        .map_or(&[][..], ParsedPacket::get_data);
+    hexdump::render(f, details[1], data, highlight);
+
+    render_filter_bar(f, app, rows[1]);
+}
+
+/// Filter bar: green while the typed filter is valid, red with the offending text highlighted otherwise
+fn render_filter_bar<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let input = &app.filter_input;
+    let (color, title) = match (&input.error, input.editing) {
+        (Some(err), _) => (Color::Red, format!("Filter: {}", err)),
+        (None, true) => (
+            Color::Green,
+            "Filter (Enter to apply, Esc to cancel)".to_string(),
+        ),
+        (None, false) => (Color::White, "Filter (/ to edit)".to_string()),
+    };
+
+    let text = match &input.error {
+        Some(err) => {
+            let start = err.span.start.min(input.text.len());
+            let end = err.span.end.clamp(start, input.text.len());
+            let error_style = Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD);
+            // Errors at the end of the input have an empty span, mark the missing part instead
+            let marker = if start == end {
+                " "
+            } else {
+                &input.text[start..end]
             };
-            // Add a example datetime and apply proper spacing between them
-            let header = Spans::from(vec![
-                Span::styled(format!("{:<9}", level), s),
-                Span::raw(" "),
-                Span::styled(
-                    "2020-01-01 10:00:00",
-                    Style::default().add_modifier(Modifier::ITALIC),
-                ),
-            ]);
-            // The event gets its own line
-            let log = Spans::from(vec![Span::raw(event)]);
-
-            // Here several things happen:
-            // 1. Add a `---` spacing line above the final list entry
-            // 2. Add the Level + datetime
-            // 3. Add a spacer line
-            // 4. Add the actual event
-            ListItem::new(vec![
-                Spans::from("-".repeat(chunks[1].width as usize)),
-                header,
-                Spans::from(""),
-                log,
+            Spans::from(vec![
+                Span::raw(&input.text[..start]),
+                Span::styled(marker, error_style),
+                Span::raw(&input.text[end..]),
             ])
-        })
-        .collect();
-    let events_list = List::new(events)
-        .block(Block::default().borders(Borders::ALL).title("List"))
-        .start_corner(Corner::BottomLeft);
-    f.render_widget(events_list, chunks[1]);
+        }
+        None => Spans::from(input.text.as_str()),
+    };
+
+    let bar = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color))
+            .title(title),
+    );
+    f.render_widget(bar, area);
+
+    if input.editing {
+        f.set_cursor(area.x + 1 + input.cursor as u16, area.y + 1);
+    }
 }
@@ -0,0 +1,884 @@
+use std::net::IpAddr;
+
+use super::{FilterDirection, FilterExpression, FilterPrimitive, FilterProtocol};
+use crate::parser::{capture::LinkType, ParserError};
+
+// Instruction classes
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Load sizes
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+
+// Load modes
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+const BPF_MSH: u16 = 0xa0;
+
+// ALU and jump operations
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Operand sources
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+const BPF_MEMWORDS: usize = 16;
+/// Longest program the kernel accepts
+const BPF_MAXINSNS: usize = 4096;
+
+/// Number of bytes accepted frames are truncated to
+const SNAPLEN: u32 = 262_144;
+
+const ETHERTYPE_IPV4: u32 = 0x0800;
+const ETHERTYPE_IPV6: u32 = 0x86dd;
+const ETHERTYPE_ARP: u32 = 0x0806;
+
+/// A classic BPF instruction, laid out like the kernel's `struct sock_filter`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BpfInstruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+/// A compiled classic BPF program
+#[derive(Clone, Debug)]
+pub struct BpfProgram {
+    instructions: Vec<BpfInstruction>,
+}
+
+impl BpfProgram {
+    /// Compile a filter expression for frames of `link_type`
+    pub fn compile(
+        expression: &FilterExpression,
+        link_type: LinkType,
+    ) -> Result<Self, ParserError> {
+        let layout = LinkLayout::of(link_type)?;
+        let code = lower(expression, &layout)?;
+
+        let mut compiler = Compiler::default();
+        let accept = compiler.label();
+        let reject = compiler.label();
+        compiler.compile(&code, accept, reject);
+        compiler.place(accept);
+        compiler.emit(BPF_RET | BPF_K, SNAPLEN, Target::Next, Target::Next);
+        compiler.place(reject);
+        compiler.emit(BPF_RET | BPF_K, 0, Target::Next, Target::Next);
+
+        Ok(BpfProgram {
+            instructions: compiler.resolve()?,
+        })
+    }
+
+    /// Get instructions in the layout expected by `SO_ATTACH_FILTER`
+    pub fn instructions(&self) -> &[BpfInstruction] {
+        &self.instructions
+    }
+
+    /// Run the program over a frame, returning the number of bytes to keep (0 drops the frame)
+    pub fn run(&self, frame: &[u8]) -> u32 {
+        let mut a: u32 = 0;
+        let mut x: u32 = 0;
+        let mut memory = [0u32; BPF_MEMWORDS];
+        let mut pc = 0;
+
+        let load = |offset: u32, size: u16| -> Option<u32> {
+            let offset = offset as usize;
+            let bytes = match size {
+                BPF_W => frame.get(offset..offset.checked_add(4)?)?,
+                BPF_H => frame.get(offset..offset.checked_add(2)?)?,
+                _ => frame.get(offset..offset.checked_add(1)?)?,
+            };
+            Some(
+                bytes
+                    .iter()
+                    .fold(0u32, |value, &byte| value << 8 | byte as u32),
+            )
+        };
+
+        while let Some(instruction) = self.instructions.get(pc) {
+            let BpfInstruction { code, jt, jf, k } = *instruction;
+            pc += 1;
+
+            match code & 0x07 {
+                BPF_LD => {
+                    a = match code & 0xe0 {
+                        BPF_IMM => k,
+                        BPF_ABS => match load(k, code & 0x18) {
+                            Some(value) => value,
+                            None => return 0,
+                        },
+                        BPF_IND => match load(x.wrapping_add(k), code & 0x18) {
+                            Some(value) => value,
+                            None => return 0,
+                        },
+                        BPF_MEM => memory[k as usize % BPF_MEMWORDS],
+                        BPF_LEN => frame.len() as u32,
+                        _ => return 0,
+                    }
+                }
+                BPF_LDX => {
+                    x = match code & 0xe0 {
+                        BPF_IMM => k,
+                        BPF_MEM => memory[k as usize % BPF_MEMWORDS],
+                        BPF_LEN => frame.len() as u32,
+                        BPF_MSH => match load(k, BPF_B) {
+                            Some(value) => (value & 0x0f) * 4,
+                            None => return 0,
+                        },
+                        _ => return 0,
+                    }
+                }
+                BPF_ST => memory[k as usize % BPF_MEMWORDS] = a,
+                BPF_STX => memory[k as usize % BPF_MEMWORDS] = x,
+                BPF_ALU => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    a = match code & 0xf0 {
+                        BPF_ADD => a.wrapping_add(operand),
+                        BPF_SUB => a.wrapping_sub(operand),
+                        BPF_MUL => a.wrapping_mul(operand),
+                        BPF_DIV if operand == 0 => return 0,
+                        BPF_DIV => a / operand,
+                        BPF_MOD if operand == 0 => return 0,
+                        BPF_MOD => a % operand,
+                        BPF_OR => a | operand,
+                        BPF_AND => a & operand,
+                        BPF_XOR => a ^ operand,
+                        BPF_LSH => a.checked_shl(operand).unwrap_or(0),
+                        BPF_RSH => a.checked_shr(operand).unwrap_or(0),
+                        BPF_NEG => a.wrapping_neg(),
+                        _ => return 0,
+                    }
+                }
+                BPF_JMP => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    let taken = match code & 0xf0 {
+                        BPF_JA => {
+                            pc += k as usize;
+                            continue;
+                        }
+                        BPF_JEQ => a == operand,
+                        BPF_JGT => a > operand,
+                        BPF_JGE => a >= operand,
+                        BPF_JSET => a & operand != 0,
+                        _ => return 0,
+                    };
+                    pc += if taken { jt as usize } else { jf as usize };
+                }
+                BPF_RET => {
+                    return match code & 0x18 {
+                        BPF_A => a,
+                        _ => k,
+                    }
+                }
+                BPF_MISC => match code & 0xf8 {
+                    BPF_TAX => x = a,
+                    BPF_TXA => a = x,
+                    _ => return 0,
+                },
+                _ => return 0,
+            }
+        }
+
+        0
+    }
+}
+
+/// Where the headers the filter looks at live for a given link type
+struct LinkLayout {
+    /// Offset of the EtherType field, `None` for raw IP links
+    ethertype: Option<u32>,
+    /// Offset of the network-layer header
+    network: u32,
+    has_ethernet_addresses: bool,
+}
+
+impl LinkLayout {
+    fn of(link_type: LinkType) -> Result<Self, ParserError> {
+        match link_type {
+            LinkType::ETHERNET => Ok(LinkLayout {
+                ethertype: Some(12),
+                network: 14,
+                has_ethernet_addresses: true,
+            }),
+            LinkType::LINUX_SLL => Ok(LinkLayout {
+                ethertype: Some(14),
+                network: 16,
+                has_ethernet_addresses: false,
+            }),
+            LinkType::RAW | LinkType::IPV4 | LinkType::IPV6 => Ok(LinkLayout {
+                ethertype: None,
+                network: 0,
+                has_ethernet_addresses: false,
+            }),
+            LinkType(link_type) => Err(ParserError::UnsupportedLinkType(link_type)),
+        }
+    }
+
+    fn is_ipv4(&self) -> Code {
+        match self.ethertype {
+            Some(offset) => equal(BPF_H, offset, ETHERTYPE_IPV4),
+            None => masked(BPF_B, 0, 0xf0, 0x40),
+        }
+    }
+
+    fn is_ipv6(&self) -> Code {
+        match self.ethertype {
+            Some(offset) => equal(BPF_H, offset, ETHERTYPE_IPV6),
+            None => masked(BPF_B, 0, 0xf0, 0x60),
+        }
+    }
+
+    fn is_arp(&self) -> Code {
+        match self.ethertype {
+            Some(offset) => equal(BPF_H, offset, ETHERTYPE_ARP),
+            None => Code::Never,
+        }
+    }
+}
+
+/// A value loaded into the accumulator before a comparison
+#[derive(Clone, Copy)]
+enum Load {
+    Absolute(u16, u32),
+    /// Relative to the index register, which must have been set by `Code::LoadHeaderLength`
+    Indirect(u16, u32),
+    Length,
+}
+
+#[derive(Clone, Copy)]
+enum Comparison {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Filter lowered to loads and comparisons, but not yet laid out as jumps
+enum Code {
+    All(Vec<Code>),
+    Any(Vec<Code>),
+    Not(Box<Code>),
+    Test(Load, Option<u32>, Comparison, u32),
+    /// Load an IPv4 header length at the given offset into the index register
+    LoadHeaderLength(u32),
+    Never,
+}
+
+fn equal(size: u16, offset: u32, value: u32) -> Code {
+    Code::Test(Load::Absolute(size, offset), None, Comparison::Equal, value)
+}
+
+fn masked(size: u16, offset: u32, mask: u32, value: u32) -> Code {
+    Code::Test(
+        Load::Absolute(size, offset),
+        Some(mask),
+        Comparison::Equal,
+        value,
+    )
+}
+
+fn directed(direction: FilterDirection, source: Code, destination: Code) -> Code {
+    match direction {
+        FilterDirection::Src => source,
+        FilterDirection::Dst => destination,
+        FilterDirection::SrcOrDst => Code::Any(vec![source, destination]),
+    }
+}
+
+fn lower(expression: &FilterExpression, layout: &LinkLayout) -> Result<Code, ParserError> {
+    Ok(match expression {
+        FilterExpression::And(left, right) => {
+            Code::All(vec![lower(left, layout)?, lower(right, layout)?])
+        }
+        FilterExpression::Or(left, right) => {
+            Code::Any(vec![lower(left, layout)?, lower(right, layout)?])
+        }
+        FilterExpression::Not(inner) => Code::Not(Box::new(lower(inner, layout)?)),
+        FilterExpression::Primitive(primitive) => lower_primitive(primitive, layout)?,
+    })
+}
+
+fn lower_primitive(primitive: &FilterPrimitive, layout: &LinkLayout) -> Result<Code, ParserError> {
+    let network = layout.network;
+    let ipv4_protocol =
+        |protocol| Code::All(vec![layout.is_ipv4(), equal(BPF_B, network + 9, protocol)]);
+    let ipv6_protocol =
+        |protocol| Code::All(vec![layout.is_ipv6(), equal(BPF_B, network + 6, protocol)]);
+
+    Ok(match *primitive {
+        FilterPrimitive::Protocol(protocol) => match protocol {
+            FilterProtocol::Ether => {
+                return Err(ParserError::InvalidFilter(
+                    "'ether' needs a qualifier".to_string(),
+                ))
+            }
+            FilterProtocol::Ip => layout.is_ipv4(),
+            FilterProtocol::Ip6 => layout.is_ipv6(),
+            FilterProtocol::Arp => layout.is_arp(),
+            FilterProtocol::Tcp => Code::Any(vec![ipv4_protocol(6), ipv6_protocol(6)]),
+            FilterProtocol::Udp => Code::Any(vec![ipv4_protocol(17), ipv6_protocol(17)]),
+            FilterProtocol::Icmp => ipv4_protocol(1),
+            FilterProtocol::Icmp6 => ipv6_protocol(58),
+        },
+        FilterPrimitive::Host(protocol, direction, address) => {
+            lower_net(protocol, direction, address, None, layout)
+        }
+        FilterPrimitive::Net(protocol, direction, address, prefix) => {
+            lower_net(protocol, direction, address, Some(prefix), layout)
+        }
+        FilterPrimitive::EtherHost(direction, address) => {
+            if !layout.has_ethernet_addresses {
+                return Err(ParserError::InvalidFilter(
+                    "'ether' is not available on this link type".to_string(),
+                ));
+            }
+            let octets = address.octets();
+            let address = |offset| {
+                Code::All(vec![
+                    equal(
+                        BPF_W,
+                        offset,
+                        u32::from_be_bytes([octets[0], octets[1], octets[2], octets[3]]),
+                    ),
+                    equal(
+                        BPF_H,
+                        offset + 4,
+                        u16::from_be_bytes([octets[4], octets[5]]) as u32,
+                    ),
+                ])
+            };
+            directed(direction, address(6), address(0))
+        }
+        FilterPrimitive::Port(protocol, direction, low, high) => {
+            let protocols: &[u32] = match protocol {
+                Some(FilterProtocol::Tcp) => &[6],
+                Some(FilterProtocol::Udp) => &[17],
+                _ => &[6, 17],
+            };
+            let in_range = |load| {
+                if low == high {
+                    Code::Test(load, None, Comparison::Equal, low as u32)
+                } else {
+                    Code::All(vec![
+                        Code::Test(load, None, Comparison::GreaterOrEqual, low as u32),
+                        Code::Not(Box::new(Code::Test(
+                            load,
+                            None,
+                            Comparison::Greater,
+                            high as u32,
+                        ))),
+                    ])
+                }
+            };
+
+            let ipv4 = Code::All(vec![
+                layout.is_ipv4(),
+                Code::Any(
+                    protocols
+                        .iter()
+                        .map(|&p| equal(BPF_B, network + 9, p))
+                        .collect(),
+                ),
+                // Only the first fragment carries the transport header
+                masked(BPF_H, network + 6, 0x1fff, 0),
+                Code::LoadHeaderLength(network),
+                directed(
+                    direction,
+                    in_range(Load::Indirect(BPF_H, network)),
+                    in_range(Load::Indirect(BPF_H, network + 2)),
+                ),
+            ]);
+            let ipv6 = Code::All(vec![
+                layout.is_ipv6(),
+                Code::Any(
+                    protocols
+                        .iter()
+                        .map(|&p| equal(BPF_B, network + 6, p))
+                        .collect(),
+                ),
+                directed(
+                    direction,
+                    in_range(Load::Absolute(BPF_H, network + 40)),
+                    in_range(Load::Absolute(BPF_H, network + 42)),
+                ),
+            ]);
+            Code::Any(vec![ipv4, ipv6])
+        }
+        FilterPrimitive::Less(length) => Code::Not(Box::new(Code::Test(
+            Load::Length,
+            None,
+            Comparison::Greater,
+            length,
+        ))),
+        FilterPrimitive::Greater(length) => {
+            Code::Test(Load::Length, None, Comparison::GreaterOrEqual, length)
+        }
+    })
+}
+
+/// Host or network match, `prefix` of `None` compares the whole address
+fn lower_net(
+    protocol: Option<FilterProtocol>,
+    direction: FilterDirection,
+    address: IpAddr,
+    prefix: Option<u8>,
+    layout: &LinkLayout,
+) -> Code {
+    let network = layout.network;
+    match address {
+        IpAddr::V4(address) => {
+            let prefix = prefix.unwrap_or(32) as u32;
+            let mask = if prefix == 0 {
+                0
+            } else {
+                u32::MAX << (32 - prefix)
+            };
+            let address = u32::from(address) & mask;
+            let test = |offset| match mask {
+                u32::MAX => equal(BPF_W, offset, address),
+                _ => masked(BPF_W, offset, mask, address),
+            };
+
+            let ipv4 = Code::All(vec![
+                layout.is_ipv4(),
+                directed(direction, test(network + 12), test(network + 16)),
+            ]);
+            // ARP sender and target protocol addresses, only for whole hosts
+            let arp = match prefix {
+                32 => Code::All(vec![
+                    layout.is_arp(),
+                    directed(direction, test(network + 14), test(network + 24)),
+                ]),
+                _ => Code::Never,
+            };
+
+            match protocol {
+                Some(FilterProtocol::Ip) => ipv4,
+                Some(FilterProtocol::Arp) => arp,
+                _ => Code::Any(vec![ipv4, arp]),
+            }
+        }
+        IpAddr::V6(address) => {
+            let prefix = prefix.unwrap_or(128) as u32;
+            let words: Vec<(u32, u32)> = address
+                .octets()
+                .chunks(4)
+                .enumerate()
+                .map(|(i, word)| {
+                    let bits = prefix.saturating_sub(i as u32 * 32).min(32);
+                    let mask = if bits == 0 {
+                        0
+                    } else {
+                        u32::MAX << (32 - bits)
+                    };
+                    (
+                        u32::from_be_bytes([word[0], word[1], word[2], word[3]]) & mask,
+                        mask,
+                    )
+                })
+                .collect();
+            let test = |offset: u32| {
+                Code::All(
+                    words
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &(_, mask))| mask != 0)
+                        .map(|(i, &(word, mask))| match mask {
+                            u32::MAX => equal(BPF_W, offset + i as u32 * 4, word),
+                            _ => masked(BPF_W, offset + i as u32 * 4, mask, word),
+                        })
+                        .collect(),
+                )
+            };
+
+            Code::All(vec![
+                layout.is_ipv6(),
+                directed(direction, test(network + 8), test(network + 24)),
+            ])
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Next,
+    Label(usize),
+}
+
+struct Pending {
+    code: u16,
+    k: u32,
+    jt: Target,
+    jf: Target,
+}
+
+/// Lays out lowered code as instructions with symbolic jump targets
+#[derive(Default)]
+struct Compiler {
+    pending: Vec<Pending>,
+    labels: Vec<Option<usize>>,
+}
+
+impl Compiler {
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: usize) {
+        self.labels[label] = Some(self.pending.len());
+    }
+
+    fn emit(&mut self, code: u16, k: u32, jt: Target, jf: Target) {
+        self.pending.push(Pending { code, k, jt, jf });
+    }
+
+    /// Emit `code`, continuing at `success` when it holds and at `failure` otherwise
+    fn compile(&mut self, code: &Code, success: usize, failure: usize) {
+        match code {
+            Code::All(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i + 1 == items.len() {
+                        self.compile(item, success, failure);
+                    } else {
+                        let next = self.label();
+                        self.compile(item, next, failure);
+                        self.place(next);
+                    }
+                }
+            }
+            Code::Any(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i + 1 == items.len() {
+                        self.compile(item, success, failure);
+                    } else {
+                        let next = self.label();
+                        self.compile(item, success, next);
+                        self.place(next);
+                    }
+                }
+            }
+            Code::Not(inner) => self.compile(inner, failure, success),
+            Code::Test(load, mask, comparison, value) => {
+                match *load {
+                    Load::Absolute(size, offset) => {
+                        self.emit(BPF_LD | size | BPF_ABS, offset, Target::Next, Target::Next)
+                    }
+                    Load::Indirect(size, offset) => {
+                        self.emit(BPF_LD | size | BPF_IND, offset, Target::Next, Target::Next)
+                    }
+                    Load::Length => {
+                        self.emit(BPF_LD | BPF_W | BPF_LEN, 0, Target::Next, Target::Next)
+                    }
+                }
+                if let Some(mask) = mask {
+                    self.emit(BPF_ALU | BPF_AND | BPF_K, *mask, Target::Next, Target::Next);
+                }
+                let operation = match comparison {
+                    Comparison::Equal => BPF_JEQ,
+                    Comparison::Greater => BPF_JGT,
+                    Comparison::GreaterOrEqual => BPF_JGE,
+                };
+                self.emit(
+                    BPF_JMP | operation | BPF_K,
+                    *value,
+                    Target::Label(success),
+                    Target::Label(failure),
+                );
+            }
+            Code::LoadHeaderLength(offset) => {
+                self.emit(
+                    BPF_LDX | BPF_B | BPF_MSH,
+                    *offset,
+                    Target::Next,
+                    Target::Next,
+                );
+                self.emit(BPF_JMP | BPF_JA, 0, Target::Label(success), Target::Next);
+            }
+            Code::Never => self.emit(BPF_JMP | BPF_JA, 0, Target::Label(failure), Target::Next),
+        }
+    }
+
+    /// Replace labels by relative jump offsets
+    fn resolve(self) -> Result<Vec<BpfInstruction>, ParserError> {
+        if self.pending.len() > BPF_MAXINSNS {
+            return Err(ParserError::InvalidFilter(format!(
+                "filter compiles to {} instructions, at most {} are allowed",
+                self.pending.len(),
+                BPF_MAXINSNS
+            )));
+        }
+        let labels = self.labels;
+        let offset = |target: Target, from: usize| -> Result<u32, ParserError> {
+            match target {
+                Target::Next => Ok(0),
+                Target::Label(label) => {
+                    let destination = labels[label].expect("jump to an unplaced label");
+                    Ok((destination - from - 1) as u32)
+                }
+            }
+        };
+
+        self.pending
+            .iter()
+            .enumerate()
+            .map(|(i, pending)| {
+                if pending.code == BPF_JMP | BPF_JA {
+                    return Ok(BpfInstruction {
+                        code: pending.code,
+                        jt: 0,
+                        jf: 0,
+                        k: offset(pending.jt, i)?,
+                    });
+                }
+
+                let jt = offset(pending.jt, i)?;
+                let jf = offset(pending.jf, i)?;
+                if jt > u8::MAX as u32 || jf > u8::MAX as u32 {
+                    return Err(ParserError::InvalidFilter(
+                        "filter is too complex".to_string(),
+                    ));
+                }
+                Ok(BpfInstruction {
+                    code: pending.code,
+                    jt: jt as u8,
+                    jf: jf as u8,
+                    k: pending.k,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::capture::CaptureFilter;
+
+    fn instruction(code: u16, jt: u8, jf: u8, k: u32) -> BpfInstruction {
+        BpfInstruction { code, jt, jf, k }
+    }
+
+    /// Ethernet and IPv4 carrying `protocol` from 10.0.0.1:`source` to 192.168.1.20:`destination`,
+    /// `options` words of IP options and `fragment` as flags and fragment offset
+    fn ipv4(protocol: u8, source: u16, destination: u16, options: usize, fragment: u16) -> Vec<u8> {
+        let mut frame = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+        ];
+        frame.extend([0x08, 0x00, 0x45 + options as u8, 0, 0, 0, 0, 0]);
+        frame.extend(fragment.to_be_bytes());
+        frame.extend([64, protocol, 0, 0, 10, 0, 0, 1, 192, 168, 1, 20]);
+        frame.extend(vec![1; options * 4]);
+        frame.extend(source.to_be_bytes());
+        frame.extend(destination.to_be_bytes());
+        frame.extend([0; 16]);
+        frame
+    }
+
+    /// Ethernet and IPv6 carrying UDP from 2001:db8::1:`source` to 2001:db8:1::2:`destination`
+    fn ipv6_udp(source: u16, destination: u16) -> Vec<u8> {
+        let mut frame = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+        ];
+        frame.extend([0x86, 0xdd, 0x60, 0, 0, 0, 0, 8, 17, 64]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        frame.extend(source.to_be_bytes());
+        frame.extend(destination.to_be_bytes());
+        frame.extend([0; 4]);
+        frame
+    }
+
+    fn matches(filter: &str, link_type: LinkType, frame: &[u8]) -> bool {
+        CaptureFilter::parse(filter)
+            .unwrap()
+            .matches(link_type, frame)
+    }
+
+    #[test]
+    fn interpreter_runs_loads_and_jumps() {
+        // Accept frames whose byte 1 is 0x22, keeping the whole frame
+        let program = BpfProgram {
+            instructions: vec![
+                instruction(BPF_LD | BPF_B | BPF_ABS, 0, 0, 1),
+                instruction(BPF_JMP | BPF_JEQ | BPF_K, 0, 1, 0x22),
+                instruction(BPF_RET | BPF_K, 0, 0, 100),
+                instruction(BPF_RET | BPF_K, 0, 0, 0),
+            ],
+        };
+        assert_eq!(program.run(&[0x11, 0x22]), 100);
+        assert_eq!(program.run(&[0x11, 0x33]), 0);
+        // Loads past the end of the frame drop it
+        assert_eq!(program.run(&[0x11]), 0);
+    }
+
+    #[test]
+    fn interpreter_computes_with_registers() {
+        // A = (len * 3 + X) / 2 with X = 4 * low nibble of byte 0, from scratch memory
+        let program = BpfProgram {
+            instructions: vec![
+                instruction(BPF_LDX | BPF_B | BPF_MSH, 0, 0, 0),
+                instruction(BPF_STX, 0, 0, 5),
+                instruction(BPF_LD | BPF_W | BPF_LEN, 0, 0, 0),
+                instruction(BPF_ALU | BPF_MUL | BPF_K, 0, 0, 3),
+                instruction(BPF_LDX | BPF_MEM, 0, 0, 5),
+                instruction(BPF_ALU | BPF_ADD | BPF_X, 0, 0, 0),
+                instruction(BPF_ALU | BPF_DIV | BPF_K, 0, 0, 2),
+                instruction(BPF_RET | BPF_A, 0, 0, 0),
+            ],
+        };
+        assert_eq!(program.run(&[0x45, 0, 0, 0]), (4 * 3 + 20) / 2);
+
+        // Division by zero drops the frame
+        let program = BpfProgram {
+            instructions: vec![
+                instruction(BPF_LD | BPF_IMM, 0, 0, 7),
+                instruction(BPF_LDX | BPF_IMM, 0, 0, 0),
+                instruction(BPF_ALU | BPF_DIV | BPF_X, 0, 0, 0),
+                instruction(BPF_RET | BPF_K, 0, 0, 1),
+            ],
+        };
+        assert_eq!(program.run(&[]), 0);
+    }
+
+    #[test]
+    fn programs_end_with_accept_and_reject() {
+        let filter = CaptureFilter::parse("tcp").unwrap();
+        let program = filter.compile(LinkType::ETHERNET).unwrap();
+        let instructions = program.instructions();
+        assert_eq!(
+            instructions[instructions.len() - 2],
+            instruction(BPF_RET, 0, 0, SNAPLEN)
+        );
+        assert_eq!(
+            instructions[instructions.len() - 1],
+            instruction(BPF_RET, 0, 0, 0)
+        );
+        assert!(matches!(
+            filter.compile(LinkType(147)),
+            Err(ParserError::UnsupportedLinkType(147))
+        ));
+    }
+
+    #[test]
+    fn rejects_programs_the_kernel_would_refuse() {
+        let program = |length| {
+            let mut compiler = Compiler::default();
+            for _ in 0..length {
+                compiler.emit(BPF_RET | BPF_K, 0, Target::Next, Target::Next);
+            }
+            compiler.resolve()
+        };
+        assert_eq!(program(BPF_MAXINSNS).unwrap().len(), BPF_MAXINSNS);
+        assert!(matches!(
+            program(BPF_MAXINSNS + 1),
+            Err(ParserError::InvalidFilter(_))
+        ));
+
+        // Jumps over more than 255 instructions don't fit a conditional jump
+        let hosts: Vec<String> = (0..100).map(|i| format!("host 10.0.0.{}", i)).collect();
+        let filter = CaptureFilter::parse(&hosts.join(" or ")).unwrap();
+        assert!(matches!(
+            filter.compile(LinkType::ETHERNET),
+            Err(ParserError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn matches_protocols_and_hosts() {
+        let tcp = ipv4(6, 51514, 443, 0, 0);
+        assert!(matches("tcp", LinkType::ETHERNET, &tcp));
+        assert!(matches("ip", LinkType::ETHERNET, &tcp));
+        assert!(!matches("udp or ip6 or arp", LinkType::ETHERNET, &tcp));
+        assert!(matches("host 10.0.0.1", LinkType::ETHERNET, &tcp));
+        assert!(matches("src host 10.0.0.1", LinkType::ETHERNET, &tcp));
+        assert!(!matches("dst host 10.0.0.1", LinkType::ETHERNET, &tcp));
+        assert!(matches("dst net 192.168.0.0/16", LinkType::ETHERNET, &tcp));
+        assert!(matches("net 192.168", LinkType::ETHERNET, &tcp));
+        assert!(!matches("net 192.168.2.0/24", LinkType::ETHERNET, &tcp));
+        assert!(matches(
+            "ether src 66:77:88:99:aa:bb",
+            LinkType::ETHERNET,
+            &tcp
+        ));
+        assert!(!matches(
+            "ether host 66:77:88:99:aa:00",
+            LinkType::ETHERNET,
+            &tcp
+        ));
+        assert!(matches("less 100 and greater 40", LinkType::ETHERNET, &tcp));
+        assert!(!matches("less 40", LinkType::ETHERNET, &tcp));
+
+        // Raw IP frames start with the IP header
+        assert!(matches("tcp and host 10.0.0.1", LinkType::RAW, &tcp[14..]));
+        assert!(!matches("ip6", LinkType::RAW, &tcp[14..]));
+    }
+
+    #[test]
+    fn matches_ports() {
+        let tcp = ipv4(6, 51514, 443, 0, 0);
+        assert!(matches("port 443", LinkType::ETHERNET, &tcp));
+        assert!(matches("tcp dst port https", LinkType::ETHERNET, &tcp));
+        assert!(!matches("tcp src port 443", LinkType::ETHERNET, &tcp));
+        assert!(!matches("udp port 443", LinkType::ETHERNET, &tcp));
+        assert!(matches("portrange 50000-52000", LinkType::ETHERNET, &tcp));
+        assert!(!matches("portrange 444-50000", LinkType::ETHERNET, &tcp));
+        assert!(matches("tcp and not port 22", LinkType::ETHERNET, &tcp));
+
+        // Ports follow IP options
+        assert!(matches(
+            "dst port 443",
+            LinkType::ETHERNET,
+            &ipv4(6, 51514, 443, 2, 0)
+        ));
+        // Later fragments carry no transport header
+        assert!(!matches(
+            "port 443",
+            LinkType::ETHERNET,
+            &ipv4(6, 51514, 443, 0, 0x0010)
+        ));
+
+        let udp = ipv6_udp(5353, 53);
+        assert!(matches("ip6 and udp dst port 53", LinkType::ETHERNET, &udp));
+        assert!(matches("src host 2001:db8::1", LinkType::ETHERNET, &udp));
+        assert!(matches("dst net 2001:db8:1::/48", LinkType::ETHERNET, &udp));
+        assert!(!matches(
+            "src net 2001:db8:1::/48",
+            LinkType::ETHERNET,
+            &udp
+        ));
+    }
+}
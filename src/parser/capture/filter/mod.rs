@@ -0,0 +1,554 @@
+mod bpf;
+
+pub use bpf::{BpfInstruction, BpfProgram};
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+};
+
+use pnet::util::MacAddr;
+
+use super::LinkType;
+use crate::parser::ParserError;
+
+/// Depth of operators a filter may nest, counting each `and`/`or` of a chain as one level as
+/// the expression tree does. Parsing and compiling recurse once per level.
+const MAX_NESTING: usize = 256;
+
+/// Protocol qualifier of a filter primitive, e.g. `tcp` in `tcp port 443`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterProtocol {
+    Ether,
+    Ip,
+    Ip6,
+    Arp,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6,
+}
+
+/// Direction qualifier of a filter primitive, e.g. `src` in `src host 10.0.0.1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterDirection {
+    SrcOrDst,
+    Src,
+    Dst,
+}
+
+/// What a filter primitive tests
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterPrimitive {
+    /// Packet carries the given protocol
+    Protocol(FilterProtocol),
+    Host(Option<FilterProtocol>, FilterDirection, IpAddr),
+    EtherHost(FilterDirection, MacAddr),
+    Net(Option<FilterProtocol>, FilterDirection, IpAddr, u8),
+    Port(Option<FilterProtocol>, FilterDirection, u16, u16),
+    /// Frame length is at most the given number of bytes
+    Less(u32),
+    /// Frame length is at least the given number of bytes
+    Greater(u32),
+}
+
+/// Boolean expression over filter primitives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterExpression {
+    And(Box<FilterExpression>, Box<FilterExpression>),
+    Or(Box<FilterExpression>, Box<FilterExpression>),
+    Not(Box<FilterExpression>),
+    Primitive(FilterPrimitive),
+}
+
+/// A tcpdump-style capture filter, e.g. `tcp port 443 and host 10.0.0.1`
+///
+/// The expression is compiled into classic BPF once per link type. Live captures attach the
+/// program to the socket, capture files run it through the userspace interpreter.
+pub struct CaptureFilter {
+    source: String,
+    expression: FilterExpression,
+    programs: HashMap<LinkType, Option<BpfProgram>>,
+}
+
+impl CaptureFilter {
+    /// Parse a filter expression
+    pub fn parse(source: &str) -> Result<Self, ParserError> {
+        let tokens = tokenize(source)?;
+        let mut parser = FilterParser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let expression = parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(format!("unexpected '{}'", token)));
+        }
+
+        Ok(CaptureFilter {
+            source: source.to_string(),
+            expression,
+            programs: HashMap::new(),
+        })
+    }
+
+    /// Get the expression as written by the user
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the parsed expression
+    pub fn expression(&self) -> &FilterExpression {
+        &self.expression
+    }
+
+    /// Compile the filter for frames of `link_type`
+    pub fn compile(&self, link_type: LinkType) -> Result<BpfProgram, ParserError> {
+        BpfProgram::compile(&self.expression, link_type)
+    }
+
+    /// Compile the filter for frames of `link_type` ahead of matching them, failing if it
+    /// cannot be
+    pub fn prepare(&mut self, link_type: LinkType) -> Result<(), ParserError> {
+        let program = self.compile(link_type)?;
+        self.programs.insert(link_type, Some(program));
+        Ok(())
+    }
+
+    /// Whether a frame of `link_type` passes the filter
+    ///
+    /// Frames of link types the filter cannot be compiled for never match.
+    pub fn matches(&mut self, link_type: LinkType, frame: &[u8]) -> bool {
+        let expression = &self.expression;
+        self.programs
+            .entry(link_type)
+            .or_insert_with(|| BpfProgram::compile(expression, link_type).ok())
+            .as_ref()
+            .is_some_and(|program| program.run(frame) != 0)
+    }
+}
+
+fn invalid(reason: String) -> ParserError {
+    ParserError::InvalidFilter(reason)
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, ParserError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '!' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    return Err(invalid(format!("expected '{}{}'", c, c)));
+                }
+                tokens.push(format!("{}{}", c, c));
+            }
+            c if c.is_ascii_alphanumeric() || "._:/-".contains(c) => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || "._:/-".contains(c)) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+            c => return Err(invalid(format!("unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct FilterParser {
+    tokens: Vec<String>,
+    position: usize,
+    /// Operators enclosing the expression being parsed
+    depth: usize,
+}
+
+impl FilterParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn advance(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, alternatives: &[&str]) -> bool {
+        if self
+            .peek()
+            .is_some_and(|token| alternatives.contains(&token))
+        {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expression(&mut self) -> Result<FilterExpression, ParserError> {
+        let depth = self.depth;
+        let mut left = self.term()?;
+        while self.accept(&["or", "||"]) {
+            self.deeper()?;
+            left = FilterExpression::Or(Box::new(left), Box::new(self.term()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<FilterExpression, ParserError> {
+        let depth = self.depth;
+        let mut left = self.factor()?;
+        while self.accept(&["and", "&&"]) {
+            self.deeper()?;
+            left = FilterExpression::And(Box::new(left), Box::new(self.factor()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<FilterExpression, ParserError> {
+        let depth = self.depth;
+        let factor = if self.accept(&["not", "!"]) {
+            self.deeper()?;
+            FilterExpression::Not(Box::new(self.factor()?))
+        } else if self.accept(&["("]) {
+            self.deeper()?;
+            let expression = self.expression()?;
+            if !self.accept(&[")"]) {
+                return Err(invalid("missing ')'".to_string()));
+            }
+            expression
+        } else {
+            FilterExpression::Primitive(self.primitive()?)
+        };
+        self.depth = depth;
+        Ok(factor)
+    }
+
+    /// Go one operator deeper, failing past `MAX_NESTING`
+    fn deeper(&mut self) -> Result<(), ParserError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(invalid("filter nested too deeply".to_string()));
+        }
+        Ok(())
+    }
+
+    fn primitive(&mut self) -> Result<FilterPrimitive, ParserError> {
+        if self.accept(&["less"]) {
+            return Ok(FilterPrimitive::Less(self.number()?));
+        }
+        if self.accept(&["greater"]) {
+            return Ok(FilterPrimitive::Greater(self.number()?));
+        }
+
+        let protocol = match self.peek() {
+            Some("ether") => Some(FilterProtocol::Ether),
+            Some("ip") => Some(FilterProtocol::Ip),
+            Some("ip6") => Some(FilterProtocol::Ip6),
+            Some("arp") => Some(FilterProtocol::Arp),
+            Some("tcp") => Some(FilterProtocol::Tcp),
+            Some("udp") => Some(FilterProtocol::Udp),
+            Some("icmp") => Some(FilterProtocol::Icmp),
+            Some("icmp6") => Some(FilterProtocol::Icmp6),
+            _ => None,
+        };
+        if protocol.is_some() {
+            self.position += 1;
+        }
+
+        let direction = if self.accept(&["src"]) {
+            FilterDirection::Src
+        } else if self.accept(&["dst"]) {
+            FilterDirection::Dst
+        } else {
+            FilterDirection::SrcOrDst
+        };
+        let has_direction = direction != FilterDirection::SrcOrDst;
+
+        let kind = match self.peek() {
+            Some(kind @ ("host" | "net" | "port" | "portrange")) => {
+                let kind = kind.to_string();
+                self.position += 1;
+                Some(kind)
+            }
+            _ => None,
+        };
+
+        // A bare protocol name, e.g. `tcp` in `tcp and not port 22`
+        let has_value = self
+            .peek()
+            .is_some_and(|token| !["and", "or", "&&", "||", ")"].contains(&token));
+        if kind.is_none() && !has_value {
+            return match protocol {
+                Some(FilterProtocol::Ether) => Err(invalid("'ether' needs a qualifier".into())),
+                Some(protocol) if !has_direction => Ok(FilterPrimitive::Protocol(protocol)),
+                _ => Err(invalid("expected a filter primitive".to_string())),
+            };
+        }
+
+        let value = self
+            .advance()
+            .ok_or_else(|| invalid("expected a value".to_string()))?;
+
+        match (kind.as_deref().unwrap_or("host"), protocol) {
+            ("host", Some(FilterProtocol::Ether)) => {
+                let address = value
+                    .parse::<MacAddr>()
+                    .map_err(|_| invalid(format!("invalid MAC address '{}'", value)))?;
+                Ok(FilterPrimitive::EtherHost(direction, address))
+            }
+            (
+                "host",
+                None | Some(FilterProtocol::Ip | FilterProtocol::Ip6 | FilterProtocol::Arp),
+            ) => {
+                let address = value
+                    .parse::<IpAddr>()
+                    .map_err(|_| invalid(format!("invalid IP address '{}'", value)))?;
+                check_family(protocol, &address)?;
+                Ok(FilterPrimitive::Host(protocol, direction, address))
+            }
+            ("net", None | Some(FilterProtocol::Ip | FilterProtocol::Ip6)) => {
+                let (address, prefix) = parse_net(&value)?;
+                check_family(protocol, &address)?;
+                Ok(FilterPrimitive::Net(protocol, direction, address, prefix))
+            }
+            ("port", None | Some(FilterProtocol::Tcp | FilterProtocol::Udp)) => {
+                let port = parse_port(&value)?;
+                Ok(FilterPrimitive::Port(protocol, direction, port, port))
+            }
+            ("portrange", None | Some(FilterProtocol::Tcp | FilterProtocol::Udp)) => {
+                let (low, high) = value
+                    .split_once('-')
+                    .ok_or_else(|| invalid(format!("invalid port range '{}'", value)))?;
+                let (low, high) = (parse_port(low)?, parse_port(high)?);
+                Ok(FilterPrimitive::Port(
+                    protocol,
+                    direction,
+                    low.min(high),
+                    low.max(high),
+                ))
+            }
+            (kind, Some(protocol)) => Err(invalid(format!(
+                "'{:?}' cannot qualify '{}'",
+                protocol, kind
+            ))),
+            (kind, None) => Err(invalid(format!("unknown primitive '{}'", kind))),
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, ParserError> {
+        let value = self
+            .advance()
+            .ok_or_else(|| invalid("expected a number".to_string()))?;
+        value
+            .parse()
+            .map_err(|_| invalid(format!("invalid number '{}'", value)))
+    }
+}
+
+fn check_family(protocol: Option<FilterProtocol>, address: &IpAddr) -> Result<(), ParserError> {
+    match (protocol, address) {
+        (Some(FilterProtocol::Ip | FilterProtocol::Arp), IpAddr::V6(_))
+        | (Some(FilterProtocol::Ip6), IpAddr::V4(_)) => Err(invalid(format!(
+            "address {} does not match protocol",
+            address
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn parse_net(value: &str) -> Result<(IpAddr, u8), ParserError> {
+    let (address, prefix) = match value.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (value, None),
+    };
+
+    // tcpdump accepts truncated IPv4 networks such as `net 10.1`
+    let address = match address.parse::<IpAddr>() {
+        Ok(address) => address,
+        Err(_) => {
+            let mut octets = [0u8; 4];
+            let parts: Vec<&str> = address.split('.').collect();
+            if parts.len() > 4 || prefix.is_some() {
+                return Err(invalid(format!("invalid network '{}'", value)));
+            }
+            for (octet, part) in octets.iter_mut().zip(&parts) {
+                *octet = part
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid network '{}'", value)))?;
+            }
+            return Ok((IpAddr::V4(Ipv4Addr::from(octets)), parts.len() as u8 * 8));
+        }
+    };
+
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|&prefix| prefix <= max_prefix)
+            .ok_or_else(|| invalid(format!("invalid prefix length in '{}'", value)))?,
+        None => max_prefix,
+    };
+
+    Ok((address, prefix))
+}
+
+fn parse_port(value: &str) -> Result<u16, ParserError> {
+    let well_known = match value {
+        "ftp" => Some(21),
+        "ssh" => Some(22),
+        "telnet" => Some(23),
+        "smtp" => Some(25),
+        "domain" => Some(53),
+        "bootps" => Some(67),
+        "bootpc" => Some(68),
+        "http" => Some(80),
+        "ntp" => Some(123),
+        "https" => Some(443),
+        _ => None,
+    };
+
+    well_known
+        .or_else(|| value.parse().ok())
+        .ok_or_else(|| invalid(format!("invalid port '{}'", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primitive(source: &str) -> FilterPrimitive {
+        match CaptureFilter::parse(source).unwrap().expression {
+            FilterExpression::Primitive(primitive) => primitive,
+            expression => panic!("{} parsed as {:?}", source, expression),
+        }
+    }
+
+    #[test]
+    fn parses_primitives() {
+        use FilterDirection::*;
+        use FilterProtocol::*;
+
+        assert_eq!(primitive("tcp"), FilterPrimitive::Protocol(Tcp));
+        assert_eq!(
+            primitive("src host 10.0.0.1"),
+            FilterPrimitive::Host(None, Src, "10.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            primitive("10.0.0.1"),
+            FilterPrimitive::Host(None, SrcOrDst, "10.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            primitive("ether dst ff:ff:ff:ff:ff:ff"),
+            FilterPrimitive::EtherHost(Dst, MacAddr::broadcast())
+        );
+        assert_eq!(
+            primitive("ip6 net 2001:db8::/32"),
+            FilterPrimitive::Net(Some(Ip6), SrcOrDst, "2001:db8::".parse().unwrap(), 32)
+        );
+        assert_eq!(
+            primitive("net 10.1"),
+            FilterPrimitive::Net(None, SrcOrDst, "10.1.0.0".parse().unwrap(), 16)
+        );
+        assert_eq!(
+            primitive("udp dst port domain"),
+            FilterPrimitive::Port(Some(Udp), Dst, 53, 53)
+        );
+        assert_eq!(
+            primitive("portrange 2000-1000"),
+            FilterPrimitive::Port(None, SrcOrDst, 1000, 2000)
+        );
+        assert_eq!(primitive("greater 1000"), FilterPrimitive::Greater(1000));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let protocol = |protocol| {
+            Box::new(FilterExpression::Primitive(FilterPrimitive::Protocol(
+                protocol,
+            )))
+        };
+        let (tcp, udp, icmp) = (
+            || protocol(FilterProtocol::Tcp),
+            || protocol(FilterProtocol::Udp),
+            || protocol(FilterProtocol::Icmp),
+        );
+
+        assert_eq!(
+            CaptureFilter::parse("tcp or udp and icmp")
+                .unwrap()
+                .expression,
+            FilterExpression::Or(tcp(), Box::new(FilterExpression::And(udp(), icmp())))
+        );
+        assert_eq!(
+            CaptureFilter::parse("(tcp || udp) && !icmp")
+                .unwrap()
+                .expression,
+            FilterExpression::And(
+                Box::new(FilterExpression::Or(tcp(), udp())),
+                Box::new(FilterExpression::Not(icmp()))
+            )
+        );
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth| format!("{}tcp", "not ".repeat(depth));
+        assert!(CaptureFilter::parse(&nested(MAX_NESTING)).is_ok());
+        assert!(CaptureFilter::parse(&nested(MAX_NESTING + 1)).is_err());
+        let chained = |length| vec!["tcp"; length].join(" and ");
+        assert!(CaptureFilter::parse(&chained(MAX_NESTING + 1)).is_ok());
+        assert!(CaptureFilter::parse(&chained(MAX_NESTING + 2)).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        for source in [
+            "",
+            "tcp and",
+            "(tcp",
+            "tcp)",
+            "tcp & udp",
+            "host 10.0.0.256",
+            "ip host ::1",
+            "ip6 host 10.0.0.1",
+            "net 10.0.0.0/33",
+            "port 65536",
+            "portrange 10",
+            "icmp port 7",
+            "ether",
+            "src tcp",
+            "foo bar",
+            "tcp $",
+            &"not ".repeat(100_000),
+            &format!("{}tcp{}", "(".repeat(100_000), ")".repeat(100_000)),
+            &vec!["tcp"; 100_000].join(" or "),
+        ] {
+            assert!(
+                matches!(
+                    CaptureFilter::parse(source),
+                    Err(ParserError::InvalidFilter(_))
+                ),
+                "{:?} was accepted",
+                source
+            );
+        }
+    }
+}
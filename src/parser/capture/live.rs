@@ -0,0 +1,111 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use pnet::datalink::{self, Channel::Ethernet, Config, DataLinkReceiver, NetworkInterface};
+
+#[cfg(target_os = "linux")]
+use super::socket::PacketSocket;
+use super::{filter::CaptureFilter, CapturedFrame, LinkType};
+use crate::parser::ParserError;
+
+/// How long a read waits for traffic before giving up, so idle captures can be stopped
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+enum Receiver {
+    Datalink(Box<dyn DataLinkReceiver>),
+    #[cfg(target_os = "linux")]
+    Socket(PacketSocket),
+}
+
+/// Capture from a network interface through a `pnet` datalink channel
+///
+/// With a capture filter set, Linux captures switch to a packet socket filtered in the kernel,
+/// other platforms evaluate the filter in userspace.
+pub struct LiveCapture {
+    interface: NetworkInterface,
+    rx: Receiver,
+    filter: Option<CaptureFilter>,
+    buffer: Vec<u8>,
+}
+
+impl LiveCapture {
+    pub fn new(interface: NetworkInterface) -> Result<Self, ParserError> {
+        let config = Config {
+            read_timeout: Some(READ_TIMEOUT),
+            ..Default::default()
+        };
+
+        let rx = match datalink::channel(&interface, config) {
+            Ok(Ethernet(_, rx)) => rx,
+            Ok(_) => return Err(ParserError::ChannelNotSupported),
+            Err(_) => return Err(ParserError::ChannelCreationError),
+        };
+
+        Ok(Self {
+            interface,
+            rx: Receiver::Datalink(rx),
+            filter: None,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Get the interface this capture is bound to
+    pub fn interface(&self) -> &NetworkInterface {
+        &self.interface
+    }
+
+    /// Get the capture filter, if any
+    pub fn filter(&self) -> Option<&CaptureFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Only capture frames matching `filter`
+    pub fn set_filter(&mut self, filter: CaptureFilter) -> Result<(), ParserError> {
+        let program = filter.compile(LinkType::ETHERNET)?;
+
+        #[cfg(target_os = "linux")]
+        {
+            let socket = PacketSocket::open(&self.interface, &program, READ_TIMEOUT)
+                .map_err(|_| ParserError::ChannelCreationError)?;
+            self.rx = Receiver::Socket(socket);
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = program;
+
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    /// Get the next frame, `None` when nothing arrived within the read timeout
+    pub fn next_frame(&mut self) -> Option<CapturedFrame<'_>> {
+        let (data, original_length) = match &mut self.rx {
+            Receiver::Datalink(rx) => match self.filter.as_mut() {
+                None => {
+                    let data = rx.next().ok()?;
+                    (data, data.len())
+                }
+                Some(filter) => loop {
+                    let data = rx.next().ok()?;
+                    if filter.matches(LinkType::ETHERNET, data) {
+                        self.buffer.clear();
+                        self.buffer.extend_from_slice(data);
+                        break (&self.buffer[..], self.buffer.len());
+                    }
+                },
+            },
+            #[cfg(target_os = "linux")]
+            Receiver::Socket(socket) => socket.recv().ok()?,
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Some(CapturedFrame {
+            data,
+            timestamp,
+            original_length,
+            link_type: LinkType::ETHERNET,
+            interface_name: Some(&self.interface.name),
+            comments: &[],
+        })
+    }
+}
@@ -0,0 +1,87 @@
+pub mod filter;
+mod live;
+mod pcap;
+mod pcapng;
+#[cfg(target_os = "linux")]
+mod socket;
+mod writer;
+
+pub use filter::CaptureFilter;
+pub use live::LiveCapture;
+pub use pcap::PcapReader;
+pub use pcapng::{PcapngInterface, PcapngReader};
+pub use writer::{CaptureWriter, FileFormat, Rotation};
+
+use std::{fs::File, io::BufReader, time::Duration};
+
+use super::ParserError;
+
+/// Link-layer header type of captured frames, as registered in the tcpdump.org LINKTYPE_* list
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LinkType(pub u32);
+
+impl LinkType {
+    pub const NULL: LinkType = LinkType(0);
+    pub const ETHERNET: LinkType = LinkType(1);
+    pub const RAW: LinkType = LinkType(101);
+    pub const LINUX_SLL: LinkType = LinkType(113);
+    pub const IPV4: LinkType = LinkType(228);
+    pub const IPV6: LinkType = LinkType(229);
+}
+
+/// A single frame handed out by a capture source, borrowed until the next read
+pub struct CapturedFrame<'a> {
+    /// Captured bytes, possibly truncated to the snapshot length
+    pub data: &'a [u8],
+    /// Capture time since the UNIX epoch
+    pub timestamp: Duration,
+    /// Length of the frame on the wire
+    pub original_length: usize,
+    /// Link-layer header type of `data`
+    pub link_type: LinkType,
+    /// Name of the interface the frame was captured on, when known
+    pub interface_name: Option<&'a str>,
+    /// Comments attached to the frame in the capture file
+    pub comments: &'a [String],
+}
+
+/// Where the parser reads its frames from
+pub enum CaptureSource {
+    Live(LiveCapture),
+    Pcap(PcapReader<BufReader<File>>),
+    Pcapng(PcapngReader<BufReader<File>>),
+}
+
+impl CaptureSource {
+    /// Get the next frame, `Ok(None)` at the end of a file or when a live read timed out
+    pub fn next_frame(&mut self) -> Result<Option<CapturedFrame<'_>>, ParserError> {
+        match self {
+            CaptureSource::Live(live) => Ok(live.next_frame()),
+            CaptureSource::Pcap(reader) => reader.next_frame(),
+            CaptureSource::Pcapng(reader) => reader.next_frame(),
+        }
+    }
+
+    /// Only produce frames matching `filter`
+    pub fn set_filter(&mut self, filter: CaptureFilter) -> Result<(), ParserError> {
+        match self {
+            CaptureSource::Live(live) => live.set_filter(filter),
+            CaptureSource::Pcap(reader) => reader.set_filter(filter),
+            CaptureSource::Pcapng(reader) => reader.set_filter(filter),
+        }
+    }
+
+    /// Whether frames come from a network interface rather than a file
+    pub fn is_live(&self) -> bool {
+        matches!(self, CaptureSource::Live(_))
+    }
+
+    /// Get link-layer header type of the frames produced by this source
+    pub fn link_type(&self) -> LinkType {
+        match self {
+            CaptureSource::Live(_) => LinkType::ETHERNET,
+            CaptureSource::Pcap(reader) => reader.link_type(),
+            CaptureSource::Pcapng(reader) => reader.link_type(),
+        }
+    }
+}
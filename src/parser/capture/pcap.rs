@@ -0,0 +1,343 @@
+use std::{io::Read, time::Duration};
+
+use super::{filter::CaptureFilter, CapturedFrame, LinkType};
+use crate::parser::ParserError;
+
+const MAGIC_MICROSECONDS: u32 = 0xa1b2_c3d4;
+const MAGIC_NANOSECONDS: u32 = 0xa1b2_3c4d;
+
+const GLOBAL_HEADER_LENGTH: usize = 24;
+const RECORD_HEADER_LENGTH: usize = 16;
+
+/// Upper bound for a single record, protects against allocating garbage lengths
+const MAX_RECORD_LENGTH: u32 = 256 * 1024;
+
+/// Reader for classic libpcap capture files
+///
+/// Both microsecond and nanosecond timestamp variants are supported, in either byte order.
+pub struct PcapReader<R> {
+    reader: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    version: (u16, u16),
+    snaplen: u32,
+    link_type: LinkType,
+    filter: Option<CaptureFilter>,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the global header and prepare to read records
+    pub fn new(mut reader: R) -> Result<Self, ParserError> {
+        let mut header = [0u8; GLOBAL_HEADER_LENGTH];
+        reader.read_exact(&mut header)?;
+
+        let magic = [header[0], header[1], header[2], header[3]];
+        let (big_endian, nanoseconds) = if u32::from_le_bytes(magic) == MAGIC_MICROSECONDS {
+            (false, false)
+        } else if u32::from_be_bytes(magic) == MAGIC_MICROSECONDS {
+            (true, false)
+        } else if u32::from_le_bytes(magic) == MAGIC_NANOSECONDS {
+            (false, true)
+        } else if u32::from_be_bytes(magic) == MAGIC_NANOSECONDS {
+            (true, true)
+        } else {
+            return Err(ParserError::InvalidCaptureFile(format!(
+                "unknown pcap magic number {:02x?}",
+                magic
+            )));
+        };
+
+        let u16_at = |offset: usize| read_u16(&header[offset..], big_endian);
+        let u32_at = |offset: usize| read_u32(&header[offset..], big_endian);
+
+        Ok(Self {
+            reader,
+            big_endian,
+            nanoseconds,
+            version: (u16_at(4), u16_at(6)),
+            snaplen: u32_at(16),
+            // The upper bits may carry FCS information, only the lower 16 are the link type
+            link_type: LinkType(u32_at(20) & 0xffff),
+            filter: None,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Get file format version as (major, minor)
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// Get snapshot length declared in the file header
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Get link-layer header type of every record in the file
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Whether record timestamps have nanosecond resolution
+    pub fn is_nanosecond(&self) -> bool {
+        self.nanoseconds
+    }
+
+    /// Skip records not matching `filter`
+    pub fn set_filter(&mut self, mut filter: CaptureFilter) -> Result<(), ParserError> {
+        filter.prepare(self.link_type)?;
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    /// Read the next record, `Ok(None)` at a clean end of file
+    pub fn next_frame(&mut self) -> Result<Option<CapturedFrame<'_>>, ParserError> {
+        let (ts_sec, ts_frac, original_length) = loop {
+            let mut header = [0u8; RECORD_HEADER_LENGTH];
+            if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+
+            let ts_sec = read_u32(&header[0..], self.big_endian);
+            let ts_frac = read_u32(&header[4..], self.big_endian);
+            let captured_length = read_u32(&header[8..], self.big_endian);
+            let original_length = read_u32(&header[12..], self.big_endian);
+
+            // The snapshot length comes from the file as well, it can't raise the limit
+            if captured_length > MAX_RECORD_LENGTH {
+                return Err(ParserError::InvalidCaptureFile(format!(
+                    "record length {} exceeds limit",
+                    captured_length
+                )));
+            }
+
+            self.buffer.resize(captured_length as usize, 0);
+            read_exact_or_truncated(&mut self.reader, &mut self.buffer, "record")?;
+
+            let link_type = self.link_type;
+            if let Some(filter) = self.filter.as_mut() {
+                if !filter.matches(link_type, &self.buffer) {
+                    continue;
+                }
+            }
+            break (ts_sec, ts_frac, original_length);
+        };
+
+        let timestamp = if self.nanoseconds {
+            Duration::new(ts_sec as u64, ts_frac)
+        } else {
+            Duration::new(ts_sec as u64, 0) + Duration::from_micros(ts_frac as u64)
+        };
+
+        Ok(Some(CapturedFrame {
+            data: &self.buffer,
+            timestamp,
+            original_length: original_length as usize,
+            link_type: self.link_type,
+            interface_name: None,
+            comments: &[],
+        }))
+    }
+}
+
+pub(crate) fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+pub(crate) fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Fill `buffer` completely, returning `false` if the reader was already at end of file
+pub(crate) fn read_exact_or_eof<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> Result<bool, ParserError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(ParserError::InvalidCaptureFile(
+                    "truncated record header".to_string(),
+                ))
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// Fill `buffer` completely, the file ending first means the `what` being read is truncated
+pub(crate) fn read_exact_or_truncated<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    what: &str,
+) -> Result<(), ParserError> {
+    reader.read_exact(buffer).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            ParserError::InvalidCaptureFile(format!("truncated {}", what))
+        } else {
+            err.into()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, net::Ipv4Addr};
+
+    use super::*;
+    use crate::parser::{
+        defrag::Defragmenter,
+        wrapers::{SerializableIpv4Packet, SerializableUdpPacket},
+        Parser,
+    };
+
+    /// Ethernet, IPv4 and UDP from 10.0.0.1:4000 to 10.0.0.2:9000 with a 4 byte payload
+    const FRAME: [u8; 46] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00, 0x45,
+        0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x66, 0xca, 0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02, 0x0f, 0xa0, 0x23, 0x28, 0x00, 0x0c, 0x00, 0x00, 0x74, 0x65, 0x73,
+        0x74,
+    ];
+
+    fn u16_bytes(value: u16, big_endian: bool) -> [u8; 2] {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    fn u32_bytes(value: u32, big_endian: bool) -> [u8; 4] {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    /// A capture file holding `records` as (seconds, fraction, bytes)
+    fn capture(
+        magic: u32,
+        big_endian: bool,
+        snaplen: u32,
+        records: &[(u32, u32, &[u8])],
+    ) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend(u32_bytes(magic, big_endian));
+        file.extend(u16_bytes(2, big_endian));
+        file.extend(u16_bytes(4, big_endian));
+        file.extend([0; 8]);
+        file.extend(u32_bytes(snaplen, big_endian));
+        file.extend(u32_bytes(1, big_endian));
+        for (seconds, fraction, data) in records {
+            file.extend(u32_bytes(*seconds, big_endian));
+            file.extend(u32_bytes(*fraction, big_endian));
+            file.extend(u32_bytes(data.len() as u32, big_endian));
+            file.extend(u32_bytes(data.len() as u32 + 10, big_endian));
+            file.extend(*data);
+        }
+        file
+    }
+
+    #[test]
+    fn reads_every_magic_and_byte_order() {
+        for (magic, nanoseconds, fraction) in [
+            (MAGIC_MICROSECONDS, false, Duration::from_micros(250)),
+            (MAGIC_NANOSECONDS, true, Duration::from_nanos(250)),
+        ] {
+            for big_endian in [false, true] {
+                let file = capture(magic, big_endian, 65535, &[(7, 250, &FRAME)]);
+                let mut reader = PcapReader::new(Cursor::new(file)).unwrap();
+                assert_eq!(reader.version(), (2, 4));
+                assert_eq!(reader.snaplen(), 65535);
+                assert_eq!(reader.link_type(), LinkType::ETHERNET);
+                assert_eq!(reader.is_nanosecond(), nanoseconds);
+
+                let frame = reader.next_frame().unwrap().unwrap();
+                assert_eq!(frame.data, FRAME);
+                assert_eq!(frame.timestamp, Duration::from_secs(7) + fraction);
+                assert_eq!(frame.original_length, FRAME.len() + 10);
+                assert!(reader.next_frame().unwrap().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let mut file = capture(MAGIC_MICROSECONDS, false, 65535, &[]);
+        file[0] = 0;
+        assert!(matches!(
+            PcapReader::new(Cursor::new(file)),
+            Err(ParserError::InvalidCaptureFile(_))
+        ));
+        // Shorter than the global header
+        assert!(matches!(
+            PcapReader::new(Cursor::new(vec![0xd4, 0xc3, 0xb2, 0xa1])),
+            Err(ParserError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn reports_truncated_records() {
+        let file = capture(
+            MAGIC_MICROSECONDS,
+            false,
+            65535,
+            &[(1, 0, &FRAME), (2, 0, &FRAME)],
+        );
+        for cut in [5, 20] {
+            let mut reader = PcapReader::new(Cursor::new(&file[..file.len() - cut])).unwrap();
+            assert!(reader.next_frame().unwrap().is_some());
+            assert!(matches!(
+                reader.next_frame(),
+                Err(ParserError::InvalidCaptureFile(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn snaplen_does_not_raise_record_limit() {
+        let mut file = capture(MAGIC_MICROSECONDS, false, u32::MAX, &[(1, 0, &FRAME)]);
+        file[32..36].copy_from_slice(&(MAX_RECORD_LENGTH + 1).to_le_bytes());
+        let mut reader = PcapReader::new(Cursor::new(file)).unwrap();
+        assert!(matches!(
+            reader.next_frame(),
+            Err(ParserError::InvalidCaptureFile(_))
+        ));
+    }
+
+    #[test]
+    fn parses_frames_read_from_file() {
+        let file = capture(MAGIC_MICROSECONDS, true, 65535, &[(1, 500, &FRAME)]);
+        let mut reader = PcapReader::new(Cursor::new(file)).unwrap();
+        let frame = reader.next_frame().unwrap().unwrap();
+        let packet = Parser::parse_frame(&frame, 3, &mut Defragmenter::new());
+
+        assert_eq!(packet.get_id(), 3);
+        assert_eq!(packet.get_timestamp(), Duration::new(1, 500_000));
+        assert_eq!(packet.get_length(), FRAME.len() + 10);
+        assert_eq!(packet.get_data(), FRAME);
+        let ipv4 = packet.find::<SerializableIpv4Packet>().unwrap();
+        assert_eq!(ipv4.source, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(ipv4.destination, Ipv4Addr::new(10, 0, 0, 2));
+        let udp = packet.find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination), (4000, 9000));
+        assert_eq!(packet.get_field_range("udp.dstport"), Some(36..38));
+    }
+}
@@ -0,0 +1,760 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::Range,
+    time::Duration,
+};
+
+use super::{
+    filter::CaptureFilter,
+    pcap::{read_exact_or_eof, read_exact_or_truncated, read_u16, read_u32},
+    CapturedFrame, LinkType,
+};
+use crate::parser::ParserError;
+
+pub(crate) const BLOCK_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+pub(crate) const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_PACKET: u32 = 0x0000_0002;
+const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+const BLOCK_NAME_RESOLUTION: u32 = 0x0000_0004;
+pub(crate) const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+pub(crate) const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+pub(crate) const OPTION_END: u16 = 0;
+const OPTION_COMMENT: u16 = 1;
+const OPTION_IF_NAME: u16 = 2;
+const OPTION_IF_DESCRIPTION: u16 = 3;
+pub(crate) const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_IF_TSOFFSET: u16 = 14;
+
+const NRB_RECORD_END: u16 = 0;
+const NRB_RECORD_IPV4: u16 = 1;
+const NRB_RECORD_IPV6: u16 = 2;
+
+/// Upper bound for a single block, protects against allocating garbage lengths
+const MAX_BLOCK_LENGTH: u32 = 16 * 1024 * 1024;
+
+/// Interface described by an Interface Description Block
+#[derive(Clone, Debug)]
+pub struct PcapngInterface {
+    pub link_type: LinkType,
+    pub snaplen: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Timestamp resolution as given by `if_tsresol`, defaults to microseconds
+    pub timestamp_resolution: u8,
+    /// Seconds added to every timestamp, as given by `if_tsoffset`
+    pub timestamp_offset: i64,
+}
+
+impl PcapngInterface {
+    fn new(link_type: LinkType, snaplen: u32) -> Self {
+        PcapngInterface {
+            link_type,
+            snaplen,
+            name: None,
+            description: None,
+            timestamp_resolution: 6,
+            timestamp_offset: 0,
+        }
+    }
+
+    /// Convert a raw 64-bit timestamp in this interface's units into a time since the epoch
+    fn timestamp(&self, units: u64) -> Duration {
+        let units = units as u128;
+        let nanos = if self.timestamp_resolution & 0x80 != 0 {
+            // Negative power of two
+            let exponent = (self.timestamp_resolution & 0x7f) as u32;
+            (units * 1_000_000_000) >> exponent.min(127)
+        } else {
+            // Negative power of ten
+            let exponent = self.timestamp_resolution as u32;
+            if exponent <= 9 {
+                units * 10u128.pow(9 - exponent)
+            } else {
+                units / 10u128.pow((exponent - 9).min(38))
+            }
+        };
+        let nanos = nanos.min(u64::MAX as u128) as u64;
+        let timestamp = Duration::from_nanos(nanos);
+
+        if self.timestamp_offset >= 0 {
+            timestamp + Duration::from_secs(self.timestamp_offset as u64)
+        } else {
+            timestamp.saturating_sub(Duration::from_secs(self.timestamp_offset.unsigned_abs()))
+        }
+    }
+}
+
+/// Reader for pcapng capture files
+///
+/// Handles multiple sections and interfaces, Enhanced, Simple and obsolete Packet Blocks,
+/// Name Resolution Blocks and packet comments. Other block types are skipped.
+pub struct PcapngReader<R> {
+    reader: R,
+    big_endian: bool,
+    interfaces: Vec<PcapngInterface>,
+    resolved_names: HashMap<IpAddr, String>,
+    filter: Option<CaptureFilter>,
+    block: Vec<u8>,
+    comments: Vec<String>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the first Section Header Block and prepare to read packets
+    pub fn new(reader: R) -> Result<Self, ParserError> {
+        let mut pcapng = PcapngReader {
+            reader,
+            big_endian: false,
+            interfaces: Vec::new(),
+            resolved_names: HashMap::new(),
+            filter: None,
+            block: Vec::new(),
+            comments: Vec::new(),
+        };
+
+        match pcapng.read_block()? {
+            Some(BLOCK_SECTION_HEADER) => Ok(pcapng),
+            _ => Err(ParserError::InvalidCaptureFile(
+                "pcapng file does not start with a section header".to_string(),
+            )),
+        }
+    }
+
+    /// Get interfaces of the current section
+    pub fn interfaces(&self) -> &[PcapngInterface] {
+        &self.interfaces
+    }
+
+    /// Get addresses named by Name Resolution Blocks read so far
+    pub fn resolved_names(&self) -> &HashMap<IpAddr, String> {
+        &self.resolved_names
+    }
+
+    /// Get link-layer header type of the first interface
+    pub fn link_type(&self) -> LinkType {
+        self.interfaces
+            .first()
+            .map_or(LinkType::ETHERNET, |interface| interface.link_type)
+    }
+
+    /// Skip packets not matching `filter`
+    ///
+    /// Interfaces are described as the file is read, the filter is compiled for each one when
+    /// its description block arrives. Reading fails on an interface it cannot be compiled for.
+    pub fn set_filter(&mut self, mut filter: CaptureFilter) -> Result<(), ParserError> {
+        for interface in &self.interfaces {
+            filter.prepare(interface.link_type)?;
+        }
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    /// Read blocks until the next packet, `Ok(None)` at a clean end of file
+    pub fn next_frame(&mut self) -> Result<Option<CapturedFrame<'_>>, ParserError> {
+        let (interface_id, units, data, original_length) = loop {
+            let block_type = match self.read_block()? {
+                Some(block_type) => block_type,
+                None => return Ok(None),
+            };
+
+            let packet = match block_type {
+                BLOCK_ENHANCED_PACKET => self.enhanced_packet()?,
+                BLOCK_PACKET => self.obsolete_packet()?,
+                BLOCK_SIMPLE_PACKET => self.simple_packet()?,
+                _ => None,
+            };
+
+            if let Some((interface_id, units, data, original_length)) = packet {
+                let link_type = self.interfaces[interface_id].link_type;
+                if let Some(filter) = self.filter.as_mut() {
+                    if !filter.matches(link_type, &self.block[data.clone()]) {
+                        continue;
+                    }
+                }
+                break (interface_id, units, data, original_length);
+            }
+        };
+
+        let interface = &self.interfaces[interface_id];
+        Ok(Some(CapturedFrame {
+            data: &self.block[data],
+            timestamp: interface.timestamp(units),
+            original_length,
+            link_type: interface.link_type,
+            interface_name: interface.name.as_deref(),
+            comments: &self.comments,
+        }))
+    }
+
+    /// Read one block into `self.block` and handle the metadata blocks
+    fn read_block(&mut self) -> Result<Option<u32>, ParserError> {
+        let mut header = [0u8; 8];
+        if !read_exact_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+
+        let block_type = read_u32(&header, self.big_endian);
+        let mut body_start = 0;
+        let mut total_length = read_u32(&header[4..], self.big_endian);
+        let mut magic = [0u8; 4];
+
+        if block_type == BLOCK_SECTION_HEADER {
+            // A new section may switch byte order, which is only known after its magic
+            read_exact_or_truncated(&mut self.reader, &mut magic, "section header")?;
+            self.big_endian = if u32::from_le_bytes(magic) == BYTE_ORDER_MAGIC {
+                false
+            } else if u32::from_be_bytes(magic) == BYTE_ORDER_MAGIC {
+                true
+            } else {
+                return Err(ParserError::InvalidCaptureFile(format!(
+                    "unknown pcapng byte-order magic {:02x?}",
+                    magic
+                )));
+            };
+            total_length = read_u32(&header[4..], self.big_endian);
+            body_start = 4;
+        }
+
+        if total_length < 12 || !total_length.is_multiple_of(4) || total_length > MAX_BLOCK_LENGTH {
+            return Err(ParserError::InvalidCaptureFile(format!(
+                "invalid block length {}",
+                total_length
+            )));
+        }
+
+        // Body plus trailing length, the section magic has already been consumed
+        let remaining = total_length as usize - 8;
+        self.block.resize(remaining, 0);
+        self.block[..body_start].copy_from_slice(&magic[..body_start]);
+        read_exact_or_truncated(&mut self.reader, &mut self.block[body_start..], "block")?;
+        self.block.truncate(remaining - 4);
+
+        match block_type {
+            BLOCK_SECTION_HEADER => self.interfaces.clear(),
+            BLOCK_INTERFACE_DESCRIPTION => self.interface_description()?,
+            BLOCK_NAME_RESOLUTION => self.name_resolution(),
+            _ => {}
+        }
+
+        Ok(Some(block_type))
+    }
+
+    fn interface_description(&mut self) -> Result<(), ParserError> {
+        if self.block.len() < 8 {
+            return Err(ParserError::InvalidCaptureFile(
+                "truncated interface description".to_string(),
+            ));
+        }
+
+        let link_type = LinkType(read_u16(&self.block, self.big_endian) as u32);
+        let snaplen = read_u32(&self.block[4..], self.big_endian);
+        let mut interface = PcapngInterface::new(link_type, snaplen);
+
+        for (code, value) in options(&self.block[8..], self.big_endian) {
+            match code {
+                OPTION_IF_NAME => interface.name = Some(utf8(value)),
+                OPTION_IF_DESCRIPTION => interface.description = Some(utf8(value)),
+                OPTION_IF_TSRESOL if !value.is_empty() => interface.timestamp_resolution = value[0],
+                OPTION_IF_TSOFFSET if value.len() == 8 => {
+                    let high = read_u32(value, self.big_endian) as u64;
+                    let low = read_u32(&value[4..], self.big_endian) as u64;
+                    interface.timestamp_offset = if self.big_endian {
+                        ((high << 32) | low) as i64
+                    } else {
+                        ((low << 32) | high) as i64
+                    };
+                }
+                _ => {}
+            }
+        }
+        if let Some(filter) = self.filter.as_mut() {
+            filter.prepare(link_type)?;
+        }
+        self.interfaces.push(interface);
+
+        Ok(())
+    }
+
+    fn name_resolution(&mut self) {
+        let mut records = &self.block[..];
+        while records.len() >= 4 {
+            let record_type = read_u16(records, self.big_endian);
+            let length = read_u16(&records[2..], self.big_endian) as usize;
+            if record_type == NRB_RECORD_END || records.len() < 4 + length {
+                break;
+            }
+
+            let value = &records[4..4 + length];
+            let address = match record_type {
+                NRB_RECORD_IPV4 if length > 4 => Some((
+                    IpAddr::V4(Ipv4Addr::new(value[0], value[1], value[2], value[3])),
+                    4,
+                )),
+                NRB_RECORD_IPV6 if length > 16 => {
+                    let mut octets = [0u8; 16];
+                    octets.copy_from_slice(&value[..16]);
+                    Some((IpAddr::V6(Ipv6Addr::from(octets)), 16))
+                }
+                _ => None,
+            };
+            if let Some((address, names_start)) = address {
+                if let Some(name) = value[names_start..]
+                    .split(|&byte| byte == 0)
+                    .find(|name| !name.is_empty())
+                {
+                    self.resolved_names.insert(address, utf8(name));
+                }
+            }
+
+            records = &records[(4 + padded(length)).min(records.len())..];
+        }
+    }
+
+    fn enhanced_packet(&mut self) -> Result<Option<PacketPosition>, ParserError> {
+        if self.block.len() < 20 {
+            return Err(ParserError::InvalidCaptureFile(
+                "truncated enhanced packet block".to_string(),
+            ));
+        }
+
+        let interface_id = read_u32(&self.block, self.big_endian) as usize;
+        let timestamp = self.timestamp_at(4);
+        let captured_length = read_u32(&self.block[12..], self.big_endian) as usize;
+        let original_length = read_u32(&self.block[16..], self.big_endian) as usize;
+
+        self.packet_at(
+            interface_id,
+            timestamp,
+            20,
+            captured_length,
+            original_length,
+        )
+    }
+
+    fn obsolete_packet(&mut self) -> Result<Option<PacketPosition>, ParserError> {
+        if self.block.len() < 20 {
+            return Err(ParserError::InvalidCaptureFile(
+                "truncated packet block".to_string(),
+            ));
+        }
+
+        let interface_id = read_u16(&self.block, self.big_endian) as usize;
+        let timestamp = self.timestamp_at(4);
+        let captured_length = read_u32(&self.block[12..], self.big_endian) as usize;
+        let original_length = read_u32(&self.block[16..], self.big_endian) as usize;
+
+        self.packet_at(
+            interface_id,
+            timestamp,
+            20,
+            captured_length,
+            original_length,
+        )
+    }
+
+    fn simple_packet(&mut self) -> Result<Option<PacketPosition>, ParserError> {
+        if self.block.len() < 4 {
+            return Err(ParserError::InvalidCaptureFile(
+                "truncated simple packet block".to_string(),
+            ));
+        }
+
+        // Simple packets carry no timestamp and always belong to the first interface
+        let original_length = read_u32(&self.block, self.big_endian) as usize;
+        let snaplen = match self.interfaces.first() {
+            Some(interface) if interface.snaplen != 0 => interface.snaplen as usize,
+            _ => original_length,
+        };
+        let captured_length = original_length.min(snaplen).min(self.block.len() - 4);
+
+        self.packet_at(0, 0, 4, captured_length, original_length)
+    }
+
+    /// Validate a packet's position in the current block and collect its comments
+    fn packet_at(
+        &mut self,
+        interface_id: usize,
+        timestamp: u64,
+        data_start: usize,
+        captured_length: usize,
+        original_length: usize,
+    ) -> Result<Option<PacketPosition>, ParserError> {
+        if interface_id >= self.interfaces.len() {
+            return Err(ParserError::InvalidCaptureFile(format!(
+                "packet references unknown interface {}",
+                interface_id
+            )));
+        }
+        let data_end = data_start + captured_length;
+        if data_end > self.block.len() {
+            return Err(ParserError::InvalidCaptureFile(
+                "packet data exceeds its block".to_string(),
+            ));
+        }
+
+        self.comments.clear();
+        let options_start = (data_start + padded(captured_length)).min(self.block.len());
+        for (code, value) in options(&self.block[options_start..], self.big_endian) {
+            if code == OPTION_COMMENT {
+                self.comments.push(utf8(value));
+            }
+        }
+
+        Ok(Some((
+            interface_id,
+            timestamp,
+            data_start..data_end,
+            original_length,
+        )))
+    }
+
+    fn timestamp_at(&self, offset: usize) -> u64 {
+        let high = read_u32(&self.block[offset..], self.big_endian) as u64;
+        let low = read_u32(&self.block[offset + 4..], self.big_endian) as u64;
+        (high << 32) | low
+    }
+}
+
+/// Interface index, raw timestamp, data range within the block and original length
+type PacketPosition = (usize, u64, Range<usize>, usize);
+
+/// Iterate over the (code, value) pairs of an options list
+fn options(mut bytes: &[u8], big_endian: bool) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if bytes.len() < 4 {
+            return None;
+        }
+        let code = read_u16(bytes, big_endian);
+        let length = read_u16(&bytes[2..], big_endian) as usize;
+        if code == OPTION_END || bytes.len() < 4 + length {
+            return None;
+        }
+
+        let value = &bytes[4..4 + length];
+        bytes = &bytes[(4 + padded(length)).min(bytes.len())..];
+        Some((code, value))
+    })
+}
+
+fn padded(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn utf8(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Ethernet, IPv4 and UDP from 10.0.0.1:4000 to 10.0.0.2:9000 with a 4 byte payload
+    const FRAME: [u8; 46] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00, 0x45,
+        0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x66, 0xca, 0x0a, 0x00, 0x00, 0x01,
+        0x0a, 0x00, 0x00, 0x02, 0x0f, 0xa0, 0x23, 0x28, 0x00, 0x0c, 0x00, 0x00, 0x74, 0x65, 0x73,
+        0x74,
+    ];
+
+    /// Blocks of a capture file, in the byte order of the section being written
+    #[derive(Default)]
+    struct Capture {
+        file: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Capture {
+        fn u16(&self, value: u16) -> [u8; 2] {
+            if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        }
+
+        fn u32(&self, value: u32) -> [u8; 4] {
+            if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        }
+
+        fn option(&self, code: u16, value: &[u8]) -> Vec<u8> {
+            let mut option = Vec::new();
+            option.extend(self.u16(code));
+            option.extend(self.u16(value.len() as u16));
+            option.extend(value);
+            option.resize(4 + padded(value.len()), 0);
+            option
+        }
+
+        fn block(&mut self, block_type: u32, mut body: Vec<u8>) -> &mut Self {
+            body.resize(padded(body.len()), 0);
+            let total_length = self.u32(body.len() as u32 + 12);
+            self.file.extend(self.u32(block_type));
+            self.file.extend(total_length);
+            self.file.extend(body);
+            self.file.extend(total_length);
+            self
+        }
+
+        fn section(&mut self, big_endian: bool) -> &mut Self {
+            self.big_endian = big_endian;
+            let mut body = self.u32(BYTE_ORDER_MAGIC).to_vec();
+            body.extend(self.u16(1));
+            body.extend(self.u16(0));
+            body.extend([0xff; 8]);
+            self.block(BLOCK_SECTION_HEADER, body)
+        }
+
+        fn interface(&mut self, link_type: LinkType, options: &[(u16, &[u8])]) -> &mut Self {
+            let mut body = self.u16(link_type.0 as u16).to_vec();
+            body.extend(self.u16(0));
+            body.extend(self.u32(0));
+            for (code, value) in options {
+                body.extend(self.option(*code, value));
+            }
+            body.extend(self.option(OPTION_END, &[]));
+            self.block(BLOCK_INTERFACE_DESCRIPTION, body)
+        }
+
+        fn enhanced(
+            &mut self,
+            interface: u32,
+            units: u64,
+            data: &[u8],
+            comment: &str,
+        ) -> &mut Self {
+            let mut body = self.u32(interface).to_vec();
+            body.extend(self.u32((units >> 32) as u32));
+            body.extend(self.u32(units as u32));
+            body.extend(self.u32(data.len() as u32));
+            body.extend(self.u32(data.len() as u32 + 2));
+            body.extend(data);
+            body.resize(padded(body.len()), 0);
+            if !comment.is_empty() {
+                body.extend(self.option(OPTION_COMMENT, comment.as_bytes()));
+                body.extend(self.option(OPTION_END, &[]));
+            }
+            self.block(BLOCK_ENHANCED_PACKET, body)
+        }
+
+        fn simple(&mut self, data: &[u8]) -> &mut Self {
+            let mut body = self.u32(data.len() as u32).to_vec();
+            body.extend(data);
+            self.block(BLOCK_SIMPLE_PACKET, body)
+        }
+
+        fn obsolete(&mut self, interface: u16, units: u64, data: &[u8]) -> &mut Self {
+            let mut body = self.u16(interface).to_vec();
+            body.extend(self.u16(0));
+            body.extend(self.u32((units >> 32) as u32));
+            body.extend(self.u32(units as u32));
+            body.extend(self.u32(data.len() as u32));
+            body.extend(self.u32(data.len() as u32));
+            body.extend(data);
+            self.block(BLOCK_PACKET, body)
+        }
+
+        fn reader(&self) -> PcapngReader<Cursor<Vec<u8>>> {
+            PcapngReader::new(Cursor::new(self.file.clone())).unwrap()
+        }
+    }
+
+    /// Frames of a capture as (data, timestamp, original length, link type, interface, comments)
+    type Frame = (
+        Vec<u8>,
+        Duration,
+        usize,
+        LinkType,
+        Option<String>,
+        Vec<String>,
+    );
+
+    fn frames(reader: &mut PcapngReader<Cursor<Vec<u8>>>) -> Result<Vec<Frame>, ParserError> {
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame()? {
+            frames.push((
+                frame.data.to_vec(),
+                frame.timestamp,
+                frame.original_length,
+                frame.link_type,
+                frame.interface_name.map(str::to_string),
+                frame.comments.to_vec(),
+            ));
+        }
+        Ok(frames)
+    }
+
+    #[test]
+    fn reads_packets_of_every_interface() {
+        let mut capture = Capture::default();
+        capture
+            .section(false)
+            .interface(LinkType::ETHERNET, &[(OPTION_IF_NAME, b"eth0")])
+            .interface(
+                LinkType::RAW,
+                &[(OPTION_IF_NAME, b"tun0"), (OPTION_IF_TSRESOL, &[9])],
+            )
+            .interface(LinkType::ETHERNET, &[(OPTION_IF_TSRESOL, &[0x80 | 10])])
+            .enhanced(0, 1_500_000, &FRAME, "first")
+            .enhanced(1, 2_000_000_001, &FRAME[14..], "")
+            .enhanced(2, 3 * 1024 + 512, &FRAME, "")
+            .simple(&FRAME)
+            .obsolete(1, 4_000_000_000, &FRAME[14..]);
+        let mut reader = capture.reader();
+        let frames = frames(&mut reader).unwrap();
+
+        assert_eq!(reader.interfaces().len(), 3);
+        assert_eq!(reader.link_type(), LinkType::ETHERNET);
+        let eth0 = Some("eth0".to_string());
+        let tun0 = Some("tun0".to_string());
+        assert_eq!(
+            frames,
+            [
+                (
+                    FRAME.to_vec(),
+                    Duration::from_micros(1_500_000),
+                    48,
+                    LinkType::ETHERNET,
+                    eth0.clone(),
+                    vec!["first".to_string()]
+                ),
+                (
+                    FRAME[14..].to_vec(),
+                    Duration::from_nanos(2_000_000_001),
+                    34,
+                    LinkType::RAW,
+                    tun0.clone(),
+                    vec![]
+                ),
+                (
+                    FRAME.to_vec(),
+                    Duration::from_millis(3500),
+                    48,
+                    LinkType::ETHERNET,
+                    None,
+                    vec![]
+                ),
+                // Simple packets have no timestamp and belong to the first interface
+                (
+                    FRAME.to_vec(),
+                    Duration::ZERO,
+                    46,
+                    LinkType::ETHERNET,
+                    eth0,
+                    vec![]
+                ),
+                (
+                    FRAME[14..].to_vec(),
+                    Duration::from_secs(4),
+                    32,
+                    LinkType::RAW,
+                    tun0,
+                    vec![]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_byte_order_of_each_section() {
+        let mut capture = Capture::default();
+        capture
+            .section(true)
+            .interface(LinkType::RAW, &[(OPTION_IF_NAME, b"big")])
+            .enhanced(0, 7_000_000, &FRAME[14..], "big endian")
+            .section(false)
+            .interface(LinkType::ETHERNET, &[(OPTION_IF_NAME, b"little")])
+            .enhanced(0, 8_000_000, &FRAME, "");
+        let mut reader = capture.reader();
+
+        let frame = reader.next_frame().unwrap().unwrap();
+        assert_eq!(frame.data, &FRAME[14..]);
+        assert_eq!(frame.timestamp, Duration::from_secs(7));
+        assert_eq!(frame.interface_name, Some("big"));
+        assert_eq!(frame.comments, ["big endian"]);
+        // The new section forgets the interfaces of the previous one
+        let frame = reader.next_frame().unwrap().unwrap();
+        assert_eq!(frame.data, FRAME);
+        assert_eq!(frame.timestamp, Duration::from_secs(8));
+        assert_eq!(frame.link_type, LinkType::ETHERNET);
+        assert_eq!(frame.interface_name, Some("little"));
+        assert!(frame.comments.is_empty());
+        assert_eq!(reader.interfaces().len(), 1);
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn reports_truncated_blocks() {
+        let mut capture = Capture::default();
+        capture
+            .section(false)
+            .interface(LinkType::ETHERNET, &[])
+            .enhanced(0, 0, &FRAME, "")
+            .enhanced(0, 0, &FRAME, "");
+        let file = capture.file;
+        // Cut in the block body and in the block header
+        for cut in [10, 76] {
+            let mut reader =
+                PcapngReader::new(Cursor::new(file[..file.len() - cut].to_vec())).unwrap();
+            assert!(reader.next_frame().unwrap().is_some());
+            assert!(matches!(
+                reader.next_frame(),
+                Err(ParserError::InvalidCaptureFile(_))
+            ));
+        }
+        assert!(matches!(
+            PcapngReader::new(Cursor::new(file[..20].to_vec())),
+            Err(ParserError::InvalidCaptureFile(_))
+        ));
+
+        // A packet of an interface that was never described
+        let mut capture = Capture::default();
+        capture.section(false).enhanced(0, 0, &FRAME, "");
+        assert!(matches!(
+            frames(&mut capture.reader()),
+            Err(ParserError::InvalidCaptureFile(_))
+        ));
+    }
+
+    #[test]
+    fn compiles_filter_for_each_interface() {
+        let mut capture = Capture::default();
+        capture
+            .section(false)
+            .interface(LinkType::ETHERNET, &[])
+            .interface(LinkType::RAW, &[])
+            .enhanced(0, 1, &FRAME, "")
+            .enhanced(1, 2, &FRAME[14..], "")
+            .enhanced(0, 3, &FRAME[..14], "");
+
+        let mut reader = capture.reader();
+        reader
+            .set_filter(CaptureFilter::parse("udp port 9000").unwrap())
+            .unwrap();
+        let link_types: Vec<LinkType> = frames(&mut reader)
+            .unwrap()
+            .into_iter()
+            .map(|frame| frame.3)
+            .collect();
+        assert_eq!(link_types, [LinkType::ETHERNET, LinkType::RAW]);
+
+        // Raw IP has no MAC addresses, reading stops at that interface
+        let mut reader = capture.reader();
+        reader
+            .set_filter(CaptureFilter::parse("ether host 00:11:22:33:44:55").unwrap())
+            .unwrap();
+        assert!(matches!(
+            frames(&mut reader),
+            Err(ParserError::InvalidFilter(_))
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use std::{io, mem, os::unix::io::RawFd, time::Duration};
+
+use pnet::datalink::NetworkInterface;
+
+use super::filter::BpfProgram;
+
+/// Largest frame read from the socket, longer frames are truncated
+const BUFFER_SIZE: usize = 262_144;
+
+/// Raw `AF_PACKET` socket with a classic BPF program attached in the kernel
+///
+/// `pnet` does not expose the file descriptor of its channels, so filtered live captures
+/// open their own socket.
+pub struct PacketSocket {
+    fd: RawFd,
+    buffer: Vec<u8>,
+}
+
+impl PacketSocket {
+    pub fn open(
+        interface: &NetworkInterface,
+        program: &BpfProgram,
+        read_timeout: Duration,
+    ) -> io::Result<Self> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+
+        // Protocol 0 receives nothing until bound, so no frame slips in before the filter
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = PacketSocket {
+            fd,
+            buffer: vec![0; BUFFER_SIZE],
+        };
+
+        // Compiled programs are at most BPF_MAXINSNS long, so the length fits
+        let filter = libc::sock_fprog {
+            len: program.instructions().len() as u16,
+            filter: program.instructions().as_ptr() as *mut libc::sock_filter,
+        };
+        socket.set_option(libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, &filter)?;
+
+        let timeout = libc::timeval {
+            tv_sec: read_timeout.as_secs() as libc::time_t,
+            tv_usec: read_timeout.subsec_micros() as libc::suseconds_t,
+        };
+        socket.set_option(libc::SOL_SOCKET, libc::SO_RCVTIMEO, &timeout)?;
+
+        let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        address.sll_family = libc::AF_PACKET as u16;
+        address.sll_protocol = protocol;
+        address.sll_ifindex = interface.index as i32;
+        let result = unsafe {
+            libc::bind(
+                fd,
+                &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let membership = libc::packet_mreq {
+            mr_ifindex: interface.index as i32,
+            mr_type: libc::PACKET_MR_PROMISC as u16,
+            mr_alen: 0,
+            mr_address: [0; 8],
+        };
+        socket.set_option(libc::SOL_PACKET, libc::PACKET_ADD_MEMBERSHIP, &membership)?;
+
+        Ok(socket)
+    }
+
+    /// Wait up to the read timeout for a frame passing the filter, returning it with its original length
+    pub fn recv(&mut self) -> io::Result<(&[u8], usize)> {
+        let length = unsafe {
+            libc::recv(
+                self.fd,
+                self.buffer.as_mut_ptr() as *mut libc::c_void,
+                self.buffer.len(),
+                libc::MSG_TRUNC,
+            )
+        };
+        if length < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let length = length as usize;
+        Ok((&self.buffer[..length.min(self.buffer.len())], length))
+    }
+
+    fn set_option<T>(&self, level: libc::c_int, name: libc::c_int, value: &T) -> io::Result<()> {
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd,
+                level,
+                name,
+                value as *const T as *const libc::c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PacketSocket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
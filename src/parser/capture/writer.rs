@@ -0,0 +1,464 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    pcapng::{
+        BLOCK_ENHANCED_PACKET, BLOCK_INTERFACE_DESCRIPTION, BLOCK_SECTION_HEADER, BYTE_ORDER_MAGIC,
+        OPTION_END, OPTION_IF_TSRESOL,
+    },
+    CapturedFrame, LinkType,
+};
+use crate::parser::ParserError;
+
+const PCAP_MAGIC_NANOSECONDS: u32 = 0xa1b2_3c4d;
+const SNAPLEN: u32 = 262_144;
+
+/// On-disk format of a recording
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Pcap,
+    Pcapng,
+}
+
+impl FileFormat {
+    /// Guess format from a file name, anything but `.pcapng` is written as classic pcap
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("pcapng") => FileFormat::Pcapng,
+            _ => FileFormat::Pcap,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Pcap => "pcap",
+            FileFormat::Pcapng => "pcapng",
+        }
+    }
+}
+
+/// When to close the current file and continue recording into the next one
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rotation {
+    /// Rotate once the file reaches this many bytes
+    pub max_size: Option<u64>,
+    /// Rotate once the file spans this much capture time
+    pub max_duration: Option<Duration>,
+}
+
+impl Rotation {
+    fn is_enabled(&self) -> bool {
+        self.max_size.is_some() || self.max_duration.is_some()
+    }
+}
+
+/// Streams captured frames into pcap or pcapng files
+///
+/// Frames are written with nanosecond timestamps and their original wire length. With rotation
+/// enabled, files are named after the requested path with a zero-padded number,
+/// `<stem>_00001.<ext>`, `<stem>_00002.<ext>` and so on.
+pub struct CaptureWriter {
+    path: PathBuf,
+    format: FileFormat,
+    link_type: LinkType,
+    interfaces: Vec<LinkType>,
+    rotation: Rotation,
+    file_index: usize,
+    current_path: PathBuf,
+    output: BufWriter<File>,
+    written: u64,
+    first_timestamp: Option<Duration>,
+    frames: usize,
+}
+
+impl CaptureWriter {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        format: FileFormat,
+        link_type: LinkType,
+        rotation: Rotation,
+    ) -> Result<Self, ParserError> {
+        let path = path.as_ref().to_path_buf();
+        let current_path = file_path(&path, format, &rotation, 1);
+        let mut writer = CaptureWriter {
+            output: BufWriter::new(File::create(&current_path)?),
+            path,
+            format,
+            link_type,
+            interfaces: Vec::new(),
+            rotation,
+            file_index: 1,
+            current_path,
+            written: 0,
+            first_timestamp: None,
+            frames: 0,
+        };
+        writer.write_file_header()?;
+
+        Ok(writer)
+    }
+
+    /// Get path of the file currently written to
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+
+    /// Get number of frames written across all files
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Append a frame, rotating to a new file first if a limit has been reached
+    pub fn write_frame(&mut self, frame: &CapturedFrame) -> Result<(), ParserError> {
+        if self.should_rotate(frame.timestamp) {
+            self.rotate()?;
+        }
+        self.first_timestamp.get_or_insert(frame.timestamp);
+
+        let captured_length = frame.data.len() as u32;
+        let original_length = frame.original_length as u32;
+        let seconds = frame.timestamp.as_secs();
+        let nanos = frame.timestamp.subsec_nanos();
+
+        match self.format {
+            FileFormat::Pcap if frame.link_type != self.link_type => {
+                return Err(ParserError::UnsupportedLinkType(frame.link_type.0));
+            }
+            FileFormat::Pcap => {
+                let mut header = Vec::with_capacity(16);
+                header.extend_from_slice(&(seconds as u32).to_le_bytes());
+                header.extend_from_slice(&nanos.to_le_bytes());
+                header.extend_from_slice(&captured_length.to_le_bytes());
+                header.extend_from_slice(&original_length.to_le_bytes());
+                self.write(&header)?;
+                self.write(frame.data)?;
+            }
+            FileFormat::Pcapng => {
+                let interface_id = self.interface_id(frame.link_type)?;
+                // pcapng timestamps are a single 64-bit count of if_tsresol units
+                let timestamp = seconds * 1_000_000_000 + nanos as u64;
+                let mut body = Vec::with_capacity(20 + frame.data.len() + 3);
+                body.extend_from_slice(&interface_id.to_le_bytes());
+                body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+                body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+                body.extend_from_slice(&captured_length.to_le_bytes());
+                body.extend_from_slice(&original_length.to_le_bytes());
+                body.extend_from_slice(frame.data);
+                pad_to_32_bits(&mut body);
+                self.write_block(BLOCK_ENHANCED_PACKET, &body)?;
+            }
+        }
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    /// Flush buffered frames to disk
+    pub fn flush(&mut self) -> Result<(), ParserError> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn should_rotate(&self, timestamp: Duration) -> bool {
+        let size_reached = self
+            .rotation
+            .max_size
+            .is_some_and(|max_size| self.written >= max_size);
+        let duration_reached = match (self.rotation.max_duration, self.first_timestamp) {
+            (Some(max_duration), Some(first)) => timestamp.saturating_sub(first) >= max_duration,
+            _ => false,
+        };
+
+        size_reached || duration_reached
+    }
+
+    fn rotate(&mut self) -> Result<(), ParserError> {
+        self.flush()?;
+        self.file_index += 1;
+        self.current_path = file_path(&self.path, self.format, &self.rotation, self.file_index);
+        self.output = BufWriter::new(File::create(&self.current_path)?);
+        self.written = 0;
+        self.first_timestamp = None;
+        self.write_file_header()
+    }
+
+    fn write_file_header(&mut self) -> Result<(), ParserError> {
+        match self.format {
+            FileFormat::Pcap => {
+                let mut header = Vec::with_capacity(24);
+                header.extend_from_slice(&PCAP_MAGIC_NANOSECONDS.to_le_bytes());
+                header.extend_from_slice(&2u16.to_le_bytes());
+                header.extend_from_slice(&4u16.to_le_bytes());
+                header.extend_from_slice(&0i32.to_le_bytes());
+                header.extend_from_slice(&0u32.to_le_bytes());
+                header.extend_from_slice(&SNAPLEN.to_le_bytes());
+                header.extend_from_slice(&self.link_type.0.to_le_bytes());
+                self.write(&header)
+            }
+            FileFormat::Pcapng => {
+                let mut section = Vec::with_capacity(16);
+                section.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+                section.extend_from_slice(&1u16.to_le_bytes());
+                section.extend_from_slice(&0u16.to_le_bytes());
+                // Section length is not known up front
+                section.extend_from_slice(&(-1i64).to_le_bytes());
+                self.write_block(BLOCK_SECTION_HEADER, &section)?;
+
+                self.interfaces.clear();
+                self.interface_id(self.link_type).map(|_| ())
+            }
+        }
+    }
+
+    /// Get the interface recording frames of `link_type`, describing a new one if needed
+    fn interface_id(&mut self, link_type: LinkType) -> Result<u32, ParserError> {
+        if let Some(id) = self.interfaces.iter().position(|&known| known == link_type) {
+            return Ok(id as u32);
+        }
+
+        let mut interface = Vec::with_capacity(20);
+        interface.extend_from_slice(&(link_type.0 as u16).to_le_bytes());
+        interface.extend_from_slice(&0u16.to_le_bytes());
+        interface.extend_from_slice(&SNAPLEN.to_le_bytes());
+        interface.extend_from_slice(&OPTION_IF_TSRESOL.to_le_bytes());
+        interface.extend_from_slice(&1u16.to_le_bytes());
+        interface.extend_from_slice(&[9, 0, 0, 0]);
+        interface.extend_from_slice(&OPTION_END.to_le_bytes());
+        interface.extend_from_slice(&0u16.to_le_bytes());
+        self.write_block(BLOCK_INTERFACE_DESCRIPTION, &interface)?;
+        self.interfaces.push(link_type);
+
+        Ok(self.interfaces.len() as u32 - 1)
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> Result<(), ParserError> {
+        let total_length = (body.len() + 12) as u32;
+        self.write(&block_type.to_le_bytes())?;
+        self.write(&total_length.to_le_bytes())?;
+        self.write(body)?;
+        self.write(&total_length.to_le_bytes())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ParserError> {
+        self.output.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl Drop for CaptureWriter {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+    }
+}
+
+fn file_path(path: &Path, format: FileFormat, rotation: &Rotation, index: usize) -> PathBuf {
+    if !rotation.is_enabled() {
+        return path.to_path_buf();
+    }
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("capture");
+    path.with_file_name(format!("{}_{:05}.{}", stem, index, format.extension()))
+}
+
+fn pad_to_32_bits(bytes: &mut Vec<u8>) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::BufReader};
+
+    use super::*;
+    use crate::parser::capture::{PcapReader, PcapngReader};
+
+    /// Empty directory of its own for each test, removed by the test once it passed
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("netui-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn frame(data: &[u8], nanos: u64, link_type: LinkType) -> CapturedFrame<'_> {
+        CapturedFrame {
+            data,
+            timestamp: Duration::from_nanos(nanos),
+            original_length: data.len() + 4,
+            link_type,
+            interface_name: None,
+            comments: &[],
+        }
+    }
+
+    #[test]
+    fn round_trips_pcap() {
+        let directory = directory("writer-pcap");
+        let path = directory.join("out.pcap");
+        let mut writer = CaptureWriter::create(
+            &path,
+            FileFormat::Pcap,
+            LinkType::ETHERNET,
+            Rotation::default(),
+        )
+        .unwrap();
+        writer
+            .write_frame(&frame(&[1; 20], 1_000_000_123, LinkType::ETHERNET))
+            .unwrap();
+        writer
+            .write_frame(&frame(&[2; 30], 2_500_000_000, LinkType::ETHERNET))
+            .unwrap();
+        // Classic pcap has a single link type for the whole file
+        assert!(matches!(
+            writer.write_frame(&frame(&[3; 20], 3_000_000_000, LinkType::RAW)),
+            Err(ParserError::UnsupportedLinkType(101))
+        ));
+        assert_eq!(writer.frames(), 2);
+        assert_eq!(writer.current_path(), path);
+        drop(writer);
+
+        let mut reader = PcapReader::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert!(reader.is_nanosecond());
+        assert_eq!(reader.link_type(), LinkType::ETHERNET);
+        let first = reader.next_frame().unwrap().unwrap();
+        assert_eq!(first.data, [1; 20]);
+        assert_eq!(first.timestamp, Duration::from_nanos(1_000_000_123));
+        assert_eq!(first.original_length, 24);
+        let second = reader.next_frame().unwrap().unwrap();
+        assert_eq!(second.data, [2; 30]);
+        assert_eq!(second.timestamp, Duration::from_nanos(2_500_000_000));
+        assert!(reader.next_frame().unwrap().is_none());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn round_trips_pcapng_with_several_link_types() {
+        let directory = directory("writer-pcapng");
+        let path = directory.join("out.pcapng");
+        let mut writer = CaptureWriter::create(
+            &path,
+            FileFormat::from_path(&path),
+            LinkType::ETHERNET,
+            Rotation::default(),
+        )
+        .unwrap();
+        // Odd lengths check the padding of packet data
+        writer
+            .write_frame(&frame(&[1; 21], 1_000_000_123, LinkType::ETHERNET))
+            .unwrap();
+        writer
+            .write_frame(&frame(&[0x45; 23], 2_000_000_456, LinkType::RAW))
+            .unwrap();
+        writer
+            .write_frame(&frame(&[3; 22], 3_000_000_789, LinkType::ETHERNET))
+            .unwrap();
+        writer.flush().unwrap();
+
+        let mut reader = PcapngReader::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame().unwrap() {
+            frames.push((
+                frame.data.to_vec(),
+                frame.timestamp,
+                frame.original_length,
+                frame.link_type,
+            ));
+        }
+        let link_types: Vec<LinkType> = reader
+            .interfaces()
+            .iter()
+            .map(|interface| interface.link_type)
+            .collect();
+        assert_eq!(link_types, [LinkType::ETHERNET, LinkType::RAW]);
+        assert!(reader
+            .interfaces()
+            .iter()
+            .all(|interface| interface.timestamp_resolution == 9));
+        assert_eq!(
+            frames,
+            [
+                (
+                    vec![1; 21],
+                    Duration::from_nanos(1_000_000_123),
+                    25,
+                    LinkType::ETHERNET
+                ),
+                (
+                    vec![0x45; 23],
+                    Duration::from_nanos(2_000_000_456),
+                    27,
+                    LinkType::RAW
+                ),
+                (
+                    vec![3; 22],
+                    Duration::from_nanos(3_000_000_789),
+                    26,
+                    LinkType::ETHERNET
+                ),
+            ]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn rotates_into_numbered_files() {
+        let directory = directory("writer-rotation");
+        let rotation = Rotation {
+            max_size: Some(100),
+            max_duration: None,
+        };
+        let mut writer = CaptureWriter::create(
+            directory.join("capture.pcap"),
+            FileFormat::Pcap,
+            LinkType::ETHERNET,
+            rotation,
+        )
+        .unwrap();
+        assert_eq!(writer.current_path(), directory.join("capture_00001.pcap"));
+        // 24 byte header and 62 bytes per frame, files fill up after two frames
+        for i in 0..5 {
+            writer
+                .write_frame(&frame(&[i; 46], i as u64, LinkType::ETHERNET))
+                .unwrap();
+        }
+        assert_eq!(writer.current_path(), directory.join("capture_00003.pcap"));
+        drop(writer);
+
+        let mut names: Vec<String> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "capture_00001.pcap",
+                "capture_00002.pcap",
+                "capture_00003.pcap"
+            ]
+        );
+        let counts: Vec<usize> = names
+            .iter()
+            .map(|name| {
+                let file = File::open(directory.join(name)).unwrap();
+                let mut reader = PcapReader::new(BufReader::new(file)).unwrap();
+                let mut count = 0;
+                while reader.next_frame().unwrap().is_some() {
+                    count += 1;
+                }
+                count
+            })
+            .collect();
+        assert_eq!(counts, [2, 2, 1]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
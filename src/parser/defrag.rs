@@ -0,0 +1,507 @@
+//! IP fragment reassembly, so transport dissectors see whole datagrams
+//!
+//! Fragments are kept per datagram until every byte up to the last fragment arrived. Where
+//! fragments overlap the bytes received first are kept. Datagrams that stay incomplete for
+//! longer than the timeout are discarded, as are the oldest ones when the buffered bytes go
+//! over the memory limit.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::Range,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+/// How long fragments wait for the rest of their datagram, as in Linux' `ipfrag_time`
+pub const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Bytes buffered over all incomplete datagrams, as in Linux' `ipfrag_high_thresh`
+pub const FRAGMENT_MEMORY: usize = 4 * 1024 * 1024;
+/// Largest datagram a fragment offset and length can describe
+const MAX_DATAGRAM: usize = 65535;
+
+/// Fragments belong to the same IPv4 datagram when all of these match
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ipv4FragmentKey {
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+    pub identification: u16,
+    pub protocol: u8,
+}
+
+/// Fragments belong to the same IPv6 datagram when all of these match
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ipv6FragmentKey {
+    pub source: Ipv6Addr,
+    pub destination: Ipv6Addr,
+    pub identification: u32,
+}
+
+/// A datagram put back together from its fragments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Datagram {
+    pub payload: Vec<u8>,
+    /// Ids of the frames the fragments came in, in the order they were received
+    pub frames: Vec<usize>,
+    /// Whether some fragments overlapped, the bytes received first were kept
+    pub overlapping: bool,
+}
+
+/// Why a fragment could not be buffered
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentError {
+    #[error("fragment ends past the largest possible datagram")]
+    TooLong,
+    #[error("fragment does not fit the datagram length given by the last fragment")]
+    Inconsistent,
+}
+
+struct Fragments {
+    data: Vec<u8>,
+    /// Received byte ranges, sorted and not overlapping
+    received: Vec<Range<usize>>,
+    /// Length of the datagram, known once the last fragment arrived
+    length: Option<usize>,
+    frames: Vec<usize>,
+    overlapping: bool,
+    first_seen: Duration,
+}
+
+impl Fragments {
+    fn new(now: Duration) -> Self {
+        Fragments {
+            data: Vec::new(),
+            received: Vec::new(),
+            length: None,
+            frames: Vec::new(),
+            overlapping: false,
+            first_seen: now,
+        }
+    }
+
+    fn insert(&mut self, offset: usize, payload: &[u8], last: bool) -> Result<(), FragmentError> {
+        let end = offset + payload.len();
+        if end > MAX_DATAGRAM {
+            return Err(FragmentError::TooLong);
+        }
+        match self.length {
+            Some(length) if end > length || (last && end != length) => {
+                return Err(FragmentError::Inconsistent)
+            }
+            None if last && self.received.last().is_some_and(|range| range.end > end) => {
+                return Err(FragmentError::Inconsistent)
+            }
+            _ => {}
+        }
+        if last {
+            self.length = Some(end);
+        }
+
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        // Only fill the gaps between what was received, earlier bytes win
+        let mut start = offset;
+        for range in &self.received {
+            if range.end <= start || range.start >= end {
+                continue;
+            }
+            self.overlapping = true;
+            if range.start > start {
+                self.data[start..range.start]
+                    .copy_from_slice(&payload[start - offset..range.start - offset]);
+            }
+            start = start.max(range.end);
+        }
+        if start < end {
+            self.data[start..end].copy_from_slice(&payload[start - offset..]);
+        }
+
+        self.received.push(offset..end);
+        self.received.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(self.received.len());
+        for range in self.received.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        self.received = merged;
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        match (self.length, self.received.as_slice()) {
+            (Some(length), [range]) => range.start == 0 && range.end == length,
+            _ => false,
+        }
+    }
+}
+
+/// Incomplete datagrams of one IP version, keyed on what identifies their fragments
+pub struct FragmentTable<K> {
+    datagrams: HashMap<K, Fragments>,
+    /// Bytes buffered over all datagrams
+    memory: usize,
+    max_memory: usize,
+    timeout: Duration,
+}
+
+impl<K: Clone + Eq + Hash> FragmentTable<K> {
+    pub fn new(max_memory: usize, timeout: Duration) -> Self {
+        FragmentTable {
+            datagrams: HashMap::new(),
+            memory: 0,
+            max_memory,
+            timeout,
+        }
+    }
+
+    /// Add the fragment carrying `payload` at `offset` of the datagram, `last` when no more
+    /// fragments follow it, returning the datagram once it is complete
+    pub fn add(
+        &mut self,
+        key: K,
+        offset: usize,
+        last: bool,
+        payload: &[u8],
+        frame: usize,
+        now: Duration,
+    ) -> Result<Option<Datagram>, FragmentError> {
+        self.expire(now);
+
+        let fragments = self
+            .datagrams
+            .entry(key.clone())
+            .or_insert_with(|| Fragments::new(now));
+        let before = fragments.data.len();
+        if let Err(err) = fragments.insert(offset, payload, last) {
+            if fragments.frames.is_empty() {
+                self.datagrams.remove(&key);
+            }
+            return Err(err);
+        }
+        fragments.frames.push(frame);
+        self.memory += fragments.data.len() - before;
+
+        if fragments.is_complete() {
+            let fragments = self.remove(&key);
+            return Ok(fragments.map(|fragments| Datagram {
+                payload: fragments.data,
+                frames: fragments.frames,
+                overlapping: fragments.overlapping,
+            }));
+        }
+
+        // Make room by giving up on the oldest datagrams
+        while self.memory > self.max_memory {
+            let oldest = self
+                .datagrams
+                .iter()
+                .min_by_key(|(_, fragments)| fragments.first_seen)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => {
+                    self.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get how many datagrams are waiting for fragments
+    pub fn len(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.datagrams.is_empty()
+    }
+
+    /// Drop datagrams whose first fragment is older than the timeout
+    fn expire(&mut self, now: Duration) {
+        let timeout = self.timeout;
+        let mut freed = 0;
+        self.datagrams.retain(|_, fragments| {
+            let keep = now.saturating_sub(fragments.first_seen) < timeout;
+            if !keep {
+                freed += fragments.data.len();
+            }
+            keep
+        });
+        self.memory -= freed;
+    }
+
+    fn remove(&mut self, key: &K) -> Option<Fragments> {
+        let fragments = self.datagrams.remove(key)?;
+        self.memory -= fragments.data.len();
+        Some(fragments)
+    }
+}
+
+/// Fragment tables of a capture, fed in capture order
+pub struct Defragmenter {
+    ipv4: FragmentTable<Ipv4FragmentKey>,
+    ipv6: FragmentTable<Ipv6FragmentKey>,
+    /// Capture time of the frame being dissected
+    now: Duration,
+}
+
+impl Default for Defragmenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Defragmenter {
+    pub fn new() -> Self {
+        Defragmenter {
+            ipv4: FragmentTable::new(FRAGMENT_MEMORY, FRAGMENT_TIMEOUT),
+            ipv6: FragmentTable::new(FRAGMENT_MEMORY, FRAGMENT_TIMEOUT),
+            now: Duration::ZERO,
+        }
+    }
+
+    /// Set capture time of the frame about to be dissected, timeouts follow capture time so
+    /// replayed files behave like live captures
+    pub fn set_time(&mut self, now: Duration) {
+        self.now = now;
+    }
+
+    /// Add an IPv4 fragment, see `FragmentTable::add`
+    pub fn add_ipv4(
+        &mut self,
+        key: Ipv4FragmentKey,
+        offset: usize,
+        last: bool,
+        payload: &[u8],
+        frame: usize,
+    ) -> Result<Option<Datagram>, FragmentError> {
+        self.ipv4.add(key, offset, last, payload, frame, self.now)
+    }
+
+    /// Add an IPv6 fragment, see `FragmentTable::add`
+    pub fn add_ipv6(
+        &mut self,
+        key: Ipv6FragmentKey,
+        offset: usize,
+        last: bool,
+        payload: &[u8],
+        frame: usize,
+    ) -> Result<Option<Datagram>, FragmentError> {
+        self.ipv6.add(key, offset, last, payload, frame, self.now)
+    }
+
+    /// Get the IPv4 datagrams waiting for fragments
+    pub fn ipv4(&self) -> &FragmentTable<Ipv4FragmentKey> {
+        &self.ipv4
+    }
+
+    /// Get the IPv6 datagrams waiting for fragments
+    pub fn ipv6(&self) -> &FragmentTable<Ipv6FragmentKey> {
+        &self.ipv6
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        wrapers::SerializableUdpPacket,
+        Parser,
+    };
+
+    fn at(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    fn table() -> FragmentTable<u16> {
+        FragmentTable::new(FRAGMENT_MEMORY, FRAGMENT_TIMEOUT)
+    }
+
+    #[test]
+    fn reassembles_fragments_in_any_order() {
+        let mut table = table();
+        assert_eq!(table.add(1, 8, false, b"89abcdef", 1, at(0)), Ok(None));
+        assert_eq!(table.add(1, 16, true, b"gh", 2, at(0)), Ok(None));
+        assert_eq!(table.len(), 1);
+        let datagram = table
+            .add(1, 0, false, b"01234567", 3, at(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(datagram.payload, b"0123456789abcdefgh");
+        assert_eq!(datagram.frames, [1, 2, 3]);
+        assert!(!datagram.overlapping);
+        assert!(table.is_empty());
+        assert_eq!(table.memory, 0);
+    }
+
+    #[test]
+    fn keeps_bytes_received_first() {
+        let mut table = table();
+        assert_eq!(table.add(1, 4, false, b"AAAA", 1, at(0)), Ok(None));
+        let datagram = table
+            .add(1, 0, true, b"bbbbbbbbbb", 2, at(0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(datagram.payload, b"bbbbAAAAbb");
+        assert!(datagram.overlapping);
+    }
+
+    #[test]
+    fn keeps_datagrams_apart() {
+        let mut table = table();
+        assert_eq!(table.add(1, 0, false, b"11111111", 1, at(0)), Ok(None));
+        assert_eq!(table.add(2, 0, false, b"22222222", 2, at(0)), Ok(None));
+        let datagram = table.add(2, 8, true, b"2", 3, at(0)).unwrap().unwrap();
+        assert_eq!(datagram.payload, b"222222222");
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn rejects_inconsistent_fragments() {
+        let mut table = table();
+        assert_eq!(
+            table.add(1, 65530, false, b"too long", 1, at(0)),
+            Err(FragmentError::TooLong)
+        );
+        // A fragment that failed first leaves nothing behind
+        assert!(table.is_empty());
+
+        assert_eq!(table.add(1, 8, false, b"89abcdef", 1, at(0)), Ok(None));
+        assert_eq!(
+            table.add(1, 0, true, b"0123", 2, at(0)),
+            Err(FragmentError::Inconsistent)
+        );
+        assert_eq!(table.add(1, 16, true, b"gh", 3, at(0)), Ok(None));
+        assert_eq!(
+            table.add(1, 16, false, b"ghij", 4, at(0)),
+            Err(FragmentError::Inconsistent)
+        );
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn expires_old_datagrams() {
+        let mut table = table();
+        assert_eq!(table.add(1, 0, false, b"01234567", 1, at(0)), Ok(None));
+        assert_eq!(table.add(2, 0, false, b"01234567", 2, at(20)), Ok(None));
+        // The first datagram timed out, its last fragment starts a datagram of its own
+        assert_eq!(table.add(1, 8, true, b"89", 3, at(30)), Ok(None));
+        assert_eq!(table.len(), 2);
+        assert!(table.add(2, 8, true, b"89", 4, at(40)).unwrap().is_some());
+    }
+
+    #[test]
+    fn evicts_oldest_datagrams_over_memory_limit() {
+        let mut table = FragmentTable::new(100, FRAGMENT_TIMEOUT);
+        assert_eq!(table.add(1, 0, false, &[0; 40], 1, at(0)), Ok(None));
+        assert_eq!(table.add(2, 0, false, &[0; 40], 2, at(1)), Ok(None));
+        assert_eq!(table.add(3, 0, false, &[0; 40], 3, at(2)), Ok(None));
+        assert_eq!(table.len(), 2);
+        assert!(!table.datagrams.contains_key(&1));
+        assert_eq!(table.memory, 80);
+    }
+
+    /// Ethernet and IPv4 fragment of a UDP datagram from 10.0.0.1 to 10.0.0.2
+    fn fragment(offset: u16, more: bool, payload: &[u8]) -> Vec<u8> {
+        let flags = if more { 0x2000 } else { 0 };
+        let mut frame = vec![0; 12];
+        frame.extend([0x08, 0x00, 0x45, 0]);
+        frame.extend((20 + payload.len() as u16).to_be_bytes());
+        frame.extend([0x12, 0x34]);
+        frame.extend((flags | (offset / 8)).to_be_bytes());
+        frame.extend([64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn dissects_reassembled_datagrams() {
+        let mut datagram = vec![0x13, 0x88, 0x13, 0x89, 0x00, 0x14, 0x00, 0x00];
+        datagram.extend(b"fragmented!!");
+        let frames = [
+            fragment(8, false, &datagram[8..]),
+            fragment(0, true, &datagram[..8]),
+        ];
+
+        let mut defragmenter = Defragmenter::new();
+        let packets: Vec<_> = frames
+            .iter()
+            .enumerate()
+            .map(|(id, data)| {
+                let frame = CapturedFrame {
+                    data,
+                    timestamp: at(id as u64),
+                    original_length: data.len(),
+                    link_type: LinkType::ETHERNET,
+                    interface_name: None,
+                    comments: &[],
+                };
+                Parser::parse_frame(&frame, id, &mut defragmenter)
+            })
+            .collect();
+
+        assert!(packets[0].find::<SerializableUdpPacket>().is_none());
+        let udp = packets[1].find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination, udp.length), (5000, 5001, 20));
+        assert_eq!(packets[1].get_fragments(), [0, 1]);
+        assert!(defragmenter.ipv4().is_empty());
+    }
+
+    /// Ethernet and IPv6 fragment of datagram 7 from 2001:db8::1, after a Hop-by-Hop header
+    fn ipv6_fragment(offset: u16, more: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend([0x86, 0xdd, 0x60, 0, 0, 0]);
+        frame.extend((16 + payload.len() as u16).to_be_bytes());
+        frame.extend([0, 64]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        frame.extend([44, 0, 1, 4, 0, 0, 0, 0]);
+        frame.extend([17, 0]);
+        frame.extend((offset | more as u16).to_be_bytes());
+        frame.extend(7u32.to_be_bytes());
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn dissects_ipv6_datagrams_reassembled_out_of_order() {
+        let mut datagram = vec![0x13, 0x88, 0x13, 0x89, 0x00, 0x1c, 0x00, 0x00];
+        datagram.extend(b"out of order, twice!");
+        let frames = [
+            ipv6_fragment(8, true, &datagram[8..24]),
+            ipv6_fragment(24, false, &datagram[24..]),
+            ipv6_fragment(0, true, &datagram[..8]),
+        ];
+
+        let mut defragmenter = Defragmenter::new();
+        let packets: Vec<_> = frames
+            .iter()
+            .enumerate()
+            .map(|(id, data)| {
+                let frame = CapturedFrame {
+                    data,
+                    timestamp: at(id as u64),
+                    original_length: data.len(),
+                    link_type: LinkType::ETHERNET,
+                    interface_name: None,
+                    comments: &[],
+                };
+                Parser::parse_frame(&frame, id, &mut defragmenter)
+            })
+            .collect();
+
+        assert!(packets[..2]
+            .iter()
+            .all(|packet| packet.find::<SerializableUdpPacket>().is_none()));
+        let udp = packets[2].find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination, udp.length), (5000, 5001, 28));
+        assert_eq!(packets[2].get_fragments(), [0, 1, 2]);
+        assert!(defragmenter.ipv6().is_empty());
+    }
+}
@@ -0,0 +1,1005 @@
+//! DHCP and BOOTP decoding, and tracking of the leases DHCP and DHCPv6 servers hand out
+//!
+//! DHCPv6 messages are decoded in `dhcpv6`, both feed the same `DhcpTracker`.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use super::{
+    dns,
+    wrapers::{
+        format_duid, format_hardware_address, ParsedPacket, SerializableDhcpOption,
+        SerializableDhcpOptionData, SerializableDhcpPacket, SerializableDhcpRoute,
+        SerializableDhcpSubOption, SerializableDhcpv6Packet, SerializablePacket,
+    },
+};
+
+/// Port DHCP servers and relays listen on, RFC 2131
+pub const DHCP_SERVER_PORT: u16 = 67;
+/// Port DHCP clients listen on
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+/// Fixed BOOTP header before the options, RFC 951
+const HEADER_LENGTH: usize = 236;
+/// Marks the options of DHCP messages, RFC 2131 section 3
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Option telling the `file` and `sname` fields hold options, RFC 2132 section 9.3
+const OPTION_OVERLOAD: u8 = 52;
+
+/// Whether a datagram between a pair of ports is DHCP, relays send from the server port
+pub fn is_dhcp(source: u16, destination: u16) -> bool {
+    [source, destination]
+        .iter()
+        .all(|&port| port == DHCP_SERVER_PORT || port == DHCP_CLIENT_PORT)
+}
+
+/// Decode a DHCP or BOOTP message, the payload of a UDP datagram
+pub fn handle_dhcp_packet(packet: &[u8], offset: usize, parsed_packet: &mut ParsedPacket) {
+    let mut ranges = vec![
+        ("dhcp", 0, packet.len()),
+        ("dhcp.type", 0, 1),
+        ("dhcp.hw.type", 1, 1),
+        ("dhcp.hw.len", 2, 1),
+        ("dhcp.hops", 3, 1),
+        ("dhcp.id", 4, 4),
+        ("dhcp.secs", 8, 2),
+        ("dhcp.flags", 10, 2),
+        ("dhcp.ip.client", 12, 4),
+        ("dhcp.ip.your", 16, 4),
+        ("dhcp.ip.server", 20, 4),
+        ("dhcp.ip.relay", 24, 4),
+    ];
+    let Some(dhcp) = decode_message(packet, &mut ranges) else {
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed DHCP Packet".to_string(),
+        )));
+        return;
+    };
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::DhcpPacket(dhcp)));
+    parsed_packet.add_field_ranges(offset, packet.len(), &ranges);
+}
+
+fn decode_message(
+    packet: &[u8],
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableDhcpPacket> {
+    let header = packet.get(..HEADER_LENGTH)?;
+    let address =
+        |at: usize| Ipv4Addr::new(header[at], header[at + 1], header[at + 2], header[at + 3]);
+    let hardware_length = header[2];
+    let client_hardware_address = header[28..28 + (hardware_length as usize).min(16)].to_vec();
+    ranges.push(("dhcp.hw.mac_addr", 28, client_hardware_address.len()));
+
+    let mut options = Vec::new();
+    let mut overload = 0;
+    if packet.get(HEADER_LENGTH..HEADER_LENGTH + 4) == Some(&MAGIC_COOKIE[..]) {
+        ranges.push(("dhcp.cookie", HEADER_LENGTH, 4));
+        decode_options(
+            packet,
+            HEADER_LENGTH + 4..packet.len(),
+            &mut options,
+            ranges,
+        )?;
+        overload = options
+            .iter()
+            .find_map(|option| match &option.data {
+                SerializableDhcpOptionData::Unknown(value) if option.code == OPTION_OVERLOAD => {
+                    value.first().copied()
+                }
+                _ => None,
+            })
+            .unwrap_or(0);
+        // The file field is read before the sname field, RFC 2131 section 4.1
+        if overload & 1 != 0 {
+            decode_options(packet, 108..236, &mut options, ranges)?;
+        }
+        if overload & 2 != 0 {
+            decode_options(packet, 44..108, &mut options, ranges)?;
+        }
+    }
+    if overload & 2 == 0 {
+        ranges.push(("dhcp.server", 44, 64));
+    }
+    if overload & 1 == 0 {
+        ranges.push(("dhcp.file", 108, 128));
+    }
+
+    Some(SerializableDhcpPacket {
+        op: header[0],
+        hardware_type: header[1],
+        hardware_length,
+        hops: header[3],
+        transaction_id: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+        seconds: u16::from_be_bytes([header[8], header[9]]),
+        flags: u16::from_be_bytes([header[10], header[11]]),
+        client_address: address(12),
+        your_address: address(16),
+        server_address: address(20),
+        relay_address: address(24),
+        client_hardware_address,
+        server_name: match overload & 2 {
+            0 => c_string(&header[44..108]),
+            _ => String::new(),
+        },
+        boot_file: match overload & 1 {
+            0 => c_string(&header[108..236]),
+            _ => String::new(),
+        },
+        options,
+    })
+}
+
+/// Decode the options in `area` of `packet` up to the end option, `None` if one is cut short
+fn decode_options(
+    packet: &[u8],
+    area: std::ops::Range<usize>,
+    options: &mut Vec<SerializableDhcpOption>,
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<()> {
+    packet.get(area.clone())?;
+    let mut position = area.start;
+    while position < area.end {
+        match packet[position] {
+            // Pad
+            0 => position += 1,
+            255 => {
+                ranges.push(("dhcp.option.end", position, 1));
+                break;
+            }
+            code => {
+                let length = *packet[..area.end].get(position + 1)? as usize;
+                let data = packet[..area.end].get(position + 2..position + 2 + length)?;
+                options.push(SerializableDhcpOption {
+                    code,
+                    data: option_data(code, data),
+                });
+                ranges.push(("dhcp.option", position, 2 + length));
+                position += 2 + length;
+            }
+        }
+    }
+    Some(())
+}
+
+/// Decode the value of an option, options of an unexpected length are kept as bytes
+fn option_data(code: u8, data: &[u8]) -> SerializableDhcpOptionData {
+    use SerializableDhcpOptionData as Data;
+
+    let decoded = match code {
+        53 => match data {
+            &[message_type] => Some(Data::MessageType(message_type)),
+            _ => None,
+        },
+        1 | 3..=7 | 9 | 16 | 28 | 32 | 41 | 42 | 44 | 45 | 50 | 54 | 65 | 69..=76 | 118 | 150 => {
+            addresses(data).map(Data::Addresses)
+        }
+        24 | 35 | 38 | 51 | 58 | 59 | 108 => <[u8; 4]>::try_from(data)
+            .ok()
+            .map(|bytes| Data::Seconds(u32::from_be_bytes(bytes))),
+        2 => <[u8; 4]>::try_from(data)
+            .ok()
+            .map(|bytes| Data::TimeOffset(i32::from_be_bytes(bytes))),
+        13 | 22 | 26 | 57 => <[u8; 2]>::try_from(data)
+            .ok()
+            .map(|bytes| Data::Number(u16::from_be_bytes(bytes))),
+        12 | 14 | 15 | 17 | 18 | 40 | 47 | 56 | 60 | 64 | 66 | 67 | 100 | 101 | 114 => {
+            Some(Data::Text(c_string(data)))
+        }
+        55 => Some(Data::ParameterList(data.to_vec())),
+        61 => data
+            .split_first()
+            .map(|(&hardware_type, id)| Data::ClientId {
+                hardware_type,
+                id: id.to_vec(),
+            }),
+        81 => match data {
+            // Names are in DNS wire format when the E flag is set, RFC 4702 section 2.1
+            [flags, _, _, name @ ..] if flags & 0x04 != 0 => dns::names(name)
+                .and_then(|names| names.into_iter().next())
+                .map(|name| Data::Fqdn {
+                    flags: *flags,
+                    name,
+                }),
+            [flags, _, _, name @ ..] => Some(Data::Fqdn {
+                flags: *flags,
+                name: c_string(name),
+            }),
+            _ => None,
+        },
+        119 => dns::names(data).map(Data::DomainSearch),
+        // 249 is Microsoft's, from before classless routes were standardized
+        121 | 249 => routes(data).map(Data::Routes),
+        82 => suboptions(data).map(Data::RelayAgent),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| Data::Unknown(data.to_vec()))
+}
+
+fn addresses(data: &[u8]) -> Option<Vec<Ipv4Addr>> {
+    if data.is_empty() || !data.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(
+        data.chunks_exact(4)
+            .map(|bytes| Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))
+            .collect(),
+    )
+}
+
+/// Decode classless routes, destinations are given by as many bytes as their prefix needs
+fn routes(mut data: &[u8]) -> Option<Vec<SerializableDhcpRoute>> {
+    let mut routes = Vec::new();
+    while let Some((&prefix_length, rest)) = data.split_first() {
+        if prefix_length > 32 {
+            return None;
+        }
+        let significant = (prefix_length as usize).div_ceil(8);
+        let mut destination = [0; 4];
+        destination[..significant].copy_from_slice(rest.get(..significant)?);
+        let router: [u8; 4] = rest.get(significant..significant + 4)?.try_into().ok()?;
+        routes.push(SerializableDhcpRoute {
+            destination: destination.into(),
+            prefix_length,
+            router: router.into(),
+        });
+        data = &rest[significant + 4..];
+    }
+    Some(routes)
+}
+
+fn suboptions(mut data: &[u8]) -> Option<Vec<SerializableDhcpSubOption>> {
+    let mut suboptions = Vec::new();
+    while let [code, length, rest @ ..] = data {
+        let length = *length as usize;
+        suboptions.push(SerializableDhcpSubOption {
+            code: *code,
+            data: rest.get(..length)?.to_vec(),
+        });
+        data = &rest[length..];
+    }
+    data.is_empty().then_some(suboptions)
+}
+
+/// Read text up to its first NUL, fields and some clients pad with them
+fn c_string(data: &[u8]) -> String {
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// DHCP flavour of an exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DhcpVersion {
+    V4,
+    V6,
+}
+
+/// How far an exchange got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeaseState {
+    /// Discover or Solicit sent, waiting for offers
+    Selecting,
+    /// Offer or Advertise received
+    Offered,
+    /// Request, Renew, Rebind or Confirm sent
+    Requesting,
+    /// Address acknowledged by an ACK or Reply
+    Bound,
+    /// Refused by a NAK, or a Reply with an error status
+    Rejected,
+    /// Address declined by the client, found in use
+    Declined,
+    Released,
+    /// Configuration asked for without an address, by Inform or Information-request
+    Informed,
+}
+
+impl LeaseState {
+    /// Get the name shown in the lease view
+    pub fn name(self) -> &'static str {
+        match self {
+            LeaseState::Selecting => "Selecting",
+            LeaseState::Offered => "Offered",
+            LeaseState::Requesting => "Requesting",
+            LeaseState::Bound => "Bound",
+            LeaseState::Rejected => "Rejected",
+            LeaseState::Declined => "Declined",
+            LeaseState::Released => "Released",
+            LeaseState::Informed => "Informed",
+        }
+    }
+
+    /// Whether the exchange is over, the client's next message starts another
+    fn is_done(self) -> bool {
+        !matches!(
+            self,
+            LeaseState::Selecting | LeaseState::Offered | LeaseState::Requesting
+        )
+    }
+}
+
+/// An exchange of a client with DHCP servers, e.g. DISCOVER, OFFER, REQUEST and ACK
+pub struct Lease {
+    pub version: DhcpVersion,
+    /// Client hardware address, or DUID for DHCPv6
+    pub client: String,
+    pub hostname: Option<String>,
+    pub state: LeaseState,
+    /// Address requested, offered or assigned
+    pub address: Option<IpAddr>,
+    /// Prefix delegated to a DHCPv6 client
+    pub prefix: Option<(Ipv6Addr, u8)>,
+    pub server: Option<IpAddr>,
+    /// Lease time, or valid lifetime, in seconds
+    pub lease_time: Option<u32>,
+    /// Capture time of the exchange's first message
+    pub started: Duration,
+    /// Time from the first message to the first offer
+    pub offer_time: Option<Duration>,
+    /// Time from the first message to the server's final answer
+    pub reply_time: Option<Duration>,
+    /// Ids of the packets of the exchange
+    pub packets: Vec<usize>,
+    /// Transaction id of the client's last message, servers answer with it
+    transaction_id: u32,
+}
+
+/// What a message does to an exchange, the same for DHCP and DHCPv6
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Start,
+    Request,
+    Inform,
+    Decline,
+    Release,
+    Offer,
+    Ack,
+    Nak,
+}
+
+impl Step {
+    fn is_from_client(self) -> bool {
+        !matches!(self, Step::Offer | Step::Ack | Step::Nak)
+    }
+}
+
+/// Correlates DHCP and DHCPv6 messages into exchanges per client
+///
+/// DHCP clients keep their transaction id across an exchange, DHCPv6 clients pick a new one
+/// for each message, so the exchange a client message belongs to is the client's latest until
+/// it is over. Server messages belong to the exchange whose last client message they answer.
+#[derive(Default)]
+pub struct DhcpTracker {
+    leases: Vec<Lease>,
+    /// Index in `leases` of each client's latest exchange, keyed on its hardware address or
+    /// DUID
+    current: HashMap<(DhcpVersion, Vec<u8>), usize>,
+}
+
+impl DhcpTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the exchanges seen, in the order they started
+    pub fn leases(&self) -> &[Lease] {
+        &self.leases
+    }
+
+    /// Add a DHCP or DHCPv6 message to the exchange it belongs to
+    pub fn process(&mut self, packet: &ParsedPacket) {
+        let source = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4)) => Some(IpAddr::from(ipv4.source)),
+            Some(SerializablePacket::Ipv6Packet(ipv6)) => Some(IpAddr::from(ipv6.source)),
+            _ => None,
+        };
+        if let Some(dhcp) = packet.find::<SerializableDhcpPacket>() {
+            self.process_dhcp(dhcp, source, packet);
+        } else if let Some(dhcpv6) = packet.find::<SerializableDhcpv6Packet>() {
+            self.process_dhcpv6(dhcpv6.relayed(), source, packet);
+        }
+    }
+
+    fn process_dhcp(
+        &mut self,
+        dhcp: &SerializableDhcpPacket,
+        source: Option<IpAddr>,
+        packet: &ParsedPacket,
+    ) {
+        let step = match dhcp.message_type() {
+            Some(1) => Step::Start,
+            Some(2) => Step::Offer,
+            Some(3) => Step::Request,
+            Some(4) => Step::Decline,
+            Some(5) => Step::Ack,
+            Some(6) => Step::Nak,
+            Some(7) => Step::Release,
+            Some(8) => Step::Inform,
+            _ => return,
+        };
+        let key = (DhcpVersion::V4, dhcp.client_hardware_address.clone());
+        let client = format_hardware_address(&dhcp.client_hardware_address);
+        let lease = self.step(key, client, dhcp.transaction_id, step, packet);
+
+        let assigned = Some(dhcp.your_address).filter(|address| !address.is_unspecified());
+        let address = match step {
+            Step::Offer | Step::Ack => assigned,
+            Step::Start | Step::Request | Step::Decline => dhcp.address_option(50),
+            _ => None,
+        };
+        let address = address.or(Some(dhcp.client_address).filter(|a| !a.is_unspecified()));
+        if let Some(address) = address {
+            lease.address = Some(address.into());
+        }
+        let server = dhcp.address_option(54).map(IpAddr::from);
+        if step.is_from_client() {
+            lease.server = server.or(lease.server);
+            if let Some(hostname) = dhcp.text_option(12) {
+                lease.hostname = Some(hostname.to_string());
+            }
+        } else {
+            lease.server = server.or(source);
+            lease.lease_time = dhcp.lease_time().or(lease.lease_time);
+        }
+    }
+
+    fn process_dhcpv6(
+        &mut self,
+        dhcpv6: &SerializableDhcpv6Packet,
+        source: Option<IpAddr>,
+        packet: &ParsedPacket,
+    ) {
+        let step = match dhcpv6.message_type {
+            1 => Step::Start,
+            2 => Step::Offer,
+            3..=6 => Step::Request,
+            7 if dhcpv6.error_status().is_some() => Step::Nak,
+            7 => Step::Ack,
+            8 => Step::Release,
+            9 => Step::Decline,
+            11 => Step::Inform,
+            _ => return,
+        };
+        let Some(duid) = dhcpv6.client_id() else {
+            return;
+        };
+        let key = (DhcpVersion::V6, duid.to_vec());
+        let lease = self.step(key, format_duid(duid), dhcpv6.transaction_id, step, packet);
+
+        for (address, prefix_length, valid_lifetime) in dhcpv6.leases() {
+            if prefix_length == 128 {
+                lease.address = Some(address.into());
+            } else {
+                lease.prefix = Some((address, prefix_length));
+            }
+            if !step.is_from_client() {
+                lease.lease_time = Some(valid_lifetime);
+            }
+        }
+        if !step.is_from_client() {
+            lease.server = source;
+        }
+    }
+
+    /// Get the exchange a message belongs to, starting one if needed, and move it along
+    fn step(
+        &mut self,
+        key: (DhcpVersion, Vec<u8>),
+        client: String,
+        transaction_id: u32,
+        step: Step,
+        packet: &ParsedPacket,
+    ) -> &mut Lease {
+        let timestamp = packet.get_timestamp();
+        let continues = self.current.get(&key).is_some_and(|&index| {
+            let lease = &self.leases[index];
+            match step {
+                // Retransmissions keep the transaction id
+                Step::Start | Step::Inform => lease.transaction_id == transaction_id,
+                _ if step.is_from_client() => {
+                    lease.transaction_id == transaction_id || !lease.state.is_done()
+                }
+                _ => lease.transaction_id == transaction_id,
+            }
+        });
+        if !continues {
+            // Renewing clients don't always send their host name again
+            let hostname = self
+                .current
+                .insert(key.clone(), self.leases.len())
+                .and_then(|previous| self.leases[previous].hostname.clone());
+            self.leases.push(Lease {
+                version: key.0,
+                client,
+                hostname,
+                state: LeaseState::Selecting,
+                address: None,
+                prefix: None,
+                server: None,
+                lease_time: None,
+                started: timestamp,
+                offer_time: None,
+                reply_time: None,
+                packets: Vec::new(),
+                transaction_id,
+            });
+        }
+        let lease = &mut self.leases[self.current[&key]];
+        let elapsed = timestamp.saturating_sub(lease.started);
+        lease.packets.push(packet.get_id());
+        lease.state = match step {
+            Step::Start => LeaseState::Selecting,
+            Step::Request => LeaseState::Requesting,
+            Step::Inform => LeaseState::Informed,
+            Step::Decline => LeaseState::Declined,
+            Step::Release => LeaseState::Released,
+            // Late offers from other servers don't take a client back
+            Step::Offer if lease.state == LeaseState::Selecting => LeaseState::Offered,
+            Step::Offer => lease.state,
+            // Servers acknowledge informs, declines and releases too
+            Step::Ack if lease.state.is_done() && lease.state != LeaseState::Rejected => {
+                lease.state
+            }
+            Step::Ack => LeaseState::Bound,
+            Step::Nak => LeaseState::Rejected,
+        };
+        if step.is_from_client() {
+            lease.transaction_id = transaction_id;
+        } else if step == Step::Offer {
+            lease.offer_time.get_or_insert(elapsed);
+        } else {
+            lease.reply_time = Some(elapsed);
+        }
+        lease
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        defrag::Defragmenter,
+        dhcpv6::{DHCPV6_CLIENT_PORT, DHCPV6_SERVER_PORT},
+        Parser,
+    };
+
+    const CLIENT: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+
+    fn option(code: u8, data: &[u8]) -> Vec<u8> {
+        let mut option = vec![code, data.len() as u8];
+        option.extend(data);
+        option
+    }
+
+    /// A message from or to `CLIENT` with `options` after the cookie
+    fn message(op: u8, transaction_id: u32, client_address: [u8; 4], options: &[u8]) -> Vec<u8> {
+        let mut message = vec![0; HEADER_LENGTH];
+        message[..3].copy_from_slice(&[op, 1, 6]);
+        message[4..8].copy_from_slice(&transaction_id.to_be_bytes());
+        message[12..16].copy_from_slice(&client_address);
+        message[28..34].copy_from_slice(&CLIENT);
+        message.extend(MAGIC_COOKIE);
+        message.extend(options);
+        message
+    }
+
+    /// A DHCP message of type `message_type`, servers offer and acknowledge `192.0.2.10`
+    fn dhcp(message_type: u8, transaction_id: u32, options: &[u8]) -> Vec<u8> {
+        let from_server = matches!(message_type, 2 | 5 | 6);
+        let mut options = [&option(53, &[message_type]), options, &[255]].concat();
+        if from_server {
+            options.splice(..0, option(54, &[192, 0, 2, 1]));
+        }
+        let mut message = message(1 + from_server as u8, transaction_id, [0; 4], &options);
+        if matches!(message_type, 2 | 5) {
+            message[16..20].copy_from_slice(&[192, 0, 2, 10]);
+        }
+        message
+    }
+
+    fn decode(message: &[u8]) -> Option<SerializableDhcpPacket> {
+        decode_message(message, &mut Vec::new())
+    }
+
+    /// Parse a datagram carrying `payload` from the client of `version`, or from its server
+    fn packet(
+        id: usize,
+        millis: u64,
+        version: DhcpVersion,
+        from_server: bool,
+        payload: &[u8],
+    ) -> ParsedPacket {
+        let udp_length = (8 + payload.len() as u16).to_be_bytes();
+        let mut frame = vec![0; 12];
+        let (client_port, server_port) = match version {
+            DhcpVersion::V4 => {
+                let (source, destination) = match from_server {
+                    true => ([192, 0, 2, 1], [255; 4]),
+                    false => ([0; 4], [255; 4]),
+                };
+                frame.extend([0x08, 0x00, 0x45, 0]);
+                frame.extend((20 + 8 + payload.len() as u16).to_be_bytes());
+                frame.extend([0, 0, 0, 0, 64, 17, 0, 0]);
+                frame.extend(source);
+                frame.extend(destination);
+                (DHCP_CLIENT_PORT, DHCP_SERVER_PORT)
+            }
+            DhcpVersion::V6 => {
+                let server: Ipv6Addr = "2001:db8::1".parse().unwrap();
+                let client: Ipv6Addr = "fe80::1".parse().unwrap();
+                let servers: Ipv6Addr = "ff02::1:2".parse().unwrap();
+                let (source, destination) = match from_server {
+                    true => (server, client),
+                    false => (client, servers),
+                };
+                frame.extend([0x86, 0xdd, 0x60, 0, 0, 0]);
+                frame.extend(udp_length);
+                frame.extend([17, 64]);
+                frame.extend(source.octets());
+                frame.extend(destination.octets());
+                (DHCPV6_CLIENT_PORT, DHCPV6_SERVER_PORT)
+            }
+        };
+        let (source_port, destination_port) = match from_server {
+            true => (server_port, client_port),
+            false => (client_port, server_port),
+        };
+        frame.extend(source_port.to_be_bytes());
+        frame.extend(destination_port.to_be_bytes());
+        frame.extend(udp_length);
+        frame.extend([0, 0]);
+        frame.extend(payload);
+
+        let frame = CapturedFrame {
+            data: &frame,
+            timestamp: Duration::from_millis(millis),
+            original_length: frame.len(),
+            link_type: LinkType::ETHERNET,
+            interface_name: None,
+            comments: &[],
+        };
+        Parser::parse_frame(&frame, id, &mut Defragmenter::new())
+    }
+
+    /// Feed DHCP messages to a tracker, one every 5 ms
+    fn track(messages: &[Vec<u8>]) -> DhcpTracker {
+        let mut tracker = DhcpTracker::new();
+        for (id, message) in messages.iter().enumerate() {
+            let packet = packet(id, 5 * id as u64, DhcpVersion::V4, message[0] == 2, message);
+            tracker.process(&packet);
+        }
+        tracker
+    }
+
+    #[test]
+    fn decodes_options() {
+        let options = [
+            &[0, 0][..],
+            &option(53, &[1]),
+            &option(3, &[192, 0, 2, 1, 192, 0, 2, 2]),
+            &option(51, &3600u32.to_be_bytes()),
+            &option(12, b"laptop\0"),
+            &option(61, &[1, 2, 0, 0, 0, 0, 1]),
+            &option(121, &[24, 198, 51, 100, 192, 0, 2, 1, 0, 192, 0, 2, 1]),
+            // A lease time of the wrong length is kept as bytes
+            &option(58, &[0, 1, 2]),
+            &[0, 255],
+            // Nothing after the end option is read, even if it is cut short
+            &[53, 4, 1],
+        ]
+        .concat();
+        let mut ranges = Vec::new();
+        let dhcp = decode_message(&message(1, 7, [0; 4], &options), &mut ranges).unwrap();
+        assert_eq!(dhcp.transaction_id, 7);
+        assert_eq!(dhcp.client_hardware_address, CLIENT);
+        assert_eq!(dhcp.message_type(), Some(1));
+        assert_eq!(dhcp.address_option(3), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(dhcp.lease_time(), Some(3600));
+        assert_eq!(dhcp.text_option(12), Some("laptop"));
+        assert!(matches!(
+            dhcp.option(61),
+            Some(SerializableDhcpOptionData::ClientId { hardware_type: 1, id }) if id == &[2, 0, 0, 0, 0, 1]
+        ));
+        let Some(SerializableDhcpOptionData::Routes(routes)) = dhcp.option(121) else {
+            panic!("routes not decoded");
+        };
+        let routes: Vec<_> = routes
+            .iter()
+            .map(|route| (route.destination, route.prefix_length, route.router))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                (
+                    Ipv4Addr::new(198, 51, 100, 0),
+                    24,
+                    Ipv4Addr::new(192, 0, 2, 1)
+                ),
+                (Ipv4Addr::UNSPECIFIED, 0, Ipv4Addr::new(192, 0, 2, 1)),
+            ]
+        );
+        assert!(matches!(
+            dhcp.option(58),
+            Some(SerializableDhcpOptionData::Unknown(bytes)) if bytes == &[0, 1, 2]
+        ));
+        assert_eq!(dhcp.options.len(), 7);
+
+        let option_ranges: Vec<_> = ranges
+            .iter()
+            .filter(|(name, _, _)| name.starts_with("dhcp.option"))
+            .map(|&(name, start, length)| (name, start - HEADER_LENGTH - 4, length))
+            .collect();
+        assert_eq!(option_ranges.first(), Some(&("dhcp.option", 2, 3)));
+        assert_eq!(option_ranges.last(), Some(&("dhcp.option.end", 60, 1)));
+        assert!(ranges.contains(&("dhcp.server", 44, 64)));
+        assert!(ranges.contains(&("dhcp.file", 108, 128)));
+    }
+
+    #[test]
+    fn decodes_bootp_messages() {
+        let mut message = message(2, 7, [0; 4], &[]);
+        message.truncate(HEADER_LENGTH);
+        message[44..52].copy_from_slice(b"boothost");
+        message[108..116].copy_from_slice(b"pxelinux");
+        let bootp = decode(&message).unwrap();
+        assert_eq!(bootp.message_type(), None);
+        assert!(bootp.options.is_empty());
+        assert_eq!(bootp.server_name, "boothost");
+        assert_eq!(bootp.boot_file, "pxelinux");
+    }
+
+    #[test]
+    fn reads_overloaded_fields() {
+        let mut message = message(
+            2,
+            7,
+            [0; 4],
+            &[option(53, &[5]), option(OPTION_OVERLOAD, &[3]), vec![255]].concat(),
+        );
+        // The file field ends at its end option, the sname field at its end
+        message[108..114].copy_from_slice(&option(51, &3600u32.to_be_bytes()));
+        message[114] = 255;
+        message[115] = 12;
+        message[44..52].copy_from_slice(&option(12, b"server"));
+        message[52..107].fill(0);
+        message[107] = 255;
+        let mut ranges = Vec::new();
+        let dhcp = decode_message(&message, &mut ranges).unwrap();
+        let codes: Vec<_> = dhcp.options.iter().map(|option| option.code).collect();
+        assert_eq!(codes, [53, OPTION_OVERLOAD, 51, 12]);
+        assert_eq!(dhcp.lease_time(), Some(3600));
+        assert_eq!(dhcp.text_option(12), Some("server"));
+        assert_eq!(dhcp.server_name, "");
+        assert_eq!(dhcp.boot_file, "");
+        assert!(ranges.contains(&("dhcp.option.end", 114, 1)));
+        assert!(ranges.contains(&("dhcp.option.end", 107, 1)));
+        assert!(!ranges
+            .iter()
+            .any(|&(name, _, _)| name == "dhcp.server" || name == "dhcp.file"));
+
+        // Only the file field
+        message[HEADER_LENGTH + 9] = 1;
+        let dhcp = decode(&message).unwrap();
+        assert_eq!(dhcp.options.len(), 3);
+        assert_eq!(dhcp.server_name, "\x0c\x06server");
+        assert_eq!(dhcp.boot_file, "");
+    }
+
+    #[test]
+    fn rejects_truncated_options() {
+        let decodes = |options: &[u8]| decode(&message(1, 7, [0; 4], options)).is_some();
+        assert!(decodes(&[53, 1, 1, 255]));
+        // Without an end option, the options end with the message
+        assert!(decodes(&[53, 1, 1]));
+        assert!(!decodes(&[53, 1, 1, 12]));
+        assert!(!decodes(&[53, 1, 1, 12, 4, b'a', b'b']));
+        // The header is cut short
+        assert!(decode(&message(1, 7, [0; 4], &[])[..HEADER_LENGTH - 1]).is_none());
+
+        // Options in overloaded fields don't run past them
+        let mut message = message(1, 7, [0; 4], &[52, 1, 1, 255]);
+        message[234..236].copy_from_slice(&[12, 1]);
+        assert!(decode(&message).is_none());
+        message[235] = 0;
+        assert!(decode(&message).is_some());
+
+        let packet = packet(0, 0, DhcpVersion::V4, false, &message[..HEADER_LENGTH - 1]);
+        assert!(matches!(
+            packet.get_application_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed DHCP Packet"
+        ));
+    }
+
+    #[test]
+    fn follows_exchanges() {
+        let tracker = track(&[
+            dhcp(1, 7, &option(12, b"laptop")),
+            dhcp(2, 7, &option(51, &3600u32.to_be_bytes())),
+            dhcp(
+                3,
+                7,
+                &[option(50, &[192, 0, 2, 10]), option(54, &[192, 0, 2, 1])].concat(),
+            ),
+            dhcp(5, 7, &option(51, &3600u32.to_be_bytes())),
+        ]);
+        let [lease] = tracker.leases() else {
+            panic!("expected one exchange");
+        };
+        assert_eq!(lease.version, DhcpVersion::V4);
+        assert_eq!(lease.client, format_hardware_address(&CLIENT));
+        assert_eq!(lease.hostname.as_deref(), Some("laptop"));
+        assert_eq!(lease.state, LeaseState::Bound);
+        assert_eq!(lease.address, Some(IpAddr::from([192, 0, 2, 10])));
+        assert_eq!(lease.server, Some(IpAddr::from([192, 0, 2, 1])));
+        assert_eq!(lease.lease_time, Some(3600));
+        assert_eq!(lease.offer_time, Some(Duration::from_millis(5)));
+        assert_eq!(lease.reply_time, Some(Duration::from_millis(15)));
+        assert_eq!(lease.packets, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn moves_through_each_state() {
+        let states = |messages: &[Vec<u8>]| {
+            let mut tracker = DhcpTracker::new();
+            messages
+                .iter()
+                .enumerate()
+                .map(|(id, message)| {
+                    let from_server = message[0] == 2;
+                    tracker.process(&packet(id, 0, DhcpVersion::V4, from_server, message));
+                    let lease = tracker.leases().last().unwrap();
+                    (tracker.leases().len(), lease.state)
+                })
+                .collect::<Vec<_>>()
+        };
+        use LeaseState::*;
+        assert_eq!(
+            states(&[
+                dhcp(1, 7, &[]),
+                dhcp(2, 7, &[]),
+                dhcp(3, 7, &[]),
+                dhcp(6, 7, &[])
+            ]),
+            [(1, Selecting), (1, Offered), (1, Requesting), (1, Rejected)]
+        );
+        assert_eq!(
+            states(&[
+                dhcp(1, 7, &[]),
+                dhcp(2, 7, &[]),
+                // A second server's offer comes after the client chose the first
+                dhcp(3, 7, &[]),
+                dhcp(2, 7, &[]),
+                dhcp(5, 7, &[]),
+                // Found in use
+                dhcp(4, 7, &[]),
+            ]),
+            [
+                (1, Selecting),
+                (1, Offered),
+                (1, Requesting),
+                (1, Requesting),
+                (1, Bound),
+                (1, Declined),
+            ]
+        );
+        // Each message after an exchange is over starts the next one
+        assert_eq!(
+            states(&[
+                dhcp(8, 7, &[]),
+                dhcp(5, 7, &[]),
+                dhcp(7, 8, &[]),
+                dhcp(1, 9, &[])
+            ]),
+            [(1, Informed), (1, Informed), (2, Released), (3, Selecting)]
+        );
+        // Retransmitted discovers keep their transaction id
+        assert_eq!(
+            states(&[dhcp(1, 7, &[]), dhcp(1, 7, &[]), dhcp(1, 8, &[])]),
+            [(1, Selecting), (1, Selecting), (2, Selecting)]
+        );
+        // Servers answer the client's last transaction only
+        assert_eq!(
+            states(&[dhcp(1, 7, &[]), dhcp(2, 8, &[])]),
+            [(1, Selecting), (2, Offered)]
+        );
+    }
+
+    #[test]
+    fn starts_an_exchange_per_renewal() {
+        let bound = [
+            dhcp(1, 7, &option(12, b"laptop")),
+            dhcp(2, 7, &[]),
+            dhcp(3, 7, &[]),
+            dhcp(5, 7, &option(51, &60u32.to_be_bytes())),
+        ];
+        // Renewing before the lease runs out, from the address it assigned
+        let mut renew = dhcp(3, 8, &[]);
+        renew[12..16].copy_from_slice(&[192, 0, 2, 10]);
+        let mut release = dhcp(7, 9, &[]);
+        release[12..16].copy_from_slice(&[192, 0, 2, 10]);
+        let messages = [&bound[..], &[renew, dhcp(5, 8, &[]), release]].concat();
+        let tracker = track(&messages);
+
+        let [first, renewal, released] = tracker.leases() else {
+            panic!("expected three exchanges");
+        };
+        assert_eq!(first.state, LeaseState::Bound);
+        assert_eq!(first.lease_time, Some(60));
+        assert_eq!(renewal.state, LeaseState::Bound);
+        assert_eq!(renewal.started, Duration::from_millis(20));
+        assert_eq!(renewal.reply_time, Some(Duration::from_millis(5)));
+        assert_eq!(renewal.offer_time, None);
+        assert_eq!(renewal.hostname.as_deref(), Some("laptop"));
+        assert_eq!(renewal.address, Some(IpAddr::from([192, 0, 2, 10])));
+        assert_eq!(renewal.packets, [4, 5]);
+        assert_eq!(released.state, LeaseState::Released);
+        assert_eq!(released.address, Some(IpAddr::from([192, 0, 2, 10])));
+        assert_eq!(released.reply_time, None);
+    }
+
+    #[test]
+    fn follows_dhcpv6_exchanges() {
+        fn dhcpv6(message_type: u8, transaction_id: u32, options: &[u8]) -> Vec<u8> {
+            let mut message = transaction_id.to_be_bytes();
+            message[0] = message_type;
+            [&message[..], &[0, 1, 0, 4, 0, 3, 0, 1], options].concat()
+        }
+        let address: Ipv6Addr = "2001:db8::10".parse().unwrap();
+        let ia_address = [
+            &[0, 5, 0, 24][..],
+            &address.octets(),
+            &[0, 0, 0, 60, 0, 0, 0, 120],
+        ]
+        .concat();
+        let ia_na = [
+            &[0, 3, 0, 40, 0, 0, 0, 1, 0, 0, 0, 30, 0, 0, 0, 48][..],
+            &ia_address,
+        ]
+        .concat();
+        let no_binding = [
+            &[0, 3, 0, 18, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0][..],
+            &[0, 13, 0, 2, 0, 3],
+        ]
+        .concat();
+        let messages = [
+            (false, dhcpv6(1, 1, &[])),
+            (true, dhcpv6(2, 1, &ia_na)),
+            // Clients pick a new transaction id for each message
+            (false, dhcpv6(3, 2, &ia_na)),
+            (true, dhcpv6(7, 2, &ia_na)),
+            (false, dhcpv6(5, 3, &ia_na)),
+            (true, dhcpv6(7, 3, &no_binding)),
+        ];
+        let mut tracker = DhcpTracker::new();
+        for (id, (from_server, message)) in messages.iter().enumerate() {
+            tracker.process(&packet(
+                id,
+                5 * id as u64,
+                DhcpVersion::V6,
+                *from_server,
+                message,
+            ));
+        }
+
+        let [lease, renewal] = tracker.leases() else {
+            panic!("expected two exchanges");
+        };
+        assert_eq!(lease.version, DhcpVersion::V6);
+        assert_eq!(lease.client, format_duid(&[0, 3, 0, 1]));
+        assert_eq!(lease.state, LeaseState::Bound);
+        assert_eq!(lease.address, Some(address.into()));
+        assert_eq!(lease.server, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(lease.lease_time, Some(120));
+        assert_eq!(lease.offer_time, Some(Duration::from_millis(5)));
+        assert_eq!(lease.reply_time, Some(Duration::from_millis(15)));
+        assert_eq!(lease.packets, [0, 1, 2, 3]);
+        assert_eq!(renewal.state, LeaseState::Rejected);
+        assert_eq!(renewal.packets, [4, 5]);
+    }
+}
@@ -0,0 +1,362 @@
+//! DHCPv6 decoding, RFC 8415, leases are tracked along with DHCP ones by `dhcp::DhcpTracker`
+
+use std::net::Ipv6Addr;
+
+use super::{
+    dns,
+    wrapers::{
+        ParsedPacket, SerializableDhcpv6Option, SerializableDhcpv6OptionData,
+        SerializableDhcpv6Packet, SerializableDhcpv6Relay, SerializablePacket,
+    },
+};
+
+/// Port DHCPv6 clients listen on
+pub const DHCPV6_CLIENT_PORT: u16 = 546;
+/// Port DHCPv6 servers and relays listen on
+pub const DHCPV6_SERVER_PORT: u16 = 547;
+
+/// Relay messages nested in each other, so crafted messages can't recurse forever
+const MAX_RELAY_DEPTH: usize = 32;
+
+/// Whether a datagram between a pair of ports is DHCPv6, relays send from the server port
+pub fn is_dhcpv6(source: u16, destination: u16) -> bool {
+    [source, destination]
+        .iter()
+        .all(|&port| port == DHCPV6_CLIENT_PORT || port == DHCPV6_SERVER_PORT)
+}
+
+/// Decode a DHCPv6 message, the payload of a UDP datagram
+pub fn handle_dhcpv6_packet(packet: &[u8], offset: usize, parsed_packet: &mut ParsedPacket) {
+    let mut ranges = vec![("dhcpv6", 0, packet.len()), ("dhcpv6.msgtype", 0, 1)];
+    let Some(dhcpv6) = decode_message(packet, 0, &mut ranges) else {
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed DHCPv6 Packet".to_string(),
+        )));
+        return;
+    };
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::Dhcpv6Packet(dhcpv6)));
+    parsed_packet.add_field_ranges(offset, packet.len(), &ranges);
+}
+
+/// Decode a message, only the ranges of the outermost one are recorded
+fn decode_message(
+    message: &[u8],
+    depth: usize,
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableDhcpv6Packet> {
+    let (&message_type, rest) = message.split_first()?;
+    let (transaction_id, relay, options) = match message_type {
+        // Relay-forward and Relay-reply, RFC 8415 section 9
+        12 | 13 => {
+            let header = rest.get(..33)?;
+            let address = |at: usize| {
+                let bytes: [u8; 16] = header[at..at + 16].try_into().unwrap();
+                Ipv6Addr::from(bytes)
+            };
+            ranges.extend([
+                ("dhcpv6.hopcount", 1, 1),
+                ("dhcpv6.linkaddr", 2, 16),
+                ("dhcpv6.peeraddr", 18, 16),
+            ]);
+            let relay = SerializableDhcpv6Relay {
+                hop_count: header[0],
+                link_address: address(1),
+                peer_address: address(17),
+            };
+            (0, Some(relay), 34)
+        }
+        _ => {
+            let &[a, b, c] = rest.get(..3)? else {
+                return None;
+            };
+            ranges.push(("dhcpv6.xid", 1, 3));
+            (u32::from_be_bytes([0, a, b, c]), None, 4)
+        }
+    };
+    let options = decode_options(message, options, depth, Some(ranges))?;
+    Some(SerializableDhcpv6Packet {
+        message_type,
+        transaction_id,
+        relay,
+        options,
+    })
+}
+
+/// Decode the options from `start` to the end of `data`, recording their ranges if `ranges`
+/// is given
+fn decode_options(
+    data: &[u8],
+    start: usize,
+    depth: usize,
+    mut ranges: Option<&mut Vec<(&'static str, usize, usize)>>,
+) -> Option<Vec<SerializableDhcpv6Option>> {
+    let mut options = Vec::new();
+    let mut position = start;
+    while position < data.len() {
+        let &[a, b, c, d] = data.get(position..position + 4)? else {
+            return None;
+        };
+        let code = u16::from_be_bytes([a, b]);
+        let length = u16::from_be_bytes([c, d]) as usize;
+        let value = data.get(position + 4..position + 4 + length)?;
+        options.push(SerializableDhcpv6Option {
+            code,
+            data: option_data(code, value, depth)?,
+        });
+        if let Some(ranges) = ranges.as_deref_mut() {
+            ranges.push(("dhcpv6.option", position, 4 + length));
+        }
+        position += 4 + length;
+    }
+    Some(options)
+}
+
+/// Decode the value of an option, `None` if options nested in it don't fit
+fn option_data(code: u16, data: &[u8], depth: usize) -> Option<SerializableDhcpv6OptionData> {
+    use SerializableDhcpv6OptionData as Data;
+
+    let u32_at = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+    let decoded = match code {
+        1 | 2 if !data.is_empty() => Data::Duid(data.to_vec()),
+        // IA_NA and IA_PD
+        3 | 25 if data.len() >= 12 => Data::Ia {
+            iaid: u32_at(0),
+            t1: u32_at(4),
+            t2: u32_at(8),
+            options: decode_options(data, 12, depth, None)?,
+        },
+        4 if data.len() >= 4 => Data::IaTemporary {
+            iaid: u32_at(0),
+            options: decode_options(data, 4, depth, None)?,
+        },
+        5 if data.len() >= 24 => Data::IaAddress {
+            address: address(&data[..16]),
+            preferred_lifetime: u32_at(16),
+            valid_lifetime: u32_at(20),
+            options: decode_options(data, 24, depth, None)?,
+        },
+        26 if data.len() >= 25 => Data::IaPrefix {
+            preferred_lifetime: u32_at(0),
+            valid_lifetime: u32_at(4),
+            prefix_length: data[8],
+            prefix: address(&data[9..25]),
+            options: decode_options(data, 25, depth, None)?,
+        },
+        6 if data.len().is_multiple_of(2) => Data::OptionRequest(
+            data.chunks_exact(2)
+                .map(|code| u16::from_be_bytes([code[0], code[1]]))
+                .collect(),
+        ),
+        7 if data.len() == 1 => Data::Preference(data[0]),
+        8 if data.len() == 2 => Data::ElapsedTime(u16::from_be_bytes([data[0], data[1]])),
+        9 if depth < MAX_RELAY_DEPTH => {
+            Data::RelayMessage(Box::new(decode_message(data, depth + 1, &mut Vec::new())?))
+        }
+        13 if data.len() >= 2 => Data::StatusCode {
+            code: u16::from_be_bytes([data[0], data[1]]),
+            message: String::from_utf8_lossy(&data[2..]).into_owned(),
+        },
+        // Rapid Commit, Reconfigure Accept
+        14 | 20 if data.is_empty() => Data::Empty,
+        // DNS, SIP and SNTP servers
+        22 | 23 | 31 if !data.is_empty() && data.len().is_multiple_of(16) => {
+            Data::Addresses(data.chunks_exact(16).map(address).collect())
+        }
+        21 | 24 => match dns::names(data) {
+            Some(names) => Data::DomainList(names),
+            None => Data::Unknown(data.to_vec()),
+        },
+        _ => Data::Unknown(data.to_vec()),
+    };
+    Some(decoded)
+}
+
+fn address(bytes: &[u8]) -> Ipv6Addr {
+    let bytes: [u8; 16] = bytes.try_into().unwrap();
+    Ipv6Addr::from(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(code: u16, data: &[u8]) -> Vec<u8> {
+        let mut option = code.to_be_bytes().to_vec();
+        option.extend((data.len() as u16).to_be_bytes());
+        option.extend(data);
+        option
+    }
+
+    /// A Solicit with transaction id 0x123456 and `options`
+    fn solicit(options: &[u8]) -> Vec<u8> {
+        [&[1, 0x12, 0x34, 0x56][..], options].concat()
+    }
+
+    /// A Relay-forward from hop `hop_count` carrying `message`
+    fn relay(hop_count: u8, message: &[u8]) -> Vec<u8> {
+        let mut relay = vec![12, hop_count];
+        relay.extend([0; 16]);
+        relay.extend("fe80::1".parse::<Ipv6Addr>().unwrap().octets());
+        relay.extend(option(9, message));
+        relay
+    }
+
+    fn decode(message: &[u8]) -> Option<SerializableDhcpv6Packet> {
+        decode_message(message, 0, &mut Vec::new())
+    }
+
+    #[test]
+    fn decodes_options() {
+        let address: Ipv6Addr = "2001:db8::10".parse().unwrap();
+        let ia_address = [&address.octets()[..], &[0, 0, 0, 60, 0, 0, 0, 120]].concat();
+        let ia_na = [
+            &[0, 0, 0, 1, 0, 0, 0, 30, 0, 0, 0, 48][..],
+            &option(5, &ia_address),
+            &option(13, b"\x00\x00ok"),
+        ]
+        .concat();
+        let ia_prefix = [
+            &[0, 0, 0, 60, 0, 0, 0, 120, 56][..],
+            &[0x20, 0x01, 0x0d, 0xb8],
+        ]
+        .concat();
+        let ia_pd = [
+            &[0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0][..],
+            &option(26, &[&ia_prefix[..], &[0; 12]].concat()),
+        ]
+        .concat();
+        let options = [
+            option(1, &[0, 3, 0, 1, 2, 0, 0, 0, 0, 1]),
+            option(3, &ia_na),
+            option(25, &ia_pd),
+            option(6, &[0, 23, 0, 24]),
+            option(8, &[0, 100]),
+            option(14, &[]),
+            option(23, &address.octets()),
+            option(24, b"\x07example\x03com\x00"),
+            // A preference of the wrong length is kept as bytes
+            option(7, &[0, 255]),
+        ]
+        .concat();
+        let mut ranges = Vec::new();
+        let dhcpv6 = decode_message(&solicit(&options), 0, &mut ranges).unwrap();
+        assert_eq!(dhcpv6.message_type, 1);
+        assert_eq!(dhcpv6.transaction_id, 0x123456);
+        assert_eq!(
+            dhcpv6.client_id(),
+            Some(&[0, 3, 0, 1, 2, 0, 0, 0, 0, 1][..])
+        );
+        assert_eq!(
+            dhcpv6.leases(),
+            [
+                (address, 128, 120),
+                ("2001:db8::".parse().unwrap(), 56, 120)
+            ]
+        );
+        assert_eq!(dhcpv6.error_status(), None);
+        assert!(matches!(
+            dhcpv6.option(6),
+            Some(SerializableDhcpv6OptionData::OptionRequest(codes)) if codes == &[23, 24]
+        ));
+        assert!(matches!(
+            dhcpv6.option(8),
+            Some(SerializableDhcpv6OptionData::ElapsedTime(100))
+        ));
+        assert!(matches!(
+            dhcpv6.option(14),
+            Some(SerializableDhcpv6OptionData::Empty)
+        ));
+        assert!(matches!(
+            dhcpv6.option(23),
+            Some(SerializableDhcpv6OptionData::Addresses(addresses)) if addresses == &[address]
+        ));
+        assert!(matches!(
+            dhcpv6.option(24),
+            Some(SerializableDhcpv6OptionData::DomainList(names)) if names == &["example.com"]
+        ));
+        assert!(matches!(
+            dhcpv6.option(7),
+            Some(SerializableDhcpv6OptionData::Unknown(bytes)) if bytes == &[0, 255]
+        ));
+        // Only the options of the message itself have ranges, not nested ones
+        let options = ranges
+            .iter()
+            .filter(|(name, _, _)| *name == "dhcpv6.option")
+            .count();
+        assert_eq!(options, 9);
+    }
+
+    #[test]
+    fn reports_error_status_of_identity_associations() {
+        let no_binding = [
+            &[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0][..],
+            &option(13, &[0, 3]),
+        ]
+        .concat();
+        let reply = [&[7, 0, 0, 1][..], &option(3, &no_binding)].concat();
+        assert_eq!(decode(&reply).unwrap().error_status(), Some(3));
+        let reply = [&[7, 0, 0, 1][..], &option(13, b"\x00\x02none")].concat();
+        assert_eq!(decode(&reply).unwrap().error_status(), Some(2));
+    }
+
+    #[test]
+    fn unwraps_relay_messages() {
+        let solicit = solicit(&option(1, &[0, 3, 0, 1]));
+        let forward = decode(&relay(1, &relay(0, &solicit))).unwrap();
+        assert_eq!(forward.message_type, 12);
+        assert_eq!(forward.transaction_id, 0);
+        let header = forward.relay.as_ref().unwrap();
+        assert_eq!(header.hop_count, 1);
+        assert_eq!(header.peer_address, "fe80::1".parse::<Ipv6Addr>().unwrap());
+        let relayed = forward.relayed();
+        assert_eq!(relayed.message_type, 1);
+        assert_eq!(relayed.client_id(), Some(&[0, 3, 0, 1][..]));
+
+        // Relays nested too deep are kept as bytes
+        let mut message = solicit;
+        for hop_count in 0..=MAX_RELAY_DEPTH as u8 + 1 {
+            message = relay(hop_count, &message);
+        }
+        let mut forward = &decode(&message).unwrap();
+        let mut depth = 0;
+        while let Some(SerializableDhcpv6OptionData::RelayMessage(message)) = forward.option(9) {
+            forward = message;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_RELAY_DEPTH);
+        assert_eq!(forward.message_type, 12);
+        assert!(matches!(
+            forward.option(9),
+            Some(SerializableDhcpv6OptionData::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_options() {
+        let client_id = option(1, &[0, 3, 0, 1]);
+        assert!(decode(&solicit(&client_id)).is_some());
+        assert!(decode(&solicit(&client_id[..client_id.len() - 1])).is_none());
+        // The option header is cut short
+        assert!(decode(&solicit(&client_id[..3])).is_none());
+        // The transaction id is
+        assert!(decode(&[1, 0, 0]).is_none());
+        // Options in an identity association don't run past it
+        let ia_na = [&[0; 12][..], &option(5, &[0; 24])].concat();
+        assert!(decode(&solicit(&option(3, &ia_na))).is_some());
+        assert!(decode(&solicit(&option(3, &ia_na[..ia_na.len() - 1]))).is_none());
+        // Nor do they in a relayed message
+        let relayed = relay(0, &solicit(&client_id));
+        assert!(decode(&relay(1, &relayed)).is_some());
+        assert!(decode(&relay(1, &relayed[..relayed.len() - 1])).is_none());
+        // The relay header is cut short
+        assert!(decode(&relayed[..33]).is_none());
+        assert!(decode(&relayed[..34]).is_some());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dhcpv6_packet(&relayed[..33], 0, &mut parsed_packet);
+        assert!(matches!(
+            parsed_packet.get_application_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed DHCPv6 Packet"
+        ));
+    }
+}
@@ -0,0 +1,813 @@
+//! DNS, mDNS and LLMNR decoding, and matching of responses to their queries
+//!
+//! Messages are decoded from UDP datagrams and from TCP segments holding a whole
+//! length-prefixed message, those split across segments are left to stream reassembly.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use super::wrapers::{
+    DnsService, ParsedPacket, SerializableDnsData, SerializableDnsPacket, SerializableDnsQuestion,
+    SerializableDnsRecord, SerializableEdnsOption, SerializablePacket, SerializableSvcParam,
+};
+
+/// Port of DNS over UDP and TCP, RFC 1035
+pub const DNS_PORT: u16 = 53;
+/// Port of Multicast DNS, RFC 6762
+pub const MDNS_PORT: u16 = 5353;
+/// Port of Link-Local Multicast Name Resolution, RFC 4795
+pub const LLMNR_PORT: u16 = 5355;
+/// Queries with no response after this much capture time are flagged as unanswered
+pub const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Compression pointers followed in a single name, so crafted messages can't loop
+const MAX_POINTERS: usize = 64;
+/// Longest name on the wire, RFC 1035 section 2.3.4
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Get the DNS flavour spoken on a pair of ports, if any. Port 53 wins, as clients may send
+/// unicast queries from the mDNS or LLMNR port; otherwise the destination port is looked at
+/// first, as it is the server's for queries
+pub fn dns_service(source: u16, destination: u16) -> Option<DnsService> {
+    if source == DNS_PORT || destination == DNS_PORT {
+        return Some(DnsService::Dns);
+    }
+    [destination, source]
+        .into_iter()
+        .find_map(|port| match port {
+            MDNS_PORT => Some(DnsService::Mdns),
+            LLMNR_PORT => Some(DnsService::Llmnr),
+            _ => None,
+        })
+}
+
+/// Decode a DNS message, the payload of a UDP datagram
+pub fn handle_dns_packet(
+    service: DnsService,
+    packet: &[u8],
+    offset: usize,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let mut ranges = vec![
+        ("dns", 0, packet.len()),
+        ("dns.id", 0, 2),
+        ("dns.flags", 2, 2),
+        ("dns.count.queries", 4, 2),
+        ("dns.count.answers", 6, 2),
+        ("dns.count.auth_rr", 8, 2),
+        ("dns.count.add_rr", 10, 2),
+    ];
+    let Some(dns) = decode_message(service, packet, &mut ranges) else {
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed DNS Packet".to_string(),
+        )));
+        return;
+    };
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::DnsPacket(dns)));
+    parsed_packet.add_field_ranges(offset, packet.len(), &ranges);
+}
+
+/// Decode a DNS message at the start of a TCP segment, behind its two-byte length
+pub fn handle_dns_tcp_segment(
+    service: DnsService,
+    payload: &[u8],
+    offset: usize,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some(&[a, b]) = payload.get(..2) else {
+        return;
+    };
+    let length = u16::from_be_bytes([a, b]) as usize;
+    if let Some(message) = payload.get(2..2 + length) {
+        handle_dns_packet(service, message, offset + 2, parsed_packet);
+    }
+}
+
+fn decode_message(
+    service: DnsService,
+    message: &[u8],
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableDnsPacket> {
+    let mut reader = Reader {
+        message,
+        position: 0,
+    };
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let counts = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+
+    let mut questions = Vec::new();
+    for _ in 0..counts[0] {
+        let start = reader.position;
+        questions.push(SerializableDnsQuestion {
+            name: reader.name()?,
+            record_type: reader.u16()?,
+            class: reader.u16()?,
+        });
+        ranges.push(("dns.qry", start, reader.position - start));
+    }
+    let mut sections = [Vec::new(), Vec::new(), Vec::new()];
+    for (section, count) in sections.iter_mut().zip(&counts[1..]) {
+        for _ in 0..*count {
+            let start = reader.position;
+            section.push(reader.record()?);
+            ranges.push(("dns.resp", start, reader.position - start));
+        }
+    }
+    let [answers, authorities, additionals] = sections;
+
+    Some(SerializableDnsPacket {
+        service,
+        id,
+        flags,
+        questions,
+        answers,
+        authorities,
+        additionals,
+        response_to: None,
+        time: None,
+        unanswered: false,
+    })
+}
+
+/// Decode a list of names filling `data`, as in DHCP domain search options, pointers are
+/// relative to the start of `data`
+pub(super) fn names(data: &[u8]) -> Option<Vec<String>> {
+    let mut reader = Reader {
+        message: data,
+        position: 0,
+    };
+    let mut names = Vec::new();
+    while reader.position < data.len() {
+        names.push(reader.name()?);
+    }
+    Some(names)
+}
+
+/// Reads a message front to back, names may point back into bytes already read
+struct Reader<'a> {
+    message: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, length: usize) -> Option<&[u8]> {
+        let bytes = self.message.get(self.position..self.position + length)?;
+        self.position += length;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let &[a, b] = self.bytes(2)? else {
+            return None;
+        };
+        Some(u16::from_be_bytes([a, b]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let &[a, b, c, d] = self.bytes(4)? else {
+            return None;
+        };
+        Some(u32::from_be_bytes([a, b, c, d]))
+    }
+
+    /// Read a possibly compressed domain name, `<Root>` for the root
+    fn name(&mut self) -> Option<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut position = self.position;
+        let mut pointers = 0;
+        let mut name_length = 1;
+        // Where reading continues once the name ends, right after the first pointer if any
+        let mut end = None;
+        loop {
+            let length = *self.message.get(position)? as usize;
+            match length & 0xc0 {
+                0x00 if length == 0 => {
+                    position += 1;
+                    break;
+                }
+                0x00 => {
+                    name_length += 1 + length;
+                    if name_length > MAX_NAME_LENGTH {
+                        return None;
+                    }
+                    let label = self.message.get(position + 1..position + 1 + length)?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    position += 1 + length;
+                }
+                0xc0 => {
+                    let low = *self.message.get(position + 1)? as usize;
+                    pointers += 1;
+                    if pointers > MAX_POINTERS {
+                        return None;
+                    }
+                    end.get_or_insert(position + 2);
+                    position = (length & 0x3f) << 8 | low;
+                }
+                // 0x40 and 0x80 were extended label types, none are in use
+                _ => return None,
+            }
+        }
+        self.position = end.unwrap_or(position);
+        if labels.is_empty() {
+            return Some("<Root>".to_string());
+        }
+        Some(labels.join("."))
+    }
+
+    /// Read a length-prefixed character string
+    fn string(&mut self) -> Option<String> {
+        let length = self.u8()? as usize;
+        Some(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    fn record(&mut self) -> Option<SerializableDnsRecord> {
+        let name = self.name()?;
+        let record_type = self.u16()?;
+        let class = self.u16()?;
+        let ttl = self.u32()?;
+        let length = self.u16()? as usize;
+        let end = self.position + length;
+        if end > self.message.len() {
+            return None;
+        }
+        // Names in the data may point anywhere before them, so read it off the whole message
+        let mut rdata = Reader {
+            message: &self.message[..end],
+            position: self.position,
+        };
+        let data = rdata.data(record_type, class, ttl, end).unwrap_or_else(|| {
+            SerializableDnsData::Unknown(self.message[self.position..end].to_vec())
+        });
+        self.position = end;
+        Some(SerializableDnsRecord {
+            name,
+            record_type,
+            class,
+            ttl,
+            data,
+        })
+    }
+
+    /// Read the data of a record ending at `end`, `None` for types that are not decoded
+    fn data(
+        &mut self,
+        record_type: u16,
+        class: u16,
+        ttl: u32,
+        end: usize,
+    ) -> Option<SerializableDnsData> {
+        let data = match record_type {
+            1 => SerializableDnsData::A(Ipv4Addr::from(self.array::<4>()?)),
+            28 => SerializableDnsData::Aaaa(Ipv6Addr::from(self.array::<16>()?)),
+            2 => SerializableDnsData::Ns(self.name()?),
+            5 => SerializableDnsData::Cname(self.name()?),
+            12 => SerializableDnsData::Ptr(self.name()?),
+            15 => SerializableDnsData::Mx {
+                preference: self.u16()?,
+                exchange: self.name()?,
+            },
+            16 => {
+                let mut strings = Vec::new();
+                while self.position < end {
+                    strings.push(self.string()?);
+                }
+                SerializableDnsData::Txt(strings)
+            }
+            33 => SerializableDnsData::Srv {
+                priority: self.u16()?,
+                weight: self.u16()?,
+                port: self.u16()?,
+                target: self.name()?,
+            },
+            6 => SerializableDnsData::Soa {
+                mname: self.name()?,
+                rname: self.name()?,
+                serial: self.u32()?,
+                refresh: self.u32()?,
+                retry: self.u32()?,
+                expire: self.u32()?,
+                minimum: self.u32()?,
+            },
+            64 | 65 => self.svcb(end)?,
+            41 => self.opt(class, ttl, end)?,
+            _ => return None,
+        };
+        Some(data)
+    }
+
+    fn svcb(&mut self, end: usize) -> Option<SerializableDnsData> {
+        let priority = self.u16()?;
+        let target = self.name()?;
+        let mut params = Vec::new();
+        while self.position < end {
+            let key = self.u16()?;
+            let length = self.u16()? as usize;
+            let value = self.bytes(length)?;
+            params.push(match key {
+                1 => {
+                    let mut value = Reader {
+                        message: value,
+                        position: 0,
+                    };
+                    let mut protocols = Vec::new();
+                    while value.position < length {
+                        protocols.push(value.string()?);
+                    }
+                    SerializableSvcParam::Alpn(protocols)
+                }
+                3 if length == 2 => {
+                    SerializableSvcParam::Port(u16::from_be_bytes([value[0], value[1]]))
+                }
+                4 if length.is_multiple_of(4) => SerializableSvcParam::Ipv4Hint(
+                    value
+                        .chunks_exact(4)
+                        .filter_map(|chunk| <[u8; 4]>::try_from(chunk).ok())
+                        .map(Ipv4Addr::from)
+                        .collect(),
+                ),
+                6 if length.is_multiple_of(16) => SerializableSvcParam::Ipv6Hint(
+                    value
+                        .chunks_exact(16)
+                        .filter_map(|chunk| <[u8; 16]>::try_from(chunk).ok())
+                        .map(Ipv6Addr::from)
+                        .collect(),
+                ),
+                key => SerializableSvcParam::Other {
+                    key,
+                    value: value.to_vec(),
+                },
+            });
+        }
+        Some(SerializableDnsData::Svcb {
+            priority,
+            target,
+            params,
+        })
+    }
+
+    /// Read EDNS options, the header fields are packed into the record's class and TTL
+    fn opt(&mut self, class: u16, ttl: u32, end: usize) -> Option<SerializableDnsData> {
+        let mut options = Vec::new();
+        while self.position < end {
+            let code = self.u16()?;
+            let length = self.u16()? as usize;
+            options.push(SerializableEdnsOption {
+                code,
+                data: self.bytes(length)?.to_vec(),
+            });
+        }
+        Some(SerializableDnsData::Opt {
+            udp_payload_size: class,
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: ttl & 0x8000 != 0,
+            options,
+        })
+    }
+}
+
+/// A query waiting for its response
+struct PendingQuery {
+    id: usize,
+    timestamp: Duration,
+}
+
+/// Matches DNS responses to their queries, packets are fed in capture order
+///
+/// Responses get the query's packet id and the time it took, queries left without a response
+/// for `DNS_TIMEOUT` are reported by `expire`. mDNS is not tracked, its queries are multicast
+/// and answered by anyone, or no one.
+#[derive(Default)]
+pub struct DnsTracker {
+    /// Queries keyed on the client's address and the transaction id, LLMNR responses come
+    /// from another address than the multicast one queried
+    pending: HashMap<(SocketAddr, u16), PendingQuery>,
+}
+
+impl DnsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a query, or fill in the query a response answers
+    pub fn process(&mut self, packet: &mut ParsedPacket) {
+        let Some((source, destination)) = endpoints(packet) else {
+            return;
+        };
+        let id = packet.get_id();
+        let timestamp = packet.get_timestamp();
+        let Some(dns) = packet.find_mut::<SerializableDnsPacket>() else {
+            return;
+        };
+        if dns.service == DnsService::Mdns {
+            return;
+        }
+        if !dns.is_response() {
+            // Retransmissions keep waiting on the first query
+            self.pending
+                .entry((source, dns.id))
+                .or_insert(PendingQuery { id, timestamp });
+            return;
+        }
+        let key = (destination, dns.id);
+        // Late responses don't count, whether the query expired already depends on batching
+        let Some(query) = self
+            .pending
+            .get(&key)
+            .filter(|query| timestamp.saturating_sub(query.timestamp) < DNS_TIMEOUT)
+        else {
+            return;
+        };
+        dns.response_to = Some(query.id);
+        dns.time = Some(timestamp.saturating_sub(query.timestamp));
+        self.pending.remove(&key);
+    }
+
+    /// Forget queries sent `DNS_TIMEOUT` before `now`, returning their packet ids
+    pub fn expire(&mut self, now: Duration) -> Vec<usize> {
+        let mut expired = Vec::new();
+        self.pending.retain(|_, query| {
+            let waiting = now.saturating_sub(query.timestamp) < DNS_TIMEOUT;
+            if !waiting {
+                expired.push(query.id);
+            }
+            waiting
+        });
+        expired.sort_unstable();
+        expired
+    }
+}
+
+/// Flag the query in `packet` as never answered
+pub fn mark_unanswered(packet: &mut ParsedPacket) {
+    if let Some(dns) = packet.find_mut::<SerializableDnsPacket>() {
+        dns.unanswered = true;
+    }
+}
+
+/// Get source and destination sockets of a UDP or TCP packet
+fn endpoints(packet: &ParsedPacket) -> Option<(SocketAddr, SocketAddr)> {
+    let (source, destination): (IpAddr, IpAddr) = match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4) => (ipv4.source.into(), ipv4.destination.into()),
+        SerializablePacket::Ipv6Packet(ipv6) => (ipv6.source.into(), ipv6.destination.into()),
+        _ => return None,
+    };
+    let (source_port, destination_port) = match packet.get_transport_layer_packet()? {
+        SerializablePacket::UdpPacket(udp) => (udp.source, udp.destination),
+        SerializablePacket::TcpPacket(tcp) => (tcp.source, tcp.destination),
+        _ => return None,
+    };
+    Some((
+        SocketAddr::new(source, source_port),
+        SocketAddr::new(destination, destination_port),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        defrag::Defragmenter,
+        Parser,
+    };
+
+    /// Header of a message with `answers` and `additionals` records, and a question for
+    /// example.com at offset 12
+    fn message(flags: u16, answers: u16, additionals: u16) -> Vec<u8> {
+        let mut message = vec![0x12, 0x34];
+        message.extend(flags.to_be_bytes());
+        message.extend([0, 1]);
+        message.extend(answers.to_be_bytes());
+        message.extend([0, 0]);
+        message.extend(additionals.to_be_bytes());
+        message.extend(b"\x07example\x03com\x00");
+        message.extend([0, 1, 0, 1]);
+        message
+    }
+
+    fn record(name: &[u8], record_type: u16, class: u16, ttl: u32, data: &[u8]) -> Vec<u8> {
+        let mut record = name.to_vec();
+        record.extend(record_type.to_be_bytes());
+        record.extend(class.to_be_bytes());
+        record.extend(ttl.to_be_bytes());
+        record.extend((data.len() as u16).to_be_bytes());
+        record.extend(data);
+        record
+    }
+
+    fn decode(message: &[u8]) -> Option<SerializableDnsPacket> {
+        decode_message(DnsService::Dns, message, &mut Vec::new())
+    }
+
+    /// Read the name at `position` of `message`
+    fn name_at(message: &[u8], position: usize) -> Option<String> {
+        let mut reader = Reader { message, position };
+        reader.name()
+    }
+
+    #[test]
+    fn picks_service_from_ports() {
+        assert_eq!(dns_service(40000, DNS_PORT), Some(DnsService::Dns));
+        assert_eq!(dns_service(DNS_PORT, 40000), Some(DnsService::Dns));
+        assert_eq!(dns_service(MDNS_PORT, DNS_PORT), Some(DnsService::Dns));
+        assert_eq!(dns_service(DNS_PORT, LLMNR_PORT), Some(DnsService::Dns));
+        assert_eq!(dns_service(MDNS_PORT, MDNS_PORT), Some(DnsService::Mdns));
+        assert_eq!(dns_service(40000, LLMNR_PORT), Some(DnsService::Llmnr));
+        assert_eq!(dns_service(MDNS_PORT, LLMNR_PORT), Some(DnsService::Llmnr));
+        assert_eq!(dns_service(40000, 40001), None);
+    }
+
+    #[test]
+    fn follows_compression_pointers() {
+        let mut message = message(0, 0, 0);
+        message.extend(b"\x03www\xc0\x0c");
+        let mut reader = Reader {
+            message: &message,
+            position: 29,
+        };
+        assert_eq!(reader.name().as_deref(), Some("www.example.com"));
+        // Reading goes on after the pointer, not after the name pointed to
+        assert_eq!(reader.position, 35);
+        assert_eq!(name_at(&message, 20).as_deref(), Some("com"));
+    }
+
+    #[test]
+    fn rejects_pointer_loops() {
+        // A pointer to itself, and two pointing to each other
+        assert_eq!(name_at(&[0xc0, 0x00], 0), None);
+        assert_eq!(name_at(b"\x01a\xc0\x04\x01b\xc0\x00", 0), None);
+
+        // The root, then pointers each pointing to the one before
+        let mut chain = vec![0];
+        for i in 0..=MAX_POINTERS {
+            chain.extend([0xc0, (i * 2).saturating_sub(1) as u8]);
+        }
+        let last = chain.len() - 2;
+        assert_eq!(name_at(&chain, last - 2).as_deref(), Some("<Root>"));
+        assert_eq!(name_at(&chain, last), None);
+    }
+
+    #[test]
+    fn rejects_pointers_past_the_end() {
+        assert_eq!(name_at(b"\x01a\xc0\x10", 0), None);
+        // The pointer's second byte is missing
+        assert_eq!(name_at(b"\x01a\xc0", 0), None);
+        // So is the label's end
+        assert_eq!(name_at(b"\x05abc", 0), None);
+        assert_eq!(name_at(b"\x01a", 0), None);
+    }
+
+    #[test]
+    fn rejects_over_long_names() {
+        let label = [&[63][..], &[b'a'; 63]].concat();
+        let mut longest = label.repeat(3);
+        longest.push(61);
+        longest.extend([b'b'; 61]);
+        longest.push(0);
+        let name = name_at(&longest, 0).unwrap();
+        assert_eq!(name.len(), MAX_NAME_LENGTH - 2);
+
+        let mut too_long = label.repeat(3);
+        too_long.push(62);
+        too_long.extend([b'b'; 62]);
+        too_long.push(0);
+        assert_eq!(name_at(&too_long, 0), None);
+        // Pointers don't make names longer than they may be
+        let mut pointed = label.repeat(2);
+        pointed.push(0);
+        pointed.extend(label.repeat(2));
+        pointed.extend([0xc0, 0x00]);
+        assert_eq!(name_at(&pointed, 129), None);
+    }
+
+    #[test]
+    fn handles_truncated_record_data() {
+        // The length says 4 bytes, 2 are left
+        let mut truncated = message(0x8180, 1, 0);
+        truncated.extend(record(b"\xc0\x0c", 1, 1, 60, &[192, 0, 2, 1]));
+        truncated.truncate(truncated.len() - 2);
+        assert!(decode(&truncated).is_none());
+
+        // The data is all there but too short for an address, it is kept undecoded
+        let mut short = message(0x8180, 2, 0);
+        short.extend(record(b"\xc0\x0c", 1, 1, 60, &[192, 0]));
+        short.extend(record(b"\xc0\x0c", 1, 1, 60, &[192, 0, 2, 1]));
+        let dns = decode(&short).unwrap();
+        assert!(
+            matches!(&dns.answers[0].data, SerializableDnsData::Unknown(data) if data == &[192, 0])
+        );
+        assert!(matches!(
+            dns.answers[1].data,
+            SerializableDnsData::A(address) if address == Ipv4Addr::new(192, 0, 2, 1)
+        ));
+        // Names in the data can't run past it
+        let mut name = message(0x8180, 1, 0);
+        name.extend(record(b"\xc0\x0c", 5, 1, 60, b"\x03www"));
+        name.extend(b"\xc0\x0c");
+        let dns = decode(&name).unwrap();
+        assert!(matches!(
+            &dns.answers[0].data,
+            SerializableDnsData::Unknown(data) if data == b"\x03www"
+        ));
+    }
+
+    #[test]
+    fn decodes_soa_txt_svcb_and_opt_records() {
+        let mut soa = b"\x03ns1\xc0\x0c\x0ahostmaster\xc0\x0c".to_vec();
+        for value in [2024010101u32, 7200, 3600, 1209600, 300] {
+            soa.extend(value.to_be_bytes());
+        }
+        let mut https = vec![0, 1, 0];
+        https.extend([0, 1, 0, 6, 2, b'h', b'2', 2, b'h', b'3']);
+        https.extend([0, 3, 0, 2, 0x01, 0xbb]);
+        https.extend([0, 4, 0, 4, 192, 0, 2, 1]);
+        https.extend([0, 7, 0, 1, 0xab]);
+        let mut message = message(0x8183, 3, 1);
+        message.extend(record(b"\xc0\x0c", 6, 1, 60, &soa));
+        message.extend(record(b"\xc0\x0c", 16, 1, 60, b"\x06v=spf1\x00"));
+        message.extend(record(b"\xc0\x0c", 65, 1, 60, &https));
+        // 1232 bytes over UDP, extended rcode 1, version 0, DNSSEC OK, and a cookie
+        let cookie = [0, 10, 0, 8, 1, 2, 3, 4, 5, 6, 7, 8];
+        message.extend(record(b"\x00", 41, 1232, 0x0100_8000, &cookie));
+
+        let dns = decode(&message).unwrap();
+        assert!(matches!(
+            &dns.answers[0].data,
+            SerializableDnsData::Soa {
+                mname,
+                rname,
+                serial: 2024010101,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 300,
+            } if mname == "ns1.example.com" && rname == "hostmaster.example.com"
+        ));
+        assert!(matches!(
+            &dns.answers[1].data,
+            SerializableDnsData::Txt(strings) if strings == &["v=spf1", ""]
+        ));
+        let SerializableDnsData::Svcb {
+            priority,
+            target,
+            params,
+        } = &dns.answers[2].data
+        else {
+            panic!("HTTPS record not decoded");
+        };
+        assert_eq!((*priority, target.as_str()), (1, "<Root>"));
+        assert!(matches!(&params[0], SerializableSvcParam::Alpn(alpn) if alpn == &["h2", "h3"]));
+        assert!(matches!(params[1], SerializableSvcParam::Port(443)));
+        assert!(matches!(
+            &params[2],
+            SerializableSvcParam::Ipv4Hint(hints) if hints == &[Ipv4Addr::new(192, 0, 2, 1)]
+        ));
+        assert!(matches!(
+            &params[3],
+            SerializableSvcParam::Other { key: 7, value } if value == &[0xab]
+        ));
+        let SerializableDnsData::Opt {
+            udp_payload_size,
+            extended_rcode,
+            version,
+            dnssec_ok,
+            options,
+        } = &dns.additionals[0].data
+        else {
+            panic!("OPT record not decoded");
+        };
+        assert_eq!(
+            (*udp_payload_size, *extended_rcode, *version, *dnssec_ok),
+            (1232, 1, 0, true)
+        );
+        assert_eq!(
+            (options[0].code, options[0].data.as_slice()),
+            (10, &cookie[4..])
+        );
+        // The OPT record extends NXDOMAIN's 3 into BADVERS/BADSIG's 19
+        assert_eq!(dns.rcode(), 19);
+    }
+
+    /// A DNS message over UDP between 10.0.0.1:`client_port` and 10.0.0.53:53
+    fn packet(
+        id: usize,
+        millis: u64,
+        client_port: u16,
+        response: bool,
+        dns_id: u16,
+    ) -> ParsedPacket {
+        let mut dns = message(if response { 0x8180 } else { 0x0100 }, response as u16, 0);
+        dns[..2].copy_from_slice(&dns_id.to_be_bytes());
+        if response {
+            dns.extend(record(b"\xc0\x0c", 1, 1, 60, &[192, 0, 2, 1]));
+        }
+        let (client, server) = ([10, 0, 0, 1], [10, 0, 0, 53]);
+        let (source, destination) = if response {
+            ((server, DNS_PORT), (client, client_port))
+        } else {
+            ((client, client_port), (server, DNS_PORT))
+        };
+        let mut frame = vec![0; 12];
+        frame.extend([0x08, 0x00, 0x45, 0]);
+        frame.extend((28 + dns.len() as u16).to_be_bytes());
+        frame.extend([0, 0, 0, 0, 64, 17, 0, 0]);
+        frame.extend(source.0);
+        frame.extend(destination.0);
+        frame.extend(source.1.to_be_bytes());
+        frame.extend(destination.1.to_be_bytes());
+        frame.extend((8 + dns.len() as u16).to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(dns);
+
+        let frame = CapturedFrame {
+            data: &frame,
+            timestamp: Duration::from_millis(millis),
+            original_length: frame.len(),
+            link_type: LinkType::ETHERNET,
+            interface_name: None,
+            comments: &[],
+        };
+        Parser::parse_frame(&frame, id, &mut Defragmenter::new())
+    }
+
+    /// Feed `packets` to a tracker, getting the query each answers and how long it took
+    fn track(
+        tracker: &mut DnsTracker,
+        packets: Vec<ParsedPacket>,
+    ) -> Vec<(Option<usize>, Option<Duration>)> {
+        packets
+            .into_iter()
+            .map(|mut packet| {
+                tracker.process(&mut packet);
+                let dns = packet.find::<SerializableDnsPacket>().unwrap();
+                (dns.response_to, dns.time)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_responses_on_client_and_id() {
+        let mut tracker = DnsTracker::new();
+        let matched = track(
+            &mut tracker,
+            vec![
+                packet(0, 0, 40000, false, 7),
+                packet(1, 1, 40001, false, 7),
+                packet(2, 2, 40000, false, 8),
+                // A retransmission keeps the first query's time
+                packet(3, 3, 40000, false, 7),
+                packet(4, 13, 40001, true, 7),
+                packet(5, 14, 40000, true, 7),
+                packet(6, 20, 40000, true, 9),
+                // A second response to the same query is not matched again
+                packet(7, 21, 40000, true, 7),
+            ],
+        );
+        let millis = |millis| Some(Duration::from_millis(millis));
+        assert_eq!(matched[4], (Some(1), millis(12)));
+        assert_eq!(matched[5], (Some(0), millis(14)));
+        assert_eq!(matched[6], (None, None));
+        assert_eq!(matched[7], (None, None));
+        assert!(matched[..4].iter().all(|&matched| matched == (None, None)));
+        assert_eq!(tracker.expire(Duration::MAX), [2]);
+    }
+
+    #[test]
+    fn expires_unanswered_queries() {
+        let mut tracker = DnsTracker::new();
+        let timeout = DNS_TIMEOUT.as_millis() as u64;
+        let matched = track(
+            &mut tracker,
+            vec![
+                packet(0, 0, 40000, false, 1),
+                packet(1, 1000, 40000, false, 2),
+                // Too late to count as the answer
+                packet(2, timeout, 40000, true, 1),
+            ],
+        );
+        assert_eq!(matched[2], (None, None));
+        assert!(tracker
+            .expire(DNS_TIMEOUT - Duration::from_millis(1))
+            .is_empty());
+        assert_eq!(tracker.expire(DNS_TIMEOUT), [0]);
+        assert_eq!(tracker.expire(DNS_TIMEOUT + Duration::from_secs(1)), [1]);
+        assert!(tracker.expire(Duration::MAX).is_empty());
+
+        let mut query = packet(0, 0, 40000, false, 1);
+        mark_unanswered(&mut query);
+        assert!(query.find::<SerializableDnsPacket>().unwrap().unanswered);
+    }
+}
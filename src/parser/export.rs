@@ -0,0 +1,39 @@
+//! Export of parsed packets as newline-delimited JSON, one object per line
+//!
+//! Each line is a serialized `ParsedPacket`. Layers are listed outermost first with their
+//! level, tunnel depth and header bytes, enum variants are in snake case and raw bytes are
+//! hex strings, so lines can be fed to `jq` or log pipelines and read back with serde.
+
+use std::io::{self, Write};
+
+use super::{wrapers::ParsedPacket, ParserError};
+
+/// Writes parsed packets as NDJSON
+pub struct NdjsonWriter<W: Write> {
+    output: W,
+    packets: usize,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(output: W) -> Self {
+        NdjsonWriter { output, packets: 0 }
+    }
+
+    /// Get number of packets written
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// Append a packet as one line
+    pub fn write_packet(&mut self, packet: &ParsedPacket) -> Result<(), ParserError> {
+        serde_json::to_writer(&mut self.output, packet).map_err(io::Error::from)?;
+        self.output.write_all(b"\n")?;
+        self.packets += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), ParserError> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
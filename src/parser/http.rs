@@ -0,0 +1,801 @@
+//! HTTP/1.x decoding on top of reassembled TCP streams
+//!
+//! Each direction of a stream is recognised by how it starts, with a request method or with
+//! `HTTP/1.`, so servers on any port are decoded. Messages are attached to the packet carrying
+//! their last byte, and responses are paired with requests in order, which covers pipelining.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use super::{
+    reassembly::{Direction, StreamSegment},
+    wrapers::{
+        Level, ParsedPacket, SerializableHttpBody, SerializableHttpHeader,
+        SerializableHttpRequestInfo, SerializableHttpRequestPacket, SerializableHttpResponsePacket,
+        SerializablePacket,
+    },
+};
+
+/// Body bytes kept in each message, the rest is only counted
+pub const HTTP_BODY_LIMIT: usize = 64 * 1024;
+/// Start lines with headers, or chunk size lines, longer than this are given up on
+const MAX_HEAD_LENGTH: usize = 64 * 1024;
+
+/// How each direction of a stream may start, requests with a method and responses with a version
+const PREFIXES: [(&[u8], Role); 10] = [
+    (b"GET ", Role::Requests),
+    (b"POST ", Role::Requests),
+    (b"PUT ", Role::Requests),
+    (b"DELETE ", Role::Requests),
+    (b"HEAD ", Role::Requests),
+    (b"OPTIONS ", Role::Requests),
+    (b"PATCH ", Role::Requests),
+    (b"CONNECT ", Role::Requests),
+    (b"TRACE ", Role::Requests),
+    (b"HTTP/1.", Role::Responses),
+];
+
+/// What one direction of a stream carries
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Role {
+    /// Too few bytes seen to tell
+    #[default]
+    Unknown,
+    Requests,
+    Responses,
+    /// Not HTTP, or no longer decodable
+    Other,
+}
+
+/// How the end of a body is found
+enum Framing {
+    /// Bytes left
+    Length(usize),
+    Chunked(Chunk),
+    /// The sender closes the connection after the body
+    UntilClose,
+}
+
+/// Where a chunked body is at
+enum Chunk {
+    Size,
+    /// Bytes left in the chunk
+    Data(usize),
+    /// Line break after the chunk's data
+    DataEnd,
+    /// Trailer fields after the last chunk, up to an empty line
+    Trailer,
+}
+
+enum StartLine {
+    Request {
+        method: String,
+        uri: String,
+        version: String,
+    },
+    Response {
+        version: String,
+        status: u16,
+        reason: String,
+    },
+}
+
+/// A message whose head was decoded, waiting for the end of its body
+struct Message {
+    start: StartLine,
+    headers: Vec<SerializableHttpHeader>,
+    framing: Framing,
+    body: SerializableHttpBody,
+    first_packet: usize,
+}
+
+/// One direction of a stream
+#[derive(Default)]
+struct HalfStream {
+    role: Role,
+    /// Bytes not decoded yet, an incomplete head or chunk size line
+    buffer: Vec<u8>,
+    message: Option<Message>,
+    /// Packet the message being decoded started in
+    first_packet: Option<usize>,
+}
+
+/// A request waiting for its response
+struct PendingRequest {
+    method: String,
+    uri: String,
+    packet: usize,
+    timestamp: Duration,
+}
+
+#[derive(Default)]
+struct Conversation {
+    to_server: HalfStream,
+    to_client: HalfStream,
+    /// Requests waiting for their response, oldest first
+    requests: VecDeque<PendingRequest>,
+    /// After CONNECT or a protocol upgrade, the stream carries another protocol
+    upgraded: bool,
+}
+
+/// Decodes HTTP messages from TCP streams, segments are fed in capture order
+#[derive(Default)]
+pub struct HttpTracker {
+    conversations: HashMap<usize, Conversation>,
+}
+
+impl HttpTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop the conversation of stream number `stream`, once the reassembler forgot it
+    pub fn forget(&mut self, stream: usize) {
+        self.conversations.remove(&stream);
+    }
+
+    /// Take in what `packet` added to its stream, adding the messages it completes as layers
+    pub fn process(&mut self, packet: &mut ParsedPacket, segment: &StreamSegment) {
+        let conversation = self.conversations.entry(segment.stream).or_default();
+        if conversation.upgraded {
+            return;
+        }
+        let half = match segment.direction {
+            Direction::ToServer => &mut conversation.to_server,
+            Direction::ToClient => &mut conversation.to_client,
+        };
+        // The message being decoded lost bytes, wait for one starting after the gap
+        if segment.missing > 0 {
+            *half = HalfStream {
+                role: Role::Other,
+                ..HalfStream::default()
+            };
+        }
+        let mut decoder = Decoder {
+            half,
+            requests: &mut conversation.requests,
+            upgraded: &mut conversation.upgraded,
+            packet: packet.get_id(),
+            timestamp: packet.get_timestamp(),
+            finished: segment.finished,
+        };
+        for message in decoder.receive(&segment.data) {
+            packet.push_layer(Level::Application, message);
+        }
+    }
+}
+
+/// Decodes a direction of a conversation with the bytes of a packet
+struct Decoder<'a> {
+    half: &'a mut HalfStream,
+    requests: &'a mut VecDeque<PendingRequest>,
+    upgraded: &'a mut bool,
+    packet: usize,
+    timestamp: Duration,
+    finished: bool,
+}
+
+impl Decoder<'_> {
+    fn receive(&mut self, data: &[u8]) -> Vec<SerializablePacket> {
+        // Captures may start inside a message, or a message was malformed, segments starting
+        // like a new message resynchronise the direction
+        if self.half.role == Role::Other && !data.is_empty() && sniff(data) != Role::Other {
+            self.half.role = Role::Unknown;
+        }
+        if self.half.role == Role::Other {
+            return Vec::new();
+        }
+        self.half.buffer.extend_from_slice(data);
+        if self.half.role == Role::Unknown {
+            self.half.role = sniff(&self.half.buffer);
+            if self.half.role == Role::Other {
+                self.half.buffer = Vec::new();
+            }
+        }
+        if matches!(self.half.role, Role::Unknown | Role::Other) {
+            return Vec::new();
+        }
+
+        let mut messages = Vec::new();
+        loop {
+            let message = match self.half.message.as_mut() {
+                Some(message) => message,
+                None => match self.head() {
+                    Ok(Some(message)) => self.half.message.insert(message),
+                    Ok(None) => break,
+                    Err(reason) => {
+                        messages.push(self.give_up(reason));
+                        break;
+                    }
+                },
+            };
+            match body(message, &mut self.half.buffer, self.finished) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(reason) => {
+                    messages.push(self.give_up(reason));
+                    break;
+                }
+            }
+            let Some(message) = self.half.message.take() else {
+                break;
+            };
+            self.half.first_packet = None;
+            messages.push(self.complete(message));
+            if *self.upgraded {
+                break;
+            }
+        }
+        messages
+    }
+
+    /// Decode the start line and headers of the next message, once they are all buffered
+    fn head(&mut self) -> Result<Option<Message>, &'static str> {
+        // Stray line breaks between messages are allowed, RFC 9112 section 2.2
+        let start = self
+            .half
+            .buffer
+            .iter()
+            .position(|&byte| byte != b'\r' && byte != b'\n')
+            .unwrap_or(self.half.buffer.len());
+        self.half.buffer.drain(..start);
+        if self.half.buffer.is_empty() {
+            return Ok(None);
+        }
+        let first_packet = *self.half.first_packet.get_or_insert(self.packet);
+
+        let Some(end) = head_end(&self.half.buffer) else {
+            if self.half.buffer.len() > MAX_HEAD_LENGTH {
+                return Err("HTTP headers too long");
+            }
+            return Ok(None);
+        };
+        let head = String::from_utf8_lossy(&self.half.buffer[..end]).into_owned();
+        self.half.buffer.drain(..end);
+
+        let mut lines = head.lines().map(|line| line.trim_end_matches('\r'));
+        let start = match (self.half.role, lines.next()) {
+            (Role::Requests, Some(line)) => request_line(line),
+            (_, Some(line)) => status_line(line),
+            (_, None) => None,
+        }
+        .ok_or("Malformed HTTP start line")?;
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some(SerializableHttpHeader {
+                    name: name.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Malformed HTTP header")?;
+
+        let framing = self.framing(&start, &headers)?;
+        Ok(Some(Message {
+            body: SerializableHttpBody {
+                length: 0,
+                chunked: matches!(framing, Framing::Chunked(_)),
+                data: Vec::new(),
+            },
+            start,
+            headers,
+            framing,
+            first_packet,
+        }))
+    }
+
+    /// Find out how a message's body ends, RFC 9112 section 6.3
+    fn framing(
+        &mut self,
+        start: &StartLine,
+        headers: &[SerializableHttpHeader],
+    ) -> Result<Framing, &'static str> {
+        if let StartLine::Response { status, .. } = start {
+            let method = self.requests.front().map(|request| request.method.as_str());
+            let tunnel = *status == 101 || (method == Some("CONNECT") && status / 100 == 2);
+            if tunnel {
+                *self.upgraded = true;
+            }
+            if tunnel || method == Some("HEAD") || *status / 100 == 1 || matches!(status, 204 | 304)
+            {
+                return Ok(Framing::Length(0));
+            }
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .map(|header| header.value.as_str())
+        };
+        if header("Transfer-Encoding")
+            .is_some_and(|coding| coding.to_ascii_lowercase().contains("chunked"))
+        {
+            return Ok(Framing::Chunked(Chunk::Size));
+        }
+        match (header("Content-Length"), start) {
+            (Some(length), _) => length
+                .parse()
+                .map(Framing::Length)
+                .map_err(|_| "Malformed HTTP Content-Length"),
+            (None, StartLine::Request { .. }) => Ok(Framing::Length(0)),
+            (None, StartLine::Response { .. }) => Ok(Framing::UntilClose),
+        }
+    }
+
+    /// Turn a decoded message into a layer, pairing responses with their request
+    fn complete(&mut self, message: Message) -> SerializablePacket {
+        match message.start {
+            StartLine::Request {
+                method,
+                uri,
+                version,
+            } => {
+                self.requests.push_back(PendingRequest {
+                    method: method.clone(),
+                    uri: uri.clone(),
+                    packet: self.packet,
+                    timestamp: self.timestamp,
+                });
+                SerializablePacket::HttpRequestPacket(SerializableHttpRequestPacket {
+                    method,
+                    uri,
+                    version,
+                    headers: message.headers,
+                    body: message.body,
+                    first_packet: message.first_packet,
+                })
+            }
+            StartLine::Response {
+                version,
+                status,
+                reason,
+            } => {
+                let request = self
+                    .requests
+                    .front()
+                    .map(|request| SerializableHttpRequestInfo {
+                        method: request.method.clone(),
+                        uri: request.uri.clone(),
+                        packet: request.packet,
+                        time: self.timestamp.saturating_sub(request.timestamp),
+                    });
+                // Interim responses come before the final one to the same request
+                if status / 100 != 1 || status == 101 {
+                    self.requests.pop_front();
+                }
+                SerializablePacket::HttpResponsePacket(SerializableHttpResponsePacket {
+                    version,
+                    status,
+                    reason,
+                    headers: message.headers,
+                    body: message.body,
+                    first_packet: message.first_packet,
+                    request,
+                })
+            }
+        }
+    }
+
+    /// Stop decoding a direction that went off the rails
+    fn give_up(&mut self, reason: &str) -> SerializablePacket {
+        self.half.role = Role::Other;
+        self.half.buffer = Vec::new();
+        self.half.message = None;
+        SerializablePacket::MalformedPacket(reason.to_string())
+    }
+}
+
+/// Read as much of a message's body as buffered, returning whether it is complete
+fn body(message: &mut Message, buffer: &mut Vec<u8>, finished: bool) -> Result<bool, &'static str> {
+    loop {
+        match &mut message.framing {
+            Framing::Length(left) => {
+                let length = (*left).min(buffer.len());
+                take_body(&mut message.body, buffer, length);
+                *left -= length;
+                return Ok(*left == 0);
+            }
+            Framing::UntilClose => {
+                let length = buffer.len();
+                take_body(&mut message.body, buffer, length);
+                return Ok(finished);
+            }
+            Framing::Chunked(chunk) => match chunk {
+                Chunk::Size => {
+                    let Some(line) = take_line(buffer)? else {
+                        return Ok(false);
+                    };
+                    // Chunk extensions after `;` are ignored
+                    let size = line.split(';').next().unwrap_or_default().trim();
+                    let size =
+                        usize::from_str_radix(size, 16).map_err(|_| "Malformed HTTP chunk size")?;
+                    *chunk = match size {
+                        0 => Chunk::Trailer,
+                        size => Chunk::Data(size),
+                    };
+                }
+                Chunk::Data(left) => {
+                    let length = (*left).min(buffer.len());
+                    take_body(&mut message.body, buffer, length);
+                    *left -= length;
+                    if *left > 0 {
+                        return Ok(false);
+                    }
+                    *chunk = Chunk::DataEnd;
+                }
+                Chunk::DataEnd => {
+                    let Some(line) = take_line(buffer)? else {
+                        return Ok(false);
+                    };
+                    if !line.is_empty() {
+                        return Err("Malformed HTTP chunk");
+                    }
+                    *chunk = Chunk::Size;
+                }
+                Chunk::Trailer => {
+                    let Some(line) = take_line(buffer)? else {
+                        return Ok(false);
+                    };
+                    if line.is_empty() {
+                        return Ok(true);
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Move `length` bytes of body out of `buffer`, keeping up to `HTTP_BODY_LIMIT` of them
+fn take_body(body: &mut SerializableHttpBody, buffer: &mut Vec<u8>, length: usize) {
+    let kept = HTTP_BODY_LIMIT.saturating_sub(body.data.len()).min(length);
+    body.data.extend_from_slice(&buffer[..kept]);
+    body.length += length;
+    buffer.drain(..length);
+}
+
+/// Take a line off the front of `buffer` without its line break, `None` while incomplete
+fn take_line(buffer: &mut Vec<u8>) -> Result<Option<String>, &'static str> {
+    let Some(end) = buffer.iter().position(|&byte| byte == b'\n') else {
+        if buffer.len() > MAX_HEAD_LENGTH {
+            return Err("HTTP chunk line too long");
+        }
+        return Ok(None);
+    };
+    let line = String::from_utf8_lossy(&buffer[..end])
+        .trim_end_matches('\r')
+        .to_string();
+    buffer.drain(..=end);
+    Ok(Some(line))
+}
+
+/// Tell requests from responses by the first bytes of a direction
+fn sniff(buffer: &[u8]) -> Role {
+    if let Some((_, role)) = PREFIXES
+        .iter()
+        .find(|(prefix, _)| buffer.starts_with(prefix))
+    {
+        return *role;
+    }
+    if PREFIXES
+        .iter()
+        .any(|(prefix, _)| prefix.starts_with(buffer))
+    {
+        Role::Unknown
+    } else {
+        Role::Other
+    }
+}
+
+/// Get the length of the head, up to and including the empty line ending it
+fn head_end(buffer: &[u8]) -> Option<usize> {
+    buffer
+        .windows(2)
+        .enumerate()
+        .find_map(|(i, window)| match (window, buffer.get(i + 2)) {
+            (b"\n\n", _) => Some(i + 2),
+            (b"\n\r", Some(b'\n')) => Some(i + 3),
+            _ => None,
+        })
+}
+
+/// Decode `GET /index.html HTTP/1.1`
+fn request_line(line: &str) -> Option<StartLine> {
+    let mut parts = line.split(' ');
+    let (method, uri, version) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || !version.starts_with("HTTP/1.") {
+        return None;
+    }
+    Some(StartLine::Request {
+        method: method.to_string(),
+        uri: uri.to_string(),
+        version: version.to_string(),
+    })
+}
+
+/// Decode `HTTP/1.1 200 OK`, the reason phrase may be empty or hold spaces
+fn status_line(line: &str) -> Option<StartLine> {
+    let (version, rest) = line.split_once(' ')?;
+    let (status, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+    if !version.starts_with("HTTP/1.") || status.len() != 3 {
+        return None;
+    }
+    Some(StartLine::Response {
+        version: version.to_string(),
+        status: status.parse().ok()?,
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        defrag::Defragmenter,
+        reassembly::TcpReassembler,
+        wrapers::format_size,
+        Parser,
+    };
+    use pnet::packet::tcp::TcpFlags;
+
+    /// A connection from 10.0.0.1:40000 to 10.0.0.2:80, its segments fed in capture order
+    struct Connection {
+        streams: TcpReassembler,
+        http: HttpTracker,
+        /// Next sequence number of the client, then of the server
+        sequence: [u32; 2],
+        packets: usize,
+    }
+
+    impl Connection {
+        fn open() -> Self {
+            let mut connection = Connection {
+                streams: TcpReassembler::new(),
+                http: HttpTracker::new(),
+                sequence: [1000, 5000],
+                packets: 0,
+            };
+            connection.segment(true, 0, TcpFlags::SYN, b"");
+            connection.segment(false, 0, TcpFlags::SYN | TcpFlags::ACK, b"");
+            connection
+        }
+
+        /// Send `data` to the server, or to the client, `millis` into the capture
+        fn send(&mut self, to_server: bool, millis: u64, data: &[u8]) -> ParsedPacket {
+            self.segment(to_server, millis, TcpFlags::ACK, data)
+        }
+
+        fn segment(
+            &mut self,
+            to_server: bool,
+            millis: u64,
+            flags: u16,
+            data: &[u8],
+        ) -> ParsedPacket {
+            let (source, destination) = if to_server {
+                (([10, 0, 0, 1], 40000u16), ([10, 0, 0, 2], 80u16))
+            } else {
+                (([10, 0, 0, 2], 80), ([10, 0, 0, 1], 40000))
+            };
+            let sequence = &mut self.sequence[!to_server as usize];
+            let mut frame = vec![0; 12];
+            frame.extend([0x08, 0x00, 0x45, 0]);
+            frame.extend((40 + data.len() as u16).to_be_bytes());
+            frame.extend([0, 0, 0, 0, 64, 6, 0, 0]);
+            frame.extend(source.0);
+            frame.extend(destination.0);
+            frame.extend(source.1.to_be_bytes());
+            frame.extend(destination.1.to_be_bytes());
+            frame.extend(sequence.to_be_bytes());
+            frame.extend([0, 0, 0, 0, 0x50, flags as u8, 0xff, 0xff, 0, 0, 0, 0]);
+            frame.extend(data);
+            *sequence += data.len() as u32 + (flags & (TcpFlags::SYN | TcpFlags::FIN) != 0) as u32;
+
+            let frame = CapturedFrame {
+                data: &frame,
+                timestamp: Duration::from_millis(millis),
+                original_length: frame.len(),
+                link_type: LinkType::ETHERNET,
+                interface_name: None,
+                comments: &[],
+            };
+            let mut packet = Parser::parse_frame(&frame, self.packets, &mut Defragmenter::new());
+            self.packets += 1;
+            let segment = self.streams.process(&packet).unwrap();
+            self.http.process(&mut packet, &segment);
+            packet
+        }
+    }
+
+    fn requests(packet: &ParsedPacket) -> Vec<&SerializableHttpRequestPacket> {
+        packet.find_all().collect()
+    }
+
+    fn responses(packet: &ParsedPacket) -> Vec<&SerializableHttpResponsePacket> {
+        packet.find_all().collect()
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let mut connection = Connection::open();
+        connection.send(
+            true,
+            1,
+            b"GET /stream HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        );
+        // Split inside the size line, inside a chunk and inside the trailer
+        let segments: [&[u8]; 4] = [
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5;na",
+            b"me=value\r\nhel",
+            b"lo\r\n7\r\n, world\r\n0\r\nExpires: ne",
+            b"ver\r\n\r\n",
+        ];
+        let packets: Vec<_> = segments
+            .iter()
+            .map(|segment| connection.send(false, 2, segment))
+            .collect();
+
+        assert!(packets[..3]
+            .iter()
+            .all(|packet| responses(packet).is_empty()));
+        let response = responses(&packets[3]);
+        let response = response[0];
+        assert_eq!((response.status, response.reason.as_str()), (200, "OK"));
+        assert!(response.body.chunked);
+        assert_eq!(response.body.data, b"hello, world");
+        assert_eq!((response.body.length, response.first_packet), (12, 3));
+
+        // The next message starts right after the trailer
+        let packet = connection.send(false, 3, b"HTTP/1.1 204 No Content\r\n\r\n");
+        assert_eq!(responses(&packet)[0].status, 204);
+    }
+
+    #[test]
+    fn rejects_malformed_chunks() {
+        let mut connection = Connection::open();
+        let packet = connection.send(
+            false,
+            1,
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+        );
+        assert!(matches!(
+            packet.get_application_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed HTTP chunk size"
+        ));
+        let packet = connection.send(false, 2, b"hello");
+        assert!(responses(&packet).is_empty());
+    }
+
+    #[test]
+    fn decodes_content_length_bodies_across_segments() {
+        let mut connection = Connection::open();
+        let head = connection.send(
+            true,
+            1,
+            b"POST /form HTTP/1.1\r\nContent-Length: 11\r\n\r\nhel",
+        );
+        let middle = connection.send(true, 2, b"lo wo");
+        // The rest of the body, and the start of the next request
+        let last = connection.send(true, 3, b"rld\r\nGET /next HTTP/1.1\r\n");
+
+        assert!(requests(&head).is_empty() && requests(&middle).is_empty());
+        let request = requests(&last);
+        assert_eq!(request.len(), 1);
+        let request = request[0];
+        assert_eq!(
+            (request.method.as_str(), request.uri.as_str()),
+            ("POST", "/form")
+        );
+        assert_eq!(request.header("content-length"), Some("11"));
+        assert_eq!(
+            (request.body.data.as_slice(), request.first_packet),
+            (&b"hello world"[..], 2)
+        );
+        assert!(!request.body.chunked);
+
+        let next = connection.send(true, 4, b"\r\n");
+        let request = requests(&next);
+        assert_eq!(
+            (request[0].uri.as_str(), request[0].first_packet),
+            ("/next", 4)
+        );
+    }
+
+    #[test]
+    fn pairs_pipelined_requests_with_responses() {
+        let mut connection = Connection::open();
+        let sent = connection.send(
+            true,
+            10,
+            b"GET /a HTTP/1.1\r\n\r\nHEAD /b HTTP/1.1\r\n\r\nGET /c HTTP/1.1\r\n\r\n",
+        );
+        let uris: Vec<_> = requests(&sent)
+            .iter()
+            .map(|request| request.uri.as_str())
+            .collect();
+        assert_eq!(uris, ["/a", "/b", "/c"]);
+
+        // The response to HEAD has no body whatever its Content-Length says
+        let first = connection.send(
+            false,
+            15,
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na\
+              HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n",
+        );
+        let last = connection.send(
+            false,
+            40,
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+        );
+        let paired: Vec<_> = responses(&first)
+            .into_iter()
+            .chain(responses(&last))
+            .map(|response| {
+                let request = response.request.as_ref().unwrap();
+                (
+                    response.status,
+                    request.uri.as_str(),
+                    request.packet,
+                    request.time.as_millis(),
+                )
+            })
+            .collect();
+        // The interim response does not use up the request
+        assert_eq!(
+            paired,
+            [
+                (200, "/a", 2, 5),
+                (200, "/b", 2, 5),
+                (100, "/c", 2, 30),
+                (404, "/c", 2, 30)
+            ]
+        );
+
+        // No request is left for this one
+        let unpaired = connection.send(false, 50, b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        assert!(responses(&unpaired)[0].request.is_none());
+    }
+
+    #[test]
+    fn summarises_responses_with_their_request() {
+        let mut connection = Connection::open();
+        connection.send(true, 100, b"GET /x HTTP/1.1\r\n\r\n");
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 3400\r\n\r\n".to_vec();
+        response.extend([b'a'; 3400]);
+        let packet = connection.send(false, 112, &response);
+        let summary = packet.summary();
+        assert_eq!(summary.protocol, "HTTP");
+        assert_eq!(summary.info, "GET /x \u{2192} 200 (12 ms, 3.4 KB)");
+
+        let mut connection = Connection::open();
+        let packet = connection.send(
+            false,
+            1,
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+        );
+        assert_eq!(packet.summary().info, "HTTP/1.1 404 Not Found (0 B)");
+    }
+
+    #[test]
+    fn formats_sizes() {
+        let sizes = [
+            0,
+            999,
+            1_000,
+            3_449,
+            999_949,
+            999_950,
+            1_234_567,
+            999_950_000,
+        ];
+        let formatted: Vec<_> = sizes.into_iter().map(format_size).collect();
+        assert_eq!(
+            formatted,
+            ["0 B", "999 B", "1.0 KB", "3.4 KB", "999.9 KB", "1.0 MB", "1.2 MB", "1.0 GB"]
+        );
+    }
+}
@@ -0,0 +1,741 @@
+//! ICMP decoding, RFC 792, with the datagrams quoted by error messages linked back to the
+//! packets that carried them by `IcmpTracker`
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+use pnet::packet::{
+    icmp::{echo_reply::EchoReplyPacket, echo_request::EchoRequestPacket, IcmpPacket},
+    ip::IpNextHeaderProtocols,
+    ipv4::Ipv4Packet,
+    udp::UdpPacket,
+};
+
+use super::{
+    network::ipv4_field_ranges,
+    wrapers::{
+        ParsedPacket, SerializableEchoReplyPacket, SerializableEchoRequestPacket,
+        SerializableIcmpBody, SerializableIcmpPacket, SerializableIcmpQuote,
+        SerializableIcmpQuotedTransport, SerializableIcmpRouterEntry, SerializableIpv4Packet,
+        SerializablePacket, SerializableUdpPacket,
+    },
+};
+
+/// How long a datagram is remembered for linking the ICMP errors it triggers
+pub const ICMP_ERROR_WINDOW: Duration = Duration::from_secs(30);
+
+/// Build a ICMP packet from a network-layer packet, save it in a Parsed Packet
+pub fn handle_icmp_packet(
+    _source: IpAddr,
+    _destination: IpAddr,
+    packet: &[u8],
+    offset: usize,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some(icmp_packet) = IcmpPacket::new(packet) else {
+        return set_malformed(parsed_packet);
+    };
+    parsed_packet.add_field_ranges(
+        offset,
+        packet.len(),
+        &[
+            ("icmp.type", 0, 1),
+            ("icmp.code", 1, 1),
+            ("icmp.checksum", 2, 2),
+        ],
+    );
+    let echo_ranges = [("icmp", 0, 8), ("icmp.ident", 4, 2), ("icmp.seq", 6, 2)];
+    let decoded = match icmp_packet.get_icmp_type().0 {
+        0 => EchoReplyPacket::new(packet).map(|echo| {
+            parsed_packet.add_field_ranges(offset, packet.len(), &echo_ranges);
+            SerializablePacket::EchoReplyPacket(SerializableEchoReplyPacket::from(&echo))
+        }),
+        8 => EchoRequestPacket::new(packet).map(|echo| {
+            parsed_packet.add_field_ranges(offset, packet.len(), &echo_ranges);
+            SerializablePacket::EchoRequestPacket(SerializableEchoRequestPacket::from(&echo))
+        }),
+        icmp_type => {
+            let mut ranges = Vec::new();
+            decode_body(
+                icmp_type,
+                icmp_packet.get_icmp_code().0,
+                packet,
+                &mut ranges,
+            )
+            .map(|body| {
+                parsed_packet.add_field_ranges(offset, packet.len(), &ranges);
+                SerializablePacket::IcmpPacket(SerializableIcmpPacket {
+                    icmp_type: SerializableIcmpPacket::icmp_type_to_string(
+                        icmp_packet.get_icmp_type(),
+                    ),
+                    icmp_code: icmp_packet.get_icmp_code().0,
+                    checksum: icmp_packet.get_checksum(),
+                    length: packet.len() - 4,
+                    body,
+                })
+            })
+        }
+    };
+    match decoded {
+        Some(icmp) => parsed_packet.set_transport_layer_packet(Some(icmp)),
+        None => set_malformed(parsed_packet),
+    }
+}
+
+fn set_malformed(parsed_packet: &mut ParsedPacket) {
+    parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+        "Malformed ICMP Packet".to_string(),
+    )));
+}
+
+/// Decode the fields after the checksum, `None` if the message is too short for its type
+fn decode_body(
+    icmp_type: u8,
+    code: u8,
+    packet: &[u8],
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableIcmpBody> {
+    use SerializableIcmpBody as Body;
+
+    let u16_at = |at: usize| u16::from_be_bytes([packet[at], packet[at + 1]]);
+    let u32_at = |at: usize| u32::from_be_bytes(packet[at..at + 4].try_into().unwrap());
+    let address_at = |at: usize| Ipv4Addr::from(u32_at(at));
+    let length = match icmp_type {
+        3 | 4 | 5 | 9 | 11 | 12 => 8,
+        13 | 14 => 20,
+        17 | 18 => 12,
+        _ => 4,
+    };
+    if packet.len() < length {
+        return None;
+    }
+    // The router advertisement header is as long as its entries
+    if icmp_type != 9 {
+        ranges.push(("icmp", 0, length));
+    }
+    let body = match icmp_type {
+        3 => {
+            // Routers predating RFC 1191 leave the MTU zero
+            let next_hop_mtu = Some(u16_at(6)).filter(|&mtu| code == 4 && mtu != 0);
+            if next_hop_mtu.is_some() {
+                ranges.push(("icmp.mtu", 6, 2));
+            }
+            Body::DestinationUnreachable {
+                next_hop_mtu,
+                original: decode_quote(&packet[8..], ranges),
+            }
+        }
+        4 => Body::SourceQuench {
+            original: decode_quote(&packet[8..], ranges),
+        },
+        5 => {
+            ranges.push(("icmp.redir_gw", 4, 4));
+            Body::Redirect {
+                gateway: address_at(4),
+                original: decode_quote(&packet[8..], ranges),
+            }
+        }
+        11 => Body::TimeExceeded {
+            original: decode_quote(&packet[8..], ranges),
+        },
+        12 => {
+            ranges.push(("icmp.pointer", 4, 1));
+            Body::ParameterProblem {
+                pointer: packet[4],
+                original: decode_quote(&packet[8..], ranges),
+            }
+        }
+        // Router advertisement, RFC 1256
+        9 => {
+            let count = packet[4] as usize;
+            let entry_size = packet[5] as usize * 4;
+            if count > 0 && entry_size < 8 || packet.len() < 8 + count * entry_size {
+                return None;
+            }
+            ranges.extend([
+                ("icmp", 0, 8 + count * entry_size),
+                ("icmp.num_addrs", 4, 1),
+                ("icmp.addr_entry_size", 5, 1),
+                ("icmp.lifetime", 6, 2),
+            ]);
+            let entries = (0..count)
+                .map(|i| {
+                    let start = 8 + i * entry_size;
+                    ranges.extend([
+                        ("icmp.router_address", start, 4),
+                        ("icmp.pref_level", start + 4, 4),
+                    ]);
+                    SerializableIcmpRouterEntry {
+                        address: address_at(start),
+                        preference: u32_at(start + 4) as i32,
+                    }
+                })
+                .collect();
+            Body::RouterAdvertisement {
+                lifetime: u16_at(6),
+                entries,
+            }
+        }
+        13 | 14 => {
+            ranges.extend([
+                ("icmp.ident", 4, 2),
+                ("icmp.seq", 6, 2),
+                ("icmp.originate_timestamp", 8, 4),
+                ("icmp.receive_timestamp", 12, 4),
+                ("icmp.transmit_timestamp", 16, 4),
+            ]);
+            Body::Timestamp {
+                identifier: u16_at(4),
+                sequence_number: u16_at(6),
+                originate: u32_at(8),
+                receive: u32_at(12),
+                transmit: u32_at(16),
+            }
+        }
+        // Address mask request and reply, RFC 950
+        17 | 18 => {
+            ranges.extend([
+                ("icmp.ident", 4, 2),
+                ("icmp.seq", 6, 2),
+                ("icmp.address_mask", 8, 4),
+            ]);
+            Body::AddressMask {
+                identifier: u16_at(4),
+                sequence_number: u16_at(6),
+                mask: address_at(8),
+            }
+        }
+        _ => Body::Other,
+    };
+    Some(body)
+}
+
+/// Decode the IP header and the start of the transport header of the datagram an error was
+/// sent about, found 8 bytes into the message
+fn decode_quote(
+    data: &[u8],
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableIcmpQuote> {
+    let header = Ipv4Packet::new(data)?;
+    let header_length = header.get_header_length() as usize * 4;
+    if header.get_version() != 4 || header_length < 20 || data.len() < header_length {
+        return None;
+    }
+    ranges.extend(
+        ipv4_field_ranges(header_length)
+            .into_iter()
+            .map(|(field, start, length)| (field, 8 + start, length)),
+    );
+    // Only the first fragment starts with the transport header
+    let transport = data
+        .get(header_length..header_length + 8)
+        .filter(|_| header.get_fragment_offset() == 0)
+        .and_then(|transport| {
+            let start = 8 + header_length;
+            let u16_at = |at: usize| u16::from_be_bytes([transport[at], transport[at + 1]]);
+            let (decoded, fields): (_, &[(&'static str, usize, usize)]) =
+                match header.get_next_level_protocol() {
+                    IpNextHeaderProtocols::Tcp => (
+                        SerializableIcmpQuotedTransport::Tcp {
+                            source: u16_at(0),
+                            destination: u16_at(2),
+                            sequence: u32::from_be_bytes(transport[4..8].try_into().unwrap()),
+                        },
+                        &[
+                            ("tcp", 0, 8),
+                            ("tcp.srcport", 0, 2),
+                            ("tcp.dstport", 2, 2),
+                            ("tcp.seq", 4, 4),
+                        ],
+                    ),
+                    IpNextHeaderProtocols::Udp => (
+                        SerializableIcmpQuotedTransport::Udp(SerializableUdpPacket::from(
+                            &UdpPacket::new(transport)?,
+                        )),
+                        &[
+                            ("udp", 0, 8),
+                            ("udp.srcport", 0, 2),
+                            ("udp.dstport", 2, 2),
+                            ("udp.length", 4, 2),
+                            ("udp.checksum", 6, 2),
+                        ],
+                    ),
+                    IpNextHeaderProtocols::Icmp if matches!(transport[0], 0 | 8) => (
+                        SerializableIcmpQuotedTransport::Echo {
+                            icmp_type: transport[0],
+                            icmp_code: transport[1],
+                            identifier: u16_at(4),
+                            sequence_number: u16_at(6),
+                        },
+                        &[
+                            ("icmp", 0, 8),
+                            ("icmp.type", 0, 1),
+                            ("icmp.code", 1, 1),
+                            ("icmp.ident", 4, 2),
+                            ("icmp.seq", 6, 2),
+                        ],
+                    ),
+                    _ => return None,
+                };
+            ranges.extend(
+                fields
+                    .iter()
+                    .map(|&(field, offset, length)| (field, start + offset, length)),
+            );
+            Some(decoded)
+        });
+    Some(SerializableIcmpQuote {
+        ipv4: SerializableIpv4Packet::from(&header),
+        transport,
+        original_in: None,
+    })
+}
+
+/// What identifies a datagram when it is quoted, ICMP errors carry its IP header and the first
+/// 8 bytes of its payload
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct DatagramKey {
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    identification: u16,
+    transport: Option<TransportKey>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum TransportKey {
+    Tcp(u16, u16, u32),
+    Udp(u16, u16),
+    Echo(u8, u16, u16),
+}
+
+/// Links the datagrams quoted by ICMP errors to the packets they were captured in
+#[derive(Default)]
+pub struct IcmpTracker {
+    /// Latest packet carrying each datagram, with its capture time
+    datagrams: HashMap<DatagramKey, (usize, Duration)>,
+    /// Remembered datagrams oldest first, with their capture time
+    seen: VecDeque<(Duration, DatagramKey)>,
+}
+
+impl IcmpTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the datagram in `packet`, or fill in the packet an ICMP error quotes
+    pub fn process(&mut self, packet: &mut ParsedPacket) {
+        let timestamp = packet.get_timestamp();
+        while let Some(&(seen, key)) = self.seen.front() {
+            if timestamp.saturating_sub(seen) < ICMP_ERROR_WINDOW {
+                break;
+            }
+            self.seen.pop_front();
+            // The datagram may have been seen again since
+            if self
+                .datagrams
+                .get(&key)
+                .is_some_and(|&(_, latest)| latest == seen)
+            {
+                self.datagrams.remove(&key);
+            }
+        }
+
+        let id = packet.get_id();
+        if let Some(icmp) = packet.find_mut::<SerializableIcmpPacket>() {
+            if let Some(original) = icmp.original_mut() {
+                let key = quote_key(original);
+                original.original_in = self.datagrams.get(&key).map(|&(id, _)| id);
+                return;
+            }
+        }
+        let Some(key) = datagram_key(packet) else {
+            return;
+        };
+        // Several packets may look alike, the latest is the likeliest to be quoted
+        self.datagrams.insert(key, (id, timestamp));
+        self.seen.push_back((timestamp, key));
+    }
+}
+
+fn quote_key(quote: &SerializableIcmpQuote) -> DatagramKey {
+    let transport = quote.transport.as_ref().map(|transport| match *transport {
+        SerializableIcmpQuotedTransport::Tcp {
+            source,
+            destination,
+            sequence,
+        } => TransportKey::Tcp(source, destination, sequence),
+        SerializableIcmpQuotedTransport::Udp(ref udp) => {
+            TransportKey::Udp(udp.source, udp.destination)
+        }
+        SerializableIcmpQuotedTransport::Echo {
+            icmp_type,
+            identifier,
+            sequence_number,
+            ..
+        } => TransportKey::Echo(icmp_type, identifier, sequence_number),
+    });
+    DatagramKey {
+        source: quote.ipv4.source,
+        destination: quote.ipv4.destination,
+        identification: quote.ipv4.identification,
+        transport,
+    }
+}
+
+/// Get the key of the innermost IPv4 datagram in `packet`, as it would be quoted
+fn datagram_key(packet: &ParsedPacket) -> Option<DatagramKey> {
+    let Some(SerializablePacket::Ipv4Packet(ipv4)) = packet.get_network_layer_packet() else {
+        return None;
+    };
+    let transport = match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::TcpPacket(tcp)) => {
+            Some(TransportKey::Tcp(tcp.source, tcp.destination, tcp.sequence))
+        }
+        Some(SerializablePacket::UdpPacket(udp)) => {
+            Some(TransportKey::Udp(udp.source, udp.destination))
+        }
+        Some(SerializablePacket::EchoRequestPacket(echo)) => Some(TransportKey::Echo(
+            echo.icmp_type,
+            echo.identifier,
+            echo.sequence_number,
+        )),
+        Some(SerializablePacket::EchoReplyPacket(echo)) => Some(TransportKey::Echo(
+            echo.icmp_type,
+            echo.identifier,
+            echo.sequence_number,
+        )),
+        _ => None,
+    };
+    Some(DatagramKey {
+        source: ipv4.source,
+        destination: ipv4.destination,
+        identification: ipv4.identification,
+        transport,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        defrag::Defragmenter,
+        Parser,
+    };
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 7];
+    const SERVER: [u8; 4] = [10, 0, 0, 53];
+    const ROUTER: [u8; 4] = [192, 0, 2, 1];
+
+    fn ipv4(
+        source: [u8; 4],
+        destination: [u8; 4],
+        identification: u16,
+        protocol: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut datagram = vec![0x45, 0];
+        datagram.extend((20 + payload.len() as u16).to_be_bytes());
+        datagram.extend(identification.to_be_bytes());
+        datagram.extend([0, 0, 64, protocol, 0, 0]);
+        datagram.extend(source);
+        datagram.extend(destination);
+        datagram.extend(payload);
+        datagram
+    }
+
+    /// The query of the client to its DNS server, as ICMP errors quote it
+    fn query(identification: u16, client_port: u16) -> Vec<u8> {
+        let mut udp = client_port.to_be_bytes().to_vec();
+        udp.extend([0, 53, 0, 12, 0, 0]);
+        udp.extend(b"\x12\x34\x01\x00");
+        ipv4(CLIENT, SERVER, identification, 17, &udp)
+    }
+
+    /// An ICMP message from the router to the client, `rest` holds the 4 bytes after the
+    /// checksum
+    fn icmp(icmp_type: u8, code: u8, rest: [u8; 4], data: &[u8]) -> Vec<u8> {
+        let message = [&[icmp_type, code, 0, 0][..], &rest, data].concat();
+        ipv4(ROUTER, CLIENT, 0, 1, &message)
+    }
+
+    fn parse(id: usize, timestamp: Duration, datagram: &[u8]) -> ParsedPacket {
+        let mut frame = vec![0; 12];
+        frame.extend([0x08, 0x00]);
+        frame.extend(datagram);
+        let frame = CapturedFrame {
+            data: &frame,
+            timestamp,
+            original_length: frame.len(),
+            link_type: LinkType::ETHERNET,
+            interface_name: None,
+            comments: &[],
+        };
+        Parser::parse_frame(&frame, id, &mut Defragmenter::new())
+    }
+
+    fn decode(datagram: &[u8]) -> ParsedPacket {
+        parse(0, Duration::ZERO, datagram)
+    }
+
+    fn body(packet: &ParsedPacket) -> &SerializableIcmpBody {
+        &packet.find::<SerializableIcmpPacket>().unwrap().body
+    }
+
+    #[test]
+    fn decodes_destination_unreachable() {
+        let query = query(0x1234, 40000);
+        let packet = decode(&icmp(3, 4, [0, 0, 0x05, 0x78], &query));
+        let SerializableIcmpBody::DestinationUnreachable {
+            next_hop_mtu,
+            original: Some(original),
+        } = body(&packet)
+        else {
+            panic!("no quoted datagram");
+        };
+        assert_eq!(*next_hop_mtu, Some(1400));
+        assert_eq!(original.ipv4.identification, 0x1234);
+        assert_eq!(original.ports(), Some((40000, 53)));
+        assert!(matches!(
+            &original.transport,
+            Some(SerializableIcmpQuotedTransport::Udp(udp)) if udp.length == 12
+        ));
+        assert_eq!(
+            packet.summary().info,
+            "Destination unreachable (Fragmentation needed, MTU=1400), \
+             UDP 10.0.0.7:40000 \u{2192} 10.0.0.53:53"
+        );
+        // Fields of the quoted datagram are found after those of the outer one
+        assert_eq!(packet.get_field_range("icmp.mtu"), Some(40..42));
+        assert_eq!(packet.get_nth_field_range("ip.id", 1), Some(46..48));
+        assert_eq!(packet.get_field_range("udp.dstport"), Some(64..66));
+
+        // Routers predating path MTU discovery leave it zero, and it means nothing for other
+        // codes
+        let packet = decode(&icmp(3, 4, [0; 4], &query));
+        assert!(matches!(
+            body(&packet),
+            SerializableIcmpBody::DestinationUnreachable {
+                next_hop_mtu: None,
+                ..
+            }
+        ));
+        assert!(packet
+            .summary()
+            .info
+            .starts_with("Destination unreachable (Fragmentation needed), "));
+        let packet = decode(&icmp(3, 3, [0, 0, 0x05, 0x78], &query));
+        assert!(packet
+            .summary()
+            .info
+            .starts_with("Destination unreachable (Port unreachable), UDP"));
+        assert_eq!(packet.get_field_range("icmp.mtu"), None);
+    }
+
+    #[test]
+    fn links_errors_to_the_datagrams_they_quote() {
+        let mut tracker = IcmpTracker::new();
+        let mut process = |id: usize, secs: u64, datagram: &[u8]| {
+            let mut packet = parse(id, Duration::from_secs(secs), datagram);
+            tracker.process(&mut packet);
+            packet
+                .find::<SerializableIcmpPacket>()
+                .and_then(|icmp| icmp.original()?.original_in)
+        };
+        let unreachable = icmp(3, 3, [0; 4], &query(1, 40000));
+        assert_eq!(process(0, 0, &query(1, 40000)), None);
+        assert_eq!(process(1, 0, &query(2, 40000)), None);
+        assert_eq!(process(2, 0, &query(1, 40001)), None);
+        assert_eq!(process(3, 1, &unreachable), Some(0));
+        // Only the first 8 bytes of the UDP payload are quoted
+        let truncated = &unreachable[..unreachable.len() - 4];
+        assert_eq!(process(4, 1, truncated), Some(0));
+        // A datagram seen again is linked to its latest packet
+        assert_eq!(process(5, 10, &query(1, 40000)), None);
+        assert_eq!(process(6, 11, &unreachable), Some(5));
+        assert_eq!(
+            process(7, 10 + ICMP_ERROR_WINDOW.as_secs(), &unreachable),
+            None
+        );
+
+        // Quoted echo requests and TCP segments are linked too
+        let echo = ipv4(CLIENT, SERVER, 3, 1, &[8, 0, 0, 0, 0, 7, 0, 1]);
+        let mut tcp = vec![0x9c, 0x40, 0, 80, 0, 0, 0x10, 0];
+        tcp.extend([0; 12]);
+        tcp[12] = 0x50;
+        let segment = ipv4(CLIENT, SERVER, 4, 6, &tcp);
+        assert_eq!(process(8, 100, &echo), None);
+        assert_eq!(process(9, 100, &segment), None);
+        assert_eq!(process(10, 100, &icmp(11, 0, [0; 4], &echo[..28])), Some(8));
+        assert_eq!(
+            process(11, 100, &icmp(3, 1, [0; 4], &segment[..28])),
+            Some(9)
+        );
+        // The sequence number tells segments of a connection apart
+        tcp[6] = 0x20;
+        let next = ipv4(CLIENT, SERVER, 4, 6, &tcp);
+        assert_eq!(process(12, 100, &icmp(3, 1, [0; 4], &next[..28])), None);
+    }
+
+    #[test]
+    fn decodes_other_error_messages() {
+        let echo = ipv4(CLIENT, SERVER, 3, 1, &[8, 0, 0, 0, 0, 7, 0, 1]);
+        let packet = decode(&icmp(11, 0, [0; 4], &echo));
+        assert!(matches!(
+            body(&packet),
+            SerializableIcmpBody::TimeExceeded {
+                original: Some(SerializableIcmpQuote {
+                    transport: Some(SerializableIcmpQuotedTransport::Echo {
+                        icmp_type: 8,
+                        identifier: 7,
+                        sequence_number: 1,
+                        ..
+                    }),
+                    ..
+                }),
+            }
+        ));
+        assert_eq!(
+            packet.summary().info,
+            "Time-to-live exceeded (Time to live exceeded in transit), \
+             ICMP 10.0.0.7 \u{2192} 10.0.0.53 id=0x0007, seq=1"
+        );
+
+        let packet = decode(&icmp(5, 1, [192, 0, 2, 254], &query(1, 40000)));
+        assert!(matches!(
+            body(&packet),
+            SerializableIcmpBody::Redirect { gateway, original: Some(_) }
+                if *gateway == Ipv4Addr::new(192, 0, 2, 254)
+        ));
+        assert!(packet
+            .summary()
+            .info
+            .starts_with("Redirect (Redirect for host), Gateway 192.0.2.254, UDP"));
+        assert_eq!(packet.get_field_range("icmp.redir_gw"), Some(38..42));
+
+        let packet = decode(&icmp(12, 0, [9, 0, 0, 0], &query(1, 40000)));
+        assert!(matches!(
+            body(&packet),
+            SerializableIcmpBody::ParameterProblem {
+                pointer: 9,
+                original: Some(_)
+            }
+        ));
+    }
+
+    #[test]
+    fn decodes_informational_messages() {
+        let times = [&[0, 0, 0, 1][..], &[0, 0, 0, 2], &[0, 0, 0, 3]].concat();
+        let packet = decode(&icmp(14, 0, [0, 7, 0, 1], &times));
+        assert!(matches!(
+            body(&packet),
+            SerializableIcmpBody::Timestamp {
+                identifier: 7,
+                sequence_number: 1,
+                originate: 1,
+                receive: 2,
+                transmit: 3,
+            }
+        ));
+        assert_eq!(packet.summary().info, "Timestamp reply id=0x0007, seq=1");
+        assert_eq!(
+            packet.get_field_range("icmp.transmit_timestamp"),
+            Some(50..54)
+        );
+
+        let entries = [
+            &ROUTER[..],
+            &[0, 0, 0, 1],
+            &[192, 0, 2, 2],
+            &[0x80, 0, 0, 0],
+        ]
+        .concat();
+        let packet = decode(&icmp(9, 0, [2, 2, 0x07, 0x08], &entries));
+        let SerializableIcmpBody::RouterAdvertisement { lifetime, entries } = body(&packet) else {
+            panic!("not a router advertisement");
+        };
+        assert_eq!(*lifetime, 1800);
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.address, entry.preference))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (Ipv4Addr::from(ROUTER), 1),
+                (Ipv4Addr::new(192, 0, 2, 2), i32::MIN)
+            ]
+        );
+        assert_eq!(
+            packet.summary().info,
+            "Router advertisement (Normal router advertisement), \
+             Routers [192.0.2.1, 192.0.2.2] Lifetime=1800"
+        );
+        assert_eq!(packet.get_field_range("icmp"), Some(34..58));
+        assert_eq!(
+            packet.get_nth_field_range("icmp.pref_level", 1),
+            Some(54..58)
+        );
+
+        let packet = decode(&icmp(18, 0, [0, 7, 0, 1], &[255, 255, 255, 0]));
+        assert_eq!(
+            packet.summary().info,
+            "Address mask reply id=0x0007, seq=1, Mask 255.255.255.0"
+        );
+    }
+
+    #[test]
+    fn handles_truncated_quotes_and_messages() {
+        let quote = |data: &[u8]| match body(&decode(&icmp(3, 3, [0; 4], data))) {
+            SerializableIcmpBody::DestinationUnreachable { original, .. } => original
+                .as_ref()
+                .map(|original| original.transport.is_some()),
+            _ => panic!("not a destination unreachable"),
+        };
+        let query = query(1, 40000);
+        assert_eq!(quote(&query[..28]), Some(true));
+        // Cut in the transport header, or in the IP header
+        assert_eq!(quote(&query[..27]), Some(false));
+        assert_eq!(quote(&query[..20]), Some(false));
+        assert_eq!(quote(&query[..19]), None);
+        assert_eq!(quote(&query[..12]), None);
+        assert_eq!(quote(&[]), None);
+        // Cut in the options of the IP header
+        let mut options = query.clone();
+        options[0] = 0x46;
+        assert_eq!(quote(&options[..23]), None);
+        // Not an IPv4 header
+        let mut ipv6 = query.clone();
+        ipv6[0] = 0x65;
+        assert_eq!(quote(&ipv6), None);
+        // Later fragments don't start with the transport header
+        let mut fragment = query.clone();
+        fragment[7] = 1;
+        assert_eq!(quote(&fragment), Some(false));
+        let packet = decode(&icmp(3, 3, [0; 4], &query[..27]));
+        assert_eq!(
+            packet.summary().info,
+            "Destination unreachable (Port unreachable), Udp 10.0.0.7 \u{2192} 10.0.0.53"
+        );
+
+        let malformed = |datagram: &[u8]| {
+            matches!(
+                decode(datagram).get_transport_layer_packet(),
+                Some(SerializablePacket::MalformedPacket(reason))
+                    if reason == "Malformed ICMP Packet"
+            )
+        };
+        let unreachable = icmp(3, 3, [0; 4], &[]);
+        assert!(!malformed(&unreachable));
+        assert!(malformed(&unreachable[..27]));
+        assert!(malformed(&icmp(13, 0, [0; 4], &[0; 11])));
+        assert!(!malformed(&icmp(13, 0, [0; 4], &[0; 12])));
+        // Router advertisement entries must fit, and hold an address and preference
+        assert!(malformed(&icmp(9, 0, [2, 2, 0, 0], &[0; 15])));
+        assert!(malformed(&icmp(9, 0, [1, 1, 0, 0], &[0; 4])));
+        assert!(!malformed(&icmp(9, 0, [0, 0, 0, 0], &[])));
+    }
+}
@@ -0,0 +1,637 @@
+//! ICMPv6 decoding, RFC 4443, with the Neighbor Discovery messages of RFC 4861 and the
+//! Multicast Listener Discovery ones of RFC 2710 and 3810
+
+use std::net::{IpAddr, Ipv6Addr};
+
+use pnet::{packet::icmpv6::Icmpv6Packet, util::MacAddr};
+
+use super::{
+    dns,
+    wrapers::{
+        ParsedPacket, SerializableIcmpv6Body, SerializableIcmpv6Packet, SerializableMldRecord,
+        SerializableMldv2Query, SerializableNdpOption, SerializableNdpOptionData,
+        SerializablePacket,
+    },
+};
+
+/// Build a ICMPv6 packet from a network-layer packet, save it in a Parsed Packet
+pub fn handle_icmpv6_packet(
+    _source: IpAddr,
+    _destination: IpAddr,
+    packet: &[u8],
+    offset: usize,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let mut ranges = vec![
+        ("icmpv6.type", 0, 1),
+        ("icmpv6.code", 1, 1),
+        ("icmpv6.checksum", 2, 2),
+    ];
+    let decoded = Icmpv6Packet::new(packet).and_then(|icmpv6_packet| {
+        let body = decode_body(icmpv6_packet.get_icmpv6_type().0, packet, &mut ranges)?;
+        Some(SerializableIcmpv6Packet {
+            icmpv6_type: SerializableIcmpv6Packet::icmpv6_type_to_string(
+                icmpv6_packet.get_icmpv6_type(),
+            ),
+            icmpv6_code: icmpv6_packet.get_icmpv6_code().0,
+            checksum: icmpv6_packet.get_checksum(),
+            length: packet.len() - 4,
+            body,
+        })
+    });
+    match decoded {
+        Some(icmpv6) => {
+            parsed_packet.add_field_ranges(offset, packet.len(), &ranges);
+            parsed_packet
+                .set_transport_layer_packet(Some(SerializablePacket::Icmpv6Packet(icmpv6)));
+        }
+        None => {
+            parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed ICMPv6 Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Decode the fields after the checksum, `None` if the message is too short for its type or
+/// its options overrun it
+fn decode_body(
+    icmpv6_type: u8,
+    packet: &[u8],
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<SerializableIcmpv6Body> {
+    use SerializableIcmpv6Body as Body;
+
+    let u16_at = |at: usize| u16::from_be_bytes([packet[at], packet[at + 1]]);
+    let u32_at = |at: usize| u32::from_be_bytes(packet[at..at + 4].try_into().unwrap());
+    let length = match icmpv6_type {
+        133 => 8,
+        134 => 16,
+        135 | 136 | 130..=132 => 24,
+        137 => 40,
+        128 | 129 | 143 => 8,
+        _ => 4,
+    };
+    if packet.len() < length {
+        return None;
+    }
+    // Neighbor Discovery and MLD messages fill the packet, others carry a payload
+    let header = if matches!(icmpv6_type, 130..=137 | 143) {
+        packet.len()
+    } else {
+        length
+    };
+    ranges.push(("icmpv6", 0, header));
+    let body = match icmpv6_type {
+        128 | 129 => {
+            ranges.extend([
+                ("icmpv6.echo.identifier", 4, 2),
+                ("icmpv6.echo.sequence_number", 6, 2),
+            ]);
+            let (identifier, sequence_number) = (u16_at(4), u16_at(6));
+            if icmpv6_type == 128 {
+                Body::EchoRequest {
+                    identifier,
+                    sequence_number,
+                    unanswered: false,
+                }
+            } else {
+                Body::EchoReply {
+                    identifier,
+                    sequence_number,
+                    response_to: None,
+                    time: None,
+                    duplicate: false,
+                }
+            }
+        }
+        133 => Body::RouterSolicitation {
+            options: decode_options(packet, 8, ranges)?,
+        },
+        134 => {
+            ranges.extend([
+                ("icmpv6.nd.ra.cur_hop_limit", 4, 1),
+                ("icmpv6.nd.ra.flag", 5, 1),
+                ("icmpv6.nd.ra.router_lifetime", 6, 2),
+                ("icmpv6.nd.ra.reachable_time", 8, 4),
+                ("icmpv6.nd.ra.retrans_timer", 12, 4),
+            ]);
+            Body::RouterAdvertisement {
+                hop_limit: packet[4],
+                flags: packet[5],
+                lifetime: u16_at(6),
+                reachable_time: u32_at(8),
+                retransmit_timer: u32_at(12),
+                options: decode_options(packet, 16, ranges)?,
+            }
+        }
+        135 => {
+            ranges.push(("icmpv6.nd.ns.target_address", 8, 16));
+            Body::NeighborSolicitation {
+                target: address(&packet[8..24]),
+                options: decode_options(packet, 24, ranges)?,
+            }
+        }
+        136 => {
+            ranges.extend([
+                ("icmpv6.nd.na.flag", 4, 4),
+                ("icmpv6.nd.na.target_address", 8, 16),
+            ]);
+            Body::NeighborAdvertisement {
+                flags: packet[4],
+                target: address(&packet[8..24]),
+                options: decode_options(packet, 24, ranges)?,
+            }
+        }
+        137 => {
+            ranges.extend([
+                ("icmpv6.nd.rd.target_address", 8, 16),
+                ("icmpv6.nd.rd.destination_address", 24, 16),
+            ]);
+            Body::Redirect {
+                target: address(&packet[8..24]),
+                destination: address(&packet[24..40]),
+                options: decode_options(packet, 40, ranges)?,
+            }
+        }
+        130 => {
+            ranges.extend([
+                ("icmpv6.mld.maximum_response_delay", 4, 2),
+                ("icmpv6.mld.multicast_address", 8, 16),
+            ]);
+            let code = u16_at(4);
+            // MLDv1 queries are 24 bytes long, MLDv2 ones at least 28, RFC 3810 section 8.1
+            let (maximum_response_delay, v2) = if packet.len() >= 28 {
+                let count = u16_at(26) as usize;
+                if packet.len() < 28 + count * 16 {
+                    return None;
+                }
+                ranges.extend([
+                    ("icmpv6.mld.flag", 24, 1),
+                    ("icmpv6.mld.qqi", 25, 1),
+                    ("icmpv6.mld.nb_sources", 26, 2),
+                ]);
+                let sources = (0..count)
+                    .map(|i| {
+                        let start = 28 + i * 16;
+                        ranges.push(("icmpv6.mld.source_address", start, 16));
+                        address(&packet[start..start + 16])
+                    })
+                    .collect();
+                let query = SerializableMldv2Query {
+                    suppress: packet[24] & 0x08 != 0,
+                    robustness: packet[24] & 0x07,
+                    interval: floating_point(packet[25] as u32, 4, 3),
+                    sources,
+                };
+                (floating_point(code as u32, 12, 3), Some(query))
+            } else {
+                (code as u32, None)
+            };
+            Body::MulticastListenerQuery {
+                maximum_response_delay,
+                address: address(&packet[8..24]),
+                v2,
+            }
+        }
+        131 | 132 => {
+            ranges.push(("icmpv6.mld.multicast_address", 8, 16));
+            let address = address(&packet[8..24]);
+            if icmpv6_type == 131 {
+                Body::MulticastListenerReport { address }
+            } else {
+                Body::MulticastListenerDone { address }
+            }
+        }
+        143 => {
+            ranges.push(("icmpv6.mldr.nb_mcast_records", 6, 2));
+            let mut records = Vec::new();
+            let mut position = 8;
+            for _ in 0..u16_at(6) {
+                let record = packet.get(position..position + 20)?;
+                let sources = u16::from_be_bytes([record[2], record[3]]) as usize;
+                let length = 20 + sources * 16 + record[1] as usize * 4;
+                let record = packet.get(position..position + length)?;
+                ranges.push(("icmpv6.mldr.mar", position, length));
+                records.push(SerializableMldRecord {
+                    record_type: record[0],
+                    address: address(&record[4..20]),
+                    sources: record[20..20 + sources * 16]
+                        .chunks_exact(16)
+                        .map(address)
+                        .collect(),
+                });
+                position += length;
+            }
+            Body::MulticastListenerReportV2 { records }
+        }
+        _ => Body::Other,
+    };
+    Some(body)
+}
+
+/// Decode the Neighbor Discovery options from `start` to the end of the packet
+fn decode_options(
+    packet: &[u8],
+    start: usize,
+    ranges: &mut Vec<(&'static str, usize, usize)>,
+) -> Option<Vec<SerializableNdpOption>> {
+    let mut options = Vec::new();
+    let mut position = start;
+    while position < packet.len() {
+        let &[option_type, length] = packet.get(position..position + 2)? else {
+            return None;
+        };
+        // Lengths are in units of 8 bytes, a zero length would never end, RFC 4861 section 4.6
+        if length == 0 {
+            return None;
+        }
+        let length = length as usize * 8;
+        let option = packet.get(position..position + length)?;
+        ranges.push(("icmpv6.opt", position, length));
+        let field = match option_type {
+            1 | 2 => Some(("icmpv6.opt.linkaddr", 2, 6)),
+            3 => Some(("icmpv6.opt.prefix", 16, 16)),
+            5 => Some(("icmpv6.opt.mtu", 4, 4)),
+            25 => Some(("icmpv6.opt.rdnss", 8, length - 8)),
+            31 => Some(("icmpv6.opt.dnssl", 8, length - 8)),
+            _ => None,
+        };
+        let data = option_data(option_type, option);
+        if let (Some((field, offset, length)), false) =
+            (field, matches!(data, SerializableNdpOptionData::Unknown(_)))
+        {
+            ranges.push((field, position + offset, length));
+        }
+        options.push(SerializableNdpOption { option_type, data });
+        position += length;
+    }
+    Some(options)
+}
+
+/// Decode an option, including its type and length bytes, those that don't fit their type
+/// are kept as unknown
+fn option_data(option_type: u8, option: &[u8]) -> SerializableNdpOptionData {
+    use SerializableNdpOptionData as Data;
+
+    let u32_at = |at: usize| u32::from_be_bytes(option[at..at + 4].try_into().unwrap());
+    match option_type {
+        // Ethernet addresses, RFC 2464 section 6
+        1 | 2 if option.len() == 8 => {
+            let mut bytes = [0; 6];
+            bytes.copy_from_slice(&option[2..8]);
+            Data::LinkLayerAddress(MacAddr::from(bytes))
+        }
+        3 if option.len() == 32 => Data::PrefixInformation {
+            prefix_length: option[2],
+            flags: option[3],
+            valid_lifetime: u32_at(4),
+            preferred_lifetime: u32_at(8),
+            prefix: address(&option[16..32]),
+        },
+        5 if option.len() == 8 => Data::Mtu(u32_at(4)),
+        25 if option.len() >= 24 && (option.len() - 8).is_multiple_of(16) => Data::Rdnss {
+            lifetime: u32_at(4),
+            servers: option[8..].chunks_exact(16).map(address).collect(),
+        },
+        31 if option.len() >= 16 => {
+            // Names are padded with zeros to the end of the option
+            let names = option[8..]
+                .iter()
+                .rposition(|&byte| byte != 0)
+                .map_or(Some(Vec::new()), |last| {
+                    option[8..].get(..last + 2).and_then(dns::names)
+                });
+            match names {
+                Some(domains) => Data::Dnssl {
+                    lifetime: u32_at(4),
+                    domains,
+                },
+                None => Data::Unknown(option[2..].to_vec()),
+            }
+        }
+        _ => Data::Unknown(option[2..].to_vec()),
+    }
+}
+
+/// Decode a maximum response code or querier's query interval code, values with the top bit
+/// set have an exponent and a mantissa, RFC 3810 sections 5.1.3 and 5.1.9
+fn floating_point(code: u32, mantissa_bits: u32, exponent_bits: u32) -> u32 {
+    let top = 1 << (mantissa_bits + exponent_bits);
+    if code < top {
+        return code;
+    }
+    let exponent = (code >> mantissa_bits) & ((1 << exponent_bits) - 1);
+    let mantissa = code & ((1 << mantissa_bits) - 1);
+    (mantissa | 1 << mantissa_bits) << (exponent + 3)
+}
+
+fn address(bytes: &[u8]) -> Ipv6Addr {
+    let bytes: [u8; 16] = bytes.try_into().unwrap();
+    Ipv6Addr::from(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An option of `data.len() + 2` bytes, a multiple of 8
+    fn option(option_type: u8, data: &[u8]) -> Vec<u8> {
+        let mut option = vec![option_type, (data.len() as u8 + 2) / 8];
+        option.extend(data);
+        option
+    }
+
+    fn address_bytes(address: &str) -> [u8; 16] {
+        address.parse::<Ipv6Addr>().unwrap().octets()
+    }
+
+    fn decode(message: &[u8]) -> Option<SerializableIcmpv6Body> {
+        decode_body(message[0], message, &mut Vec::new())
+    }
+
+    /// Options of a Neighbor Discovery message, type and value
+    fn options(body: &SerializableIcmpv6Body) -> Vec<(u8, String)> {
+        let (SerializableIcmpv6Body::RouterSolicitation { options }
+        | SerializableIcmpv6Body::RouterAdvertisement { options, .. }
+        | SerializableIcmpv6Body::NeighborSolicitation { options, .. }
+        | SerializableIcmpv6Body::NeighborAdvertisement { options, .. }
+        | SerializableIcmpv6Body::Redirect { options, .. }) = body
+        else {
+            panic!("not a Neighbor Discovery message");
+        };
+        options
+            .iter()
+            .map(|option| (option.option_type, option.data.to_string()))
+            .collect()
+    }
+
+    /// A router advertisement with `options`
+    fn router_advertisement(options: &[u8]) -> Vec<u8> {
+        let mut message = vec![134, 0, 0, 0, 64, 0xc0, 0x07, 0x08];
+        message.extend(30_000u32.to_be_bytes());
+        message.extend(1_000u32.to_be_bytes());
+        message.extend(options);
+        message
+    }
+
+    #[test]
+    fn decodes_neighbor_discovery_options() {
+        let mut prefix = vec![64, 0xc0];
+        prefix.extend(86_400u32.to_be_bytes());
+        prefix.extend(14_400u32.to_be_bytes());
+        prefix.extend([0; 4]);
+        prefix.extend(address_bytes("2001:db8:1::"));
+        let mut rdnss = vec![0, 0];
+        rdnss.extend(600u32.to_be_bytes());
+        rdnss.extend(address_bytes("2001:db8::53"));
+        rdnss.extend(address_bytes("2001:db8::54"));
+        let mut dnssl = vec![0, 0];
+        dnssl.extend(600u32.to_be_bytes());
+        dnssl.extend(b"\x07example\x03com\x00\x04corp\x07example\x00\x00\x00\x00\x00\x00");
+        let message = router_advertisement(
+            &[
+                option(1, &[0x02, 0, 0, 0, 0, 0x01]),
+                option(3, &prefix),
+                option(5, &[0, 0, 0, 0, 0x05, 0xdc]),
+                option(25, &rdnss),
+                option(31, &dnssl),
+            ]
+            .concat(),
+        );
+        let mut ranges = Vec::new();
+        let body = decode_body(134, &message, &mut ranges).unwrap();
+        let SerializableIcmpv6Body::RouterAdvertisement {
+            hop_limit,
+            flags,
+            lifetime,
+            reachable_time,
+            retransmit_timer,
+            ..
+        } = body
+        else {
+            panic!("not a router advertisement");
+        };
+        assert_eq!(
+            (hop_limit, flags, lifetime, reachable_time, retransmit_timer),
+            (64, 0xc0, 1800, 30_000, 1_000)
+        );
+        assert_eq!(
+            options(&body),
+            [
+                (1, "02:00:00:00:00:01".to_string()),
+                (
+                    3,
+                    "2001:db8:1::/64, on-link, autonomous, valid 86400 s (1d), \
+                     preferred 14400 s (4h)"
+                        .to_string()
+                ),
+                (5, "1500".to_string()),
+                (
+                    25,
+                    "2001:db8::53, 2001:db8::54, lifetime 600 s (10m)".to_string()
+                ),
+                (
+                    31,
+                    "example.com, corp.example, lifetime 600 s (10m)".to_string()
+                ),
+            ]
+        );
+
+        let fields: Vec<_> = ranges
+            .iter()
+            .filter(|(name, _, _)| name.starts_with("icmpv6.opt"))
+            .copied()
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("icmpv6.opt", 16, 8),
+                ("icmpv6.opt.linkaddr", 18, 6),
+                ("icmpv6.opt", 24, 32),
+                ("icmpv6.opt.prefix", 40, 16),
+                ("icmpv6.opt", 56, 8),
+                ("icmpv6.opt.mtu", 60, 4),
+                ("icmpv6.opt", 64, 40),
+                ("icmpv6.opt.rdnss", 72, 32),
+                ("icmpv6.opt", 104, 40),
+                ("icmpv6.opt.dnssl", 112, 32),
+            ]
+        );
+
+        let mut advertisement = vec![136, 0, 0, 0, 0x60, 0, 0, 0];
+        advertisement.extend(address_bytes("fe80::1"));
+        advertisement.extend(option(2, &[0x02, 0, 0, 0, 0, 0x02]));
+        let body = decode(&advertisement).unwrap();
+        assert!(matches!(
+            body,
+            SerializableIcmpv6Body::NeighborAdvertisement { flags: 0x60, target, .. }
+                if target == "fe80::1".parse::<Ipv6Addr>().unwrap()
+        ));
+        assert_eq!(options(&body), [(2, "02:00:00:00:00:02".to_string())]);
+    }
+
+    #[test]
+    fn keeps_options_of_unexpected_length() {
+        let message = router_advertisement(
+            &[
+                // An address of a link other than Ethernet
+                option(1, &[0; 14]),
+                option(5, &[0; 14]),
+                option(25, &[0; 6]),
+                // Names must end within the option
+                option(31, b"\0\0\0\0\0\0\x07example"),
+                // Padding only, without names
+                option(31, &[0; 14]),
+                option(200, &[1, 2, 3, 4, 5, 6]),
+            ]
+            .concat(),
+        );
+        let mut ranges = Vec::new();
+        let body = decode_body(134, &message, &mut ranges).unwrap();
+        assert_eq!(
+            options(&body),
+            [
+                (1, "14 bytes".to_string()),
+                (5, "14 bytes".to_string()),
+                (25, "6 bytes".to_string()),
+                (31, "14 bytes".to_string()),
+                (31, ", lifetime 0 s".to_string()),
+                (200, "6 bytes".to_string()),
+            ]
+        );
+        // Only values decoded get a field
+        let fields: Vec<_> = ranges
+            .iter()
+            .filter(|(name, _, _)| name.starts_with("icmpv6.opt."))
+            .collect();
+        assert_eq!(fields, [&("icmpv6.opt.dnssl", 80, 8)]);
+    }
+
+    #[test]
+    fn rejects_zero_length_and_truncated_options() {
+        let mut solicitation = vec![133, 0, 0, 0, 0, 0, 0, 0];
+        solicitation.extend(option(1, &[0x02, 0, 0, 0, 0, 0x01]));
+        assert!(decode(&solicitation).is_some());
+        // The option is cut short, or only its type is left
+        assert!(decode(&solicitation[..15]).is_none());
+        assert!(decode(&solicitation[..9]).is_none());
+        // A zero length would have the option read again and again
+        solicitation[9] = 0;
+        assert!(decode(&solicitation).is_none());
+        solicitation[9] = 2;
+        assert!(decode(&solicitation).is_none());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        let source = IpAddr::from(Ipv6Addr::LOCALHOST);
+        handle_icmpv6_packet(source, source, &solicitation, 0, &mut parsed_packet);
+        assert!(matches!(
+            parsed_packet.get_transport_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed ICMPv6 Packet"
+        ));
+        assert!(parsed_packet.get_field_ranges().is_empty());
+    }
+
+    #[test]
+    fn decodes_mldv2_reports() {
+        let group = |record_type: u8, address: &str, sources: &[&str], auxiliary: u8| {
+            let mut record = vec![record_type, auxiliary];
+            record.extend((sources.len() as u16).to_be_bytes());
+            record.extend(address_bytes(address));
+            for source in sources {
+                record.extend(address_bytes(source));
+            }
+            record.extend(vec![0; auxiliary as usize * 4]);
+            record
+        };
+        let records = [
+            group(4, "ff02::fb", &[], 0),
+            group(1, "ff3e::1234", &["2001:db8::1", "2001:db8::2"], 1),
+        ]
+        .concat();
+        let report = |count: u16, records: &[u8]| {
+            let mut report = vec![143, 0, 0, 0, 0, 0];
+            report.extend(count.to_be_bytes());
+            report.extend(records);
+            report
+        };
+        let message = report(2, &records);
+        let mut ranges = Vec::new();
+        let Some(SerializableIcmpv6Body::MulticastListenerReportV2 { records: decoded }) =
+            decode_body(143, &message, &mut ranges)
+        else {
+            panic!("not an MLDv2 report");
+        };
+        let decoded: Vec<_> = decoded
+            .iter()
+            .map(|record| {
+                let sources: Vec<_> = record.sources.iter().map(Ipv6Addr::to_string).collect();
+                (record.record_type, record.address.to_string(), sources)
+            })
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                (4, "ff02::fb".to_string(), vec![]),
+                (
+                    1,
+                    "ff3e::1234".to_string(),
+                    vec!["2001:db8::1".to_string(), "2001:db8::2".to_string()]
+                ),
+            ]
+        );
+        // Records span their sources and auxiliary data
+        assert!(ranges.contains(&("icmpv6.mldr.mar", 8, 20)));
+        assert!(ranges.contains(&("icmpv6.mldr.mar", 28, 56)));
+
+        // Records must all be there, whole
+        assert!(decode(&message[..message.len() - 1]).is_none());
+        assert!(decode(&report(3, &records)).is_none());
+        assert!(decode(&report(1, &records[..19])).is_none());
+        assert!(matches!(
+            decode(&report(0, &[])),
+            Some(SerializableIcmpv6Body::MulticastListenerReportV2 { records }) if records.is_empty()
+        ));
+    }
+
+    #[test]
+    fn decodes_mld_queries() {
+        let group = address_bytes("ff3e::1234");
+        let mut query = vec![130, 0, 0, 0, 0x03, 0xe8, 0, 0];
+        query.extend(group);
+        assert!(matches!(
+            decode(&query),
+            Some(SerializableIcmpv6Body::MulticastListenerQuery {
+                maximum_response_delay: 1000,
+                v2: None,
+                ..
+            })
+        ));
+
+        // Codes with the top bit set are floating point, in MLDv2
+        query[4..6].copy_from_slice(&0x9000u16.to_be_bytes());
+        query.extend([0x0a, 0x90, 0, 1]);
+        query.extend(address_bytes("2001:db8::1"));
+        let Some(SerializableIcmpv6Body::MulticastListenerQuery {
+            maximum_response_delay,
+            address,
+            v2: Some(v2),
+        }) = decode(&query)
+        else {
+            panic!("not an MLDv2 query");
+        };
+        assert_eq!(maximum_response_delay, 4096 << 4);
+        assert_eq!(address, Ipv6Addr::from(group));
+        assert!(v2.suppress);
+        assert_eq!(v2.robustness, 2);
+        assert_eq!(v2.interval, 16 << 4);
+        assert_eq!(v2.sources, ["2001:db8::1".parse::<Ipv6Addr>().unwrap()]);
+        assert_eq!(floating_point(0x7fff, 12, 3), 0x7fff);
+        assert_eq!(floating_point(0xffff, 12, 3), 0x1fff << 10);
+
+        // The sources must all be there
+        assert!(decode(&query[..query.len() - 1]).is_none());
+        query[27] = 2;
+        assert!(decode(&query).is_none());
+    }
+}
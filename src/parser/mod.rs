@@ -1,14 +1,42 @@
 use pnet::packet::{Packet, ethernet::EtherTypes};
+pub mod capture;
+pub mod defrag;
+pub mod dhcp;
+pub mod dhcpv6;
+pub mod dns;
+pub mod http;
+pub mod icmp;
+pub mod icmpv6;
+#[cfg(feature = "serde")]
+pub mod export;
 pub mod wrapers;
 pub mod network;
+pub mod ping;
+pub mod reassembly;
 pub mod transport;
-use wrapers::{ParsedPacket, SerializableEthernetPacket};
+pub mod tls;
+pub mod tunnel;
+pub mod worker;
+pub mod x509;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use defrag::Defragmenter;
+use capture::{CaptureFilter, CaptureSource, CapturedFrame, LinkType, LiveCapture, PcapReader, PcapngReader};
+use wrapers::{
+    Level, ParsedPacket, SerializableEthernetPacket, SerializableMplsPacket,
+    SerializableVlanPacket,
+};
 use pnet::{
-    datalink::{
-        self, interfaces, Channel::Ethernet, Config, DataLinkReceiver, DataLinkSender,
-        NetworkInterface,
+    datalink::{self, NetworkInterface},
+    packet::{
+        ethernet::{EtherType, EthernetPacket},
+        vlan::VlanPacket,
     },
-    packet::ethernet::EthernetPacket,
+    util::MacAddr,
 };
 use thiserror::Error;
 
@@ -20,12 +48,20 @@ pub enum ParserError {
     ChannelCreationError,
     #[error("Channel is not supported")]
     ChannelNotSupported,
+    #[error("Interface {0} not found")]
+    InterfaceNotFound(String),
+    #[error("Capture file is invalid: {0}")]
+    InvalidCaptureFile(String),
+    #[error("Link type {0} is not supported")]
+    UnsupportedLinkType(u32),
+    #[error("Invalid capture filter: {0}")]
+    InvalidFilter(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
 
 pub struct Parser {
-    interface: NetworkInterface,
-    tx: Box<dyn DataLinkSender>,
-    rx: Box<dyn DataLinkReceiver>,
+    source: CaptureSource,
 }
 
 impl Parser {
@@ -33,57 +69,408 @@ impl Parser {
         datalink::interfaces()
     }
 
+    /// Capture live traffic from the interface called `interface_name`
     pub fn new(interface_name: String) -> Result<Self, ParserError> {
         let interfaces = datalink::interfaces();
         let interface = interfaces
             .into_iter()
-            .filter(|x| x.name == interface_name)
-            .next()
-            .unwrap();
+            .find(|x| x.name == interface_name)
+            .ok_or(ParserError::InterfaceNotFound(interface_name))?;
 
-        let config = Config {
-            // read_timeout: Some(Duration::ZERO),
-            ..Default::default()
-        };
+        Ok(Self {
+            source: CaptureSource::Live(LiveCapture::new(interface)?),
+        })
+    }
+
+    /// Read recorded traffic from a pcap or pcapng capture file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let is_pcapng = reader.fill_buf()?.starts_with(&[0x0a, 0x0d, 0x0d, 0x0a]);
 
-        let (tx, rx) = match datalink::channel(&interface, config) {
-            Ok(Ethernet(tx, rx)) => (tx, rx),
-            Ok(_) => return Err(ParserError::ChannelNotSupported),
-            Err(_) => return Err(ParserError::ChannelCreationError),
+        let source = if is_pcapng {
+            CaptureSource::Pcapng(PcapngReader::new(reader)?)
+        } else {
+            CaptureSource::Pcap(PcapReader::new(reader)?)
         };
 
-        Ok(Self { interface, tx, rx })
+        Ok(Self { source })
+    }
+
+    /// Only capture frames matching `filter`
+    pub fn set_capture_filter(&mut self, filter: CaptureFilter) -> Result<(), ParserError> {
+        self.source.set_filter(filter)
     }
 
-    pub fn next(&mut self) -> Option<&[u8]> {
-        self.rx.next().ok()
+    /// Get the source frames are read from
+    pub fn source(&self) -> &CaptureSource {
+        &self.source
+    }
+
+    /// Get the next captured frame, the returned frame borrows the parser until dropped
+    ///
+    /// `Ok(None)` at the end of a capture file or when a live read timed out, errors are
+    /// corrupt or truncated capture files.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<CapturedFrame<'_>>, ParserError> {
+        self.source.next_frame()
+    }
+
+    /// Parse a captured frame according to its link type, keeping the capture metadata
+    ///
+    /// IP fragments are collected in `defragmenter`, the frame completing a datagram is
+    /// dissected with the whole datagram.
+    pub fn parse_frame(
+        frame: &CapturedFrame,
+        id: usize,
+        defragmenter: &mut Defragmenter,
+    ) -> ParsedPacket {
+        let data = frame.data;
+        defragmenter.set_time(frame.timestamp);
+        let mut parsed_packet = match frame.link_type {
+            LinkType::ETHERNET => match EthernetPacket::new(data) {
+                Some(ethernet) => Self::parse_ethernet_frame(&ethernet, id, defragmenter),
+                None => Self::malformed_frame("Malformed Ethernet Frame", id),
+            },
+            LinkType::RAW | LinkType::IPV4 | LinkType::IPV6 => {
+                let mut parsed_packet = ParsedPacket::new(id);
+                handle_ip_packet(data, 0, defragmenter, &mut parsed_packet);
+                parsed_packet
+            }
+            LinkType::NULL if data.len() >= 4 => {
+                // BSD loopback header is the address family in the capturing host's byte order
+                let family = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+                let family = if family > 0xffff {
+                    family.swap_bytes()
+                } else {
+                    family
+                };
+                let ethertype = match family {
+                    2 => EtherTypes::Ipv4,
+                    24 | 28 | 30 => EtherTypes::Ipv6,
+                    _ => EtherType(0),
+                };
+                let mut parsed_packet = ParsedPacket::new(id);
+                handle_ethertype(
+                    ethertype,
+                    &data[4..],
+                    4,
+                    MacAddr::zero(),
+                    MacAddr::zero(),
+                    defragmenter,
+                    &mut parsed_packet,
+                );
+                parsed_packet
+            }
+            LinkType::LINUX_SLL if data.len() >= 16 => {
+                let ethertype = EtherType(u16::from_be_bytes([data[14], data[15]]));
+                let mut parsed_packet = ParsedPacket::new(id);
+                handle_ethertype(
+                    ethertype,
+                    &data[16..],
+                    16,
+                    MacAddr::zero(),
+                    MacAddr::zero(),
+                    defragmenter,
+                    &mut parsed_packet,
+                );
+                parsed_packet
+            }
+            LinkType(link_type) => {
+                Self::malformed_frame(&format!("Unsupported link type {}", link_type), id)
+            }
+        };
+
+        parsed_packet.set_timestamp(frame.timestamp);
+        parsed_packet.set_length(frame.original_length);
+        if parsed_packet.get_data().is_empty() {
+            parsed_packet.set_data(data.to_vec());
+        }
+        parsed_packet.set_interface_name(frame.interface_name.map(str::to_string));
+        parsed_packet.set_comments(frame.comments.to_vec());
+        parsed_packet.resolve_layer_ranges();
+
+        parsed_packet
     }
 
     /// Parse ethernet frame obtaining the packet link-layer and network-layer representations
-    pub fn parse_ethernet_frame(ethernet: &EthernetPacket, id: usize) -> ParsedPacket {
+    pub fn parse_ethernet_frame(
+        ethernet: &EthernetPacket,
+        id: usize,
+        defragmenter: &mut Defragmenter,
+    ) -> ParsedPacket {
         let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_length(ethernet.packet().len());
+        parsed_packet.set_data(ethernet.packet().to_vec());
+        handle_ethernet_frame(ethernet, 0, defragmenter, &mut parsed_packet);
+        parsed_packet.resolve_layer_ranges();
+        parsed_packet
+    }
 
-        parsed_packet.set_link_layer_packet(Some(SerializablePacket::EthernetPacket(
-            SerializableEthernetPacket::from(ethernet),
+    fn malformed_frame(reason: &str, id: usize) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+            reason.to_string(),
         )));
+        parsed_packet
+    }
+}
 
-        match ethernet.get_ethertype() {
-            EtherTypes::Ipv4 => handle_ipv4_packet(ethernet.payload(), &mut parsed_packet),
-            EtherTypes::Ipv6 => handle_ipv6_packet(ethernet.payload(), &mut parsed_packet),
-            EtherTypes::Arp => handle_arp_packet(
-                ethernet.payload(),
-                ethernet.get_source(),
-                ethernet.get_destination(),
-                &mut parsed_packet,
-            ),
-            _ => {
-
-                parsed_packet.set_link_layer_packet(Some(SerializablePacket::UnknownPacket(
-                    SerializableUnknownPacket::from(ethernet),
-                )));
-            }
+/// Decode an Ethernet frame found at `offset` in the captured frame, which is not at the start
+/// for frames carried in tunnels
+fn handle_ethernet_frame(
+    ethernet: &EthernetPacket,
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    parsed_packet.set_link_layer_packet(Some(SerializablePacket::EthernetPacket(
+        SerializableEthernetPacket::from(ethernet),
+    )));
+    parsed_packet.add_field_ranges(
+        offset,
+        ethernet.packet().len(),
+        &[
+            ("eth", 0, 14),
+            ("eth.dst", 0, 6),
+            ("eth.src", 6, 6),
+            ("eth.type", 12, 2),
+            ("eth.payload", 14, ethernet.payload().len()),
+        ],
+    );
+
+    let known = handle_ethertype(
+        ethernet.get_ethertype(),
+        ethernet.payload(),
+        offset + 14,
+        ethernet.get_source(),
+        ethernet.get_destination(),
+        defragmenter,
+        parsed_packet,
+    );
+    if !known {
+        parsed_packet.add_field_ranges(
+            offset,
+            ethernet.packet().len(),
+            &[("data", 14, ethernet.payload().len())],
+        );
+        parsed_packet.set_network_layer_packet(Some(SerializablePacket::UnknownPacket(
+            SerializableUnknownPacket::from(ethernet),
+        )));
+    }
+}
+
+/// Dispatch a link-layer payload found at `offset` in the frame on its EtherType,
+/// `false` if the EtherType is not supported
+fn handle_ethertype(
+    ethertype: EtherType,
+    payload: &[u8],
+    offset: usize,
+    source: MacAddr,
+    destination: MacAddr,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) -> bool {
+    match ethertype {
+        EtherTypes::Ipv4 => handle_ipv4_packet(payload, offset, defragmenter, parsed_packet),
+        EtherTypes::Ipv6 => handle_ipv6_packet(payload, offset, defragmenter, parsed_packet),
+        EtherTypes::Arp => handle_arp_packet(payload, offset, source, destination, parsed_packet),
+        EtherTypes::Vlan | EtherTypes::PBridge | EtherTypes::QinQ => handle_vlan_tag(
+            ethertype,
+            payload,
+            offset,
+            source,
+            destination,
+            defragmenter,
+            parsed_packet,
+        ),
+        EtherTypes::Mpls | EtherTypes::MplsMcast => {
+            handle_mpls_stack(payload, offset, defragmenter, parsed_packet)
         }
+        _ => return false,
+    }
+    true
+}
 
-        parsed_packet
+/// Frames stacking more VLAN tags than this are not dissected further, real networks use
+/// two at most
+const MAX_VLAN_TAGS: usize = 8;
+
+/// Peel stacked 802.1Q and 802.1ad tags and dispatch what the innermost one carries
+fn handle_vlan_tag(
+    tpid: EtherType,
+    payload: &[u8],
+    offset: usize,
+    source: MacAddr,
+    destination: MacAddr,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let mut tpid = tpid;
+    let mut start = 0;
+    // Tags are peeled in a loop, recursing once per tag would let a frame of stacked tags
+    // overflow the stack
+    while matches!(
+        tpid,
+        EtherTypes::Vlan | EtherTypes::PBridge | EtherTypes::QinQ
+    ) {
+        if start / 4 == MAX_VLAN_TAGS {
+            parsed_packet.push_layer(
+                Level::Encapsulation,
+                SerializablePacket::MalformedPacket("Too Many VLAN Tags".to_string()),
+            );
+            return;
+        }
+        let Some(vlan) = VlanPacket::new(&payload[start..]) else {
+            parsed_packet.push_layer(
+                Level::Encapsulation,
+                SerializablePacket::MalformedPacket("Malformed VLAN Tag".to_string()),
+            );
+            return;
+        };
+        parsed_packet.push_layer(
+            Level::Encapsulation,
+            SerializablePacket::VlanPacket(SerializableVlanPacket::new(tpid, &vlan)),
+        );
+        parsed_packet.add_field_ranges(
+            offset + start,
+            payload.len() - start,
+            &[
+                ("vlan", 0, 4),
+                ("vlan.priority", 0, 1),
+                ("vlan.dei", 0, 1),
+                ("vlan.id", 0, 2),
+                ("vlan.etype", 2, 2),
+            ],
+        );
+        tpid = vlan.get_ethertype();
+        start += 4;
+    }
+    handle_ethertype(
+        tpid,
+        &payload[start..],
+        offset + start,
+        source,
+        destination,
+        defragmenter,
+        parsed_packet,
+    );
+}
+
+/// Pop an MPLS label stack, the payload after the bottom label is taken for IP as MPLS
+/// does not say what it carries
+fn handle_mpls_stack(
+    payload: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let mut start = 0;
+    loop {
+        let Some(&[a, b, c, d]) = payload.get(start..start + 4) else {
+            parsed_packet.push_layer(
+                Level::Encapsulation,
+                SerializablePacket::MalformedPacket("Malformed MPLS Label Stack".to_string()),
+            );
+            return;
+        };
+        let label = SerializableMplsPacket::new([a, b, c, d]);
+        let bottom_of_stack = label.bottom_of_stack;
+        parsed_packet.push_layer(Level::Encapsulation, SerializablePacket::MplsPacket(label));
+        parsed_packet.add_field_ranges(
+            offset + start,
+            payload.len() - start,
+            &[
+                ("mpls", 0, 4),
+                ("mpls.label", 0, 3),
+                ("mpls.exp", 2, 1),
+                ("mpls.bottom", 2, 1),
+                ("mpls.ttl", 3, 1),
+            ],
+        );
+        start += 4;
+        if bottom_of_stack {
+            break;
+        }
+    }
+    handle_ip_packet(
+        &payload[start..],
+        offset + start,
+        defragmenter,
+        parsed_packet,
+    );
+}
+
+/// Dispatch a raw IP packet found at `offset` in the frame on its version nibble
+fn handle_ip_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    match packet.first().map(|byte| byte >> 4) {
+        Some(4) => handle_ipv4_packet(packet, offset, defragmenter, parsed_packet),
+        Some(6) => handle_ipv6_packet(packet, offset, defragmenter, parsed_packet),
+        _ => parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed IP Packet".to_string(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::wrapers::SerializableUdpPacket;
+
+    fn vlan_frame(tags: usize) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        for id in 0..tags {
+            frame.extend([0x81, 0x00]);
+            frame.extend((id as u16 + 1).to_be_bytes());
+        }
+        // IPv4 packet from 10.0.0.1 to 10.0.0.2 with an empty UDP datagram
+        frame.extend([0x08, 0x00, 0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+        frame.extend([10, 0, 0, 1, 10, 0, 0, 2, 0x13, 0x88, 0x13, 0x89, 0, 8, 0, 0]);
+        frame
+    }
+
+    fn is_malformed(packet: &ParsedPacket, reason: &str) -> bool {
+        packet.layers().any(|layer| match layer.get_packet() {
+            SerializablePacket::MalformedPacket(message) => message == reason,
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn peels_stacked_vlan_tags() {
+        let frame = vlan_frame(MAX_VLAN_TAGS);
+        let ethernet = EthernetPacket::new(&frame).unwrap();
+        let packet = Parser::parse_ethernet_frame(&ethernet, 0, &mut Defragmenter::new());
+        assert_eq!(
+            packet.get_vlan_ids(),
+            (1..=MAX_VLAN_TAGS as u16).collect::<Vec<_>>()
+        );
+        let udp = packet.find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination), (5000, 5001));
+    }
+
+    #[test]
+    fn stops_at_too_many_vlan_tags() {
+        let frame = vlan_frame(20000);
+        let ethernet = EthernetPacket::new(&frame).unwrap();
+        let packet = Parser::parse_ethernet_frame(&ethernet, 0, &mut Defragmenter::new());
+        assert_eq!(packet.get_vlan_ids().len(), MAX_VLAN_TAGS);
+        assert!(is_malformed(&packet, "Too Many VLAN Tags"));
+        assert!(packet.find::<SerializableUdpPacket>().is_none());
+    }
+
+    #[test]
+    fn reports_truncated_vlan_tags() {
+        let mut frame = vec![0; 12];
+        frame.extend([0x81, 0x00, 0x00, 0x01, 0x81, 0x00, 0x00]);
+
+        let ethernet = EthernetPacket::new(&frame).unwrap();
+        let packet = Parser::parse_ethernet_frame(&ethernet, 0, &mut Defragmenter::new());
+        assert_eq!(packet.get_vlan_ids(), [1]);
+        assert!(is_malformed(&packet, "Malformed VLAN Tag"));
     }
 }
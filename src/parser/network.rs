@@ -1,22 +1,83 @@
 use std::net::IpAddr;
 
-use pnet::{packet::{ipv4::Ipv4Packet, Packet, ipv6::Ipv6Packet, arp::ArpPacket}, util::MacAddr};
-use super::{wrapers::{ParsedPacket, SerializableIpv4Packet, SerializablePacket, SerializableIpv6Packet, SerializableArpPacket}, transport::handle_transport_protocol};
+use super::{
+    defrag::{Defragmenter, Ipv4FragmentKey, Ipv6FragmentKey},
+    transport::handle_transport_protocol,
+    wrapers::{
+        ParsedPacket, SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6ExtensionData,
+        SerializableIpv6ExtensionHeader, SerializableIpv6Packet, SerializablePacket,
+    },
+};
+use pnet::{
+    packet::{
+        arp::ArpPacket,
+        ip::{IpNextHeaderProtocol, IpNextHeaderProtocols},
+        ipv4::{Ipv4Flags, Ipv4Packet},
+        ipv6::{ExtensionPacket, FragmentPacket, Ipv6Packet, RoutingPacket},
+        Packet,
+    },
+    util::MacAddr,
+};
 
-
-pub fn handle_ipv4_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+/// Build a IPv4 packet from a data-link packet found at `offset` in the frame, save it in a Parsed Packet
+pub fn handle_ipv4_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
     let header = Ipv4Packet::new(packet);
     if let Some(header) = header {
         parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv4Packet(
             SerializableIpv4Packet::from(&header),
         )));
-        handle_transport_protocol(
-            IpAddr::V4(header.get_source()),
-            IpAddr::V4(header.get_destination()),
-            header.get_next_level_protocol(),
-            header.payload(),
-            parsed_packet,
-        );
+        let header_length = header.get_header_length() as usize * 4;
+        parsed_packet.add_field_ranges(offset, packet.len(), &ipv4_field_ranges(header_length));
+
+        let more_fragments = header.get_flags() & Ipv4Flags::MoreFragments != 0;
+        let fragment_offset = header.get_fragment_offset() as usize * 8;
+        if !more_fragments && fragment_offset == 0 {
+            handle_transport_protocol(
+                IpAddr::V4(header.get_source()),
+                IpAddr::V4(header.get_destination()),
+                header.get_next_level_protocol(),
+                header.payload(),
+                offset + header_length,
+                defragmenter,
+                parsed_packet,
+            );
+            return;
+        }
+
+        let key = Ipv4FragmentKey {
+            source: header.get_source(),
+            destination: header.get_destination(),
+            identification: header.get_identification(),
+            protocol: header.get_next_level_protocol().0,
+        };
+        let id = parsed_packet.get_id();
+        match defragmenter.add_ipv4(key, fragment_offset, !more_fragments, header.payload(), id) {
+            Ok(Some(datagram)) => {
+                // The transport header is in the first fragment, not necessarily in this frame
+                let ranges = parsed_packet.get_field_ranges().len();
+                handle_transport_protocol(
+                    IpAddr::V4(header.get_source()),
+                    IpAddr::V4(header.get_destination()),
+                    header.get_next_level_protocol(),
+                    &datagram.payload,
+                    offset + header_length,
+                    defragmenter,
+                    parsed_packet,
+                );
+                parsed_packet.truncate_field_ranges(ranges);
+                parsed_packet.set_fragments(datagram.frames);
+            }
+            // Waiting for the rest of the datagram
+            Ok(None) => {}
+            Err(err) => parsed_packet.set_transport_layer_packet(Some(
+                SerializablePacket::MalformedPacket(format!("Invalid IPv4 fragment: {}", err)),
+            )),
+        }
     } else {
         parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
             "Malformed IPv4 Packet".to_string(),
@@ -24,20 +85,143 @@ pub fn handle_ipv4_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     }
 }
 
-/// Build a IPv6 packet from a data-link packet, save it in a Parsed Packet
-pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+/// Get where the fields of an IPv4 header of `header_length` bytes sit, for `add_field_ranges`
+pub(super) fn ipv4_field_ranges(header_length: usize) -> [(&'static str, usize, usize); 14] {
+    [
+        ("ip", 0, header_length),
+        ("ip.version", 0, 1),
+        ("ip.hdr_len", 0, 1),
+        ("ip.dsfield.dscp", 1, 1),
+        ("ip.dsfield.ecn", 1, 1),
+        ("ip.len", 2, 2),
+        ("ip.id", 4, 2),
+        ("ip.flags", 6, 1),
+        ("ip.frag_offset", 6, 2),
+        ("ip.ttl", 8, 1),
+        ("ip.proto", 9, 1),
+        ("ip.checksum", 10, 2),
+        ("ip.src", 12, 4),
+        ("ip.dst", 16, 4),
+    ]
+}
+
+/// Build a IPv6 packet from a data-link packet found at `offset` in the frame, save it in a Parsed Packet
+///
+/// The extension header chain is walked up to the upper-layer protocol, fragments are
+/// reassembled first.
+pub fn handle_ipv6_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
     let header = Ipv6Packet::new(packet);
     if let Some(header) = header {
-        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv6Packet(
-            SerializableIpv6Packet::from(&header),
-        )));
-        handle_transport_protocol(
-            IpAddr::V6(header.get_source()),
-            IpAddr::V6(header.get_destination()),
+        let mut ipv6 = SerializableIpv6Packet::from(&header);
+        parsed_packet.add_field_ranges(
+            offset,
+            packet.len(),
+            &[
+                ("ipv6", 0, 40),
+                ("ipv6.version", 0, 1),
+                ("ipv6.tclass", 0, 2),
+                ("ipv6.flow", 1, 3),
+                ("ipv6.plen", 4, 2),
+                ("ipv6.nxt", 6, 1),
+                ("ipv6.hlim", 7, 1),
+                ("ipv6.src", 8, 16),
+                ("ipv6.dst", 24, 16),
+            ],
+        );
+
+        let source = IpAddr::V6(header.get_source());
+        let destination = IpAddr::V6(header.get_destination());
+        let payload = header.payload();
+        let chain = walk_extension_headers(
             header.get_next_header(),
-            header.payload(),
+            payload,
+            offset + 40,
+            &mut ipv6.extension_headers,
             parsed_packet,
         );
+        // The upper layer is decoded once the IPv6 layer is stored, as tunnels move it out
+        let mut upper_layer = None;
+        let mut reassembled = None;
+        match chain {
+            Ok(ChainEnd::UpperLayer(protocol, start)) => upper_layer = Some((protocol, start)),
+            Ok(ChainEnd::Fragment {
+                next_header,
+                start,
+                fragment_offset,
+                last,
+                identification,
+            }) => {
+                let key = Ipv6FragmentKey {
+                    source: header.get_source(),
+                    destination: header.get_destination(),
+                    identification,
+                };
+                let id = parsed_packet.get_id();
+                match defragmenter.add_ipv6(key, fragment_offset, last, &payload[start..], id) {
+                    // Hosts must drop datagrams with overlapping fragments, RFC 5722
+                    Ok(Some(datagram)) if datagram.overlapping => {
+                        set_malformed_transport(parsed_packet, "Overlapping IPv6 fragments")
+                    }
+                    Ok(Some(datagram)) => {
+                        // Headers after the fragment header are in the first fragment, not
+                        // necessarily in this frame
+                        let ranges = parsed_packet.get_field_ranges().len();
+                        let chain = walk_extension_headers(
+                            next_header,
+                            &datagram.payload,
+                            offset + 40 + start,
+                            &mut ipv6.extension_headers,
+                            parsed_packet,
+                        );
+                        match chain {
+                            Ok(ChainEnd::UpperLayer(protocol, start)) => {
+                                upper_layer = Some((protocol, start))
+                            }
+                            Ok(ChainEnd::Fragment { .. }) => set_malformed_transport(
+                                parsed_packet,
+                                "Fragment header in a reassembled IPv6 datagram",
+                            ),
+                            Ok(ChainEnd::Opaque) => {}
+                            Err(reason) => set_malformed_transport(parsed_packet, reason),
+                        }
+                        reassembled = Some((datagram, ranges));
+                    }
+                    // Waiting for the rest of the datagram
+                    Ok(None) => {}
+                    Err(err) => set_malformed_transport(
+                        parsed_packet,
+                        &format!("Invalid IPv6 fragment: {}", err),
+                    ),
+                }
+            }
+            Ok(ChainEnd::Opaque) => {}
+            Err(reason) => set_malformed_transport(parsed_packet, reason),
+        }
+
+        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv6Packet(ipv6)));
+        if let Some((protocol, start)) = upper_layer {
+            let data = reassembled
+                .as_ref()
+                .map_or(payload, |(datagram, _)| &datagram.payload);
+            handle_transport_protocol(
+                source,
+                destination,
+                protocol,
+                &data[start..],
+                offset + 40 + start,
+                defragmenter,
+                parsed_packet,
+            );
+        }
+        if let Some((datagram, ranges)) = reassembled {
+            parsed_packet.truncate_field_ranges(ranges);
+            parsed_packet.set_fragments(datagram.frames);
+        }
     } else {
         parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
             "Malformed IPv6 Packet".to_string(),
@@ -45,11 +229,189 @@ pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     }
 }
 
-/// Build a ARP packet from a data-link packet, save it in a Parsed Packet
+/// Where walking an IPv6 extension header chain stopped, offsets are relative to the walked data
+enum ChainEnd {
+    /// The upper-layer protocol starts at the offset
+    UpperLayer(IpNextHeaderProtocol, usize),
+    /// The packet is a fragment whose data starts at `start`
+    Fragment {
+        next_header: IpNextHeaderProtocol,
+        start: usize,
+        fragment_offset: usize,
+        last: bool,
+        identification: u32,
+    },
+    /// Nothing follows that can be decoded, after ESP or No Next Header
+    Opaque,
+}
+
+/// Walk the extension headers of `data`, found at `offset` in the frame, starting with
+/// `next_header`, up to the upper-layer protocol or a fragment header
+fn walk_extension_headers(
+    mut next_header: IpNextHeaderProtocol,
+    data: &[u8],
+    offset: usize,
+    headers: &mut Vec<SerializableIpv6ExtensionHeader>,
+    parsed_packet: &mut ParsedPacket,
+) -> Result<ChainEnd, &'static str> {
+    let mut start = 0;
+    loop {
+        let rest = &data[start..];
+        let header_type = next_header.0;
+        let (header, range_key) = match next_header {
+            IpNextHeaderProtocols::Hopopt | IpNextHeaderProtocols::Ipv6Opts => {
+                let length = rest.get(1).map(|&len| (len as usize + 1) * 8);
+                let extension = ExtensionPacket::new(rest)
+                    .filter(|_| length.is_some_and(|length| length <= rest.len()))
+                    .ok_or("Truncated IPv6 options header")?;
+                let length = length.unwrap_or_default();
+                let header = SerializableIpv6ExtensionHeader {
+                    header_type,
+                    next_header: Some(protocol_name(extension.get_next_header())),
+                    length,
+                    data: SerializableIpv6ExtensionData::Options(rest[2..length].to_vec()),
+                };
+                next_header = extension.get_next_header();
+                let key = if header_type == 0 {
+                    "ipv6.hopopts"
+                } else {
+                    "ipv6.dstopts"
+                };
+                (header, key)
+            }
+            IpNextHeaderProtocols::Ipv6Route => {
+                let length = rest.get(1).map(|&len| (len as usize + 1) * 8);
+                let routing = RoutingPacket::new(rest)
+                    .filter(|_| length.is_some_and(|length| length <= rest.len()))
+                    .ok_or("Truncated IPv6 routing header")?;
+                let header = SerializableIpv6ExtensionHeader {
+                    header_type,
+                    next_header: Some(protocol_name(routing.get_next_header())),
+                    length: length.unwrap_or_default(),
+                    data: SerializableIpv6ExtensionData::Routing {
+                        routing_type: routing.get_routing_type(),
+                        segments_left: routing.get_segments_left(),
+                    },
+                };
+                next_header = routing.get_next_header();
+                (header, "ipv6.routing")
+            }
+            IpNextHeaderProtocols::Ipv6Frag => {
+                let fragment = FragmentPacket::new(rest).ok_or("Truncated IPv6 fragment header")?;
+                let offset_with_flags = fragment.get_fragment_offset_with_flags();
+                let header = SerializableIpv6ExtensionHeader {
+                    header_type,
+                    next_header: Some(protocol_name(fragment.get_next_header())),
+                    length: 8,
+                    data: SerializableIpv6ExtensionData::Fragment {
+                        offset: offset_with_flags & 0xfff8,
+                        more_fragments: offset_with_flags & 1 != 0,
+                        identification: fragment.get_id(),
+                    },
+                };
+                headers.push(header);
+                parsed_packet.add_field_ranges(
+                    offset + start,
+                    rest.len(),
+                    &[
+                        ("ipv6.fraghdr", 0, 8),
+                        ("ipv6.fraghdr.nxt", 0, 1),
+                        ("ipv6.fraghdr.offset", 2, 2),
+                        ("ipv6.fraghdr.more", 3, 1),
+                        ("ipv6.fraghdr.ident", 4, 4),
+                    ],
+                );
+                // An atomic fragment, offset 0 and no more fragments, is a whole datagram
+                if offset_with_flags & 0xfff9 == 0 {
+                    next_header = fragment.get_next_header();
+                    start += 8;
+                    continue;
+                }
+                return Ok(ChainEnd::Fragment {
+                    next_header: fragment.get_next_header(),
+                    start: start + 8,
+                    fragment_offset: (offset_with_flags & 0xfff8) as usize,
+                    last: offset_with_flags & 1 == 0,
+                    identification: fragment.get_id(),
+                });
+            }
+            IpNextHeaderProtocols::Ah => {
+                if rest.len() < 12 {
+                    return Err("Truncated IPv6 authentication header");
+                }
+                // AH counts its length in 4-byte units, unlike the other extension headers
+                let length = (rest[1] as usize + 2) * 4;
+                if length > rest.len() {
+                    return Err("Truncated IPv6 authentication header");
+                }
+                let header = SerializableIpv6ExtensionHeader {
+                    header_type,
+                    next_header: Some(protocol_name(IpNextHeaderProtocol(rest[0]))),
+                    length,
+                    data: SerializableIpv6ExtensionData::Authentication {
+                        spi: u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]),
+                        sequence: u32::from_be_bytes([rest[8], rest[9], rest[10], rest[11]]),
+                    },
+                };
+                next_header = IpNextHeaderProtocol(rest[0]);
+                (header, "ah")
+            }
+            IpNextHeaderProtocols::Esp => {
+                if rest.len() < 8 {
+                    return Err("Truncated ESP header");
+                }
+                headers.push(SerializableIpv6ExtensionHeader {
+                    header_type,
+                    next_header: None,
+                    length: rest.len(),
+                    data: SerializableIpv6ExtensionData::Esp {
+                        spi: u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]),
+                        sequence: u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]),
+                    },
+                });
+                parsed_packet.add_field_ranges(
+                    offset + start,
+                    rest.len(),
+                    &[
+                        ("esp", 0, rest.len()),
+                        ("esp.spi", 0, 4),
+                        ("esp.sequence", 4, 4),
+                    ],
+                );
+                // The rest is encrypted
+                return Ok(ChainEnd::Opaque);
+            }
+            IpNextHeaderProtocols::Ipv6NoNxt => return Ok(ChainEnd::Opaque),
+            protocol => return Ok(ChainEnd::UpperLayer(protocol, start)),
+        };
+
+        parsed_packet.add_field_ranges(
+            offset + start,
+            rest.len(),
+            &[(range_key, 0, header.length)],
+        );
+        start += header.length;
+        headers.push(header);
+    }
+}
+
+/// Name a protocol number as the other decoded fields do, e.g. `"Tcp (6)"`
+fn protocol_name(protocol: IpNextHeaderProtocol) -> String {
+    format!("{} ({})", protocol, protocol.0)
+}
+
+fn set_malformed_transport(parsed_packet: &mut ParsedPacket, reason: &str) {
+    parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+        reason.to_string(),
+    )));
+}
+
+/// Build a ARP packet from a data-link packet found at `offset` in the frame, save it in a Parsed Packet
 pub fn handle_arp_packet(
     packet: &[u8],
-    source: MacAddr,
-    dest: MacAddr,
+    offset: usize,
+    _source: MacAddr,
+    _dest: MacAddr,
     parsed_packet: &mut ParsedPacket,
 ) {
     let header = ArpPacket::new(packet);
@@ -57,9 +419,181 @@ pub fn handle_arp_packet(
         parsed_packet.set_network_layer_packet(Some(SerializablePacket::ArpPacket(
             SerializableArpPacket::from(&header),
         )));
+        parsed_packet.add_field_ranges(
+            offset,
+            packet.len(),
+            &[
+                ("arp", 0, 28),
+                ("arp.hw.type", 0, 2),
+                ("arp.proto.type", 2, 2),
+                ("arp.hw.size", 4, 1),
+                ("arp.proto.size", 5, 1),
+                ("arp.opcode", 6, 2),
+                ("arp.src.hw_mac", 8, 6),
+                ("arp.src.proto_ipv4", 14, 4),
+                ("arp.dst.hw_mac", 18, 6),
+                ("arp.dst.proto_ipv4", 24, 4),
+            ],
+        );
     } else {
         parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
             "Malformed ARP Packet".to_string(),
         )));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{wrapers::SerializableTcpPacket, Parser};
+    use pnet::packet::ethernet::EthernetPacket;
+
+    /// Ethernet and IPv6 from 2001:db8::1 to 2001:db8::2, `payload` starting with `next_header`
+    fn ipv6_frame(next_header: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend([0x86, 0xdd, 0x60, 0, 0, 0]);
+        frame.extend((payload.len() as u16).to_be_bytes());
+        frame.extend([next_header, 64]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        frame.extend(payload);
+        frame
+    }
+
+    /// Hop-by-Hop options, a type 0 routing header with one segment left, then `rest`
+    fn chain(rest: &[u8]) -> Vec<u8> {
+        // Hop-by-Hop, a PadN option filling its 8 bytes
+        let mut data = vec![43, 0, 1, 4, 0, 0, 0, 0];
+        // Routing, 24 bytes holding one address
+        data.extend([44, 2, 0, 1, 0, 0, 0, 0]);
+        data.extend([0; 16]);
+        data.extend(rest);
+        data
+    }
+
+    fn walk(next_header: u8, data: &[u8]) -> Result<ChainEnd, &'static str> {
+        let mut headers = Vec::new();
+        let mut packet = ParsedPacket::new(0);
+        walk_extension_headers(
+            IpNextHeaderProtocol(next_header),
+            data,
+            54,
+            &mut headers,
+            &mut packet,
+        )
+    }
+
+    #[test]
+    fn walks_extension_header_chain() {
+        // An atomic fragment, offset 0 and no more fragments, then TCP from port 40000 to 80
+        let mut payload = chain(&[6, 0, 0, 0, 0, 0, 0, 7]);
+        payload.extend([
+            0x9c, 0x40, 0, 80, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff,
+        ]);
+        payload.extend([0, 0, 0, 0]);
+        let frame = ipv6_frame(0, &payload);
+        let packet = Parser::parse_ethernet_frame(
+            &EthernetPacket::new(&frame).unwrap(),
+            0,
+            &mut Defragmenter::new(),
+        );
+
+        let ipv6 = packet.find::<SerializableIpv6Packet>().unwrap();
+        let headers: Vec<_> = ipv6
+            .extension_headers
+            .iter()
+            .map(|header| (header.name(), header.length, header.next_header.as_deref()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ("Hop-by-Hop Options", 8, Some("Ipv6Route (43)")),
+                ("Routing", 24, Some("Ipv6Frag (44)")),
+                ("Fragment", 8, Some("Tcp (6)")),
+            ]
+        );
+        assert!(matches!(
+            ipv6.extension_headers[1].data,
+            SerializableIpv6ExtensionData::Routing {
+                routing_type: 0,
+                segments_left: 1
+            }
+        ));
+        assert!(matches!(
+            ipv6.extension_headers[2].data,
+            SerializableIpv6ExtensionData::Fragment {
+                offset: 0,
+                more_fragments: false,
+                identification: 7
+            }
+        ));
+        let tcp = packet.find::<SerializableTcpPacket>().unwrap();
+        assert_eq!((tcp.source, tcp.destination), (40000, 80));
+        assert_eq!(packet.get_field_range("ipv6.routing"), Some(62..86));
+        assert_eq!(packet.get_field_range("ipv6.fraghdr"), Some(86..94));
+        assert_eq!(packet.get_field_range("tcp.srcport"), Some(94..96));
+    }
+
+    #[test]
+    fn ends_chain_at_fragments_and_opaque_headers() {
+        // The second fragment of datagram 7, 16 bytes in, of a UDP datagram
+        let fragment = walk(0, &chain(&[17, 0, 0, 16, 0, 0, 0, 7, 1, 2]));
+        assert!(matches!(
+            fragment,
+            Ok(ChainEnd::Fragment {
+                next_header: IpNextHeaderProtocols::Udp,
+                start: 40,
+                fragment_offset: 16,
+                last: true,
+                identification: 7,
+            })
+        ));
+        assert!(matches!(
+            walk(50, &[0, 0, 0, 1, 0, 0, 0, 1, 0xee]),
+            Ok(ChainEnd::Opaque)
+        ));
+        assert!(matches!(
+            walk(0, &[59, 0, 1, 4, 0, 0, 0, 0]),
+            Ok(ChainEnd::Opaque)
+        ));
+        assert!(matches!(
+            walk(60, &[17, 0, 1, 4, 0, 0, 0, 0, 1]),
+            Ok(ChainEnd::UpperLayer(IpNextHeaderProtocols::Udp, 8))
+        ));
+    }
+
+    #[test]
+    fn reports_truncated_extension_headers() {
+        let options = Some("Truncated IPv6 options header");
+        let authentication = Some("Truncated IPv6 authentication header");
+        assert_eq!(walk(0, &[]).err(), options);
+        // The length says 16 bytes
+        assert_eq!(walk(60, &[6, 1, 1, 4, 0, 0, 0, 0]).err(), options);
+        let routing = walk(43, &[6, 2, 0, 1, 0, 0, 0, 0]).err();
+        assert_eq!(routing, Some("Truncated IPv6 routing header"));
+        let fragment = walk(44, &[6, 0, 0, 1]).err();
+        assert_eq!(fragment, Some("Truncated IPv6 fragment header"));
+        assert_eq!(walk(51, &[6, 1, 0, 0, 1, 2, 3, 4]).err(), authentication);
+        // Authentication headers count 4-byte units, this one says 16 bytes
+        let data = [6, 2, 0, 0, 1, 2, 3, 4, 0, 0, 0, 1];
+        assert_eq!(walk(51, &data).err(), authentication);
+        assert_eq!(walk(50, &[1, 2, 3, 4]).err(), Some("Truncated ESP header"));
+
+        // Headers before the truncated one are kept, the transport layer says what went wrong
+        let mut payload = chain(&[]);
+        payload.truncate(20);
+        let frame = ipv6_frame(0, &payload);
+        let packet = Parser::parse_ethernet_frame(
+            &EthernetPacket::new(&frame).unwrap(),
+            0,
+            &mut Defragmenter::new(),
+        );
+        let ipv6 = packet.find::<SerializableIpv6Packet>().unwrap();
+        assert_eq!(ipv6.extension_headers.len(), 1);
+        assert!(matches!(
+            packet.get_transport_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason))
+                if reason == "Truncated IPv6 routing header"
+        ));
+    }
+}
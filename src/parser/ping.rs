@@ -0,0 +1,594 @@
+//! Echo sessions of pingers, ICMP and ICMPv6 echo requests matched with their replies by
+//! `PingTracker`
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::IpAddr,
+    time::Duration,
+};
+
+use super::wrapers::{
+    ParsedPacket, SerializableEchoReplyPacket, SerializableEchoRequestPacket,
+    SerializableIcmpv6Body, SerializableIcmpv6Packet, SerializablePacket,
+};
+
+/// How long a request waits for its reply before it counts as lost, as `ping -W`
+pub const ECHO_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long sessions are kept after their last request or reply
+pub const SESSION_RETENTION: Duration = Duration::from_secs(300);
+/// Sessions kept at most, those idle the longest are forgotten first
+const MAX_SESSIONS: usize = 4096;
+
+/// A source pinging a target, the requests of one run of ping share an identifier
+pub struct EchoSession {
+    pub source: IpAddr,
+    pub target: IpAddr,
+    pub identifier: u16,
+    /// Requests sent, retransmissions waiting on the same sequence number count once
+    pub sent: usize,
+    /// Requests answered
+    pub received: usize,
+    /// Requests left without a reply for `ECHO_TIMEOUT`
+    pub lost: usize,
+    /// Replies to requests answered already
+    pub duplicates: usize,
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+    /// Capture time of the first request
+    pub started: Duration,
+    /// Capture time of the latest request or reply
+    pub last_seen: Duration,
+    /// Id of the latest packet of the session
+    pub last_packet: usize,
+    /// Sum of the round-trip times
+    total: Duration,
+    /// Sum of the differences between consecutive round-trip times
+    variation: Duration,
+    last_time: Option<Duration>,
+    /// Requests waiting for their reply by sequence number, with their packet id and capture
+    /// time
+    pending: HashMap<u16, (usize, Duration)>,
+    /// Requests answered by sequence number, kept for `ECHO_TIMEOUT` to spot duplicates
+    answered: HashMap<u16, (usize, Duration)>,
+}
+
+impl EchoSession {
+    fn new(source: IpAddr, target: IpAddr, identifier: u16, started: Duration) -> Self {
+        EchoSession {
+            source,
+            target,
+            identifier,
+            sent: 0,
+            received: 0,
+            lost: 0,
+            duplicates: 0,
+            min: None,
+            max: None,
+            started,
+            last_seen: started,
+            last_packet: 0,
+            total: Duration::ZERO,
+            variation: Duration::ZERO,
+            last_time: None,
+            pending: HashMap::new(),
+            answered: HashMap::new(),
+        }
+    }
+
+    /// Get the mean round-trip time
+    pub fn average(&self) -> Option<Duration> {
+        (self.received > 0).then(|| self.total / self.received as u32)
+    }
+
+    /// Get the mean difference between consecutive round-trip times
+    pub fn jitter(&self) -> Option<Duration> {
+        (self.received > 1).then(|| self.variation / (self.received - 1) as u32)
+    }
+
+    /// Get the share of requests lost, in percent of those answered or timed out
+    pub fn loss(&self) -> Option<f64> {
+        let settled = self.received + self.lost;
+        (settled > 0).then(|| self.lost as f64 * 100.0 / settled as f64)
+    }
+
+    /// Whether a request or reply was seen `ECHO_TIMEOUT` before `now` or later
+    pub fn is_active(&self, now: Duration) -> bool {
+        now.saturating_sub(self.last_seen) < ECHO_TIMEOUT
+    }
+
+    fn add_time(&mut self, time: Duration) {
+        self.received += 1;
+        self.total += time;
+        self.min = Some(self.min.map_or(time, |min| min.min(time)));
+        self.max = Some(self.max.map_or(time, |max| max.max(time)));
+        if let Some(last) = self.last_time {
+            self.variation += time.abs_diff(last);
+        }
+        self.last_time = Some(time);
+    }
+}
+
+/// An echo request or reply, ICMP or ICMPv6
+enum Echo<'a> {
+    Request {
+        identifier: u16,
+        sequence_number: u16,
+    },
+    Reply {
+        identifier: u16,
+        sequence_number: u16,
+        response_to: &'a mut Option<usize>,
+        time: &'a mut Option<Duration>,
+        duplicate: &'a mut bool,
+    },
+}
+
+/// Matches echo replies to their requests and keeps round-trip statistics per pinger, packets
+/// are fed in capture order
+///
+/// Replies get the request's packet id and the round-trip time, requests left without a reply
+/// for `ECHO_TIMEOUT` are reported by `expire`. Sessions idle for `SESSION_RETENTION` are
+/// forgotten by `prune`.
+#[derive(Default)]
+pub struct PingTracker {
+    sessions: Vec<EchoSession>,
+    /// Index in `sessions` keyed on source, target and identifier
+    index: HashMap<(IpAddr, IpAddr, u16), usize>,
+}
+
+impl PingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the sessions seen, in the order they started
+    pub fn sessions(&self) -> &[EchoSession] {
+        &self.sessions
+    }
+
+    /// Count a request, or fill in the request a reply answers
+    pub fn process(&mut self, packet: &mut ParsedPacket) {
+        let (source, destination): (IpAddr, IpAddr) = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4)) => {
+                (ipv4.source.into(), ipv4.destination.into())
+            }
+            Some(SerializablePacket::Ipv6Packet(ipv6)) => {
+                (ipv6.source.into(), ipv6.destination.into())
+            }
+            _ => return,
+        };
+        let id = packet.get_id();
+        let timestamp = packet.get_timestamp();
+        let Some(echo) = echo(packet) else {
+            return;
+        };
+        match echo {
+            Echo::Request {
+                identifier,
+                sequence_number,
+            } => {
+                let sessions = &mut self.sessions;
+                let index = *self
+                    .index
+                    .entry((source, destination, identifier))
+                    .or_insert_with(|| {
+                        sessions.push(EchoSession::new(source, destination, identifier, timestamp));
+                        sessions.len() - 1
+                    });
+                let session = &mut self.sessions[index];
+                session.last_seen = timestamp;
+                session.last_packet = id;
+                // Retransmissions keep waiting on the first request
+                if let Entry::Vacant(entry) = session.pending.entry(sequence_number) {
+                    entry.insert((id, timestamp));
+                    session.sent += 1;
+                }
+            }
+            Echo::Reply {
+                identifier,
+                sequence_number,
+                response_to,
+                time,
+                duplicate,
+            } => {
+                let Some(&index) = self.index.get(&(destination, source, identifier)) else {
+                    return;
+                };
+                let session = &mut self.sessions[index];
+                // Late replies don't count, whether the request expired already depends on
+                // batching
+                let within = |&&(_, sent): &&(usize, Duration)| {
+                    timestamp.saturating_sub(sent) < ECHO_TIMEOUT
+                };
+                if let Some(&(request, sent)) = session.pending.get(&sequence_number).filter(within)
+                {
+                    session.pending.remove(&sequence_number);
+                    session.answered.insert(sequence_number, (request, sent));
+                    session.add_time(timestamp.saturating_sub(sent));
+                    *response_to = Some(request);
+                    *time = Some(timestamp.saturating_sub(sent));
+                } else if let Some(&(request, sent)) = session.answered.get(&sequence_number) {
+                    session.duplicates += 1;
+                    *response_to = Some(request);
+                    *time = Some(timestamp.saturating_sub(sent));
+                    *duplicate = true;
+                } else {
+                    return;
+                }
+                session.last_seen = timestamp;
+                session.last_packet = id;
+            }
+        }
+    }
+
+    /// Count requests sent `ECHO_TIMEOUT` before `now` as lost, returning their packet ids
+    pub fn expire(&mut self, now: Duration) -> Vec<usize> {
+        let mut expired = Vec::new();
+        for session in &mut self.sessions {
+            let waiting =
+                |&mut (_, sent): &mut (usize, Duration)| now.saturating_sub(sent) < ECHO_TIMEOUT;
+            let before = session.pending.len();
+            session.pending.retain(|_, request| {
+                let waiting = waiting(request);
+                if !waiting {
+                    expired.push(request.0);
+                }
+                waiting
+            });
+            session.lost += before - session.pending.len();
+            session.answered.retain(|_, request| waiting(request));
+        }
+        expired.sort_unstable();
+        expired
+    }
+
+    /// Forget sessions without a packet for `SESSION_RETENTION` before `now`, and the ones
+    /// idle the longest while there are more than `MAX_SESSIONS`
+    pub fn prune(&mut self, now: Duration) {
+        let before = self.sessions.len();
+        self.sessions
+            .retain(|session| now.saturating_sub(session.last_seen) < SESSION_RETENTION);
+        if self.sessions.len() > MAX_SESSIONS {
+            let mut idle: Vec<(Duration, usize)> = self
+                .sessions
+                .iter()
+                .enumerate()
+                .map(|(index, session)| (session.last_seen, index))
+                .collect();
+            let excess = self.sessions.len() - MAX_SESSIONS;
+            idle.select_nth_unstable(excess - 1);
+            let mut keep = vec![true; self.sessions.len()];
+            for &(_, index) in &idle[..excess] {
+                keep[index] = false;
+            }
+            // Sessions are visited in order, so they stay in the order they started
+            let mut keep = keep.into_iter();
+            self.sessions.retain(|_| keep.next().unwrap_or(true));
+        }
+        if self.sessions.len() == before {
+            return;
+        }
+        self.index = self
+            .sessions
+            .iter()
+            .enumerate()
+            .map(|(index, session)| ((session.source, session.target, session.identifier), index))
+            .collect();
+    }
+}
+
+/// Flag the echo request in `packet` as never answered
+pub fn mark_unanswered(packet: &mut ParsedPacket) {
+    if let Some(request) = packet.find_mut::<SerializableEchoRequestPacket>() {
+        request.unanswered = true;
+    } else if let Some(SerializableIcmpv6Body::EchoRequest { unanswered, .. }) = packet
+        .find_mut::<SerializableIcmpv6Packet>()
+        .map(|icmpv6| &mut icmpv6.body)
+    {
+        *unanswered = true;
+    }
+}
+
+/// Get the echo request or reply carried by `packet`
+fn echo(packet: &mut ParsedPacket) -> Option<Echo<'_>> {
+    if let Some(request) = packet.find::<SerializableEchoRequestPacket>() {
+        return Some(Echo::Request {
+            identifier: request.identifier,
+            sequence_number: request.sequence_number,
+        });
+    }
+    if packet.find::<SerializableEchoReplyPacket>().is_some() {
+        let reply = packet.find_mut::<SerializableEchoReplyPacket>()?;
+        return Some(Echo::Reply {
+            identifier: reply.identifier,
+            sequence_number: reply.sequence_number,
+            response_to: &mut reply.response_to,
+            time: &mut reply.time,
+            duplicate: &mut reply.duplicate,
+        });
+    }
+    match &mut packet.find_mut::<SerializableIcmpv6Packet>()?.body {
+        SerializableIcmpv6Body::EchoRequest {
+            identifier,
+            sequence_number,
+            ..
+        } => Some(Echo::Request {
+            identifier: *identifier,
+            sequence_number: *sequence_number,
+        }),
+        SerializableIcmpv6Body::EchoReply {
+            identifier,
+            sequence_number,
+            response_to,
+            time,
+            duplicate,
+        } => Some(Echo::Reply {
+            identifier: *identifier,
+            sequence_number: *sequence_number,
+            response_to,
+            time,
+            duplicate,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{defrag::Defragmenter, Parser};
+    use pnet::packet::ethernet::EthernetPacket;
+
+    /// Ethernet frame with an ICMP echo request from 10.0.0.1 to `target`, or its reply
+    fn echo(target: u8, identifier: u16, sequence_number: u16, reply: bool) -> Vec<u8> {
+        let (source, destination) = if reply { (target, 1) } else { (1, target) };
+        let mut frame = vec![0; 12];
+        frame.extend([0x08, 0x00, 0x45, 0, 0, 28, 0, 0, 0, 0, 64, 1, 0, 0]);
+        frame.extend([10, 0, 0, source, 10, 0, 0, destination]);
+        frame.extend([if reply { 0 } else { 8 }, 0, 0, 0]);
+        frame.extend(identifier.to_be_bytes());
+        frame.extend(sequence_number.to_be_bytes());
+        frame
+    }
+
+    /// Ethernet frame with an ICMPv6 echo request from 2001:db8::1 to 2001:db8::2, or its
+    /// reply
+    fn echo_v6(identifier: u16, sequence_number: u16, reply: bool) -> Vec<u8> {
+        let (source, destination) = if reply { (2, 1) } else { (1, 2) };
+        let mut frame = vec![0; 12];
+        frame.extend([0x86, 0xdd, 0x60, 0, 0, 0, 0, 8, 58, 64]);
+        for host in [source, destination] {
+            frame.extend([0x20, 0x01, 0x0d, 0xb8]);
+            frame.extend([0; 11]);
+            frame.push(host);
+        }
+        frame.extend([if reply { 129 } else { 128 }, 0, 0, 0]);
+        frame.extend(identifier.to_be_bytes());
+        frame.extend(sequence_number.to_be_bytes());
+        frame
+    }
+
+    fn feed(tracker: &mut PingTracker, frame: &[u8], id: usize, millis: u64) -> ParsedPacket {
+        let ethernet = EthernetPacket::new(frame).unwrap();
+        let mut packet = Parser::parse_ethernet_frame(&ethernet, id, &mut Defragmenter::new());
+        packet.set_timestamp(Duration::from_millis(millis));
+        tracker.process(&mut packet);
+        packet
+    }
+
+    /// Get the request a reply answers, its round-trip time and whether it is a duplicate
+    fn reply(packet: &ParsedPacket) -> (Option<usize>, Option<Duration>, bool) {
+        if let Some(reply) = packet.find::<SerializableEchoReplyPacket>() {
+            return (reply.response_to, reply.time, reply.duplicate);
+        }
+        match packet
+            .find::<SerializableIcmpv6Packet>()
+            .map(|icmpv6| &icmpv6.body)
+        {
+            Some(&SerializableIcmpv6Body::EchoReply {
+                response_to,
+                time,
+                duplicate,
+                ..
+            }) => (response_to, time, duplicate),
+            _ => panic!("not an echo reply"),
+        }
+    }
+
+    fn answered(request: usize, millis: u64) -> (Option<usize>, Option<Duration>, bool) {
+        (Some(request), Some(Duration::from_millis(millis)), false)
+    }
+
+    const UNMATCHED: (Option<usize>, Option<Duration>, bool) = (None, None, false);
+
+    #[test]
+    fn matches_replies_on_addresses_identifier_and_sequence() {
+        let mut tracker = PingTracker::new();
+        feed(&mut tracker, &echo(2, 1, 1, false), 0, 0);
+        feed(&mut tracker, &echo(2, 0x0102, 1, false), 1, 1);
+        feed(&mut tracker, &echo(3, 1, 1, false), 2, 2);
+        feed(&mut tracker, &echo(2, 1, 2, false), 3, 3);
+
+        // Replies carry the identifier of their request
+        let packet = feed(&mut tracker, &echo(2, 0x0102, 1, true), 4, 11);
+        let echo_reply = packet.find::<SerializableEchoReplyPacket>().unwrap();
+        assert_eq!(echo_reply.identifier, 0x0102);
+        assert_eq!(echo_reply.sequence_number, 1);
+        assert_eq!(reply(&packet), answered(1, 10));
+        let packet = feed(&mut tracker, &echo(2, 1, 2, true), 5, 13);
+        assert_eq!(reply(&packet), answered(3, 10));
+        let packet = feed(&mut tracker, &echo(3, 1, 1, true), 6, 22);
+        assert_eq!(reply(&packet), answered(2, 20));
+        // Nothing was sent to 10.0.0.4, nor with sequence number 9
+        let packet = feed(&mut tracker, &echo(4, 1, 1, true), 7, 23);
+        assert_eq!(reply(&packet), UNMATCHED);
+        let packet = feed(&mut tracker, &echo(2, 1, 9, true), 8, 24);
+        assert_eq!(reply(&packet), UNMATCHED);
+
+        let sessions: Vec<_> = tracker
+            .sessions()
+            .iter()
+            .map(|session| {
+                (
+                    session.target,
+                    session.identifier,
+                    session.sent,
+                    session.received,
+                    session.last_packet,
+                )
+            })
+            .collect();
+        let host = |host| IpAddr::from([10, 0, 0, host]);
+        assert_eq!(
+            sessions,
+            [
+                (host(2), 1, 2, 1, 5),
+                (host(2), 0x0102, 1, 1, 4),
+                (host(3), 1, 1, 1, 6),
+            ]
+        );
+        assert!(tracker
+            .sessions()
+            .iter()
+            .all(|session| session.source == host(1)));
+    }
+
+    #[test]
+    fn matches_icmpv6_replies() {
+        let mut tracker = PingTracker::new();
+        feed(&mut tracker, &echo_v6(7, 1, false), 0, 0);
+        let packet = feed(&mut tracker, &echo_v6(7, 1, true), 1, 5);
+        assert_eq!(reply(&packet), answered(0, 5));
+        let packet = feed(&mut tracker, &echo_v6(8, 1, true), 2, 6);
+        assert_eq!(reply(&packet), UNMATCHED);
+        let [session] = tracker.sessions() else {
+            panic!("expected one session");
+        };
+        assert_eq!(session.target, "2001:db8::2".parse::<IpAddr>().unwrap());
+        assert_eq!(session.received, 1);
+    }
+
+    #[test]
+    fn computes_round_trip_statistics() {
+        let mut tracker = PingTracker::new();
+        assert_eq!(tracker.sessions().len(), 0);
+        for (sequence_number, rtt) in [(1, 10), (2, 30), (3, 20), (4, 20)] {
+            let sent = 1000 * sequence_number as u64;
+            let id = 2 * sequence_number as usize;
+            feed(&mut tracker, &echo(2, 1, sequence_number, false), id, sent);
+            let session = &tracker.sessions()[0];
+            assert_eq!(session.sent, sequence_number as usize);
+            let packet = feed(
+                &mut tracker,
+                &echo(2, 1, sequence_number, true),
+                id + 1,
+                sent + rtt,
+            );
+            assert_eq!(reply(&packet), answered(id, rtt));
+        }
+        let session = &tracker.sessions()[0];
+        let millis = |millis| Some(Duration::from_millis(millis));
+        assert_eq!(session.received, 4);
+        assert_eq!(session.min, millis(10));
+        assert_eq!(session.max, millis(30));
+        assert_eq!(session.average(), millis(20));
+        // Differences of 20, 10 and 0 ms
+        assert_eq!(session.jitter(), millis(10));
+        assert_eq!(session.loss(), Some(0.0));
+        assert_eq!(session.started, Duration::from_secs(1));
+        assert_eq!(session.last_seen, Duration::from_millis(4020));
+        assert!(session.is_active(Duration::from_millis(14019)));
+        assert!(!session.is_active(Duration::from_millis(14020)));
+    }
+
+    #[test]
+    fn counts_duplicate_replies() {
+        let mut tracker = PingTracker::new();
+        feed(&mut tracker, &echo(2, 1, 1, false), 0, 0);
+        // A retransmission waits on the first request
+        feed(&mut tracker, &echo(2, 1, 1, false), 1, 100);
+        let packet = feed(&mut tracker, &echo(2, 1, 1, true), 2, 150);
+        assert_eq!(reply(&packet), answered(0, 150));
+        let packet = feed(&mut tracker, &echo(2, 1, 1, true), 3, 160);
+        assert_eq!(
+            reply(&packet),
+            (Some(0), Some(Duration::from_millis(160)), true)
+        );
+
+        let session = &tracker.sessions()[0];
+        assert_eq!(session.sent, 1);
+        assert_eq!(session.received, 1);
+        assert_eq!(session.duplicates, 1);
+        // Duplicates don't skew the round-trip times
+        assert_eq!(session.max, Some(Duration::from_millis(150)));
+
+        // Answered requests are forgotten with the pending ones
+        assert!(tracker.expire(ECHO_TIMEOUT).is_empty());
+        let packet = feed(&mut tracker, &echo(2, 1, 1, true), 4, 10_001);
+        assert_eq!(reply(&packet), UNMATCHED);
+        assert_eq!(tracker.sessions()[0].duplicates, 1);
+    }
+
+    #[test]
+    fn counts_requests_lost_after_echo_timeout() {
+        let mut tracker = PingTracker::new();
+        feed(&mut tracker, &echo(2, 1, 1, false), 0, 0);
+        feed(&mut tracker, &echo(2, 1, 2, false), 1, 1000);
+        feed(&mut tracker, &echo(2, 1, 2, true), 2, 1500);
+        assert!(tracker
+            .expire(ECHO_TIMEOUT - Duration::from_millis(1))
+            .is_empty());
+        assert_eq!(tracker.expire(ECHO_TIMEOUT), [0]);
+        assert!(tracker.expire(ECHO_TIMEOUT).is_empty());
+        let session = &tracker.sessions()[0];
+        assert_eq!((session.sent, session.received, session.lost), (2, 1, 1));
+        assert_eq!(session.loss(), Some(50.0));
+
+        // Late replies count for nothing
+        let packet = feed(&mut tracker, &echo(2, 1, 1, true), 3, 10_500);
+        assert_eq!(reply(&packet), UNMATCHED);
+        // Even before the request was expired
+        feed(&mut tracker, &echo(2, 1, 3, false), 4, 20_000);
+        let packet = feed(&mut tracker, &echo(2, 1, 3, true), 5, 30_000);
+        assert_eq!(reply(&packet), UNMATCHED);
+        assert_eq!(tracker.expire(Duration::from_secs(30)), [4]);
+        let session = &tracker.sessions()[0];
+        assert_eq!((session.sent, session.received, session.lost), (3, 1, 2));
+
+        let mut packet = feed(&mut tracker, &echo(2, 1, 4, false), 6, 30_000);
+        mark_unanswered(&mut packet);
+        assert!(
+            packet
+                .find::<SerializableEchoRequestPacket>()
+                .unwrap()
+                .unanswered
+        );
+    }
+
+    #[test]
+    fn forgets_idle_sessions() {
+        let mut tracker = PingTracker::new();
+        feed(&mut tracker, &echo(2, 1, 1, false), 0, 0);
+        feed(&mut tracker, &echo(3, 1, 1, false), 1, 200_000);
+        tracker.prune(Duration::from_secs(300));
+        assert_eq!(tracker.sessions().len(), 1);
+        assert_eq!(tracker.sessions()[0].target, IpAddr::from([10, 0, 0, 3]));
+
+        // Replies still find their session once the index was rebuilt
+        feed(&mut tracker, &echo(3, 1, 1, true), 2, 201_000);
+        assert_eq!(tracker.sessions()[0].received, 1);
+    }
+
+    #[test]
+    fn caps_session_count() {
+        let mut tracker = PingTracker::new();
+        for identifier in 0..MAX_SESSIONS as u16 + 10 {
+            feed(&mut tracker, &echo(2, identifier, 1, false), 0, 0);
+        }
+        feed(&mut tracker, &echo(2, 0, 1, false), 0, 1000);
+        tracker.prune(Duration::from_secs(1));
+        assert_eq!(tracker.sessions().len(), MAX_SESSIONS);
+        assert_eq!(tracker.sessions()[0].identifier, 0);
+        assert_eq!(tracker.index.len(), MAX_SESSIONS);
+    }
+}
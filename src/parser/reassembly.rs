@@ -0,0 +1,786 @@
+//! TCP stream reassembly, putting segments back into the byte streams each side sent
+//!
+//! Streams are keyed on their 4-tuple. Each direction is ordered by sequence number:
+//! retransmitted and overlapping bytes are dropped, segments arriving ahead of a gap wait
+//! until the gap is filled, and FIN/RST end the stream. Gaps that don't fill within
+//! `GAP_TIMEOUT`, or while too much data waits behind them, are skipped.
+//!
+//! Streams are forgotten `CLOSED_STREAM_RETENTION` after they were closed or reset, and
+//! `STREAM_RETENTION` after their last packet otherwise.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use pnet::packet::tcp::TcpFlags;
+
+use super::wrapers::{ParsedPacket, SerializablePacket, SerializableTcpPacket};
+
+/// How long data waits behind a gap before the missing bytes are given up on
+pub const GAP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Bytes a direction buffers behind a gap before the missing bytes are given up on
+const MAX_PENDING_BYTES: usize = 1 << 20;
+/// Segments a direction buffers behind a gap before the missing bytes are given up on
+const MAX_PENDING_SEGMENTS: usize = 1024;
+/// How long streams are kept after their last packet
+pub const STREAM_RETENTION: Duration = Duration::from_secs(300);
+/// How long closed or reset streams are kept after their last packet, for late retransmissions
+pub const CLOSED_STREAM_RETENTION: Duration = Duration::from_secs(30);
+/// Streams kept at most, those idle the longest are forgotten first
+const MAX_STREAMS: usize = 4096;
+/// Capture time between two looks for streams to forget
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which side sent a piece of a stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From the side that opened the connection
+    ToServer,
+    /// From the side that accepted it
+    ToClient,
+}
+
+/// How far a stream's teardown went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamState {
+    Open,
+    /// One side sent all of its data and a FIN
+    HalfClosed,
+    /// Both sides sent a FIN
+    Closed,
+    /// A side aborted the connection with a RST
+    Reset,
+}
+
+/// Consecutive bytes sent by one side before the other side spoke
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamChunk {
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+/// Bytes a packet added to its stream, for decoding protocols carried over TCP
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSegment {
+    /// Number of the stream the packet belongs to
+    pub stream: usize,
+    pub direction: Direction,
+    /// Bytes that became contiguous, possibly including earlier out-of-order segments
+    pub data: Vec<u8>,
+    /// Bytes given up on in this direction by this packet, `data` doesn't follow on from the
+    /// previous segment when non-zero
+    pub missing: u64,
+    /// Whether this direction of the stream is over, after a FIN or a RST
+    pub finished: bool,
+}
+
+/// One direction of a connection
+#[derive(Default)]
+struct Flow {
+    /// Sequence number of the next byte expected
+    next: Option<u32>,
+    /// Sequence number of the FIN, once seen
+    fin: Option<u32>,
+    finished: bool,
+    /// Offset of `next` from the start of the stream, which unlike sequence numbers never wraps
+    position: u64,
+    /// Segments that arrived ahead of a gap, keyed on their offset in the stream
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_bytes: usize,
+    /// Capture time the gap in front of `pending` started being waited on
+    gap_since: Option<Duration>,
+    bytes: u64,
+    retransmitted: u64,
+    out_of_order: u64,
+    /// Bytes skipped over by giving up on gaps
+    missing: u64,
+}
+
+impl Flow {
+    /// Take in a segment captured at `now`, returning the bytes that became contiguous
+    fn segment(&mut self, mut sequence: u32, flags: u16, payload: &[u8], now: Duration) -> Vec<u8> {
+        if flags & TcpFlags::SYN != 0 {
+            // The SYN takes up a sequence number of its own
+            sequence = sequence.wrapping_add(1);
+            if self.next.is_none() {
+                self.next = Some(sequence);
+            }
+        }
+        // Captures started mid-connection pick the stream up at the first segment seen
+        self.next.get_or_insert(sequence);
+        if flags & TcpFlags::FIN != 0 {
+            self.fin = Some(sequence.wrapping_add(payload.len() as u32));
+        }
+
+        let mut data = Vec::new();
+        self.accept(sequence, payload, now, &mut data);
+        loop {
+            while let Some(entry) = self.pending.first_entry() {
+                if *entry.key() > self.position {
+                    break;
+                }
+                let (offset, payload) = entry.remove_entry();
+                self.pending_bytes -= payload.len();
+                let sequence = self.sequence(offset);
+                self.accept(sequence, &payload, now, &mut data);
+            }
+            let Some(&offset) = self.pending.keys().next() else {
+                self.gap_since = None;
+                break;
+            };
+            let timed_out = self
+                .gap_since
+                .is_some_and(|since| now.saturating_sub(since) >= GAP_TIMEOUT);
+            if !timed_out
+                && self.pending_bytes <= MAX_PENDING_BYTES
+                && self.pending.len() <= MAX_PENDING_SEGMENTS
+            {
+                break;
+            }
+            // Give up on the gap, the next one gets its own timeout
+            let gap = offset - self.position;
+            self.missing += gap;
+            self.advance(gap as u32);
+            self.gap_since = Some(now);
+        }
+
+        if !self.finished && self.fin.is_some() && self.fin == self.next {
+            self.finished = true;
+            self.advance(1);
+        }
+        data
+    }
+
+    fn accept(&mut self, sequence: u32, payload: &[u8], now: Duration, data: &mut Vec<u8>) {
+        let Some(next) = self.next else {
+            return;
+        };
+        // Wrapping distance, so streams keep working across the 32-bit sequence space
+        let ahead = sequence.wrapping_sub(next) as i32;
+        if ahead > 0 {
+            if !payload.is_empty() {
+                self.out_of_order += 1;
+                self.gap_since.get_or_insert(now);
+                // Of segments starting at the same byte, the longest is kept
+                match self.pending.entry(self.position + ahead as u64) {
+                    Entry::Vacant(entry) => {
+                        self.pending_bytes += payload.len();
+                        entry.insert(payload.to_vec());
+                    }
+                    Entry::Occupied(mut entry) if entry.get().len() < payload.len() => {
+                        self.pending_bytes += payload.len() - entry.get().len();
+                        entry.insert(payload.to_vec());
+                    }
+                    Entry::Occupied(_) => self.retransmitted += payload.len() as u64,
+                }
+            }
+            return;
+        }
+
+        let seen = ahead.unsigned_abs() as usize;
+        if seen >= payload.len() {
+            self.retransmitted += payload.len() as u64;
+            return;
+        }
+        let new = &payload[seen..];
+        self.retransmitted += seen as u64;
+        self.bytes += new.len() as u64;
+        self.advance(new.len() as u32);
+        data.extend_from_slice(new);
+    }
+
+    /// Move `next` forward by `length` bytes
+    fn advance(&mut self, length: u32) {
+        self.next = self.next.map(|next| next.wrapping_add(length));
+        self.position += length as u64;
+    }
+
+    /// Get the sequence number of the byte at `offset`, at most `position`
+    fn sequence(&self, offset: u64) -> u32 {
+        let next = self.next.unwrap_or_default();
+        next.wrapping_sub((self.position - offset) as u32)
+    }
+}
+
+/// A reassembled TCP connection
+pub struct TcpStream {
+    index: usize,
+    client: SocketAddr,
+    server: SocketAddr,
+    to_server: Flow,
+    to_client: Flow,
+    chunks: Vec<StreamChunk>,
+    packets: Vec<usize>,
+    reset: bool,
+    /// Capture time of the latest packet
+    last_seen: Duration,
+}
+
+impl TcpStream {
+    fn new(index: usize, client: SocketAddr, server: SocketAddr) -> Self {
+        TcpStream {
+            index,
+            client,
+            server,
+            to_server: Flow::default(),
+            to_client: Flow::default(),
+            chunks: Vec::new(),
+            packets: Vec::new(),
+            reset: false,
+            last_seen: Duration::ZERO,
+        }
+    }
+
+    fn segment(
+        &mut self,
+        id: usize,
+        timestamp: Duration,
+        source: SocketAddr,
+        tcp: &SerializableTcpPacket,
+    ) -> StreamSegment {
+        self.packets.push(id);
+        self.last_seen = timestamp;
+        let (direction, flow) = if source == self.client {
+            (Direction::ToServer, &mut self.to_server)
+        } else {
+            (Direction::ToClient, &mut self.to_client)
+        };
+        if tcp.flags & TcpFlags::RST != 0 {
+            self.reset = true;
+        }
+
+        let missing = flow.missing;
+        let data = flow.segment(tcp.sequence, tcp.flags, &tcp.payload, timestamp);
+        let segment = StreamSegment {
+            stream: self.index,
+            direction,
+            finished: flow.finished || self.reset,
+            missing: flow.missing - missing,
+            data,
+        };
+        if segment.data.is_empty() {
+            return segment;
+        }
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.direction == direction => chunk.data.extend(&segment.data),
+            _ => self.chunks.push(StreamChunk {
+                direction,
+                data: segment.data.clone(),
+            }),
+        }
+        segment
+    }
+
+    /// Get the stream's number, in order of first appearance
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Get address of the side that opened the connection
+    pub fn get_client(&self) -> SocketAddr {
+        self.client
+    }
+
+    /// Get address of the side that accepted the connection
+    pub fn get_server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Get the conversation in order, alternating between the two sides
+    pub fn get_chunks(&self) -> &[StreamChunk] {
+        &self.chunks
+    }
+
+    /// Get ids of the packets belonging to the stream
+    pub fn get_packets(&self) -> &[usize] {
+        &self.packets
+    }
+
+    /// Get how far the connection's teardown went
+    pub fn get_state(&self) -> StreamState {
+        match (self.reset, self.to_server.finished, self.to_client.finished) {
+            (true, _, _) => StreamState::Reset,
+            (false, true, true) => StreamState::Closed,
+            (false, true, false) | (false, false, true) => StreamState::HalfClosed,
+            (false, false, false) => StreamState::Open,
+        }
+    }
+
+    /// Get bytes reassembled in `direction`
+    pub fn get_bytes(&self, direction: Direction) -> u64 {
+        self.flow(direction).bytes
+    }
+
+    /// Get bytes received again in `direction`, by retransmissions or overlapping segments
+    pub fn get_retransmitted(&self, direction: Direction) -> u64 {
+        self.flow(direction).retransmitted
+    }
+
+    /// Get segments in `direction` that arrived ahead of missing data
+    pub fn get_out_of_order(&self, direction: Direction) -> u64 {
+        self.flow(direction).out_of_order
+    }
+
+    /// Get bytes in `direction` still waiting for missing data before them
+    pub fn get_pending(&self, direction: Direction) -> usize {
+        self.flow(direction).pending_bytes
+    }
+
+    /// Get bytes in `direction` that never arrived and were skipped over
+    pub fn get_missing(&self, direction: Direction) -> u64 {
+        self.flow(direction).missing
+    }
+
+    fn flow(&self, direction: Direction) -> &Flow {
+        match direction {
+            Direction::ToServer => &self.to_server,
+            Direction::ToClient => &self.to_client,
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        matches!(self.get_state(), StreamState::Closed | StreamState::Reset)
+    }
+
+    /// Whether the stream is kept at `now`
+    fn is_retained(&self, now: Duration) -> bool {
+        let retention = if self.is_over() {
+            CLOSED_STREAM_RETENTION
+        } else {
+            STREAM_RETENTION
+        };
+        now.saturating_sub(self.last_seen) < retention
+    }
+}
+
+/// Reassembles every TCP connection of a capture, packets are fed in capture order
+#[derive(Default)]
+pub struct TcpReassembler {
+    /// Streams kept, in the order they started
+    streams: Vec<TcpStream>,
+    /// Number of the latest stream of each 4-tuple, with the lower address first
+    index: HashMap<(SocketAddr, SocketAddr), usize>,
+    /// Number given to the next stream
+    next_stream: usize,
+    /// Capture time streams were last looked at for forgetting
+    pruned: Duration,
+}
+
+impl TcpReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a packet to its stream, returning what it added for TCP packets
+    pub fn process(&mut self, packet: &ParsedPacket) -> Option<StreamSegment> {
+        let (source, destination, tcp) = endpoints(packet)?;
+        let key = ordered(source, destination);
+        let opens = tcp.flags & (TcpFlags::SYN | TcpFlags::ACK) == TcpFlags::SYN;
+
+        let position = match self.index.get(&key).and_then(|&index| self.position(index)) {
+            // A new SYN on a finished connection's ports starts a new stream
+            Some(position) if !(opens && self.streams[position].is_over()) => position,
+            _ => {
+                let index = self.next_stream;
+                self.next_stream += 1;
+                // The first packet seen may be the SYN-ACK, sent by the server
+                let (client, server) = if tcp.flags & TcpFlags::SYN != 0 && !opens {
+                    (destination, source)
+                } else {
+                    (source, destination)
+                };
+                self.streams.push(TcpStream::new(index, client, server));
+                self.index.insert(key, index);
+                self.streams.len() - 1
+            }
+        };
+
+        Some(self.streams[position].segment(packet.get_id(), packet.get_timestamp(), source, tcp))
+    }
+
+    /// Get the stream `packet` was added to
+    pub fn stream_of(&self, packet: &ParsedPacket) -> Option<&TcpStream> {
+        self.streams
+            .iter()
+            .rev()
+            .find(|stream| stream.packets.binary_search(&packet.get_id()).is_ok())
+    }
+
+    /// Get stream number `index`, `None` once it was forgotten
+    pub fn get_stream(&self, index: usize) -> Option<&TcpStream> {
+        self.position(index).map(|position| &self.streams[position])
+    }
+
+    /// Get every stream kept, in the order they started
+    pub fn get_streams(&self) -> &[TcpStream] {
+        &self.streams
+    }
+
+    /// Forget streams that are no longer kept at `now`, and the ones idle the longest while
+    /// there are more than `MAX_STREAMS`, returning their numbers
+    pub fn prune(&mut self, now: Duration) -> Vec<usize> {
+        if now.saturating_sub(self.pruned) < PRUNE_INTERVAL && self.streams.len() <= MAX_STREAMS {
+            return Vec::new();
+        }
+        self.pruned = now;
+
+        let mut forgotten = Vec::new();
+        let mut keep: Vec<bool> = self
+            .streams
+            .iter()
+            .map(|stream| stream.is_retained(now))
+            .collect();
+        let kept = keep.iter().filter(|&&keep| keep).count();
+        if kept > MAX_STREAMS {
+            let mut idle: Vec<(Duration, usize)> = self
+                .streams
+                .iter()
+                .enumerate()
+                .filter(|&(position, _)| keep[position])
+                .map(|(position, stream)| (stream.last_seen, position))
+                .collect();
+            let excess = kept - MAX_STREAMS;
+            idle.select_nth_unstable(excess - 1);
+            for &(_, position) in &idle[..excess] {
+                keep[position] = false;
+            }
+        }
+        // Streams are visited in order, so they stay in the order they started
+        let mut keep = keep.into_iter();
+        self.streams.retain(|stream| {
+            let kept = keep.next().unwrap_or(true);
+            if !kept {
+                forgotten.push(stream.index);
+            }
+            kept
+        });
+        if !forgotten.is_empty() {
+            let streams = &self.streams;
+            self.index.retain(|_, index| {
+                streams
+                    .binary_search_by_key(index, TcpStream::get_index)
+                    .is_ok()
+            });
+        }
+        forgotten
+    }
+
+    /// Get where stream number `index` is in `streams`
+    fn position(&self, index: usize) -> Option<usize> {
+        self.streams
+            .binary_search_by_key(&index, TcpStream::get_index)
+            .ok()
+    }
+}
+
+fn ordered(a: SocketAddr, b: SocketAddr) -> (SocketAddr, SocketAddr) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Get source and destination sockets of a TCP packet
+fn endpoints(packet: &ParsedPacket) -> Option<(SocketAddr, SocketAddr, &SerializableTcpPacket)> {
+    let (source, destination): (IpAddr, IpAddr) = match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4) => (ipv4.source.into(), ipv4.destination.into()),
+        SerializablePacket::Ipv6Packet(ipv6) => (ipv6.source.into(), ipv6.destination.into()),
+        _ => return None,
+    };
+    match packet.get_transport_layer_packet()? {
+        SerializablePacket::TcpPacket(tcp) => Some((
+            SocketAddr::new(source, tcp.source),
+            SocketAddr::new(destination, tcp.destination),
+            tcp,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        capture::{CapturedFrame, LinkType},
+        defrag::Defragmenter,
+        Parser,
+    };
+
+    const SYN: u16 = TcpFlags::SYN;
+    const ACK: u16 = TcpFlags::ACK;
+    const FIN_ACK: u16 = TcpFlags::FIN | TcpFlags::ACK;
+
+    fn at(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    /// A flow that saw the SYN with sequence number `isn`
+    fn flow(isn: u32) -> Flow {
+        let mut flow = Flow::default();
+        assert!(flow.segment(isn, SYN, &[], at(0)).is_empty());
+        flow
+    }
+
+    /// Ethernet, IPv4 and TCP between 10.0.0.1:40000 and 10.0.0.2:80, captured `id` seconds in
+    fn packet(
+        id: usize,
+        to_server: bool,
+        sequence: u32,
+        flags: u16,
+        payload: &[u8],
+    ) -> ParsedPacket {
+        let frame = frame(40000, to_server, sequence, flags, payload);
+        parse(&frame, id, at(id as u64))
+    }
+
+    /// Ethernet, IPv4 and TCP between 10.0.0.1:`client_port` and 10.0.0.2:80
+    fn frame(
+        client_port: u16,
+        to_server: bool,
+        sequence: u32,
+        flags: u16,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let (client, server) = ([10, 0, 0, 1], [10, 0, 0, 2]);
+        let (source, destination) = if to_server {
+            (client, server)
+        } else {
+            (server, client)
+        };
+        let (source_port, destination_port) = if to_server {
+            (client_port, 80)
+        } else {
+            (80, client_port)
+        };
+        let mut frame = vec![0; 12];
+        frame.extend([0x08, 0x00, 0x45, 0]);
+        frame.extend((40 + payload.len() as u16).to_be_bytes());
+        frame.extend([0, 0, 0, 0, 64, 6, 0, 0]);
+        frame.extend(source);
+        frame.extend(destination);
+        frame.extend(source_port.to_be_bytes());
+        frame.extend(destination_port.to_be_bytes());
+        frame.extend(sequence.to_be_bytes());
+        frame.extend([0, 0, 0, 0, 0x50, flags as u8, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend(payload);
+        frame
+    }
+
+    fn parse(frame: &[u8], id: usize, timestamp: Duration) -> ParsedPacket {
+        let frame = CapturedFrame {
+            data: frame,
+            timestamp,
+            original_length: frame.len(),
+            link_type: LinkType::ETHERNET,
+            interface_name: None,
+            comments: &[],
+        };
+        Parser::parse_frame(&frame, id, &mut Defragmenter::new())
+    }
+
+    #[test]
+    fn orders_segments() {
+        let mut flow = flow(1000);
+        assert_eq!(flow.segment(1001, ACK, b"GET ", at(1)), b"GET ");
+        assert!(flow.segment(1009, ACK, b"HTTP", at(2)).is_empty());
+        assert!(flow.segment(1013, ACK, b"/1.1", at(2)).is_empty());
+        assert_eq!(flow.pending_bytes, 8);
+        assert_eq!(flow.segment(1005, ACK, b"/ix ", at(3)), b"/ix HTTP/1.1");
+        assert_eq!((flow.bytes, flow.out_of_order, flow.missing), (16, 2, 0));
+        assert!(flow.pending.is_empty());
+        assert_eq!(flow.gap_since, None);
+    }
+
+    #[test]
+    fn drops_retransmitted_bytes() {
+        let mut flow = flow(1000);
+        assert_eq!(flow.segment(1001, ACK, b"abcd", at(1)), b"abcd");
+        assert!(flow.segment(1001, ACK, b"abcd", at(1)).is_empty());
+        assert_eq!(flow.segment(1003, ACK, b"cdef", at(1)), b"ef");
+        // The same out-of-order segment twice is buffered once
+        assert!(flow.segment(1009, ACK, b"ij", at(1)).is_empty());
+        assert!(flow.segment(1009, ACK, b"ij", at(1)).is_empty());
+        assert_eq!(flow.segment(1007, ACK, b"gh", at(1)), b"ghij");
+        assert_eq!((flow.bytes, flow.retransmitted), (10, 8));
+    }
+
+    #[test]
+    fn follows_sequence_numbers_across_wrap() {
+        let mut flow = flow(u32::MAX - 2);
+        assert!(flow.segment(2, ACK, b"cd", at(1)).is_empty());
+        assert_eq!(flow.segment(u32::MAX - 1, ACK, b"ab", at(1)), b"ab");
+        assert_eq!(flow.segment(0, ACK, b"xy", at(1)), b"xycd");
+        assert_eq!(flow.position, 6);
+    }
+
+    #[test]
+    fn skips_gaps_after_timeout() {
+        let mut flow = flow(0);
+        assert_eq!(flow.segment(1, ACK, b"ab", at(1)), b"ab");
+        // Bytes 3 to 12 are lost
+        assert!(flow.segment(13, ACK, b"kl", at(2)).is_empty());
+        assert!(flow.segment(15, ACK, b"mn", at(5)).is_empty());
+        assert_eq!(flow.segment(17, ACK, b"op", at(12)), b"klmnop");
+        assert_eq!((flow.missing, flow.bytes, flow.pending_bytes), (10, 8, 0));
+        // The bytes arriving after all count as retransmitted
+        assert!(flow.segment(3, ACK, b"cdefghij", at(13)).is_empty());
+        assert_eq!(flow.retransmitted, 8);
+    }
+
+    #[test]
+    fn skips_gaps_holding_too_many_segments() {
+        let mut flow = flow(0);
+        for i in 0..MAX_PENDING_SEGMENTS as u32 {
+            assert!(flow.segment(2 + i, ACK, b"a", at(1)).is_empty());
+        }
+        // One more segment than allowed, the first byte is given up on
+        let data = flow.segment(2 + MAX_PENDING_SEGMENTS as u32, ACK, b"a", at(1));
+        assert_eq!(data.len(), MAX_PENDING_SEGMENTS + 1);
+        assert_eq!(flow.missing, 1);
+        assert!(flow.pending.is_empty());
+    }
+
+    #[test]
+    fn skips_gaps_holding_too_many_bytes() {
+        let mut flow = flow(0);
+        let payload = vec![0; MAX_PENDING_BYTES / 4];
+        for i in 0..4 {
+            let sequence = 11 + i * payload.len() as u32;
+            assert!(flow.segment(sequence, ACK, &payload, at(1)).is_empty());
+        }
+        let data = flow.segment(11 + MAX_PENDING_BYTES as u32, ACK, b"a", at(1));
+        assert_eq!(data.len(), MAX_PENDING_BYTES + 1);
+        assert_eq!(flow.missing, 10);
+    }
+
+    #[test]
+    fn finishes_after_fin() {
+        let mut flow = flow(0);
+        assert!(flow.segment(4, FIN_ACK, b"", at(1)).is_empty());
+        assert!(!flow.finished);
+        assert_eq!(flow.segment(1, ACK, b"abc", at(1)), b"abc");
+        assert!(flow.finished);
+        assert_eq!(flow.next, Some(5));
+    }
+
+    #[test]
+    fn reassembles_conversations() {
+        let mut reassembler = TcpReassembler::new();
+        let packets = [
+            packet(0, true, 100, SYN, b""),
+            packet(1, false, 500, SYN | ACK, b""),
+            packet(2, true, 101, ACK, b"hello"),
+            packet(3, false, 501, ACK, b"world"),
+            packet(4, false, 506, ACK, b"!"),
+            packet(5, true, 106, FIN_ACK, b""),
+            packet(6, false, 507, FIN_ACK, b""),
+            // Reused ports start a new stream
+            packet(7, true, 9000, SYN, b""),
+        ];
+        let segments: Vec<StreamSegment> = packets
+            .iter()
+            .map(|packet| reassembler.process(packet).unwrap())
+            .collect();
+
+        assert_eq!(segments[2].data, b"hello");
+        assert_eq!(segments[3].direction, Direction::ToClient);
+        assert!(segments[5].finished && !segments[4].finished);
+        assert_eq!((segments[6].stream, segments[7].stream), (0, 1));
+
+        let stream = reassembler.get_stream(0).unwrap();
+        assert_eq!(stream.get_client(), "10.0.0.1:40000".parse().unwrap());
+        assert_eq!(stream.get_state(), StreamState::Closed);
+        assert_eq!(stream.get_packets(), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            stream.get_chunks(),
+            [
+                StreamChunk {
+                    direction: Direction::ToServer,
+                    data: b"hello".to_vec()
+                },
+                StreamChunk {
+                    direction: Direction::ToClient,
+                    data: b"world!".to_vec()
+                },
+            ]
+        );
+        assert_eq!(reassembler.stream_of(&packets[7]).unwrap().get_index(), 1);
+    }
+
+    #[test]
+    fn reports_missing_bytes_on_segments() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process(&packet(0, true, 100, SYN, b""));
+        reassembler.process(&packet(1, true, 101, ACK, b"ab"));
+        let waiting = reassembler
+            .process(&packet(2, true, 110, ACK, b"xy"))
+            .unwrap();
+        assert!(waiting.data.is_empty());
+        let skipped = reassembler
+            .process(&packet(20, true, 112, ACK, b"z"))
+            .unwrap();
+        assert_eq!((skipped.data.as_slice(), skipped.missing), (&b"xyz"[..], 7));
+        let stream = reassembler.get_stream(0).unwrap();
+        assert_eq!(stream.get_missing(Direction::ToServer), 7);
+        assert_eq!(stream.get_pending(Direction::ToServer), 0);
+    }
+
+    #[test]
+    fn forgets_closed_streams() {
+        let mut reassembler = TcpReassembler::new();
+        for packet in [
+            packet(0, true, 100, SYN, b""),
+            packet(1, false, 500, SYN | ACK, b""),
+            packet(2, true, 101, FIN_ACK, b""),
+            packet(3, false, 501, FIN_ACK, b""),
+        ] {
+            reassembler.process(&packet);
+        }
+        let closed = at(3) + CLOSED_STREAM_RETENTION;
+        assert!(reassembler.prune(closed - at(1)).is_empty());
+        assert_eq!(reassembler.prune(closed), [0]);
+        assert!(reassembler.get_stream(0).is_none());
+        // The same ports start over with the next stream number
+        let segment = reassembler
+            .process(&packet(40, true, 100, ACK, b""))
+            .unwrap();
+        assert_eq!(segment.stream, 1);
+        assert_eq!(reassembler.get_streams().len(), 1);
+    }
+
+    #[test]
+    fn forgets_idle_streams() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process(&packet(0, true, 100, SYN, b""));
+        reassembler.process(&packet(10, true, 101, ACK, b"ab"));
+        // Open streams are kept longer than closed ones
+        assert!(reassembler
+            .prune(at(10) + CLOSED_STREAM_RETENTION)
+            .is_empty());
+        assert!(reassembler.prune(at(9) + STREAM_RETENTION).is_empty());
+        assert_eq!(reassembler.prune(at(10) + STREAM_RETENTION), [0]);
+        assert!(reassembler.get_streams().is_empty());
+    }
+
+    #[test]
+    fn caps_stream_count() {
+        let mut reassembler = TcpReassembler::new();
+        for port in 0..=MAX_STREAMS as u16 {
+            let frame = frame(port, true, 100, SYN, b"");
+            // The first stream is the one seen longest ago
+            let seen = if port == 0 { at(0) } else { at(1) };
+            reassembler.process(&parse(&frame, port.into(), seen));
+        }
+        assert_eq!(reassembler.prune(at(1)), [0]);
+        assert_eq!(reassembler.get_streams().len(), MAX_STREAMS);
+        // Numbers stay those given when the streams started
+        assert!(reassembler.get_stream(0).is_none());
+        let last = reassembler.get_stream(MAX_STREAMS).unwrap();
+        assert_eq!(last.get_client(), "10.0.0.1:4096".parse().unwrap());
+        let packet = parse(&frame(4096, true, 101, ACK, b""), 5000, at(2));
+        assert_eq!(reassembler.process(&packet).unwrap().stream, MAX_STREAMS);
+    }
+}
@@ -0,0 +1,805 @@
+//! TLS decoding on top of reassembled TCP streams
+//!
+//! Records are decoded until ChangeCipherSpec, after which only their type is known. That is
+//! enough to see a TLS 1.2 handshake up to its certificates, while TLS 1.3 encrypts everything
+//! after the server hello, whose hellos still give versions, SNI, ALPN and fingerprints.
+
+use std::collections::HashMap;
+
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use super::{
+    reassembly::{Direction, StreamSegment},
+    wrapers::{
+        is_grease, Level, ParsedPacket, SerializablePacket, SerializableTlsCertificate,
+        SerializableTlsClientHello, SerializableTlsContent, SerializableTlsExtension,
+        SerializableTlsExtensionData, SerializableTlsHandshake, SerializableTlsPacket,
+        SerializableTlsRecord, SerializableTlsServerHello,
+    },
+    x509,
+};
+
+/// Longest record allowed, with the expansion TLS 1.2 leaves room for, RFC 5246 section 6.2.3
+pub const MAX_RECORD_LENGTH: usize = 16384 + 2048;
+/// Handshake messages longer than this, made of many records, are given up on
+const MAX_HANDSHAKE_LENGTH: usize = 1 << 20;
+
+/// What one direction of a stream carries
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Role {
+    /// Too few bytes seen to tell
+    #[default]
+    Unknown,
+    Tls,
+    /// Not TLS, or no longer decodable
+    Other,
+}
+
+/// One direction of a stream
+#[derive(Default)]
+struct HalfStream {
+    role: Role,
+    /// Bytes of a record not fully received yet
+    buffer: Vec<u8>,
+    /// Handshake messages not fully received yet, they may span several records
+    handshake: Vec<u8>,
+    /// Whether ChangeCipherSpec was sent, encrypting what follows
+    encrypted: bool,
+}
+
+#[derive(Default)]
+struct Conversation {
+    to_server: HalfStream,
+    to_client: HalfStream,
+    /// Version picked by the server hello
+    version: Option<u16>,
+}
+
+/// Decodes TLS records from TCP streams, segments are fed in capture order
+#[derive(Default)]
+pub struct TlsTracker {
+    conversations: HashMap<usize, Conversation>,
+}
+
+impl TlsTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop the conversation of stream number `stream`, once the reassembler forgot it
+    pub fn forget(&mut self, stream: usize) {
+        self.conversations.remove(&stream);
+    }
+
+    /// Take in what `packet` added to its stream, adding the records it completes as a layer
+    pub fn process(&mut self, packet: &mut ParsedPacket, segment: &StreamSegment) {
+        let conversation = self.conversations.entry(segment.stream).or_default();
+        let half = match segment.direction {
+            Direction::ToServer => &mut conversation.to_server,
+            Direction::ToClient => &mut conversation.to_client,
+        };
+        // The record being decoded lost bytes, wait for one starting after the gap
+        if segment.missing > 0 {
+            *half = HalfStream {
+                role: Role::Other,
+                ..HalfStream::default()
+            };
+        }
+        let (records, error) = half.receive(&segment.data);
+
+        let messages = records.iter().filter_map(|record| match &record.content {
+            SerializableTlsContent::Handshake(messages) => Some(messages),
+            _ => None,
+        });
+        for message in messages.flatten() {
+            if let SerializableTlsHandshake::ServerHello(hello) = message {
+                conversation.version = Some(hello.selected_version());
+            }
+        }
+        if !records.is_empty() {
+            let version = conversation
+                .version
+                .unwrap_or_else(|| offered_version(&records));
+            packet.push_layer(
+                Level::Application,
+                SerializablePacket::TlsPacket(SerializableTlsPacket { version, records }),
+            );
+        }
+        if let Some(reason) = error {
+            packet.push_layer(
+                Level::Application,
+                SerializablePacket::MalformedPacket(reason.to_string()),
+            );
+        }
+    }
+}
+
+/// Version shown before the server picked one, the highest of the records and client hello
+fn offered_version(records: &[SerializableTlsRecord]) -> u16 {
+    let hellos = records.iter().flat_map(|record| match &record.content {
+        SerializableTlsContent::Handshake(messages) => messages.as_slice(),
+        _ => &[],
+    });
+    let hello_versions = hellos.filter_map(|message| match message {
+        SerializableTlsHandshake::ClientHello(hello) => Some(hello.version),
+        _ => None,
+    });
+    records
+        .iter()
+        .map(|record| record.version)
+        .chain(hello_versions)
+        .max()
+        .unwrap_or_default()
+}
+
+impl HalfStream {
+    /// Decode the records completed by `data`, with the reason decoding stopped if it did
+    fn receive(&mut self, data: &[u8]) -> (Vec<SerializableTlsRecord>, Option<&'static str>) {
+        // Captures may start inside a record, or a record was malformed, segments starting
+        // like a new record resynchronise the direction
+        if self.role == Role::Other && !data.is_empty() && sniff(data) != Role::Other {
+            *self = HalfStream::default();
+        }
+        if self.role == Role::Other {
+            return (Vec::new(), None);
+        }
+        self.buffer.extend_from_slice(data);
+        if self.role == Role::Unknown {
+            self.role = sniff(&self.buffer);
+            if self.role == Role::Other {
+                self.buffer = Vec::new();
+            }
+        }
+        if self.role != Role::Tls {
+            return (Vec::new(), None);
+        }
+
+        let mut records = Vec::new();
+        while let [content_type, major, minor, high, low, ..] = self.buffer[..] {
+            let length = u16::from_be_bytes([high, low]);
+            if !(20..=24).contains(&content_type)
+                || major != 3
+                || length as usize > MAX_RECORD_LENGTH
+            {
+                return (records, Some(self.give_up("Malformed TLS record")));
+            }
+            if self.buffer.len() < 5 + length as usize {
+                break;
+            }
+            let fragment: Vec<u8> = self.buffer.drain(..5 + length as usize).skip(5).collect();
+            let content = match self.content(content_type, &fragment) {
+                Ok(content) => content,
+                Err(reason) => return (records, Some(self.give_up(reason))),
+            };
+            records.push(SerializableTlsRecord {
+                content_type,
+                version: u16::from_be_bytes([major, minor]),
+                length,
+                content,
+            });
+        }
+        (records, None)
+    }
+
+    /// Decode the fragment of a record
+    fn content(
+        &mut self,
+        content_type: u8,
+        fragment: &[u8],
+    ) -> Result<SerializableTlsContent, &'static str> {
+        let content = match content_type {
+            20 => {
+                self.encrypted = true;
+                SerializableTlsContent::ChangeCipherSpec
+            }
+            21 => match fragment {
+                &[level, description] if !self.encrypted => {
+                    SerializableTlsContent::Alert { level, description }
+                }
+                _ => SerializableTlsContent::Encrypted,
+            },
+            22 if self.encrypted => SerializableTlsContent::Encrypted,
+            22 => {
+                self.handshake.extend_from_slice(fragment);
+                let mut messages = Vec::new();
+                while let [handshake_type, a, b, c, ..] = self.handshake[..] {
+                    let length = u32::from_be_bytes([0, a, b, c]) as usize;
+                    if length > MAX_HANDSHAKE_LENGTH {
+                        return Err("TLS handshake message too long");
+                    }
+                    if self.handshake.len() < 4 + length {
+                        break;
+                    }
+                    let body: Vec<u8> = self.handshake.drain(..4 + length).skip(4).collect();
+                    let message = handshake(handshake_type, &body)
+                        .ok_or("Malformed TLS handshake message")?;
+                    messages.push(message);
+                }
+                SerializableTlsContent::Handshake(messages)
+            }
+            23 => SerializableTlsContent::ApplicationData,
+            _ => SerializableTlsContent::Heartbeat,
+        };
+        Ok(content)
+    }
+
+    /// Stop decoding a direction that went off the rails
+    fn give_up(&mut self, reason: &'static str) -> &'static str {
+        *self = HalfStream {
+            role: Role::Other,
+            ..HalfStream::default()
+        };
+        reason
+    }
+}
+
+/// Tell TLS from other protocols by the first record header of a direction
+fn sniff(buffer: &[u8]) -> Role {
+    match buffer {
+        [] | [20..=24] | [20..=24, 3] => Role::Unknown,
+        [20..=24, 3, 0..=4, ..] => Role::Tls,
+        _ => Role::Other,
+    }
+}
+
+/// Reads the vectors and integers of handshake messages, RFC 8446 section 3
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(..length)?;
+        self.data = &self.data[length..];
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let &[a, b] = self.bytes(2)? else {
+            return None;
+        };
+        Some(u16::from_be_bytes([a, b]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        let &[a, b, c] = self.bytes(3)? else {
+            return None;
+        };
+        Some(u32::from_be_bytes([0, a, b, c]) as usize)
+    }
+
+    /// Read a vector with a one byte length
+    fn vector8(&mut self) -> Option<Reader<'a>> {
+        let length = self.u8()? as usize;
+        self.bytes(length).map(|data| Reader { data })
+    }
+
+    /// Read a vector with a two byte length
+    fn vector16(&mut self) -> Option<Reader<'a>> {
+        let length = self.u16()? as usize;
+        self.bytes(length).map(|data| Reader { data })
+    }
+
+    /// Read a vector with a three byte length
+    fn vector24(&mut self) -> Option<Reader<'a>> {
+        let length = self.u24()?;
+        self.bytes(length).map(|data| Reader { data })
+    }
+
+    /// Read the rest as a list of `u16`
+    fn u16s(mut self) -> Option<Vec<u16>> {
+        let mut values = Vec::new();
+        while !self.is_empty() {
+            values.push(self.u16()?);
+        }
+        Some(values)
+    }
+}
+
+/// Decode a handshake message, `None` if it is malformed
+fn handshake(handshake_type: u8, body: &[u8]) -> Option<SerializableTlsHandshake> {
+    let mut reader = Reader { data: body };
+    let message = match handshake_type {
+        1 => SerializableTlsHandshake::ClientHello(client_hello(&mut reader)?),
+        2 => SerializableTlsHandshake::ServerHello(server_hello(&mut reader)?),
+        11 => SerializableTlsHandshake::Certificate(certificates(&mut reader)?),
+        _ => SerializableTlsHandshake::Other {
+            handshake_type,
+            length: body.len() as u32,
+        },
+    };
+    Some(message)
+}
+
+fn client_hello(reader: &mut Reader) -> Option<SerializableTlsClientHello> {
+    let version = reader.u16()?;
+    let random = reader.bytes(32)?.to_vec();
+    let session_id = reader.vector8()?.data.to_vec();
+    let cipher_suites = reader.vector16()?.u16s()?;
+    let compression_methods = reader.vector8()?.data.to_vec();
+    let extensions = extensions(reader, true)?;
+    let mut hello = SerializableTlsClientHello {
+        version,
+        random,
+        session_id,
+        cipher_suites,
+        compression_methods,
+        extensions,
+        ja3_full: String::new(),
+        ja3: String::new(),
+        ja4: String::new(),
+    };
+    hello.ja3_full = ja3(&hello);
+    hello.ja3 = format!("{:x}", Md5::digest(hello.ja3_full.as_bytes()));
+    hello.ja4 = ja4(&hello);
+    Some(hello)
+}
+
+fn server_hello(reader: &mut Reader) -> Option<SerializableTlsServerHello> {
+    let version = reader.u16()?;
+    let random = reader.bytes(32)?.to_vec();
+    let session_id = reader.vector8()?.data.to_vec();
+    let cipher_suite = reader.u16()?;
+    let compression_method = reader.u8()?;
+    let extensions = extensions(reader, false)?;
+    let ja3s_full = format!(
+        "{},{},{}",
+        version,
+        cipher_suite,
+        join(extensions.iter().map(|extension| extension.extension_type))
+    );
+    Some(SerializableTlsServerHello {
+        version,
+        random,
+        session_id,
+        cipher_suite,
+        compression_method,
+        extensions,
+        ja3s: format!("{:x}", Md5::digest(ja3s_full.as_bytes())),
+        ja3s_full,
+    })
+}
+
+/// Decode the extensions closing a hello, which may be left out entirely
+fn extensions(reader: &mut Reader, client: bool) -> Option<Vec<SerializableTlsExtension>> {
+    if reader.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut list = reader.vector16()?;
+    let mut extensions = Vec::new();
+    while !list.is_empty() {
+        let extension_type = list.u16()?;
+        let data = list.vector16()?.data;
+        // Extensions the hellos do not depend on are kept raw when they do not decode
+        let decoded = extension_data(extension_type, Reader { data }, client);
+        extensions.push(SerializableTlsExtension {
+            extension_type,
+            data: decoded.unwrap_or_else(|| SerializableTlsExtensionData::Unknown(data.to_vec())),
+        });
+    }
+    Some(extensions)
+}
+
+fn extension_data(
+    extension_type: u16,
+    mut data: Reader,
+    client: bool,
+) -> Option<SerializableTlsExtensionData> {
+    let decoded = match extension_type {
+        0 => {
+            let mut names = Vec::new();
+            // Servers acknowledge the name with an empty extension
+            if !data.is_empty() {
+                let mut list = data.vector16()?;
+                while !list.is_empty() {
+                    let name_type = list.u8()?;
+                    let name = list.vector16()?.data;
+                    if name_type == 0 {
+                        names.push(String::from_utf8_lossy(name).into_owned());
+                    }
+                }
+            }
+            SerializableTlsExtensionData::ServerName(names)
+        }
+        10 => SerializableTlsExtensionData::SupportedGroups(data.vector16()?.u16s()?),
+        11 => SerializableTlsExtensionData::EcPointFormats(data.vector8()?.data.to_vec()),
+        13 => SerializableTlsExtensionData::SignatureAlgorithms(data.vector16()?.u16s()?),
+        16 => {
+            let mut list = data.vector16()?;
+            let mut protocols = Vec::new();
+            while !list.is_empty() {
+                protocols.push(String::from_utf8_lossy(list.vector8()?.data).into_owned());
+            }
+            SerializableTlsExtensionData::Alpn(protocols)
+        }
+        43 if client => SerializableTlsExtensionData::SupportedVersions(data.vector8()?.u16s()?),
+        43 => SerializableTlsExtensionData::SupportedVersions(vec![data.u16()?]),
+        51 if client => {
+            let mut list = data.vector16()?;
+            let mut groups = Vec::new();
+            while !list.is_empty() {
+                groups.push(list.u16()?);
+                list.vector16()?;
+            }
+            SerializableTlsExtensionData::KeyShare(groups)
+        }
+        // A hello retry request only names the group
+        51 => SerializableTlsExtensionData::KeyShare(vec![data.u16()?]),
+        _ => return None,
+    };
+    Some(decoded)
+}
+
+/// Decode a certificate chain, RFC 5246 section 7.4.2
+fn certificates(reader: &mut Reader) -> Option<Vec<SerializableTlsCertificate>> {
+    let mut list = reader.vector24()?;
+    let mut certificates = Vec::new();
+    while !list.is_empty() {
+        certificates.push(x509::parse_certificate(list.vector24()?.data)?);
+    }
+    Some(certificates)
+}
+
+/// Join values that are not GREASE with `-`, as JA3 lists them
+fn join(values: impl Iterator<Item = u16>) -> String {
+    values
+        .filter(|&value| !is_grease(value))
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn supported_groups(hello: &SerializableTlsClientHello) -> &[u16] {
+    hello
+        .extensions
+        .iter()
+        .find_map(|extension| match &extension.data {
+            SerializableTlsExtensionData::SupportedGroups(groups) => Some(groups.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// JA3 fingerprint of a client hello before hashing
+fn ja3(hello: &SerializableTlsClientHello) -> String {
+    let formats = hello
+        .extensions
+        .iter()
+        .find_map(|extension| match &extension.data {
+            SerializableTlsExtensionData::EcPointFormats(formats) => Some(formats.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    format!(
+        "{},{},{},{},{}",
+        hello.version,
+        join(hello.cipher_suites.iter().copied()),
+        join(
+            hello
+                .extensions
+                .iter()
+                .map(|extension| extension.extension_type)
+        ),
+        join(supported_groups(hello).iter().copied()),
+        join(formats.iter().map(|&format| format as u16))
+    )
+}
+
+/// JA4 fingerprint of a client hello, e.g. `t13d1516h2_8daaf6152771_e5627efa2ab1`
+fn ja4(hello: &SerializableTlsClientHello) -> String {
+    let versions = hello
+        .extensions
+        .iter()
+        .find_map(|extension| match &extension.data {
+            SerializableTlsExtensionData::SupportedVersions(versions) => Some(versions),
+            _ => None,
+        });
+    let version = versions
+        .and_then(|versions| versions.iter().filter(|&&v| !is_grease(v)).max().copied())
+        .unwrap_or(hello.version);
+    let version = match version {
+        0x0304 => "13",
+        0x0303 => "12",
+        0x0302 => "11",
+        0x0301 => "10",
+        0x0300 => "s3",
+        0x0002 => "s2",
+        _ => "00",
+    };
+    let sni = if hello.server_name().is_some() {
+        'd'
+    } else {
+        'i'
+    };
+    let ciphers: Vec<u16> = hello
+        .cipher_suites
+        .iter()
+        .copied()
+        .filter(|&suite| !is_grease(suite))
+        .collect();
+    let extensions: Vec<u16> = hello
+        .extensions
+        .iter()
+        .map(|extension| extension.extension_type)
+        .filter(|&extension| !is_grease(extension))
+        .collect();
+    // First and last characters of the first protocol, in hex unless they are alphanumeric
+    let alpn = match hello.alpn().first().map(String::as_bytes) {
+        Some([first, .., last]) | Some([first @ last]) => {
+            if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+                format!("{}{}", *first as char, *last as char)
+            } else {
+                let first = format!("{:02x}", first);
+                let last = format!("{:02x}", last);
+                format!("{}{}", &first[..1], &last[1..])
+            }
+        }
+        _ => "00".to_string(),
+    };
+
+    let mut sorted_ciphers = ciphers.clone();
+    sorted_ciphers.sort_unstable();
+    let mut sorted_extensions: Vec<u16> = extensions
+        .iter()
+        .copied()
+        .filter(|&extension| extension != 0 && extension != 16)
+        .collect();
+    sorted_extensions.sort_unstable();
+    let algorithms = hello
+        .extensions
+        .iter()
+        .find_map(|extension| match &extension.data {
+            SerializableTlsExtensionData::SignatureAlgorithms(algorithms) => Some(algorithms),
+            _ => None,
+        })
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|&algorithm| !is_grease(algorithm))
+        .collect::<Vec<_>>();
+    let mut extension_list = hex_list(&sorted_extensions);
+    if !algorithms.is_empty() {
+        extension_list += "_";
+        extension_list += &hex_list(&algorithms);
+    }
+
+    format!(
+        "t{}{}{:02}{:02}{}_{}_{}",
+        version,
+        sni,
+        ciphers.len().min(99),
+        extensions.len().min(99),
+        alpn,
+        truncated_sha256(&sorted_ciphers, &hex_list(&sorted_ciphers)),
+        truncated_sha256(&sorted_extensions, &extension_list)
+    )
+}
+
+/// Format values as four hex digits separated by commas, as JA4 hashes them
+fn hex_list(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:04x}", value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// First 12 hex digits of the SHA-256 of `text`, zeros when `values` is empty
+fn truncated_sha256(values: &[u16], text: &str) -> String {
+    if values.is_empty() {
+        return "0".repeat(12);
+    }
+    format!("{:x}", Sha256::digest(text.as_bytes()))[..12].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The client hello of the example in the JA3 README, for example.com, random zeroed
+    const JA3_CLIENT_HELLO: &str = concat!(
+        "01000067030100000000000000000000000000000000000000000000000000000000000000000000",
+        "18002f00350005000ac009c00ac013c01400320038001300040100002600000010000e00000b6578",
+        "616d706c652e636f6d000a00080006001700180019000b00020100",
+    );
+    /// A Chrome client hello with the cipher suites, extensions and signature algorithms of the
+    /// example in the JA4 specification, GREASE values included, random and key share zeroed
+    const CHROME_CLIENT_HELLO: &str = concat!(
+        "01000120030300000000000000000000000000000000000000000000000000000000000000000000",
+        "204a4a130113021303c02bc02fc02cc030cca9cca8c013c014009c009d002f0035010000d70a0a00",
+        "0000000010000e00000b6578616d706c652e636f6d00170000ff01000100000a000a00081a1a001d",
+        "00170018000b00020100002300000010000e000c02683208687474702f312e310005000501000000",
+        "00000d0012001004030804040105030805050108060601001200000033002b00292a2a000100001d",
+        "00200000000000000000000000000000000000000000000000000000000000000000002d00020101",
+        "002b0007063a3a03040303001b000302000244690005000302683200150010000000000000000000",
+        "00000000000000fafa000100",
+    );
+    /// A TLS 1.3 server hello picking TLS_AES_128_GCM_SHA256 and x25519
+    const SERVER_HELLO: &str = concat!(
+        "02000056030300000000000000000000000000000000000000000000000000000000000000000013",
+        "0100002e002b0002030400330024001d002000000000000000000000000000000000000000000000",
+        "00000000000000000000",
+    );
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Wrap handshake messages in a record
+    fn record(messages: &[u8]) -> Vec<u8> {
+        let mut record = vec![22, 3, 1];
+        record.extend((messages.len() as u16).to_be_bytes());
+        record.extend(messages);
+        record
+    }
+
+    fn client_hello_of(message: &[u8]) -> SerializableTlsClientHello {
+        match handshake(message[0], &message[4..]) {
+            Some(SerializableTlsHandshake::ClientHello(hello)) => hello,
+            _ => panic!("client hello not decoded"),
+        }
+    }
+
+    /// Feed `data` to a tracker as what stream 0 carried in `direction`
+    fn feed(
+        tracker: &mut TlsTracker,
+        id: usize,
+        direction: Direction,
+        data: &[u8],
+    ) -> ParsedPacket {
+        let mut packet = ParsedPacket::new(id);
+        let segment = StreamSegment {
+            stream: 0,
+            direction,
+            data: data.to_vec(),
+            missing: 0,
+            finished: false,
+        };
+        tracker.process(&mut packet, &segment);
+        packet
+    }
+
+    #[test]
+    fn fingerprints_client_hellos() {
+        let hello = client_hello_of(&bytes(JA3_CLIENT_HELLO));
+        assert_eq!(
+            hello.ja3_full,
+            "769,47-53-5-10-49161-49162-49171-49172-50-56-19-4,0-10-11,23-24-25,0"
+        );
+        assert_eq!(hello.ja3, "ada70206e40642a3e4461f35503241d5");
+
+        let hello = client_hello_of(&bytes(CHROME_CLIENT_HELLO));
+        assert_eq!(hello.ja4, "t13d1516h2_8daaf6152771_e5627efa2ab1");
+    }
+
+    #[test]
+    fn leaves_grease_out_of_fingerprints() {
+        let hello = client_hello_of(&bytes(CHROME_CLIENT_HELLO));
+        // GREASE values are decoded, and skipped when fingerprinting
+        assert_eq!(hello.cipher_suites[0], 0x4a4a);
+        assert_eq!(hello.extensions[0].extension_type, 0x0a0a);
+        assert_eq!(
+            hello.ja3_full,
+            "771,4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172-156-157-47-53,\
+             0-23-65281-10-11-35-16-5-13-18-51-45-43-27-17513-21,29-23-24,0"
+        );
+        assert!(is_grease(0x0a0a) && is_grease(0xfafa));
+        assert!(!is_grease(0x0a0b) && !is_grease(0x1a0a) && !is_grease(0x1301));
+    }
+
+    #[test]
+    fn extracts_server_name_and_alpn() {
+        let hello = client_hello_of(&bytes(CHROME_CLIENT_HELLO));
+        assert_eq!(hello.server_name(), Some("example.com"));
+        assert_eq!(hello.alpn(), ["h2", "http/1.1"]);
+        let versions = hello
+            .extensions
+            .iter()
+            .find_map(|extension| match &extension.data {
+                SerializableTlsExtensionData::SupportedVersions(versions) => {
+                    Some(versions.as_slice())
+                }
+                _ => None,
+            });
+        assert_eq!(versions, Some(&[0x3a3a, 0x0304, 0x0303][..]));
+
+        let hello = client_hello_of(&bytes(JA3_CLIENT_HELLO));
+        assert_eq!(hello.server_name(), Some("example.com"));
+        assert!(hello.alpn().is_empty());
+        assert!(hello.ja4.starts_with("t10d120300_"));
+    }
+
+    #[test]
+    fn follows_handshakes_across_records_and_segments() {
+        let mut tracker = TlsTracker::new();
+        let hello = record(&bytes(CHROME_CLIENT_HELLO));
+        let first = feed(&mut tracker, 0, Direction::ToServer, &hello[..100]);
+        assert!(first.find::<SerializableTlsPacket>().is_none());
+        let last = feed(&mut tracker, 1, Direction::ToServer, &hello[100..]);
+        let tls = last.find::<SerializableTlsPacket>().unwrap();
+        assert_eq!(tls.version, 0x0303);
+        assert!(matches!(
+            &tls.records[0].content,
+            SerializableTlsContent::Handshake(messages)
+                if matches!(&messages[..], [SerializableTlsHandshake::ClientHello(_)])
+        ));
+
+        // The server hello tells the version picked, records show it from then on
+        let server = feed(
+            &mut tracker,
+            2,
+            Direction::ToClient,
+            &record(&bytes(SERVER_HELLO)),
+        );
+        let tls = server.find::<SerializableTlsPacket>().unwrap();
+        assert_eq!(tls.version, 0x0304);
+        let SerializableTlsContent::Handshake(messages) = &tls.records[0].content else {
+            panic!("server hello not decoded");
+        };
+        let SerializableTlsHandshake::ServerHello(hello) = &messages[0] else {
+            panic!("server hello not decoded");
+        };
+        assert_eq!(
+            (hello.cipher_suite, hello.selected_version()),
+            (0x1301, 0x0304)
+        );
+        assert_eq!(hello.ja3s_full, "771,4865,43-51");
+    }
+
+    #[test]
+    fn survives_truncated_extensions() {
+        let hello = bytes(JA3_CLIENT_HELLO);
+        // Every prefix of the hello body, whatever it cuts, is decoded or rejected
+        for length in 0..hello.len() - 4 {
+            let _ = handshake(1, &hello[4..4 + length]);
+        }
+        let chrome = bytes(CHROME_CLIENT_HELLO);
+        for length in 0..chrome.len() - 4 {
+            let _ = handshake(1, &chrome[4..4 + length]);
+        }
+
+        // The server name list says 15 bytes, the extension holds 14, the name is kept raw
+        let mut hello = bytes(JA3_CLIENT_HELLO);
+        let list = hello
+            .windows(2)
+            .position(|window| window == [0, 14])
+            .unwrap();
+        hello[list + 1] = 15;
+        let hello = client_hello_of(&hello);
+        assert_eq!(hello.server_name(), None);
+        assert!(matches!(
+            hello.extensions[0].data,
+            SerializableTlsExtensionData::Unknown(_)
+        ));
+        assert!(hello.ja4.starts_with("t10i"));
+
+        // The extensions run past the end of the hello, the record is reported malformed
+        let mut message = bytes(JA3_CLIENT_HELLO);
+        // The length of the 38 bytes of extensions
+        let length = message.len() - 39;
+        message[length] += 1;
+        let packet = feed(
+            &mut TlsTracker::new(),
+            0,
+            Direction::ToServer,
+            &record(&message),
+        );
+        assert!(matches!(
+            packet.get_application_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason))
+                if reason == "Malformed TLS handshake message"
+        ));
+    }
+}
@@ -1,20 +1,34 @@
 use std::net::IpAddr;
 
 use pnet::packet::{
+    Packet,
     ip::{IpNextHeaderProtocol, IpNextHeaderProtocols},
     tcp::TcpPacket,
     udp::UdpPacket,
-    Packet, icmp::{IcmpPacket, IcmpTypes, echo_reply, echo_request}, icmpv6::Icmpv6Packet,
 };
 
+use super::{
+    defrag::Defragmenter,
+    dhcp::{handle_dhcp_packet, is_dhcp},
+    dhcpv6::{handle_dhcpv6_packet, is_dhcpv6},
+    dns::{dns_service, handle_dns_packet, handle_dns_tcp_segment},
+    icmp::handle_icmp_packet,
+    icmpv6::handle_icmpv6_packet,
+    tunnel::{
+        handle_geneve_packet, handle_gre_packet, handle_ip_in_ip, handle_vxlan_packet, GENEVE_PORT,
+        VXLAN_PORT,
+    },
+};
 use super::wrapers::{
-    ParsedPacket, SerializablePacket, SerializableTcpPacket, SerializableUdpPacket, SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket, SerializableIcmpv6Packet,
+    ParsedPacket, SerializablePacket, SerializableTcpPacket, SerializableUdpPacket,
 };
 
 pub fn handle_udp_packet(
-    source: IpAddr,
-    destination: IpAddr,
+    _source: IpAddr,
+    _destination: IpAddr,
     packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
     parsed_packet: &mut ParsedPacket,
 ) {
     let udp = UdpPacket::new(packet);
@@ -23,6 +37,37 @@ pub fn handle_udp_packet(
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(
             SerializableUdpPacket::from(&udp),
         )));
+        parsed_packet.add_field_ranges(
+            offset,
+            packet.len(),
+            &[
+                ("udp", 0, 8),
+                ("udp.srcport", 0, 2),
+                ("udp.dstport", 2, 2),
+                ("udp.length", 4, 2),
+                ("udp.checksum", 6, 2),
+                ("udp.payload", 8, packet.len().saturating_sub(8)),
+            ],
+        );
+
+        match udp.get_destination() {
+            VXLAN_PORT => {
+                handle_vxlan_packet(udp.payload(), offset + 8, defragmenter, parsed_packet)
+            }
+            GENEVE_PORT => {
+                handle_geneve_packet(udp.payload(), offset + 8, defragmenter, parsed_packet)
+            }
+            _ => {
+                let (source, destination) = (udp.get_source(), udp.get_destination());
+                if let Some(service) = dns_service(source, destination) {
+                    handle_dns_packet(service, udp.payload(), offset + 8, parsed_packet);
+                } else if is_dhcp(source, destination) {
+                    handle_dhcp_packet(udp.payload(), offset + 8, parsed_packet);
+                } else if is_dhcpv6(source, destination) {
+                    handle_dhcpv6_packet(udp.payload(), offset + 8, parsed_packet);
+                }
+            }
+        }
 
         // handle_application_protocol(
         //     source,
@@ -40,14 +85,12 @@ pub fn handle_udp_packet(
     }
 }
 
-const ACK_BIT_SHIFT: usize = 4;
-const FIN_BIT_SHIFT: usize = 0;
-
 /// Build a TCP packet from a network-layer packet, save it in a Parsed Packet
 pub fn handle_tcp_packet(
-    source: IpAddr,
-    destination: IpAddr,
+    _source: IpAddr,
+    _destination: IpAddr,
     packet: &[u8],
+    offset: usize,
     parsed_packet: &mut ParsedPacket,
 ) {
     let tcp = TcpPacket::new(packet);
@@ -55,19 +98,41 @@ pub fn handle_tcp_packet(
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(
             SerializableTcpPacket::from(&tcp),
         )));
+        let header_length = tcp.get_data_offset() as usize * 4;
+        parsed_packet.add_field_ranges(
+            offset,
+            packet.len(),
+            &[
+                ("tcp", 0, header_length),
+                ("tcp.srcport", 0, 2),
+                ("tcp.dstport", 2, 2),
+                ("tcp.seq", 4, 4),
+                ("tcp.ack", 8, 4),
+                ("tcp.hdr_len", 12, 1),
+                ("tcp.flags", 12, 2),
+                ("tcp.window_size", 14, 2),
+                ("tcp.checksum", 16, 2),
+                ("tcp.urgent_pointer", 18, 2),
+                ("tcp.options", 20, header_length.saturating_sub(20)),
+                (
+                    "tcp.payload",
+                    header_length,
+                    packet.len().saturating_sub(header_length),
+                ),
+            ],
+        );
 
-        // let flags = tcp.get_flags();
-        // let is_fin = (flags & (1 << ACK_BIT_SHIFT)) != 0 && (flags & (1 << FIN_BIT_SHIFT)) != 0;
+        if let Some(service) = dns_service(tcp.get_source(), tcp.get_destination()) {
+            let header_length = header_length.min(packet.len());
+            handle_dns_tcp_segment(
+                service,
+                &packet[header_length..],
+                offset + header_length,
+                parsed_packet,
+            );
+        }
 
-        // handle_application_protocol(
-        //     source,
-        //     tcp.get_source(),
-        //     destination,
-        //     tcp.get_destination(),
-        //     is_fin,
-        //     tcp.payload(),
-        //     parsed_packet,
-        // );
+        // Segments are put back in order by `reassembly::TcpReassembler`, which sees every packet
     } else {
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
             "Malformed TCP Packet".to_string(),
@@ -81,78 +146,34 @@ pub fn handle_transport_protocol(
     destination: IpAddr,
     protocol: IpNextHeaderProtocol,
     packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
     parsed_packet: &mut ParsedPacket,
 ) {
-    return match protocol {
-        IpNextHeaderProtocols::Udp => handle_udp_packet(source, destination, packet, parsed_packet),
-        IpNextHeaderProtocols::Tcp => handle_tcp_packet(source, destination, packet, parsed_packet),
+    match protocol {
+        IpNextHeaderProtocols::Udp => handle_udp_packet(
+            source,
+            destination,
+            packet,
+            offset,
+            defragmenter,
+            parsed_packet,
+        ),
+        IpNextHeaderProtocols::Gre => {
+            handle_gre_packet(packet, offset, defragmenter, parsed_packet)
+        }
+        IpNextHeaderProtocols::Ipv4 | IpNextHeaderProtocols::Ipv6 => {
+            handle_ip_in_ip(packet, offset, defragmenter, parsed_packet)
+        }
+        IpNextHeaderProtocols::Tcp => {
+            handle_tcp_packet(source, destination, packet, offset, parsed_packet)
+        }
         IpNextHeaderProtocols::Icmp => {
-            handle_icmp_packet(source, destination, packet, parsed_packet)
+            handle_icmp_packet(source, destination, packet, offset, parsed_packet)
         }
         IpNextHeaderProtocols::Icmpv6 => {
-            handle_icmpv6_packet(source, destination, packet, parsed_packet)
+            handle_icmpv6_packet(source, destination, packet, offset, parsed_packet)
         }
         _ => {}
-    };
-}
-
-/// Build a ICMP packet from a network-layer packet, save it in a Parsed Packet
-pub fn handle_icmp_packet(
-    source: IpAddr,
-    destination: IpAddr,
-    packet: &[u8],
-    parsed_packet: &mut ParsedPacket,
-) {
-    let icmp_packet = IcmpPacket::new(packet);
-    if let Some(icmp_packet) = icmp_packet {
-        match icmp_packet.get_icmp_type() {
-            IcmpTypes::EchoReply => {
-                let echo_reply_packet = echo_reply::EchoReplyPacket::new(packet).unwrap();
-
-                parsed_packet.set_transport_layer_packet(Some(
-                    SerializablePacket::EchoReplyPacket(SerializableEchoReplyPacket::from(
-                        &echo_reply_packet,
-                    )),
-                ));
-            }
-            IcmpTypes::EchoRequest => {
-                let echo_request_packet = echo_request::EchoRequestPacket::new(packet).unwrap();
-
-                parsed_packet.set_transport_layer_packet(Some(
-                    SerializablePacket::EchoRequestPacket(SerializableEchoRequestPacket::from(
-                        &echo_request_packet,
-                    )),
-                ));
-            }
-            _ => {
-                parsed_packet.set_transport_layer_packet(Some(SerializablePacket::IcmpPacket(
-                    SerializableIcmpPacket::from(&icmp_packet),
-                )));
-            }
-        }
-    } else {
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
-            "Malformed ICMP Packet".to_string(),
-        )));
-    }
-}
-
-/// Build a ICMPv6 packet from a network-layer packet, save it in a Parsed Packet
-pub fn handle_icmpv6_packet(
-    source: IpAddr,
-    destination: IpAddr,
-    packet: &[u8],
-    parsed_packet: &mut ParsedPacket,
-) {
-    let icmpv6_packet = Icmpv6Packet::new(packet);
-    if let Some(icmpv6_packet) = icmpv6_packet {
-
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::Icmpv6Packet(
-            SerializableIcmpv6Packet::from(&icmpv6_packet),
-        )));
-    } else {
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
-            "Malformed ICMPv6 Packet".to_string(),
-        )));
     }
 }
@@ -0,0 +1,442 @@
+//! Tunnel decapsulation, the packets tunnels carry are decoded like captured ones
+//!
+//! Layers decoded after entering a tunnel are one level deeper in the layer stack, so the
+//! layer getters, the summary and stream reassembly see the innermost packet.
+
+use pnet::{
+    packet::ethernet::{EtherType, EtherTypes, EthernetPacket},
+    util::MacAddr,
+};
+
+use super::{
+    defrag::Defragmenter,
+    handle_ethernet_frame, handle_ethertype, handle_ip_packet,
+    wrapers::{
+        ParsedPacket, SerializableGeneveOption, SerializableGenevePacket, SerializableGrePacket,
+        SerializablePacket, SerializableVxlanPacket, TRANSPARENT_ETHERNET_BRIDGING,
+    },
+};
+
+/// UDP port of VXLAN, RFC 7348
+pub const VXLAN_PORT: u16 = 4789;
+/// UDP port of Geneve, RFC 8926
+pub const GENEVE_PORT: u16 = 6081;
+/// Tunnels nested deeper than this are not decapsulated, so crafted packets can't recurse
+/// for long
+pub const MAX_TUNNEL_DEPTH: usize = 8;
+
+/// Decode a GRE header and the packet it carries
+pub fn handle_gre_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some(&[flags, version, _, _]) = packet.get(..4) else {
+        return set_malformed_transport(parsed_packet, "Malformed GRE Packet");
+    };
+    // Source routing was deprecated by RFC 2784 and its routing entries are not decoded
+    if flags & 0x40 != 0 {
+        return set_malformed_transport(parsed_packet, "GRE source routing is not supported");
+    }
+    let version = version & 0b111;
+    let mut fields = vec![("gre.flags", 0, 2), ("gre.proto", 2, 2)];
+
+    // Optional words follow in this order, the checksum word ends with a reserved half
+    let optional = [
+        (flags & 0x80 != 0, "gre.checksum", 2),
+        (flags & 0x20 != 0, "gre.key", 4),
+        (flags & 0x10 != 0, "gre.seq", 4),
+        // Version 1 is PPTP's enhanced GRE, with the acknowledgement flag in the second byte
+        (version == 1 && packet[1] & 0x80 != 0, "gre.ack", 4),
+    ];
+    let mut words = [None; 4];
+    let mut start = 4;
+    for (i, (present, field, length)) in optional.into_iter().enumerate() {
+        if !present {
+            continue;
+        }
+        let Some(word) = word(packet, start) else {
+            return set_malformed_transport(parsed_packet, "Malformed GRE Packet");
+        };
+        words[i] = Some(word);
+        fields.push((field, start, length));
+        start += 4;
+    }
+    let [checksum, key, sequence, acknowledgement] = words;
+    let gre = SerializableGrePacket {
+        version,
+        protocol_type: u16::from_be_bytes([packet[2], packet[3]]),
+        checksum: checksum.map(|word| (word >> 16) as u16),
+        key,
+        sequence,
+        acknowledgement,
+    };
+
+    fields.insert(0, ("gre", 0, start));
+    parsed_packet.add_field_ranges(offset, packet.len(), &fields);
+    let protocol_type = gre.protocol_type;
+    parsed_packet.set_transport_layer_packet(Some(SerializablePacket::GrePacket(gre)));
+    handle_tunnel_payload(
+        protocol_type,
+        &packet[start..],
+        offset + start,
+        defragmenter,
+        parsed_packet,
+    );
+}
+
+/// Decode an IPv4 or IPv6 packet carried directly in IP, protocol 4 or 41
+pub fn handle_ip_in_ip(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    if parsed_packet.get_tunnel_depth() >= MAX_TUNNEL_DEPTH {
+        return;
+    }
+    parsed_packet.enter_tunnel();
+    handle_ip_packet(packet, offset, defragmenter, parsed_packet);
+}
+
+/// Decode a VXLAN header and the Ethernet frame it carries
+pub fn handle_vxlan_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some(&[flags, _, _, _, a, b, c, _]) = packet.get(..8) else {
+        return set_malformed_application(parsed_packet, "Malformed VXLAN Packet");
+    };
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::VxlanPacket(
+        SerializableVxlanPacket {
+            flags,
+            vni: u32::from_be_bytes([0, a, b, c]),
+        },
+    )));
+    parsed_packet.add_field_ranges(
+        offset,
+        packet.len(),
+        &[("vxlan", 0, 8), ("vxlan.flags", 0, 1), ("vxlan.vni", 4, 3)],
+    );
+    handle_tunnel_payload(
+        TRANSPARENT_ETHERNET_BRIDGING,
+        &packet[8..],
+        offset + 8,
+        defragmenter,
+        parsed_packet,
+    );
+}
+
+/// Decode a Geneve header with its options and the packet it carries
+pub fn handle_geneve_packet(
+    packet: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some(&[first, flags, p0, p1, a, b, c, _]) = packet.get(..8) else {
+        return set_malformed_application(parsed_packet, "Malformed Geneve Packet");
+    };
+    let length = 8 + (first & 0x3f) as usize * 4;
+    let Some(mut options) = packet.get(8..length) else {
+        return set_malformed_application(parsed_packet, "Malformed Geneve Packet");
+    };
+
+    let mut geneve = SerializableGenevePacket {
+        version: first >> 6,
+        oam: flags & 0x80 != 0,
+        critical: flags & 0x40 != 0,
+        protocol_type: u16::from_be_bytes([p0, p1]),
+        vni: u32::from_be_bytes([0, a, b, c]),
+        options: Vec::new(),
+    };
+    while let Some(&[c0, c1, option_type, option_length]) = options.get(..4) {
+        let end = 4 + (option_length & 0x1f) as usize * 4;
+        let Some(data) = options.get(4..end) else {
+            return set_malformed_application(parsed_packet, "Malformed Geneve Option");
+        };
+        geneve.options.push(SerializableGeneveOption {
+            class: u16::from_be_bytes([c0, c1]),
+            option_type,
+            data: data.to_vec(),
+        });
+        options = &options[end..];
+    }
+
+    let protocol_type = geneve.protocol_type;
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::GenevePacket(geneve)));
+    parsed_packet.add_field_ranges(
+        offset,
+        packet.len(),
+        &[
+            ("geneve", 0, length),
+            ("geneve.ver", 0, 1),
+            ("geneve.flags", 1, 1),
+            ("geneve.proto", 2, 2),
+            ("geneve.vni", 4, 3),
+            ("geneve.options", 8, length - 8),
+        ],
+    );
+    handle_tunnel_payload(
+        protocol_type,
+        &packet[length..],
+        offset + length,
+        defragmenter,
+        parsed_packet,
+    );
+}
+
+/// Decode the packet a tunnel carries, given by its EtherType, as the innermost packet
+fn handle_tunnel_payload(
+    protocol_type: u16,
+    payload: &[u8],
+    offset: usize,
+    defragmenter: &mut Defragmenter,
+    parsed_packet: &mut ParsedPacket,
+) {
+    if parsed_packet.get_tunnel_depth() >= MAX_TUNNEL_DEPTH {
+        return;
+    }
+    if protocol_type == TRANSPARENT_ETHERNET_BRIDGING {
+        parsed_packet.enter_tunnel();
+        match EthernetPacket::new(payload) {
+            Some(ethernet) => handle_ethernet_frame(&ethernet, offset, defragmenter, parsed_packet),
+            None => parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed Ethernet Frame".to_string(),
+            ))),
+        }
+        return;
+    }
+
+    // Payloads `handle_ethertype` can't decode stay part of the tunnel
+    let ethertype = EtherType(protocol_type);
+    if matches!(
+        ethertype,
+        EtherTypes::Ipv4
+            | EtherTypes::Ipv6
+            | EtherTypes::Arp
+            | EtherTypes::Vlan
+            | EtherTypes::PBridge
+            | EtherTypes::QinQ
+            | EtherTypes::Mpls
+            | EtherTypes::MplsMcast
+    ) {
+        parsed_packet.enter_tunnel();
+        handle_ethertype(
+            ethertype,
+            payload,
+            offset,
+            MacAddr::zero(),
+            MacAddr::zero(),
+            defragmenter,
+            parsed_packet,
+        );
+    }
+}
+
+fn word(packet: &[u8], start: usize) -> Option<u32> {
+    let &[a, b, c, d] = packet.get(start..start + 4)? else {
+        return None;
+    };
+    Some(u32::from_be_bytes([a, b, c, d]))
+}
+
+fn set_malformed_transport(parsed_packet: &mut ParsedPacket, reason: &str) {
+    parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+        reason.to_string(),
+    )));
+}
+
+fn set_malformed_application(parsed_packet: &mut ParsedPacket, reason: &str) {
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+        reason.to_string(),
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use crate::parser::{
+        wrapers::{
+            Layer, SerializableEthernetPacket, SerializableIpv4Packet, SerializableIpv6Packet,
+            SerializableUdpPacket,
+        },
+        Parser,
+    };
+
+    fn ethernet(ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend(ethertype.to_be_bytes());
+        frame.extend(payload);
+        frame
+    }
+
+    /// IPv4 from 10.0.0.`host` to 10.0.0.2
+    fn ipv4(host: u8, protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0];
+        packet.extend((20 + payload.len() as u16).to_be_bytes());
+        packet.extend([0, 0, 0, 0, 64, protocol, 0, 0, 10, 0, 0, host, 10, 0, 0, 2]);
+        packet.extend(payload);
+        packet
+    }
+
+    fn udp(destination: u16, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = 5000u16.to_be_bytes().to_vec();
+        datagram.extend(destination.to_be_bytes());
+        datagram.extend((8 + payload.len() as u16).to_be_bytes());
+        datagram.extend([0, 0]);
+        datagram.extend(payload);
+        datagram
+    }
+
+    /// The packet tunnels carry in these tests, UDP from 10.0.0.9:5000 to 10.0.0.2:9999
+    fn inner() -> Vec<u8> {
+        ipv4(9, 17, &udp(9999, b"inner"))
+    }
+
+    fn parse(frame: &[u8]) -> ParsedPacket {
+        let ethernet = EthernetPacket::new(frame).unwrap();
+        Parser::parse_ethernet_frame(&ethernet, 0, &mut Defragmenter::new())
+    }
+
+    /// Check the innermost packet is the one of `inner`, one tunnel deep
+    fn assert_inner(packet: &ParsedPacket) {
+        assert_eq!(packet.get_tunnel_depth(), 1);
+        let ipv4 = packet.find::<SerializableIpv4Packet>().unwrap();
+        assert_eq!(ipv4.source, Ipv4Addr::new(10, 0, 0, 9));
+        let udp = packet.find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination), (5000, 9999));
+        let depths: Vec<_> = packet.layers().map(Layer::get_depth).collect();
+        assert_eq!((depths.first(), depths.last()), (Some(&0), Some(&1)));
+    }
+
+    #[test]
+    fn decodes_gre_with_key_and_sequence() {
+        let mut gre = vec![0x30, 0, 0x08, 0x00];
+        gre.extend(0x1122_3344u32.to_be_bytes());
+        gre.extend(7u32.to_be_bytes());
+        gre.extend(inner());
+        let packet = parse(&ethernet(0x0800, &ipv4(1, 47, &gre)));
+
+        assert_inner(&packet);
+        let gre = packet.find::<SerializableGrePacket>().unwrap();
+        assert_eq!((gre.version, gre.protocol_type), (0, 0x0800));
+        assert_eq!(
+            (gre.checksum, gre.key, gre.sequence),
+            (None, Some(0x1122_3344), Some(7))
+        );
+        assert_eq!(gre.acknowledgement, None);
+        assert_eq!(packet.get_field_range("gre"), Some(34..46));
+        assert_eq!(packet.get_field_range("gre.seq"), Some(42..46));
+
+        // The key flag is set but the key is missing
+        let packet = parse(&ethernet(0x0800, &ipv4(1, 47, &[0x20, 0, 0x08, 0x00, 0])));
+        assert!(matches!(
+            packet.get_transport_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed GRE Packet"
+        ));
+    }
+
+    #[test]
+    fn decodes_vxlan() {
+        let mut vxlan = vec![0x08, 0, 0, 0, 0x12, 0x34, 0x56, 0];
+        vxlan.extend(ethernet(0x0800, &inner()));
+        let packet = parse(&ethernet(0x0800, &ipv4(1, 17, &udp(VXLAN_PORT, &vxlan))));
+
+        assert_inner(&packet);
+        let vxlan = packet.find::<SerializableVxlanPacket>().unwrap();
+        assert_eq!((vxlan.flags, vxlan.vni), (0x08, 0x12_3456));
+        assert_eq!(packet.find_all::<SerializableEthernetPacket>().count(), 2);
+        assert_eq!(packet.get_field_range("vxlan.vni"), Some(46..49));
+    }
+
+    #[test]
+    fn decodes_geneve_options() {
+        // Options take 3 words, the critical flag is set
+        let mut geneve = vec![0x03, 0x40, 0x08, 0x00, 0, 0, 0x2a, 0];
+        // A critical option with one word of data, then an empty one
+        geneve.extend([0x01, 0x02, 0x80, 0x01, 0xde, 0xad, 0xbe, 0xef]);
+        geneve.extend([0xff, 0xff, 0x01, 0x00]);
+        geneve.extend(inner());
+        let packet = parse(&ethernet(0x0800, &ipv4(1, 17, &udp(GENEVE_PORT, &geneve))));
+
+        assert_inner(&packet);
+        let geneve = packet.find::<SerializableGenevePacket>().unwrap();
+        assert_eq!(
+            (geneve.version, geneve.oam, geneve.critical),
+            (0, false, true)
+        );
+        assert_eq!((geneve.protocol_type, geneve.vni), (0x0800, 42));
+        let options: Vec<_> = geneve
+            .options
+            .iter()
+            .map(|option| (option.class, option.option_type, option.data.as_slice()))
+            .collect();
+        assert_eq!(
+            options,
+            [
+                (0x0102, 0x80, &[0xde, 0xad, 0xbe, 0xef][..]),
+                (0xffff, 0x01, &[][..]),
+            ]
+        );
+        assert_eq!(packet.get_field_range("geneve.options"), Some(50..62));
+
+        // The option says it has two words of data, the options end after one
+        let mut truncated = vec![0x02, 0, 0x08, 0x00, 0, 0, 0x2a, 0];
+        truncated.extend([0x01, 0x02, 0x80, 0x02, 0xde, 0xad, 0xbe, 0xef]);
+        let packet = parse(&ethernet(
+            0x0800,
+            &ipv4(1, 17, &udp(GENEVE_PORT, &truncated)),
+        ));
+        assert!(matches!(
+            packet.get_application_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(reason)) if reason == "Malformed Geneve Option"
+        ));
+    }
+
+    #[test]
+    fn decodes_ip_in_ip() {
+        assert_inner(&parse(&ethernet(0x0800, &ipv4(1, 4, &inner()))));
+
+        // 6in4, IPv6 from 2001:db8::1 carried in IPv4
+        let payload = udp(9999, b"inner");
+        let mut ipv6 = vec![0x60, 0, 0, 0];
+        ipv6.extend((payload.len() as u16).to_be_bytes());
+        ipv6.extend([17, 64]);
+        ipv6.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        ipv6.extend([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        ipv6.extend(payload);
+        let packet = parse(&ethernet(0x0800, &ipv4(1, 41, &ipv6)));
+
+        assert_eq!(packet.get_tunnel_depth(), 1);
+        let ipv6 = packet.find::<SerializableIpv6Packet>().unwrap();
+        assert_eq!(ipv6.source, "2001:db8::1".parse::<Ipv6Addr>().unwrap());
+        let udp = packet.find::<SerializableUdpPacket>().unwrap();
+        assert_eq!((udp.source, udp.destination), (5000, 9999));
+        assert_eq!(packet.get_field_range("udp.srcport"), Some(74..76));
+    }
+
+    #[test]
+    fn stops_at_max_tunnel_depth() {
+        // IP in IP and GRE carrying IPv4, each nested deeper than allowed
+        for (protocol, header) in [(4, &[][..]), (47, &[0, 0, 0x08, 0x00][..])] {
+            let mut packet = inner();
+            for _ in 0..MAX_TUNNEL_DEPTH + 4 {
+                let mut payload = header.to_vec();
+                payload.extend(packet);
+                packet = ipv4(1, protocol, &payload);
+            }
+            let packet = parse(&ethernet(0x0800, &packet));
+
+            assert_eq!(packet.get_tunnel_depth(), MAX_TUNNEL_DEPTH);
+            let ipv4 = packet.find_all::<SerializableIpv4Packet>().count();
+            assert_eq!(ipv4, MAX_TUNNEL_DEPTH + 1);
+            assert!(packet.find::<SerializableUdpPacket>().is_none());
+        }
+    }
+}
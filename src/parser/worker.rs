@@ -0,0 +1,313 @@
+//! Capture and dissection on a background thread, so a slow consumer never stalls the capture
+//! and an idle link never stalls the consumer
+
+use std::{
+    mem,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use super::{
+    capture::{CaptureWriter, FileFormat, Rotation},
+    defrag::Defragmenter,
+    wrapers::ParsedPacket,
+    Parser,
+};
+
+/// What the capture thread does when the consumer falls behind and the channel is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait for room, slowing the capture down to the consumer's pace
+    Block,
+    /// Discard the packet and count it as dropped
+    Drop,
+}
+
+/// Counters describing how well the consumer keeps up with the capture
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorkerStats {
+    /// Frames read from the capture source
+    pub captured: u64,
+    /// Packets put into the channel
+    pub delivered: u64,
+    /// Packets discarded because the channel was full
+    pub dropped: u64,
+    /// Times the channel was found full
+    pub stalls: u64,
+    /// Packets delivered but not received yet
+    pub queued: u64,
+}
+
+/// State of the recording done by the capture thread
+#[derive(Clone, Debug, Default)]
+pub struct RecordingStatus {
+    /// File currently written, `None` when not recording
+    pub path: Option<PathBuf>,
+    /// Frames written since the recording started
+    pub frames: usize,
+    /// Why the last recording could not be started or went on
+    pub error: Option<String>,
+}
+
+enum Command {
+    StartRecording {
+        path: PathBuf,
+        format: FileFormat,
+        rotation: Rotation,
+    },
+    StopRecording,
+    Stop,
+}
+
+#[derive(Default)]
+struct Shared {
+    captured: AtomicU64,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    stalls: AtomicU64,
+    finished: AtomicBool,
+    error: Mutex<Option<String>>,
+    recording: Mutex<RecordingStatus>,
+}
+
+/// Runs `Parser::next` and `Parser::parse_frame` on a dedicated thread and hands the
+/// `ParsedPacket`s over a bounded channel
+///
+/// Frames are recorded on the capture thread as well, since only it sees the raw bytes.
+pub struct CaptureWorker {
+    packets: Receiver<ParsedPacket>,
+    commands: Sender<Command>,
+    shared: Arc<Shared>,
+    received: u64,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CaptureWorker {
+    /// Start capturing from `parser`, holding at most `capacity` packets the consumer has not received
+    pub fn spawn(parser: Parser, capacity: usize, overflow: Overflow) -> Self {
+        let (packet_tx, packets) = mpsc::sync_channel(capacity);
+        let (commands, command_rx) = mpsc::channel();
+        let shared = Arc::new(Shared::default());
+
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || capture_loop(parser, packet_tx, command_rx, &shared, overflow))
+        };
+
+        CaptureWorker {
+            packets,
+            commands,
+            shared,
+            received: 0,
+            thread: Some(thread),
+        }
+    }
+
+    /// Take every packet delivered since the last call without waiting
+    pub fn drain(&mut self) -> Vec<ParsedPacket> {
+        let packets: Vec<_> = self.packets.try_iter().collect();
+        self.received += packets.len() as u64;
+        packets
+    }
+
+    /// Get the capture and backpressure counters
+    pub fn stats(&self) -> WorkerStats {
+        let delivered = self.shared.delivered.load(Ordering::Relaxed);
+        WorkerStats {
+            captured: self.shared.captured.load(Ordering::Relaxed),
+            delivered,
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            stalls: self.shared.stalls.load(Ordering::Relaxed),
+            queued: delivered.saturating_sub(self.received),
+        }
+    }
+
+    /// Whether the capture source is exhausted, a capture file has been read to the end
+    pub fn is_finished(&self) -> bool {
+        self.shared.finished.load(Ordering::Relaxed)
+    }
+
+    /// Get why the capture source stopped early, e.g. a corrupt or truncated capture file
+    pub fn error(&self) -> Option<String> {
+        self.shared
+            .error
+            .lock()
+            .ok()
+            .and_then(|error| error.clone())
+    }
+
+    /// Get the state of the recording
+    pub fn recording(&self) -> RecordingStatus {
+        self.shared
+            .recording
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    /// Record every frame captured from now on into `path`
+    pub fn start_recording(&self, path: PathBuf, format: FileFormat, rotation: Rotation) {
+        let _ = self.commands.send(Command::StartRecording {
+            path,
+            format,
+            rotation,
+        });
+    }
+
+    /// Stop recording and flush the file
+    pub fn stop_recording(&self) {
+        let _ = self.commands.send(Command::StopRecording);
+    }
+}
+
+impl Drop for CaptureWorker {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Stop);
+        // Dropping the receiver wakes the thread up if it is blocked on a full channel
+        let (_, disconnected) = mpsc::sync_channel(0);
+        drop(mem::replace(&mut self.packets, disconnected));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn capture_loop(
+    mut parser: Parser,
+    packets: SyncSender<ParsedPacket>,
+    commands: Receiver<Command>,
+    shared: &Shared,
+    overflow: Overflow,
+) {
+    let mut recorder = None;
+    let mut defragmenter = Defragmenter::new();
+    let mut id = 0;
+    let is_live = parser.source().is_live();
+
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Stop) | Err(TryRecvError::Disconnected) => {
+                    stop_recording(&mut recorder, shared);
+                    return;
+                }
+                Ok(command) => handle_command(command, &mut recorder, &parser, shared),
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        let frame = match parser.next() {
+            Ok(Some(frame)) => frame,
+            // Live reads time out on idle links, files are done
+            Ok(None) if is_live => continue,
+            Ok(None) => break,
+            Err(err) => {
+                if let Ok(mut error) = shared.error.lock() {
+                    *error = Some(err.to_string());
+                }
+                break;
+            }
+        };
+        shared.captured.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(writer) = recorder.as_mut() {
+            let result = writer.write_frame(&frame);
+            if let Ok(mut status) = shared.recording.lock() {
+                status.frames = writer.frames();
+                if status.path.as_deref() != Some(writer.current_path()) {
+                    status.path = Some(writer.current_path().to_path_buf());
+                }
+                if let Err(err) = result {
+                    status.path = None;
+                    status.error = Some(err.to_string());
+                    recorder = None;
+                }
+            }
+        }
+
+        let packet = Parser::parse_frame(&frame, id, &mut defragmenter);
+        id += 1;
+
+        let sent = match packets.try_send(packet) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(packet)) => {
+                shared.stalls.fetch_add(1, Ordering::Relaxed);
+                match overflow {
+                    Overflow::Block => packets.send(packet).map_err(|_| ()),
+                    Overflow::Drop => {
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => Err(()),
+        };
+        if sent.is_err() {
+            stop_recording(&mut recorder, shared);
+            return;
+        }
+        shared.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // The source is exhausted, keep serving commands until the worker is dropped
+    stop_recording(&mut recorder, shared);
+    shared.finished.store(true, Ordering::Relaxed);
+    for command in commands.iter() {
+        match command {
+            Command::Stop => return,
+            command => handle_command(command, &mut recorder, &parser, shared),
+        }
+    }
+}
+
+fn handle_command(
+    command: Command,
+    recorder: &mut Option<CaptureWriter>,
+    parser: &Parser,
+    shared: &Shared,
+) {
+    match command {
+        Command::StartRecording {
+            path,
+            format,
+            rotation,
+        } => {
+            stop_recording(recorder, shared);
+            let result = CaptureWriter::create(path, format, parser.source().link_type(), rotation);
+            if let Ok(mut status) = shared.recording.lock() {
+                *status = match result {
+                    Ok(writer) => {
+                        let status = RecordingStatus {
+                            path: Some(writer.current_path().to_path_buf()),
+                            frames: 0,
+                            error: None,
+                        };
+                        *recorder = Some(writer);
+                        status
+                    }
+                    Err(err) => RecordingStatus {
+                        error: Some(err.to_string()),
+                        ..Default::default()
+                    },
+                };
+            }
+        }
+        Command::StopRecording => stop_recording(recorder, shared),
+        Command::Stop => {}
+    }
+}
+
+fn stop_recording(recorder: &mut Option<CaptureWriter>, shared: &Shared) {
+    if let Some(mut writer) = recorder.take() {
+        let result = writer.flush();
+        if let Ok(mut status) = shared.recording.lock() {
+            status.path = None;
+            status.error = result.err().map(|err| err.to_string());
+        }
+    }
+}
@@ -0,0 +1,1426 @@
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use pnet::util::MacAddr;
+
+/// HTTP/1.x request, decoded from a reassembled TCP stream
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableHttpRequestPacket {
+    pub method: String,
+    pub uri: String,
+    pub version: String,
+    pub headers: Vec<SerializableHttpHeader>,
+    pub body: SerializableHttpBody,
+    /// Id of the packet the request started in, it ends in the packet it is attached to
+    pub first_packet: usize,
+}
+
+/// HTTP/1.x response, decoded from a reassembled TCP stream
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableHttpResponsePacket {
+    pub version: String,
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<SerializableHttpHeader>,
+    pub body: SerializableHttpBody,
+    /// Id of the packet the response started in, it ends in the packet it is attached to
+    pub first_packet: usize,
+    /// The request answered, `None` when it was not captured
+    pub request: Option<SerializableHttpRequestInfo>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableHttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Message body with the transfer coding removed
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableHttpBody {
+    pub length: usize,
+    pub chunked: bool,
+    /// Start of the body, longer bodies are cut at `http::HTTP_BODY_LIMIT` bytes
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub data: Vec<u8>,
+}
+
+/// Request a response answers
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableHttpRequestInfo {
+    pub method: String,
+    pub uri: String,
+    /// Id of the packet the request ended in
+    pub packet: usize,
+    /// Time between the end of the request and the end of the response
+    pub time: Duration,
+}
+
+impl SerializableHttpRequestPacket {
+    /// Get the first value of header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        http_header(&self.headers, name)
+    }
+}
+
+impl SerializableHttpResponsePacket {
+    /// Get the first value of header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        http_header(&self.headers, name)
+    }
+}
+
+fn http_header<'a>(headers: &'a [SerializableHttpHeader], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Size in bytes as `512 B`, `3.4 KB` or `1.2 MB`
+pub fn format_size(bytes: usize) -> String {
+    // Units change where rounding to one decimal would reach 1000.0
+    match bytes {
+        0..=999 => format!("{} B", bytes),
+        1_000..=999_949 => format!("{:.1} KB", bytes as f64 / 1e3),
+        999_950..=999_949_999 => format!("{:.1} MB", bytes as f64 / 1e6),
+        _ => format!("{:.1} GB", bytes as f64 / 1e9),
+    }
+}
+
+/// TLS records completed by a packet, decoded from a reassembled TCP stream
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsPacket {
+    /// Protocol version, the negotiated one once the server hello was seen
+    pub version: u16,
+    pub records: Vec<SerializableTlsRecord>,
+}
+
+/// TLS record, RFC 8446 section 5.1
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsRecord {
+    pub content_type: u8,
+    pub version: u16,
+    pub length: u16,
+    pub content: SerializableTlsContent,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableTlsContent {
+    ChangeCipherSpec,
+    Alert {
+        level: u8,
+        description: u8,
+    },
+    /// Handshake messages ending in the record, they may start in earlier ones
+    Handshake(Vec<SerializableTlsHandshake>),
+    ApplicationData,
+    Heartbeat,
+    /// Handshake or alert sent after ChangeCipherSpec
+    Encrypted,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableTlsHandshake {
+    ClientHello(SerializableTlsClientHello),
+    ServerHello(SerializableTlsServerHello),
+    /// Certificate chain, sent in clear text up to TLS 1.2
+    Certificate(Vec<SerializableTlsCertificate>),
+    Other {
+        handshake_type: u8,
+        length: u32,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsClientHello {
+    pub version: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub random: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub session_id: Vec<u8>,
+    pub cipher_suites: Vec<u16>,
+    pub compression_methods: Vec<u8>,
+    pub extensions: Vec<SerializableTlsExtension>,
+    /// JA3 fingerprint before hashing, e.g. `771,4865-4866,0-23-65281,29-23,0`
+    pub ja3_full: String,
+    /// MD5 of `ja3_full`
+    pub ja3: String,
+    pub ja4: String,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsServerHello {
+    pub version: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub random: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub session_id: Vec<u8>,
+    pub cipher_suite: u16,
+    pub compression_method: u8,
+    pub extensions: Vec<SerializableTlsExtension>,
+    /// JA3S fingerprint before hashing, e.g. `771,4865,43-51`
+    pub ja3s_full: String,
+    /// MD5 of `ja3s_full`
+    pub ja3s: String,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsExtension {
+    pub extension_type: u16,
+    pub data: SerializableTlsExtensionData,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableTlsExtensionData {
+    ServerName(Vec<String>),
+    SupportedGroups(Vec<u16>),
+    EcPointFormats(Vec<u8>),
+    SignatureAlgorithms(Vec<u16>),
+    Alpn(Vec<String>),
+    /// Versions offered by a client, or the one a server picked
+    SupportedVersions(Vec<u16>),
+    /// Groups of the key shares
+    KeyShare(Vec<u16>),
+    Unknown(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+}
+
+/// X.509 certificate of a chain, with the fields worth showing
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableTlsCertificate {
+    /// Distinguished name, e.g. `CN=example.com, O=Example, C=US`
+    pub subject: String,
+    pub issuer: String,
+    /// Serial number in hex
+    pub serial: String,
+    /// Start of the validity period, e.g. `2024-01-31 12:00:00 UTC`
+    pub not_before: String,
+    pub not_after: String,
+    /// DNS names of the subject alternative name extension
+    pub alt_names: Vec<String>,
+}
+
+impl SerializableTlsPacket {
+    /// Get every handshake message completed in the packet
+    pub fn handshakes(&self) -> impl Iterator<Item = &SerializableTlsHandshake> {
+        self.records
+            .iter()
+            .filter_map(|record| match &record.content {
+                SerializableTlsContent::Handshake(messages) => Some(messages),
+                _ => None,
+            })
+            .flatten()
+    }
+}
+
+impl SerializableTlsClientHello {
+    /// Get the host name of the server name extension
+    pub fn server_name(&self) -> Option<&str> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match &extension.data {
+                SerializableTlsExtensionData::ServerName(names) => names.first(),
+                _ => None,
+            })
+            .map(String::as_str)
+    }
+
+    /// Get the application protocols offered, most preferred first
+    pub fn alpn(&self) -> &[String] {
+        tls_alpn(&self.extensions)
+    }
+}
+
+impl SerializableTlsServerHello {
+    /// Get the version picked, which TLS 1.3 only tells in the supported versions extension
+    pub fn selected_version(&self) -> u16 {
+        self.extensions
+            .iter()
+            .find_map(|extension| match &extension.data {
+                SerializableTlsExtensionData::SupportedVersions(versions) => {
+                    versions.first().copied()
+                }
+                _ => None,
+            })
+            .unwrap_or(self.version)
+    }
+
+    /// Get the application protocol picked
+    pub fn alpn(&self) -> Option<&str> {
+        tls_alpn(&self.extensions).first().map(String::as_str)
+    }
+}
+
+fn tls_alpn(extensions: &[SerializableTlsExtension]) -> &[String] {
+    extensions
+        .iter()
+        .find_map(|extension| match &extension.data {
+            SerializableTlsExtensionData::Alpn(protocols) => Some(protocols.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Whether `value` is a GREASE value, RFC 8701, which fingerprints leave out
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// Name of a protocol version as shown in the protocol column, e.g. `TLSv1.2`
+pub fn tls_version_name(version: u16) -> String {
+    let name = match version {
+        0x0300 => "SSLv3",
+        0x0301 => "TLSv1",
+        0x0302 => "TLSv1.1",
+        0x0303 => "TLSv1.2",
+        0x0304 => "TLSv1.3",
+        _ if is_grease(version) => "GREASE",
+        _ => return format!("TLS 0x{:04x}", version),
+    };
+    name.to_string()
+}
+
+/// Name of a record content type
+pub fn tls_content_type_name(content_type: u8) -> String {
+    let name = match content_type {
+        20 => "Change Cipher Spec",
+        21 => "Alert",
+        22 => "Handshake",
+        23 => "Application Data",
+        24 => "Heartbeat",
+        _ => return format!("Unknown ({})", content_type),
+    };
+    name.to_string()
+}
+
+/// Name of a handshake message type
+pub fn tls_handshake_type_name(handshake_type: u8) -> String {
+    let name = match handshake_type {
+        0 => "Hello Request",
+        1 => "Client Hello",
+        2 => "Server Hello",
+        4 => "New Session Ticket",
+        5 => "End Of Early Data",
+        8 => "Encrypted Extensions",
+        11 => "Certificate",
+        12 => "Server Key Exchange",
+        13 => "Certificate Request",
+        14 => "Server Hello Done",
+        15 => "Certificate Verify",
+        16 => "Client Key Exchange",
+        20 => "Finished",
+        22 => "Certificate Status",
+        24 => "Key Update",
+        _ => return format!("Unknown ({})", handshake_type),
+    };
+    name.to_string()
+}
+
+/// Name of an extension as registered by IANA
+pub fn tls_extension_name(extension_type: u16) -> String {
+    let name = match extension_type {
+        0 => "server_name",
+        1 => "max_fragment_length",
+        5 => "status_request",
+        10 => "supported_groups",
+        11 => "ec_point_formats",
+        13 => "signature_algorithms",
+        16 => "application_layer_protocol_negotiation",
+        18 => "signed_certificate_timestamp",
+        21 => "padding",
+        22 => "encrypt_then_mac",
+        23 => "extended_master_secret",
+        27 => "compress_certificate",
+        35 => "session_ticket",
+        41 => "pre_shared_key",
+        42 => "early_data",
+        43 => "supported_versions",
+        45 => "psk_key_exchange_modes",
+        49 => "post_handshake_auth",
+        50 => "signature_algorithms_cert",
+        51 => "key_share",
+        57 => "quic_transport_parameters",
+        17513 => "application_settings",
+        65037 => "encrypted_client_hello",
+        65281 => "renegotiation_info",
+        _ if is_grease(extension_type) => "GREASE",
+        _ => return format!("Unknown type {}", extension_type),
+    };
+    name.to_string()
+}
+
+/// Name of a cipher suite as registered by IANA, for the common ones
+pub fn tls_cipher_suite_name(suite: u16) -> String {
+    let name = match suite {
+        0x1301 => "TLS_AES_128_GCM_SHA256",
+        0x1302 => "TLS_AES_256_GCM_SHA384",
+        0x1303 => "TLS_CHACHA20_POLY1305_SHA256",
+        0x1304 => "TLS_AES_128_CCM_SHA256",
+        0x1305 => "TLS_AES_128_CCM_8_SHA256",
+        0x000a => "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+        0x002f => "TLS_RSA_WITH_AES_128_CBC_SHA",
+        0x0035 => "TLS_RSA_WITH_AES_256_CBC_SHA",
+        0x003c => "TLS_RSA_WITH_AES_128_CBC_SHA256",
+        0x003d => "TLS_RSA_WITH_AES_256_CBC_SHA256",
+        0x009c => "TLS_RSA_WITH_AES_128_GCM_SHA256",
+        0x009d => "TLS_RSA_WITH_AES_256_GCM_SHA384",
+        0x009e => "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256",
+        0x009f => "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384",
+        0x00ff => "TLS_EMPTY_RENEGOTIATION_INFO_SCSV",
+        0x5600 => "TLS_FALLBACK_SCSV",
+        0xc009 => "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+        0xc00a => "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+        0xc013 => "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+        0xc014 => "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+        0xc023 => "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256",
+        0xc024 => "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384",
+        0xc027 => "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256",
+        0xc028 => "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384",
+        0xc02b => "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        0xc02c => "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        0xc02f => "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        0xc030 => "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        0xcca8 => "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xcca9 => "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xccaa => "TLS_DHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        _ if is_grease(suite) => "GREASE",
+        _ => return format!("Unknown (0x{:04x})", suite),
+    };
+    name.to_string()
+}
+
+/// Name of an alert description, RFC 8446 section 6
+pub fn tls_alert_name(description: u8) -> String {
+    let name = match description {
+        0 => "Close Notify",
+        10 => "Unexpected Message",
+        20 => "Bad Record MAC",
+        22 => "Record Overflow",
+        40 => "Handshake Failure",
+        42 => "Bad Certificate",
+        43 => "Unsupported Certificate",
+        44 => "Certificate Revoked",
+        45 => "Certificate Expired",
+        46 => "Certificate Unknown",
+        47 => "Illegal Parameter",
+        48 => "Unknown CA",
+        49 => "Access Denied",
+        50 => "Decode Error",
+        51 => "Decrypt Error",
+        70 => "Protocol Version",
+        71 => "Insufficient Security",
+        80 => "Internal Error",
+        86 => "Inappropriate Fallback",
+        90 => "User Canceled",
+        109 => "Missing Extension",
+        110 => "Unsupported Extension",
+        112 => "Unrecognized Name",
+        116 => "Certificate Required",
+        120 => "No Application Protocol",
+        _ => return format!("Unknown ({})", description),
+    };
+    name.to_string()
+}
+
+/// Which protocol a DNS message was sent with, they share the message format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DnsService {
+    Dns,
+    /// Multicast DNS, RFC 6762
+    Mdns,
+    /// Link-Local Multicast Name Resolution, RFC 4795
+    Llmnr,
+}
+
+/// Domain Name System message, RFC 1035
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDnsPacket {
+    pub service: DnsService,
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<SerializableDnsQuestion>,
+    pub answers: Vec<SerializableDnsRecord>,
+    pub authorities: Vec<SerializableDnsRecord>,
+    pub additionals: Vec<SerializableDnsRecord>,
+    /// Id of the packet carrying the query a response answers, see `dns::DnsTracker`
+    pub response_to: Option<usize>,
+    /// Time between the query and its response
+    pub time: Option<Duration>,
+    /// Whether no response to the query was seen in time
+    pub unanswered: bool,
+}
+
+impl SerializableDnsPacket {
+    /// Whether the message is a response
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Get the kind of query, 0 for standard queries
+    pub fn opcode(&self) -> u8 {
+        (self.flags >> 11) as u8 & 0xf
+    }
+
+    /// Get the response code, extended by the OPT record's upper bits when there is one
+    pub fn rcode(&self) -> u16 {
+        let extended = self
+            .additionals
+            .iter()
+            .find_map(|record| match record.data {
+                SerializableDnsData::Opt { extended_rcode, .. } => Some(extended_rcode),
+                _ => None,
+            });
+        (extended.unwrap_or(0) as u16) << 4 | (self.flags & 0xf)
+    }
+
+    /// Get names of the flags set, e.g. `["RD", "RA"]`
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        const NAMES: [(u16, &str); 6] = [
+            (0x0400, "AA"),
+            (0x0200, "TC"),
+            (0x0100, "RD"),
+            (0x0080, "RA"),
+            (0x0020, "AD"),
+            (0x0010, "CD"),
+        ];
+        NAMES
+            .iter()
+            .filter(|(bit, _)| self.flags & bit != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// Get every resource record, answers first
+    pub fn records(&self) -> impl Iterator<Item = &SerializableDnsRecord> {
+        self.answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDnsQuestion {
+    pub name: String,
+    pub record_type: u16,
+    /// Class, the top bit asks for a unicast response in mDNS
+    pub class: u16,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDnsRecord {
+    pub name: String,
+    pub record_type: u16,
+    /// Class, the top bit flushes cached records in mDNS, the UDP payload size for OPT
+    pub class: u16,
+    pub ttl: u32,
+    pub data: SerializableDnsData,
+}
+
+/// Decoded data of a resource record
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableDnsData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Ns(String),
+    Ptr(String),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Txt(Vec<String>),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Soa {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    /// SVCB and HTTPS records, RFC 9460, priority 0 is an alias to `target`
+    Svcb {
+        priority: u16,
+        target: String,
+        params: Vec<SerializableSvcParam>,
+    },
+    /// EDNS(0) pseudo-record, RFC 6891
+    Opt {
+        udp_payload_size: u16,
+        extended_rcode: u8,
+        version: u8,
+        dnssec_ok: bool,
+        options: Vec<SerializableEdnsOption>,
+    },
+    Unknown(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableSvcParam {
+    Alpn(Vec<String>),
+    Port(u16),
+    Ipv4Hint(Vec<Ipv4Addr>),
+    Ipv6Hint(Vec<Ipv6Addr>),
+    Other {
+        key: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+        value: Vec<u8>,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableEdnsOption {
+    pub code: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub data: Vec<u8>,
+}
+
+/// Name of a record type, as in the IANA DNS parameters registry
+pub fn dns_type_name(record_type: u16) -> String {
+    let name = match record_type {
+        1 => "A",
+        2 => "NS",
+        5 => "CNAME",
+        6 => "SOA",
+        12 => "PTR",
+        13 => "HINFO",
+        15 => "MX",
+        16 => "TXT",
+        28 => "AAAA",
+        33 => "SRV",
+        35 => "NAPTR",
+        41 => "OPT",
+        43 => "DS",
+        46 => "RRSIG",
+        47 => "NSEC",
+        48 => "DNSKEY",
+        50 => "NSEC3",
+        64 => "SVCB",
+        65 => "HTTPS",
+        99 => "SPF",
+        252 => "AXFR",
+        255 => "ANY",
+        257 => "CAA",
+        _ => return format!("TYPE{}", record_type),
+    };
+    name.to_string()
+}
+
+/// Name of a record class, without the mDNS top bit
+pub fn dns_class_name(class: u16) -> String {
+    match class & 0x7fff {
+        1 => "IN".to_string(),
+        3 => "CH".to_string(),
+        4 => "HS".to_string(),
+        254 => "NONE".to_string(),
+        255 => "ANY".to_string(),
+        class => format!("CLASS{}", class),
+    }
+}
+
+/// Name of a response code, RFC 1035 and RFC 6895
+pub fn dns_rcode_name(rcode: u16) -> String {
+    let name = match rcode {
+        0 => "No error",
+        1 => "Format error",
+        2 => "Server failure",
+        3 => "No such name",
+        4 => "Not implemented",
+        5 => "Refused",
+        6 => "Name exists",
+        7 => "RRset exists",
+        8 => "RRset does not exist",
+        9 => "Not authoritative",
+        10 => "Name out of zone",
+        16 => "Bad OPT version",
+        23 => "Bad cookie",
+        _ => return format!("Unknown ({})", rcode),
+    };
+    name.to_string()
+}
+
+/// Name of an opcode, as the start of Wireshark's info column
+pub fn dns_opcode_name(opcode: u8) -> String {
+    let name = match opcode {
+        0 => "Standard query",
+        1 => "Inverse query",
+        2 => "Server status request",
+        4 => "Zone change notification",
+        5 => "Dynamic update",
+        6 => "DNS Stateful Operations",
+        _ => return format!("Unknown operation ({})", opcode),
+    };
+    name.to_string()
+}
+
+impl fmt::Display for SerializableDnsData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializableDnsData::A(address) => write!(f, "{}", address),
+            SerializableDnsData::Aaaa(address) => write!(f, "{}", address),
+            SerializableDnsData::Cname(name)
+            | SerializableDnsData::Ns(name)
+            | SerializableDnsData::Ptr(name) => write!(f, "{}", name),
+            SerializableDnsData::Mx {
+                preference,
+                exchange,
+            } => write!(f, "{} {}", preference, exchange),
+            SerializableDnsData::Txt(strings) => {
+                let strings: Vec<_> = strings.iter().map(|s| format!("{:?}", s)).collect();
+                write!(f, "{}", strings.join(" "))
+            }
+            SerializableDnsData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{} {} {} {}", priority, weight, port, target),
+            SerializableDnsData::Soa {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            SerializableDnsData::Svcb {
+                priority,
+                target,
+                params,
+            } => {
+                write!(f, "{} {}", priority, target)?;
+                for param in params {
+                    write!(f, " {}", param)?;
+                }
+                Ok(())
+            }
+            SerializableDnsData::Opt {
+                udp_payload_size,
+                version,
+                dnssec_ok,
+                options,
+                ..
+            } => {
+                write!(
+                    f,
+                    "UDP payload size {}, version {}, DO {}",
+                    udp_payload_size, version, *dnssec_ok as u8
+                )?;
+                for option in options {
+                    write!(f, ", option {} ({} bytes)", option.code, option.data.len())?;
+                }
+                Ok(())
+            }
+            SerializableDnsData::Unknown(data) => write!(f, "{} bytes", data.len()),
+        }
+    }
+}
+
+impl fmt::Display for SerializableSvcParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: fmt::Display>(items: &[T]) -> String {
+            items.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+        }
+        match self {
+            SerializableSvcParam::Alpn(protocols) => write!(f, "alpn={}", list(protocols)),
+            SerializableSvcParam::Port(port) => write!(f, "port={}", port),
+            SerializableSvcParam::Ipv4Hint(hints) => write!(f, "ipv4hint={}", list(hints)),
+            SerializableSvcParam::Ipv6Hint(hints) => write!(f, "ipv6hint={}", list(hints)),
+            SerializableSvcParam::Other { key, value } => {
+                write!(f, "key{}=({} bytes)", key, value.len())
+            }
+        }
+    }
+}
+
+impl fmt::Display for SerializableTlsExtensionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn codes(values: &[u16]) -> String {
+            let codes: Vec<_> = values
+                .iter()
+                .map(|value| format!("0x{:04x}", value))
+                .collect();
+            codes.join(", ")
+        }
+        match self {
+            SerializableTlsExtensionData::ServerName(names) => write!(f, "{}", names.join(", ")),
+            SerializableTlsExtensionData::SupportedGroups(groups)
+            | SerializableTlsExtensionData::KeyShare(groups) => write!(f, "{}", codes(groups)),
+            SerializableTlsExtensionData::EcPointFormats(formats) => {
+                let formats: Vec<_> = formats.iter().map(u8::to_string).collect();
+                write!(f, "{}", formats.join(", "))
+            }
+            SerializableTlsExtensionData::SignatureAlgorithms(algorithms) => {
+                write!(f, "{}", codes(algorithms))
+            }
+            SerializableTlsExtensionData::Alpn(protocols) => write!(f, "{}", protocols.join(", ")),
+            SerializableTlsExtensionData::SupportedVersions(versions) => {
+                let names: Vec<_> = versions.iter().map(|&v| tls_version_name(v)).collect();
+                write!(f, "{}", names.join(", "))
+            }
+            SerializableTlsExtensionData::Unknown(data) => write!(f, "{} bytes", data.len()),
+        }
+    }
+}
+
+/// Dynamic Host Configuration Protocol message, RFC 2131, or plain BOOTP without options
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpPacket {
+    /// 1 for requests from clients, 2 for replies from servers
+    pub op: u8,
+    pub hardware_type: u8,
+    pub hardware_length: u8,
+    pub hops: u8,
+    pub transaction_id: u32,
+    /// Seconds since the client started acquiring or renewing its address
+    pub seconds: u16,
+    pub flags: u16,
+    pub client_address: Ipv4Addr,
+    /// Address offered to or assigned to the client
+    pub your_address: Ipv4Addr,
+    /// Next server of the boot process
+    pub server_address: Ipv4Addr,
+    pub relay_address: Ipv4Addr,
+    /// Client hardware address, `hardware_length` bytes of it
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub client_hardware_address: Vec<u8>,
+    /// Server host name, empty when unset or holding options
+    pub server_name: String,
+    /// Boot file name, empty when unset or holding options
+    pub boot_file: String,
+    pub options: Vec<SerializableDhcpOption>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpOption {
+    pub code: u8,
+    pub data: SerializableDhcpOptionData,
+}
+
+/// Decoded value of an option, `Unknown` for options not decoded or of an unexpected length
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableDhcpOptionData {
+    MessageType(u8),
+    Addresses(Vec<Ipv4Addr>),
+    /// Lease times and timeouts, in seconds
+    Seconds(u32),
+    /// Offset of the client's subnet from UTC, in seconds
+    TimeOffset(i32),
+    Number(u16),
+    Text(String),
+    ParameterList(Vec<u8>),
+    ClientId {
+        hardware_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+        id: Vec<u8>,
+    },
+    /// Client FQDN, RFC 4702
+    Fqdn {
+        flags: u8,
+        name: String,
+    },
+    /// Domain search list, RFC 3397
+    DomainSearch(Vec<String>),
+    /// Classless static routes, RFC 3442
+    Routes(Vec<SerializableDhcpRoute>),
+    /// Relay agent information, RFC 3046
+    RelayAgent(Vec<SerializableDhcpSubOption>),
+    Unknown(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpRoute {
+    pub destination: Ipv4Addr,
+    pub prefix_length: u8,
+    pub router: Ipv4Addr,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpSubOption {
+    pub code: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub data: Vec<u8>,
+}
+
+impl SerializableDhcpPacket {
+    /// Get the value of the first option with code `code`
+    pub fn option(&self, code: u8) -> Option<&SerializableDhcpOptionData> {
+        self.options
+            .iter()
+            .find(|option| option.code == code)
+            .map(|option| &option.data)
+    }
+
+    /// Get the DHCP message type, `None` for BOOTP
+    pub fn message_type(&self) -> Option<u8> {
+        match self.option(53)? {
+            SerializableDhcpOptionData::MessageType(message_type) => Some(*message_type),
+            _ => None,
+        }
+    }
+
+    /// Get the client's MAC address, if its hardware is Ethernet
+    pub fn client_mac(&self) -> Option<MacAddr> {
+        match (self.hardware_type, &self.client_hardware_address[..]) {
+            (1, &[a, b, c, d, e, f]) => Some(MacAddr::new(a, b, c, d, e, f)),
+            _ => None,
+        }
+    }
+
+    /// Get the first address of an address option
+    pub fn address_option(&self, code: u8) -> Option<Ipv4Addr> {
+        match self.option(code)? {
+            SerializableDhcpOptionData::Addresses(addresses) => addresses.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// Get the address lease time, in seconds
+    pub fn lease_time(&self) -> Option<u32> {
+        match self.option(51)? {
+            SerializableDhcpOptionData::Seconds(seconds) => Some(*seconds),
+            _ => None,
+        }
+    }
+
+    /// Get a text option
+    pub fn text_option(&self, code: u8) -> Option<&str> {
+        match self.option(code)? {
+            SerializableDhcpOptionData::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Name of a DHCP message type, RFC 2132 and later
+pub fn dhcp_message_type_name(message_type: u8) -> String {
+    let name = match message_type {
+        1 => "Discover",
+        2 => "Offer",
+        3 => "Request",
+        4 => "Decline",
+        5 => "ACK",
+        6 => "NAK",
+        7 => "Release",
+        8 => "Inform",
+        9 => "Force Renew",
+        10 => "Lease Query",
+        11 => "Lease Unassigned",
+        12 => "Lease Unknown",
+        13 => "Lease Active",
+        _ => return format!("Unknown ({})", message_type),
+    };
+    name.to_string()
+}
+
+/// Name of a DHCP option as registered by IANA, for the common ones
+pub fn dhcp_option_name(code: u8) -> String {
+    let name = match code {
+        1 => "Subnet Mask",
+        2 => "Time Offset",
+        3 => "Router",
+        4 => "Time Server",
+        5 => "Name Server",
+        6 => "Domain Name Server",
+        7 => "Log Server",
+        9 => "LPR Server",
+        12 => "Host Name",
+        13 => "Boot File Size",
+        14 => "Merit Dump File",
+        15 => "Domain Name",
+        16 => "Swap Server",
+        17 => "Root Path",
+        18 => "Extensions Path",
+        19 => "IP Forwarding",
+        22 => "Maximum Datagram Reassembly Size",
+        23 => "Default IP Time-to-Live",
+        24 => "Path MTU Aging Timeout",
+        26 => "Interface MTU",
+        28 => "Broadcast Address",
+        31 => "Perform Router Discover",
+        32 => "Router Solicitation Address",
+        33 => "Static Route",
+        35 => "ARP Cache Timeout",
+        37 => "TCP Default TTL",
+        38 => "TCP Keepalive Interval",
+        40 => "NIS Domain",
+        41 => "NIS Servers",
+        42 => "NTP Servers",
+        43 => "Vendor-Specific Information",
+        44 => "NetBIOS over TCP/IP Name Server",
+        45 => "NetBIOS over TCP/IP Datagram Distribution Server",
+        46 => "NetBIOS over TCP/IP Node Type",
+        47 => "NetBIOS over TCP/IP Scope",
+        50 => "Requested IP Address",
+        51 => "IP Address Lease Time",
+        52 => "Option Overload",
+        53 => "DHCP Message Type",
+        54 => "DHCP Server Identifier",
+        55 => "Parameter Request List",
+        56 => "Message",
+        57 => "Maximum DHCP Message Size",
+        58 => "Renewal Time Value",
+        59 => "Rebinding Time Value",
+        60 => "Vendor Class Identifier",
+        61 => "Client Identifier",
+        64 => "NIS+ Domain",
+        65 => "NIS+ Servers",
+        66 => "TFTP Server Name",
+        67 => "Bootfile Name",
+        69 => "SMTP Server",
+        70 => "POP3 Server",
+        72 => "WWW Server",
+        77 => "User Class Information",
+        80 => "Rapid Commit",
+        81 => "Client Fully Qualified Domain Name",
+        82 => "Agent Information Option",
+        93 => "Client System Architecture",
+        94 => "Client Network Device Interface",
+        97 => "UUID/GUID-based Client Identifier",
+        100 => "PCode",
+        101 => "TCode",
+        108 => "IPv6-Only Preferred",
+        114 => "Captive Portal",
+        116 => "DHCP Auto-Configuration",
+        118 => "Subnet Selection",
+        119 => "Domain Search",
+        121 => "Classless Static Route",
+        125 => "V-I Vendor-Specific Information",
+        145 => "Forcerenew Nonce Capable",
+        150 => "TFTP Server Address",
+        249 => "Private/Classless Static Route (Microsoft)",
+        252 => "Private/Proxy autodiscovery",
+        255 => "End",
+        _ => return format!("Unknown ({})", code),
+    };
+    name.to_string()
+}
+
+/// Format a lease time as `86400 s (1d)`, all ones meaning forever
+pub fn format_seconds(seconds: u32) -> String {
+    if seconds == u32::MAX {
+        return "Infinity".to_string();
+    }
+    let units = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+    let mut left = seconds;
+    let mut parts = Vec::new();
+    for (size, unit) in units {
+        if left >= size {
+            parts.push(format!("{}{}", left / size, unit));
+            left %= size;
+        }
+    }
+    if seconds < 60 {
+        return format!("{} s", seconds);
+    }
+    format!("{} s ({})", seconds, parts.join(" "))
+}
+
+fn hex_string(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Format hardware address bytes as `00:11:22:33:44:55`
+pub fn format_hardware_address(address: &[u8]) -> String {
+    let bytes: Vec<_> = address.iter().map(|byte| format!("{:02x}", byte)).collect();
+    bytes.join(":")
+}
+
+impl fmt::Display for SerializableDhcpOptionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: fmt::Display>(items: &[T]) -> String {
+            items
+                .iter()
+                .map(T::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        match self {
+            SerializableDhcpOptionData::MessageType(message_type) => {
+                write!(
+                    f,
+                    "{} ({})",
+                    dhcp_message_type_name(*message_type),
+                    message_type
+                )
+            }
+            SerializableDhcpOptionData::Addresses(addresses) => write!(f, "{}", list(addresses)),
+            SerializableDhcpOptionData::Seconds(seconds) => {
+                write!(f, "{}", format_seconds(*seconds))
+            }
+            SerializableDhcpOptionData::TimeOffset(offset) => write!(f, "{} s", offset),
+            SerializableDhcpOptionData::Number(number) => write!(f, "{}", number),
+            SerializableDhcpOptionData::Text(text) => write!(f, "{}", text),
+            SerializableDhcpOptionData::ParameterList(codes) => write!(f, "{}", list(codes)),
+            SerializableDhcpOptionData::ClientId {
+                hardware_type: 1,
+                id,
+            } if id.len() == 6 => write!(f, "Ethernet {}", format_hardware_address(id)),
+            SerializableDhcpOptionData::ClientId { hardware_type, id } => {
+                write!(f, "Type {} {}", hardware_type, hex_string(id))
+            }
+            SerializableDhcpOptionData::Fqdn { flags, name } => {
+                write!(f, "{} (flags 0x{:02x})", name, flags)
+            }
+            SerializableDhcpOptionData::DomainSearch(names) => write!(f, "{}", names.join(", ")),
+            SerializableDhcpOptionData::Routes(routes) => {
+                let routes: Vec<_> = routes
+                    .iter()
+                    .map(|route| {
+                        format!(
+                            "{}/{} via {}",
+                            route.destination, route.prefix_length, route.router
+                        )
+                    })
+                    .collect();
+                write!(f, "{}", routes.join(", "))
+            }
+            SerializableDhcpOptionData::RelayAgent(suboptions) => {
+                let suboptions: Vec<_> = suboptions
+                    .iter()
+                    .map(|suboption| {
+                        let name = match suboption.code {
+                            1 => "Circuit-ID".to_string(),
+                            2 => "Remote-ID".to_string(),
+                            5 => "Link Selection".to_string(),
+                            11 => "Server ID Override".to_string(),
+                            code => format!("Sub-option {}", code),
+                        };
+                        // Circuit and remote ids are often interface names
+                        let data = &suboption.data;
+                        if !data.is_empty() && data.iter().all(u8::is_ascii_graphic) {
+                            return format!("{} {:?}", name, String::from_utf8_lossy(data));
+                        }
+                        format!("{} {}", name, hex_string(data))
+                    })
+                    .collect();
+                write!(f, "{}", suboptions.join(", "))
+            }
+            SerializableDhcpOptionData::Unknown(data) => write!(f, "{}", hex_string(data)),
+        }
+    }
+}
+
+/// DHCPv6 message, RFC 8415, relay messages carry the client's message as an option
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpv6Packet {
+    pub message_type: u8,
+    /// Transaction id, 0 for relay messages which have none
+    pub transaction_id: u32,
+    pub relay: Option<SerializableDhcpv6Relay>,
+    pub options: Vec<SerializableDhcpv6Option>,
+}
+
+/// Header of a Relay-forward or Relay-reply message
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpv6Relay {
+    pub hop_count: u8,
+    pub link_address: Ipv6Addr,
+    pub peer_address: Ipv6Addr,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableDhcpv6Option {
+    pub code: u16,
+    pub data: SerializableDhcpv6OptionData,
+}
+
+/// Decoded value of an option, `Unknown` for options not decoded or of an unexpected length
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableDhcpv6OptionData {
+    /// Client or server identifier
+    Duid(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+    /// Identity association for non-temporary addresses, or for prefix delegation
+    Ia {
+        iaid: u32,
+        t1: u32,
+        t2: u32,
+        options: Vec<SerializableDhcpv6Option>,
+    },
+    /// Identity association for temporary addresses
+    IaTemporary {
+        iaid: u32,
+        options: Vec<SerializableDhcpv6Option>,
+    },
+    IaAddress {
+        address: Ipv6Addr,
+        preferred_lifetime: u32,
+        valid_lifetime: u32,
+        options: Vec<SerializableDhcpv6Option>,
+    },
+    IaPrefix {
+        preferred_lifetime: u32,
+        valid_lifetime: u32,
+        prefix_length: u8,
+        prefix: Ipv6Addr,
+        options: Vec<SerializableDhcpv6Option>,
+    },
+    OptionRequest(Vec<u16>),
+    Preference(u8),
+    /// Time since the client started the exchange, in hundredths of a second
+    ElapsedTime(u16),
+    RelayMessage(Box<SerializableDhcpv6Packet>),
+    StatusCode {
+        code: u16,
+        message: String,
+    },
+    /// Options without a value, e.g. Rapid Commit
+    Empty,
+    Addresses(Vec<Ipv6Addr>),
+    DomainList(Vec<String>),
+    Unknown(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+}
+
+impl SerializableDhcpv6Packet {
+    /// Get the value of the first option with code `code`
+    pub fn option(&self, code: u16) -> Option<&SerializableDhcpv6OptionData> {
+        self.options
+            .iter()
+            .find(|option| option.code == code)
+            .map(|option| &option.data)
+    }
+
+    /// Get the message a relay message carries, through any number of relays
+    pub fn relayed(&self) -> &SerializableDhcpv6Packet {
+        match self.option(9) {
+            Some(SerializableDhcpv6OptionData::RelayMessage(message)) => message.relayed(),
+            _ => self,
+        }
+    }
+
+    /// Get the client's DUID
+    pub fn client_id(&self) -> Option<&[u8]> {
+        match self.option(1)? {
+            SerializableDhcpv6OptionData::Duid(duid) => Some(duid),
+            _ => None,
+        }
+    }
+
+    /// Get addresses and delegated prefixes of the identity associations, with their valid
+    /// lifetimes
+    pub fn leases(&self) -> Vec<(Ipv6Addr, u8, u32)> {
+        let mut leases = Vec::new();
+        for option in &self.options {
+            let (SerializableDhcpv6OptionData::Ia { options, .. }
+            | SerializableDhcpv6OptionData::IaTemporary { options, .. }) = &option.data
+            else {
+                continue;
+            };
+            for option in options {
+                match option.data {
+                    SerializableDhcpv6OptionData::IaAddress {
+                        address,
+                        valid_lifetime,
+                        ..
+                    } => leases.push((address, 128, valid_lifetime)),
+                    SerializableDhcpv6OptionData::IaPrefix {
+                        prefix,
+                        prefix_length,
+                        valid_lifetime,
+                        ..
+                    } => leases.push((prefix, prefix_length, valid_lifetime)),
+                    _ => {}
+                }
+            }
+        }
+        leases
+    }
+
+    /// Get the first status code other than success, of the message or one of its identity
+    /// associations
+    pub fn error_status(&self) -> Option<u16> {
+        fn status(options: &[SerializableDhcpv6Option]) -> Option<u16> {
+            options.iter().find_map(|option| match &option.data {
+                SerializableDhcpv6OptionData::StatusCode { code, .. } if *code != 0 => Some(*code),
+                SerializableDhcpv6OptionData::Ia { options, .. }
+                | SerializableDhcpv6OptionData::IaTemporary { options, .. } => status(options),
+                _ => None,
+            })
+        }
+        status(&self.options)
+    }
+}
+
+/// Name of a DHCPv6 message type, RFC 8415 section 7.3
+pub fn dhcpv6_message_type_name(message_type: u8) -> String {
+    let name = match message_type {
+        1 => "Solicit",
+        2 => "Advertise",
+        3 => "Request",
+        4 => "Confirm",
+        5 => "Renew",
+        6 => "Rebind",
+        7 => "Reply",
+        8 => "Release",
+        9 => "Decline",
+        10 => "Reconfigure",
+        11 => "Information-request",
+        12 => "Relay-forward",
+        13 => "Relay-reply",
+        _ => return format!("Unknown ({})", message_type),
+    };
+    name.to_string()
+}
+
+/// Name of a DHCPv6 option as registered by IANA, for the common ones
+pub fn dhcpv6_option_name(code: u16) -> String {
+    let name = match code {
+        1 => "Client Identifier",
+        2 => "Server Identifier",
+        3 => "Identity Association for Non-temporary Address",
+        4 => "Identity Association for Temporary Address",
+        5 => "IA Address",
+        6 => "Option Request",
+        7 => "Preference",
+        8 => "Elapsed time",
+        9 => "Relay Message",
+        11 => "Authentication",
+        12 => "Server unicast",
+        13 => "Status code",
+        14 => "Rapid Commit",
+        15 => "User Class",
+        16 => "Vendor Class",
+        17 => "Vendor-specific Information",
+        18 => "Interface-Id",
+        19 => "Reconfigure Message",
+        20 => "Reconfigure Accept",
+        21 => "SIP Server Domain Name List",
+        22 => "SIP Servers IPv6 Address List",
+        23 => "DNS recursive name server",
+        24 => "Domain Search List",
+        25 => "Identity Association for Prefix Delegation",
+        26 => "IA Prefix",
+        31 => "Simple Network Time Protocol Server",
+        32 => "Information Refresh Time",
+        37 => "Relay Agent Remote-ID",
+        39 => "Fully Qualified Domain Name",
+        56 => "NTP Server",
+        79 => "Client Link-layer Address",
+        82 => "SOL_MAX_RT",
+        83 => "INF_MAX_RT",
+        _ => return format!("Unknown ({})", code),
+    };
+    name.to_string()
+}
+
+/// Name of a DHCPv6 status code, RFC 8415 section 21.13
+pub fn dhcpv6_status_name(code: u16) -> String {
+    let name = match code {
+        0 => "Success",
+        1 => "UnspecFail",
+        2 => "NoAddrsAvail",
+        3 => "NoBinding",
+        4 => "NotOnLink",
+        5 => "UseMulticast",
+        6 => "NoPrefixAvail",
+        _ => return format!("Unknown ({})", code),
+    };
+    name.to_string()
+}
+
+/// Format a DUID by its type, RFC 8415 section 11, e.g. `DUID-LL 00:11:22:33:44:55`
+pub fn format_duid(duid: &[u8]) -> String {
+    match duid {
+        // Link-layer address plus time, of Ethernet hardware
+        [0, 1, 0, 1, _, _, _, _, address @ ..] => {
+            format!("DUID-LLT {}", format_hardware_address(address))
+        }
+        [0, 2, rest @ ..] if rest.len() >= 4 => format!(
+            "DUID-EN {} {}",
+            u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]),
+            hex_string(&rest[4..])
+        ),
+        [0, 3, 0, 1, address @ ..] => format!("DUID-LL {}", format_hardware_address(address)),
+        [0, 4, uuid @ ..] => format!("DUID-UUID {}", hex_string(uuid)),
+        _ => hex_string(duid),
+    }
+}
+
+impl fmt::Display for SerializableDhcpv6OptionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializableDhcpv6OptionData::Duid(duid) => write!(f, "{}", format_duid(duid)),
+            SerializableDhcpv6OptionData::Ia { iaid, t1, t2, .. } => {
+                write!(f, "IAID 0x{:08x}, T1 {}, T2 {}", iaid, t1, t2)
+            }
+            SerializableDhcpv6OptionData::IaTemporary { iaid, .. } => {
+                write!(f, "IAID 0x{:08x}", iaid)
+            }
+            SerializableDhcpv6OptionData::IaAddress {
+                address,
+                preferred_lifetime,
+                valid_lifetime,
+                ..
+            } => write!(
+                f,
+                "{}, preferred {}, valid {}",
+                address,
+                format_seconds(*preferred_lifetime),
+                format_seconds(*valid_lifetime)
+            ),
+            SerializableDhcpv6OptionData::IaPrefix {
+                preferred_lifetime,
+                valid_lifetime,
+                prefix_length,
+                prefix,
+                ..
+            } => write!(
+                f,
+                "{}/{}, preferred {}, valid {}",
+                prefix,
+                prefix_length,
+                format_seconds(*preferred_lifetime),
+                format_seconds(*valid_lifetime)
+            ),
+            SerializableDhcpv6OptionData::OptionRequest(codes) => {
+                let codes: Vec<_> = codes.iter().map(u16::to_string).collect();
+                write!(f, "{}", codes.join(", "))
+            }
+            SerializableDhcpv6OptionData::Preference(preference) => write!(f, "{}", preference),
+            SerializableDhcpv6OptionData::ElapsedTime(time) => {
+                write!(f, "{} ms", *time as u32 * 10)
+            }
+            SerializableDhcpv6OptionData::RelayMessage(message) => {
+                write!(f, "{}", dhcpv6_message_type_name(message.message_type))
+            }
+            SerializableDhcpv6OptionData::StatusCode { code, message } => {
+                write!(f, "{} ({})", dhcpv6_status_name(*code), code)?;
+                if !message.is_empty() {
+                    write!(f, ": {}", message)?;
+                }
+                Ok(())
+            }
+            SerializableDhcpv6OptionData::Empty => Ok(()),
+            SerializableDhcpv6OptionData::Addresses(addresses) => {
+                let addresses: Vec<_> = addresses.iter().map(Ipv6Addr::to_string).collect();
+                write!(f, "{}", addresses.join(", "))
+            }
+            SerializableDhcpv6OptionData::DomainList(names) => write!(f, "{}", names.join(", ")),
+            SerializableDhcpv6OptionData::Unknown(data) => write!(f, "{}", hex_string(data)),
+        }
+    }
+}
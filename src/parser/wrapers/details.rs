@@ -0,0 +1,1765 @@
+use std::{ops::Range, time::Duration};
+
+use pnet::packet::ethernet::EtherType;
+
+use super::{
+    dhcp_message_type_name, dhcp_option_name, dhcpv6_message_type_name, dhcpv6_option_name,
+    dns_class_name, dns_opcode_name, dns_rcode_name, dns_type_name, format_hardware_address,
+    format_icmp_timestamp, format_seconds, format_size, icmp_type_name, icmpv6_type_name,
+    mld_record_type_name, ndp_flag_names, ndp_option_name, tls_alert_name, tls_cipher_suite_name,
+    tls_content_type_name, tls_extension_name, tls_handshake_type_name, tls_version_name,
+    DnsService, ParsedPacket, SerializableArpPacket, SerializableDhcpPacket,
+    SerializableDhcpv6Option, SerializableDhcpv6OptionData, SerializableDhcpv6Packet,
+    SerializableDnsData, SerializableDnsPacket, SerializableDnsRecord, SerializableEchoReplyPacket,
+    SerializableEchoRequestPacket, SerializableEthernetPacket, SerializableGenevePacket,
+    SerializableGrePacket, SerializableHttpBody, SerializableHttpHeader,
+    SerializableHttpRequestPacket, SerializableHttpResponsePacket, SerializableIcmpBody,
+    SerializableIcmpPacket, SerializableIcmpQuote, SerializableIcmpQuotedTransport,
+    SerializableIcmpv6Body, SerializableIcmpv6Packet, SerializableIpv4Packet,
+    SerializableIpv6ExtensionData, SerializableIpv6Packet, SerializableMplsPacket,
+    SerializablePacket, SerializableTcpPacket, SerializableTlsCertificate,
+    SerializableTlsClientHello, SerializableTlsContent, SerializableTlsExtension,
+    SerializableTlsHandshake, SerializableTlsPacket, SerializableTlsServerHello,
+    SerializableUdpPacket, SerializableUnknownPacket, SerializableVlanPacket,
+    SerializableVxlanPacket, TRANSPARENT_ETHERNET_BRIDGING,
+};
+
+/// A decoded layer as shown in a protocol tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerDetails {
+    /// Short protocol name, as used by display filters
+    pub protocol: &'static str,
+    /// One-line description of the layer
+    pub title: String,
+    /// Bytes of the layer's header in the frame
+    pub range: Option<Range<usize>>,
+    pub fields: Vec<FieldDetails>,
+}
+
+/// A single field with its raw value and, where it helps, a readable decode
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDetails {
+    /// Display filter name, e.g. `ip.ttl`
+    pub key: &'static str,
+    pub name: &'static str,
+    pub value: String,
+    /// Bytes of the field in the frame
+    pub range: Option<Range<usize>>,
+}
+
+/// Builds a layer, looking up where its fields were decoded from
+struct LayerBuilder<'a> {
+    packet: &'a ParsedPacket,
+    layer: LayerDetails,
+}
+
+impl<'a> LayerBuilder<'a> {
+    fn new(packet: &'a ParsedPacket, protocol: &'static str, title: String) -> Self {
+        LayerBuilder {
+            packet,
+            layer: LayerDetails {
+                protocol,
+                title,
+                range: packet.get_field_range(protocol),
+                fields: Vec::new(),
+            },
+        }
+    }
+
+    /// Add a field, `key` is its display filter name
+    fn field<V: ToString>(mut self, key: &'static str, name: &'static str, value: V) -> Self {
+        self.layer.fields.push(FieldDetails {
+            key,
+            name,
+            value: value.to_string(),
+            range: self.packet.get_field_range(key),
+        });
+        self
+    }
+
+    /// Add a field repeated in the layer, lying at the `n`th range recorded for `key`
+    fn nth_field<V: ToString>(
+        mut self,
+        key: &'static str,
+        n: usize,
+        name: &'static str,
+        value: V,
+    ) -> Self {
+        self.layer.fields.push(FieldDetails {
+            key,
+            name,
+            value: value.to_string(),
+            range: self.packet.get_nth_field_range(key, n),
+        });
+        self
+    }
+
+    fn build(self) -> LayerDetails {
+        self.layer
+    }
+}
+
+impl ParsedPacket {
+    /// Get every decoded layer with all of its fields, starting with the capture metadata
+    pub fn details(&self) -> Vec<LayerDetails> {
+        let mut frame = LayerBuilder::new(
+            self,
+            "frame",
+            format!(
+                "Frame {}: {} bytes on wire, {} bytes captured",
+                self.get_id(),
+                self.get_length(),
+                self.get_data().len()
+            ),
+        )
+        .field(
+            "frame.time",
+            "Arrival Time",
+            format!(
+                "{}.{:09} seconds since the epoch",
+                self.get_timestamp().as_secs(),
+                self.get_timestamp().subsec_nanos()
+            ),
+        )
+        .field(
+            "frame.len",
+            "Frame Length",
+            format!("{} bytes", self.get_length()),
+        );
+        if let Some(interface) = self.get_interface_name() {
+            frame = frame.field("frame.interface_name", "Interface", interface);
+        }
+        for comment in self.get_comments() {
+            frame = frame.field("frame.comment", "Comment", comment);
+        }
+
+        let mut details = vec![frame.build()];
+        for (i, layer) in self.layers().enumerate() {
+            let mut layer_details = layer_details(self, layer.get_packet());
+            layer_details.range = layer.get_range();
+            // Stacked tags and tunneled headers repeat a protocol, each takes the next ranges
+            let n = self
+                .layers()
+                .take(i)
+                .filter(|other| other.get_protocol() == layer.get_protocol())
+                .count();
+            if n > 0 {
+                for field in &mut layer_details.fields {
+                    field.range = self.get_nth_field_range(field.key, n);
+                }
+            }
+            details.push(layer_details);
+
+            if let Some(original) = quoted_datagram(layer.get_packet()) {
+                // Quoted headers follow the ICMP header and are the last ranges recorded
+                let start = layer.get_range().map_or(usize::MAX, |range| range.end);
+                let quoted_range = |key: &str| {
+                    self.get_field_ranges()
+                        .iter()
+                        .rev()
+                        .find(|range| range.field == key)
+                        .map(|range| range.range.clone())
+                        .filter(|range| range.start >= start)
+                };
+                for mut quoted in quote_details(self, original) {
+                    quoted.range = quoted_range(quoted.protocol);
+                    for field in &mut quoted.fields {
+                        field.range = quoted_range(field.key);
+                    }
+                    details.push(quoted);
+                }
+            }
+        }
+        details
+    }
+}
+
+/// Get the datagram quoted by an ICMP error layer
+fn quoted_datagram(layer: &SerializablePacket) -> Option<&SerializableIcmpQuote> {
+    match layer {
+        SerializablePacket::IcmpPacket(icmp) => icmp.original(),
+        _ => None,
+    }
+}
+
+fn layer_details(packet: &ParsedPacket, layer: &SerializablePacket) -> LayerDetails {
+    match layer {
+        SerializablePacket::EthernetPacket(ethernet) => ethernet_details(packet, ethernet),
+        SerializablePacket::UnknownPacket(unknown) => unknown_details(packet, unknown),
+        SerializablePacket::VlanPacket(vlan) => vlan_details(packet, vlan),
+        SerializablePacket::MplsPacket(mpls) => mpls_details(packet, mpls),
+        SerializablePacket::ArpPacket(arp) => arp_details(packet, arp),
+        SerializablePacket::Ipv4Packet(ipv4) => ipv4_details(packet, ipv4),
+        SerializablePacket::Ipv6Packet(ipv6) => ipv6_details(packet, ipv6),
+        SerializablePacket::TcpPacket(tcp) => tcp_details(packet, tcp),
+        SerializablePacket::UdpPacket(udp) => udp_details(packet, udp),
+        SerializablePacket::GrePacket(gre) => gre_details(packet, gre),
+        SerializablePacket::VxlanPacket(vxlan) => vxlan_details(packet, vxlan),
+        SerializablePacket::GenevePacket(geneve) => geneve_details(packet, geneve),
+        SerializablePacket::DnsPacket(dns) => dns_details(packet, dns),
+        SerializablePacket::DhcpPacket(dhcp) => dhcp_details(packet, dhcp),
+        SerializablePacket::Dhcpv6Packet(dhcpv6) => dhcpv6_details(packet, dhcpv6),
+        SerializablePacket::HttpRequestPacket(request) => http_request_details(packet, request),
+        SerializablePacket::HttpResponsePacket(response) => http_response_details(packet, response),
+        SerializablePacket::TlsPacket(tls) => tls_details(packet, tls),
+        SerializablePacket::IcmpPacket(icmp) => icmp_details(packet, icmp),
+        SerializablePacket::EchoRequestPacket(echo) => echo_request_details(packet, echo),
+        SerializablePacket::EchoReplyPacket(echo) => echo_reply_details(packet, echo),
+        SerializablePacket::Icmpv6Packet(icmpv6) => icmpv6_details(packet, icmpv6),
+        SerializablePacket::MalformedPacket(reason) => {
+            LayerBuilder::new(packet, "malformed", "[Malformed Packet]".to_string())
+                .field("malformed.reason", "Reason", reason)
+                .build()
+        }
+    }
+}
+
+fn ethernet_details(packet: &ParsedPacket, ethernet: &SerializableEthernetPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "eth",
+        format!(
+            "Ethernet II, Src: {}, Dst: {}",
+            ethernet.source, ethernet.destination
+        ),
+    )
+    .field("eth.dst", "Destination", ethernet.destination)
+    .field("eth.src", "Source", ethernet.source)
+    .field("eth.type", "Type", &ethernet.ethertype)
+    .field(
+        "eth.payload",
+        "Payload",
+        format!("{} bytes", ethernet.payload.len()),
+    )
+    .build()
+}
+
+fn unknown_details(packet: &ParsedPacket, unknown: &SerializableUnknownPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "data",
+        format!(
+            "Data ({} bytes), EtherType {} not decoded",
+            unknown.length, unknown.ethertype
+        ),
+    )
+    .field("data", "Length", format!("{} bytes", unknown.length))
+    .build()
+}
+
+fn vlan_details(packet: &ParsedPacket, vlan: &SerializableVlanPacket) -> LayerDetails {
+    let kind = if vlan.tpid == 0x8100 {
+        "802.1Q Virtual LAN"
+    } else {
+        "802.1ad Service VLAN"
+    };
+    LayerBuilder::new(
+        packet,
+        "vlan",
+        format!("{}, PRI: {}, ID: {}", kind, vlan.priority, vlan.vlan_id),
+    )
+    .field(
+        "vlan.priority",
+        "Priority",
+        format!("{} ({})", vlan.priority, priority_name(vlan.priority)),
+    )
+    .field("vlan.dei", "Drop Eligible", vlan.drop_eligible)
+    .field("vlan.id", "ID", vlan.vlan_id)
+    .field("vlan.etype", "Type", &vlan.ethertype)
+    .build()
+}
+
+/// Traffic type of an 802.1Q priority code point, as in IEEE 802.1Q Annex I
+fn priority_name(priority: u8) -> &'static str {
+    match priority {
+        0 => "Best Effort",
+        1 => "Background",
+        2 => "Excellent Effort",
+        3 => "Critical Applications",
+        4 => "Video",
+        5 => "Voice",
+        6 => "Internetwork Control",
+        _ => "Network Control",
+    }
+}
+
+fn mpls_details(packet: &ParsedPacket, mpls: &SerializableMplsPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "mpls",
+        format!(
+            "MultiProtocol Label Switching Header, Label: {}, Exp: {}, S: {}, TTL: {}",
+            mpls.label, mpls.traffic_class, mpls.bottom_of_stack as u8, mpls.ttl
+        ),
+    )
+    .field("mpls.label", "Label", mpls_label_name(mpls.label))
+    .field("mpls.exp", "Traffic Class", mpls.traffic_class)
+    .field("mpls.bottom", "Bottom of Stack", mpls.bottom_of_stack)
+    .field("mpls.ttl", "TTL", mpls.ttl)
+    .build()
+}
+
+/// Labels 0 to 15 are reserved, RFC 3032 section 2.1
+fn mpls_label_name(label: u32) -> String {
+    let name = match label {
+        0 => "IPv4 Explicit NULL",
+        1 => "Router Alert",
+        2 => "IPv6 Explicit NULL",
+        3 => "Implicit NULL",
+        7 => "Entropy Label Indicator",
+        13 => "Generic Associated Channel",
+        14 => "OAM Alert",
+        15 => "Extension",
+        _ => return label.to_string(),
+    };
+    format!("{} ({})", label, name)
+}
+
+fn arp_details(packet: &ParsedPacket, arp: &SerializableArpPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "arp",
+        format!("Address Resolution Protocol, {}", arp.operation),
+    )
+    .field("arp.hw.type", "Hardware Type", &arp.hardware_type)
+    .field(
+        "arp.proto.type",
+        "Protocol Type",
+        match arp.protocol_type {
+            0x0800 => "0x0800 (IPv4)".to_string(),
+            other => format!("0x{:04x}", other),
+        },
+    )
+    .field("arp.hw.size", "Hardware Size", arp.hw_addr_len)
+    .field("arp.proto.size", "Protocol Size", arp.proto_addr_len)
+    .field("arp.opcode", "Opcode", &arp.operation)
+    .field("arp.src.hw_mac", "Sender MAC Address", arp.sender_hw_addr)
+    .field(
+        "arp.src.proto_ipv4",
+        "Sender IP Address",
+        arp.sender_proto_addr,
+    )
+    .field("arp.dst.hw_mac", "Target MAC Address", arp.target_hw_addr)
+    .field(
+        "arp.dst.proto_ipv4",
+        "Target IP Address",
+        arp.target_proto_addr,
+    )
+    .build()
+}
+
+/// Name of a Differentiated Services codepoint, per RFC 2474, 2597 and 3246
+fn dscp_name(dscp: u8) -> String {
+    match dscp {
+        0 => "Default".to_string(),
+        46 => "Expedited Forwarding".to_string(),
+        dscp if dscp % 8 == 0 => format!("Class Selector {}", dscp / 8),
+        dscp if dscp % 2 == 0 && (10..=38).contains(&dscp) => {
+            format!("Assured Forwarding {}{}", dscp / 8, (dscp % 8) / 2)
+        }
+        _ => "Unknown".to_string(),
+    }
+}
+
+fn ecn_name(ecn: u8) -> &'static str {
+    match ecn {
+        0 => "Not-ECT",
+        1 => "ECT(1)",
+        2 => "ECT(0)",
+        _ => "CE",
+    }
+}
+
+fn ipv4_details(packet: &ParsedPacket, ipv4: &SerializableIpv4Packet) -> LayerDetails {
+    let mut layer = ipv4_header(packet, ipv4);
+    if !packet.get_fragments().is_empty() {
+        layer = layer.field(
+            "ip.fragments",
+            "Reassembled From",
+            frame_list(packet.get_fragments()),
+        );
+    }
+    layer.build()
+}
+
+/// IPv4 header fields, shared with the datagrams quoted by ICMP errors
+fn ipv4_header<'a>(packet: &'a ParsedPacket, ipv4: &SerializableIpv4Packet) -> LayerBuilder<'a> {
+    let mut flags = Vec::new();
+    if ipv4.flags & 0b010 != 0 {
+        flags.push("Don't Fragment");
+    }
+    if ipv4.flags & 0b001 != 0 {
+        flags.push("More Fragments");
+    }
+
+    LayerBuilder::new(
+        packet,
+        "ip",
+        format!(
+            "Internet Protocol Version 4, Src: {}, Dst: {}",
+            ipv4.source, ipv4.destination
+        ),
+    )
+    .field("ip.version", "Version", ipv4.version)
+    .field(
+        "ip.hdr_len",
+        "Header Length",
+        format!(
+            "{} bytes ({})",
+            ipv4.header_length as usize * 4,
+            ipv4.header_length
+        ),
+    )
+    .field(
+        "ip.dsfield.dscp",
+        "DSCP",
+        format!("{} ({})", ipv4.dscp, dscp_name(ipv4.dscp)),
+    )
+    .field(
+        "ip.dsfield.ecn",
+        "ECN",
+        format!("{} ({})", ipv4.ecn, ecn_name(ipv4.ecn)),
+    )
+    .field("ip.len", "Total Length", ipv4.total_length)
+    .field(
+        "ip.id",
+        "Identification",
+        format!("0x{:04x} ({})", ipv4.identification, ipv4.identification),
+    )
+    .field(
+        "ip.flags",
+        "Flags",
+        format!("0x{:x} ({})", ipv4.flags, flags.join(", ")),
+    )
+    .field("ip.frag_offset", "Fragment Offset", ipv4.fragment_offset)
+    .field("ip.ttl", "Time to Live", ipv4.ttl)
+    .field("ip.proto", "Protocol", &ipv4.next_level_protocol)
+    .field(
+        "ip.checksum",
+        "Header Checksum",
+        format!("0x{:04x}", ipv4.checksum),
+    )
+    .field("ip.src", "Source Address", ipv4.source)
+    .field("ip.dst", "Destination Address", ipv4.destination)
+}
+
+/// Frame ids as `#3, #4, #7`
+fn frame_list(frames: &[usize]) -> String {
+    frames
+        .iter()
+        .map(|frame| format!("#{}", frame))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn ipv6_details(packet: &ParsedPacket, ipv6: &SerializableIpv6Packet) -> LayerDetails {
+    let mut layer = LayerBuilder::new(
+        packet,
+        "ipv6",
+        format!(
+            "Internet Protocol Version 6, Src: {}, Dst: {}",
+            ipv6.source, ipv6.destination
+        ),
+    )
+    .field("ipv6.version", "Version", ipv6.version)
+    .field(
+        "ipv6.tclass",
+        "Traffic Class",
+        format!(
+            "0x{:02x} (DSCP: {}, ECN: {})",
+            ipv6.traffic_class,
+            dscp_name(ipv6.traffic_class >> 2),
+            ecn_name(ipv6.traffic_class & 0b11)
+        ),
+    )
+    .field(
+        "ipv6.flow",
+        "Flow Label",
+        format!("0x{:05x}", ipv6.flow_label),
+    )
+    .field("ipv6.plen", "Payload Length", ipv6.payload_length)
+    .field("ipv6.nxt", "Next Header", &ipv6.next_header)
+    .field("ipv6.hlim", "Hop Limit", ipv6.hop_limit)
+    .field("ipv6.src", "Source Address", ipv6.source)
+    .field("ipv6.dst", "Destination Address", ipv6.destination);
+
+    for header in &ipv6.extension_headers {
+        let next_header = header.next_header.as_deref().unwrap_or_default();
+        let (key, value) = match &header.data {
+            SerializableIpv6ExtensionData::Options(_) => (
+                if header.header_type == 0 {
+                    "ipv6.hopopts"
+                } else {
+                    "ipv6.dstopts"
+                },
+                format!(
+                    "Next Header: {}, Length: {} bytes",
+                    next_header, header.length
+                ),
+            ),
+            SerializableIpv6ExtensionData::Routing {
+                routing_type,
+                segments_left,
+            } => (
+                "ipv6.routing",
+                format!(
+                    "Next Header: {}, Type: {}, Segments Left: {}",
+                    next_header, routing_type, segments_left
+                ),
+            ),
+            SerializableIpv6ExtensionData::Fragment {
+                offset,
+                more_fragments,
+                identification,
+            } => (
+                "ipv6.fraghdr",
+                format!(
+                    "Next Header: {}, Offset: {}, More Fragments: {}, ID: 0x{:08x}",
+                    next_header, offset, more_fragments, identification
+                ),
+            ),
+            SerializableIpv6ExtensionData::Authentication { spi, sequence } => (
+                "ah",
+                format!(
+                    "Next Header: {}, SPI: 0x{:08x}, Sequence: {}",
+                    next_header, spi, sequence
+                ),
+            ),
+            SerializableIpv6ExtensionData::Esp { spi, sequence } => (
+                "esp",
+                format!(
+                    "SPI: 0x{:08x}, Sequence: {}, {} bytes encrypted",
+                    spi,
+                    sequence,
+                    header.length.saturating_sub(8)
+                ),
+            ),
+        };
+        layer = layer.field(key, header.name(), value);
+    }
+    if !packet.get_fragments().is_empty() {
+        layer = layer.field(
+            "ipv6.fragments",
+            "Reassembled From",
+            frame_list(packet.get_fragments()),
+        );
+    }
+    layer.build()
+}
+
+fn tcp_details(packet: &ParsedPacket, tcp: &SerializableTcpPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "tcp",
+        format!(
+            "Transmission Control Protocol, Src Port: {}, Dst Port: {}, Seq: {}, Len: {}",
+            tcp.source, tcp.destination, tcp.sequence, tcp.length
+        ),
+    )
+    .field("tcp.srcport", "Source Port", tcp.source)
+    .field("tcp.dstport", "Destination Port", tcp.destination)
+    .field("tcp.seq", "Sequence Number", tcp.sequence)
+    .field("tcp.ack", "Acknowledgment Number", tcp.acknowledgement)
+    .field(
+        "tcp.hdr_len",
+        "Header Length",
+        format!(
+            "{} bytes ({})",
+            tcp.data_offset as usize * 4,
+            tcp.data_offset
+        ),
+    )
+    .field(
+        "tcp.flags",
+        "Flags",
+        format!("0x{:03x} ({})", tcp.flags, tcp.flag_names().join(", ")),
+    )
+    .field("tcp.window_size", "Window", tcp.window)
+    .field(
+        "tcp.checksum",
+        "Checksum",
+        format!("0x{:04x}", tcp.checksum),
+    )
+    .field("tcp.urgent_pointer", "Urgent Pointer", tcp.urgent_ptr)
+    .field(
+        "tcp.options",
+        "Options",
+        format!("{} bytes", tcp.options.len()),
+    )
+    .field("tcp.payload", "Payload", format!("{} bytes", tcp.length))
+    .build()
+}
+
+fn udp_details(packet: &ParsedPacket, udp: &SerializableUdpPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "udp",
+        format!(
+            "User Datagram Protocol, Src Port: {}, Dst Port: {}",
+            udp.source, udp.destination
+        ),
+    )
+    .field("udp.srcport", "Source Port", udp.source)
+    .field("udp.dstport", "Destination Port", udp.destination)
+    .field("udp.length", "Length", udp.length)
+    .field(
+        "udp.checksum",
+        "Checksum",
+        format!("0x{:04x}", udp.checksum),
+    )
+    .build()
+}
+
+fn gre_details(packet: &ParsedPacket, gre: &SerializableGrePacket) -> LayerDetails {
+    let mut title = format!(
+        "Generic Routing Encapsulation ({})",
+        protocol_type_name(gre.protocol_type)
+    );
+    if let Some(key) = gre.key {
+        title += &format!(", Key: 0x{:08x}", key);
+    }
+    let mut layer = LayerBuilder::new(packet, "gre", title)
+        .field("gre.flags", "Version", gre.version)
+        .field(
+            "gre.proto",
+            "Protocol Type",
+            protocol_type_name(gre.protocol_type),
+        );
+    if let Some(checksum) = gre.checksum {
+        layer = layer.field("gre.checksum", "Checksum", format!("0x{:04x}", checksum));
+    }
+    if let Some(key) = gre.key {
+        layer = layer.field("gre.key", "Key", format!("0x{:08x} ({})", key, key));
+    }
+    if let Some(sequence) = gre.sequence {
+        layer = layer.field("gre.seq", "Sequence Number", sequence);
+    }
+    if let Some(acknowledgement) = gre.acknowledgement {
+        layer = layer.field("gre.ack", "Acknowledgment Number", acknowledgement);
+    }
+    layer.build()
+}
+
+/// EtherType of a tunnel payload with its number, as tunnels often carry uncommon ones
+fn protocol_type_name(protocol_type: u16) -> String {
+    if protocol_type == TRANSPARENT_ETHERNET_BRIDGING {
+        return format!("Transparent Ethernet Bridging (0x{:04x})", protocol_type);
+    }
+    format!("{} (0x{:04x})", EtherType(protocol_type), protocol_type)
+}
+
+fn vxlan_details(packet: &ParsedPacket, vxlan: &SerializableVxlanPacket) -> LayerDetails {
+    LayerBuilder::new(
+        packet,
+        "vxlan",
+        format!("Virtual eXtensible Local Area Network, VNI: {}", vxlan.vni),
+    )
+    .field(
+        "vxlan.flags",
+        "Flags",
+        format!(
+            "0x{:02x}{}",
+            vxlan.flags,
+            if vxlan.flags & 0x08 != 0 {
+                " (VNI valid)"
+            } else {
+                ""
+            }
+        ),
+    )
+    .field("vxlan.vni", "VXLAN Network Identifier", vxlan.vni)
+    .build()
+}
+
+fn geneve_details(packet: &ParsedPacket, geneve: &SerializableGenevePacket) -> LayerDetails {
+    let mut flags = Vec::new();
+    if geneve.oam {
+        flags.push("Operations, Administration and Management");
+    }
+    if geneve.critical {
+        flags.push("Critical Options Present");
+    }
+    let mut layer = LayerBuilder::new(
+        packet,
+        "geneve",
+        format!(
+            "Generic Network Virtualization Encapsulation, VNI: {}",
+            geneve.vni
+        ),
+    )
+    .field("geneve.ver", "Version", geneve.version)
+    .field("geneve.flags", "Flags", flags.join(", "))
+    .field(
+        "geneve.proto",
+        "Protocol Type",
+        protocol_type_name(geneve.protocol_type),
+    )
+    .field("geneve.vni", "Virtual Network Identifier", geneve.vni);
+    for option in &geneve.options {
+        let data: String = option
+            .data
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        layer = layer.field(
+            "geneve.options",
+            "Option",
+            format!(
+                "Class: 0x{:04x}, Type: 0x{:02x}, Data: {}",
+                option.class, option.option_type, data
+            ),
+        );
+    }
+    layer.build()
+}
+
+fn dns_details(packet: &ParsedPacket, dns: &SerializableDnsPacket) -> LayerDetails {
+    let name = match dns.service {
+        DnsService::Dns => "Domain Name System",
+        DnsService::Mdns => "Multicast Domain Name System",
+        DnsService::Llmnr => "Link-local Multicast Name Resolution",
+    };
+    let kind = if dns.is_response() {
+        "response"
+    } else {
+        "query"
+    };
+    let mut flags = dns_opcode_name(dns.opcode());
+    if dns.is_response() {
+        flags += &format!(" response, {}", dns_rcode_name(dns.rcode()));
+    }
+    if !dns.flag_names().is_empty() {
+        flags += &format!(" ({})", dns.flag_names().join(", "));
+    }
+
+    let mut layer = LayerBuilder::new(packet, "dns", format!("{} ({})", name, kind))
+        .field("dns.id", "Transaction ID", format!("0x{:04x}", dns.id))
+        .field(
+            "dns.flags",
+            "Flags",
+            format!("0x{:04x} {}", dns.flags, flags),
+        )
+        .field("dns.count.queries", "Questions", dns.questions.len())
+        .field("dns.count.answers", "Answer RRs", dns.answers.len())
+        .field("dns.count.auth_rr", "Authority RRs", dns.authorities.len())
+        .field("dns.count.add_rr", "Additional RRs", dns.additionals.len());
+
+    for (n, question) in dns.questions.iter().enumerate() {
+        let mut value = format!(
+            "{}: type {}, class {}",
+            question.name,
+            dns_type_name(question.record_type),
+            dns_class_name(question.class)
+        );
+        if dns.service == DnsService::Mdns && question.class & 0x8000 != 0 {
+            value += ", unicast response";
+        }
+        layer = layer.nth_field("dns.qry", n, "Query", value);
+    }
+    let sections = [
+        ("Answer", &dns.answers),
+        ("Authority", &dns.authorities),
+        ("Additional", &dns.additionals),
+    ];
+    let records = sections
+        .into_iter()
+        .flat_map(|(section, records)| records.iter().map(move |record| (section, record)));
+    for (n, (section, record)) in records.enumerate() {
+        layer = layer.nth_field("dns.resp", n, section, record_summary(dns, record));
+    }
+
+    if let Some(query) = dns.response_to {
+        layer = layer.field("dns.response_to", "Request In", format!("#{}", query));
+    }
+    if let Some(time) = dns.time {
+        layer = layer.field(
+            "dns.time",
+            "Time",
+            format!("{:.6} seconds", time.as_secs_f64()),
+        );
+    }
+    if dns.unanswered {
+        layer = layer.field("dns.unanswered", "Response", "None seen");
+    }
+    layer.build()
+}
+
+/// A resource record on one line, as `name: type A, class IN, ttl 300, 192.0.2.1`
+fn record_summary(dns: &SerializableDnsPacket, record: &SerializableDnsRecord) -> String {
+    if let SerializableDnsData::Opt { .. } = record.data {
+        return format!("{}: type OPT, {}", record.name, record.data);
+    }
+    let mut summary = format!(
+        "{}: type {}, class {}",
+        record.name,
+        dns_type_name(record.record_type),
+        dns_class_name(record.class)
+    );
+    if dns.service == DnsService::Mdns && record.class & 0x8000 != 0 {
+        summary += ", cache flush";
+    }
+    format!("{}, ttl {}, {}", summary, record.ttl, record.data)
+}
+
+fn dhcp_details(packet: &ParsedPacket, dhcp: &SerializableDhcpPacket) -> LayerDetails {
+    let (name, kind) = match dhcp.message_type() {
+        Some(message_type) => (
+            "Dynamic Host Configuration Protocol",
+            dhcp_message_type_name(message_type),
+        ),
+        None => ("Bootstrap Protocol", String::new()),
+    };
+    let title = match kind.as_str() {
+        "" => name.to_string(),
+        kind => format!("{} ({})", name, kind),
+    };
+    let op = match dhcp.op {
+        1 => "Boot Request",
+        2 => "Boot Reply",
+        _ => "Unknown",
+    };
+    let hardware_type = match dhcp.hardware_type {
+        1 => "Ethernet",
+        6 => "IEEE 802",
+        _ => "Unknown",
+    };
+    let flags = if dhcp.flags & 0x8000 != 0 {
+        "Broadcast"
+    } else {
+        "Unicast"
+    };
+    let mut layer = LayerBuilder::new(packet, "dhcp", title)
+        .field("dhcp.type", "Message type", format!("{} ({})", op, dhcp.op))
+        .field(
+            "dhcp.hw.type",
+            "Hardware type",
+            format!("{} (0x{:02x})", hardware_type, dhcp.hardware_type),
+        )
+        .field(
+            "dhcp.hw.len",
+            "Hardware address length",
+            dhcp.hardware_length,
+        )
+        .field("dhcp.hops", "Hops", dhcp.hops)
+        .field(
+            "dhcp.id",
+            "Transaction ID",
+            format!("0x{:08x}", dhcp.transaction_id),
+        )
+        .field("dhcp.secs", "Seconds elapsed", dhcp.seconds)
+        .field(
+            "dhcp.flags",
+            "Bootp flags",
+            format!("0x{:04x} ({})", dhcp.flags, flags),
+        )
+        .field("dhcp.ip.client", "Client IP address", dhcp.client_address)
+        .field(
+            "dhcp.ip.your",
+            "Your (client) IP address",
+            dhcp.your_address,
+        )
+        .field(
+            "dhcp.ip.server",
+            "Next server IP address",
+            dhcp.server_address,
+        )
+        .field(
+            "dhcp.ip.relay",
+            "Relay agent IP address",
+            dhcp.relay_address,
+        )
+        .field(
+            "dhcp.hw.mac_addr",
+            "Client hardware address",
+            format_hardware_address(&dhcp.client_hardware_address),
+        );
+    if !dhcp.server_name.is_empty() {
+        layer = layer.field("dhcp.server", "Server host name", &dhcp.server_name);
+    }
+    if !dhcp.boot_file.is_empty() {
+        layer = layer.field("dhcp.file", "Boot file name", &dhcp.boot_file);
+    }
+    for (n, option) in dhcp.options.iter().enumerate() {
+        layer = layer.nth_field(
+            "dhcp.option",
+            n,
+            "Option",
+            format!(
+                "({}) {}: {}",
+                option.code,
+                dhcp_option_name(option.code),
+                option.data
+            ),
+        );
+    }
+    layer.build()
+}
+
+fn dhcpv6_details(packet: &ParsedPacket, dhcpv6: &SerializableDhcpv6Packet) -> LayerDetails {
+    let mut layer = LayerBuilder::new(packet, "dhcpv6", "DHCPv6".to_string()).field(
+        "dhcpv6.msgtype",
+        "Message type",
+        format!(
+            "{} ({})",
+            dhcpv6_message_type_name(dhcpv6.message_type),
+            dhcpv6.message_type
+        ),
+    );
+    match &dhcpv6.relay {
+        Some(relay) => {
+            layer = layer
+                .field("dhcpv6.hopcount", "Hop count", relay.hop_count)
+                .field("dhcpv6.linkaddr", "Link address", relay.link_address)
+                .field("dhcpv6.peeraddr", "Peer address", relay.peer_address);
+        }
+        None => {
+            layer = layer.field(
+                "dhcpv6.xid",
+                "Transaction ID",
+                format!("0x{:06x}", dhcpv6.transaction_id),
+            );
+        }
+    }
+    for (n, option) in dhcpv6.options.iter().enumerate() {
+        layer = layer.nth_field("dhcpv6.option", n, "Option", dhcpv6_option_value(option));
+        layer = dhcpv6_suboption_fields(layer, option);
+    }
+    layer.build()
+}
+
+fn dhcpv6_option_value(option: &SerializableDhcpv6Option) -> String {
+    let mut value = format!("({}) {}", option.code, dhcpv6_option_name(option.code));
+    let data = option.data.to_string();
+    if !data.is_empty() {
+        value += &format!(": {}", data);
+    }
+    value
+}
+
+/// Add the options nested in an identity association or a relayed message, which have no
+/// ranges of their own
+fn dhcpv6_suboption_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    option: &SerializableDhcpv6Option,
+) -> LayerBuilder<'a> {
+    let options = match &option.data {
+        SerializableDhcpv6OptionData::Ia { options, .. }
+        | SerializableDhcpv6OptionData::IaTemporary { options, .. }
+        | SerializableDhcpv6OptionData::IaAddress { options, .. }
+        | SerializableDhcpv6OptionData::IaPrefix { options, .. } => options,
+        SerializableDhcpv6OptionData::RelayMessage(message) => {
+            if message.relay.is_none() {
+                layer = layer.field(
+                    "dhcpv6.suboption",
+                    "Relayed transaction ID",
+                    format!("0x{:06x}", message.transaction_id),
+                );
+            }
+            &message.options
+        }
+        _ => return layer,
+    };
+    for option in options {
+        layer = layer.field(
+            "dhcpv6.suboption",
+            "Sub-option",
+            dhcpv6_option_value(option),
+        );
+        layer = dhcpv6_suboption_fields(layer, option);
+    }
+    layer
+}
+
+fn http_request_details(
+    packet: &ParsedPacket,
+    request: &SerializableHttpRequestPacket,
+) -> LayerDetails {
+    let title = format!(
+        "Hypertext Transfer Protocol, {} {} {}",
+        request.method, request.uri, request.version
+    );
+    let layer = LayerBuilder::new(packet, "http", title)
+        .field("http.request.method", "Request Method", &request.method)
+        .field("http.request.uri", "Request URI", &request.uri)
+        .field("http.request.version", "Request Version", &request.version);
+    http_message_fields(
+        packet,
+        layer,
+        &request.headers,
+        &request.body,
+        request.first_packet,
+    )
+    .build()
+}
+
+fn http_response_details(
+    packet: &ParsedPacket,
+    response: &SerializableHttpResponsePacket,
+) -> LayerDetails {
+    let title = format!(
+        "Hypertext Transfer Protocol, {} {} {}",
+        response.version, response.status, response.reason
+    );
+    let mut layer = LayerBuilder::new(packet, "http", title)
+        .field(
+            "http.response.version",
+            "Response Version",
+            &response.version,
+        )
+        .field("http.response.code", "Status Code", response.status)
+        .field("http.response.phrase", "Response Phrase", &response.reason);
+    layer = http_message_fields(
+        packet,
+        layer,
+        &response.headers,
+        &response.body,
+        response.first_packet,
+    );
+    if let Some(request) = &response.request {
+        layer = layer
+            .field(
+                "http.request_in",
+                "Request In",
+                format!("#{}", request.packet),
+            )
+            .field(
+                "http.response_for.uri",
+                "Request URI",
+                format!("{} {}", request.method, request.uri),
+            )
+            .field(
+                "http.time",
+                "Time Since Request",
+                format!("{:.6} seconds", request.time.as_secs_f64()),
+            );
+    }
+    layer.build()
+}
+
+/// Headers and body, shared by requests and responses
+fn http_message_fields<'a>(
+    packet: &ParsedPacket,
+    mut layer: LayerBuilder<'a>,
+    headers: &[SerializableHttpHeader],
+    body: &SerializableHttpBody,
+    first_packet: usize,
+) -> LayerBuilder<'a> {
+    for header in headers {
+        layer = layer.field(
+            "http.header",
+            "Header",
+            format!("{}: {}", header.name, header.value),
+        );
+    }
+    if body.length > 0 || body.chunked {
+        let mut value = format_size(body.length);
+        if body.chunked {
+            value += ", chunked";
+        }
+        if body.data.len() < body.length {
+            value += &format!(", first {} kept", format_size(body.data.len()));
+        }
+        layer = layer.field("http.file_data", "Body", value);
+    }
+    if first_packet != packet.get_id() {
+        layer = layer.field(
+            "http.reassembled",
+            "Reassembled From",
+            format!("#{} to #{}", first_packet, packet.get_id()),
+        );
+    }
+    layer
+}
+
+fn tls_details(packet: &ParsedPacket, tls: &SerializableTlsPacket) -> LayerDetails {
+    let mut layer = LayerBuilder::new(packet, "tls", "Transport Layer Security".to_string());
+    for record in &tls.records {
+        let mut kind = tls_content_type_name(record.content_type);
+        if let SerializableTlsContent::Encrypted = record.content {
+            kind = format!("Encrypted {}", kind);
+        }
+        layer = layer.field(
+            "tls.record",
+            "Record",
+            format!(
+                "{} Record Layer: {}, {} bytes",
+                tls_version_name(record.version),
+                kind,
+                record.length
+            ),
+        );
+        match &record.content {
+            SerializableTlsContent::Alert { level, description } => {
+                let level_name = if *level == 2 { "Fatal" } else { "Warning" };
+                layer = layer
+                    .field(
+                        "tls.alert_message.level",
+                        "Alert Level",
+                        format!("{} ({})", level_name, level),
+                    )
+                    .field(
+                        "tls.alert_message.desc",
+                        "Alert Description",
+                        format!("{} ({})", tls_alert_name(*description), description),
+                    );
+            }
+            SerializableTlsContent::Handshake(messages) => {
+                for message in messages {
+                    layer = tls_handshake_fields(layer, message);
+                }
+            }
+            _ => {}
+        }
+    }
+    layer.build()
+}
+
+fn tls_handshake_fields<'a>(
+    layer: LayerBuilder<'a>,
+    message: &SerializableTlsHandshake,
+) -> LayerBuilder<'a> {
+    let handshake_type = match message {
+        SerializableTlsHandshake::ClientHello(_) => 1,
+        SerializableTlsHandshake::ServerHello(_) => 2,
+        SerializableTlsHandshake::Certificate(_) => 11,
+        SerializableTlsHandshake::Other { handshake_type, .. } => *handshake_type,
+    };
+    let layer = layer.field(
+        "tls.handshake.type",
+        "Handshake Type",
+        format!(
+            "{} ({})",
+            tls_handshake_type_name(handshake_type),
+            handshake_type
+        ),
+    );
+    match message {
+        SerializableTlsHandshake::ClientHello(hello) => tls_client_hello_fields(layer, hello),
+        SerializableTlsHandshake::ServerHello(hello) => tls_server_hello_fields(layer, hello),
+        SerializableTlsHandshake::Certificate(certificates) => {
+            certificates.iter().fold(layer, tls_certificate_fields)
+        }
+        SerializableTlsHandshake::Other { length, .. } => layer.field(
+            "tls.handshake.length",
+            "Length",
+            format!("{} bytes", length),
+        ),
+    }
+}
+
+fn tls_client_hello_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    hello: &SerializableTlsClientHello,
+) -> LayerBuilder<'a> {
+    layer = tls_hello_fields(layer, hello.version, &hello.random, &hello.session_id);
+    for &suite in &hello.cipher_suites {
+        layer = layer.field(
+            "tls.handshake.ciphersuite",
+            "Cipher Suite",
+            format!("{} (0x{:04x})", tls_cipher_suite_name(suite), suite),
+        );
+    }
+    layer = tls_extension_fields(layer, &hello.extensions);
+    if let Some(name) = hello.server_name() {
+        layer = layer.field("tls.handshake.extensions_server_name", "Server Name", name);
+    }
+    for protocol in hello.alpn() {
+        layer = layer.field(
+            "tls.handshake.extensions_alpn_str",
+            "ALPN Protocol",
+            protocol,
+        );
+    }
+    layer
+        .field("tls.handshake.ja3_full", "JA3 Fullstring", &hello.ja3_full)
+        .field("tls.handshake.ja3", "JA3", &hello.ja3)
+        .field("tls.handshake.ja4", "JA4", &hello.ja4)
+}
+
+fn tls_server_hello_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    hello: &SerializableTlsServerHello,
+) -> LayerBuilder<'a> {
+    layer = tls_hello_fields(layer, hello.version, &hello.random, &hello.session_id).field(
+        "tls.handshake.ciphersuite",
+        "Cipher Suite",
+        format!(
+            "{} (0x{:04x})",
+            tls_cipher_suite_name(hello.cipher_suite),
+            hello.cipher_suite
+        ),
+    );
+    layer = tls_extension_fields(layer, &hello.extensions);
+    if hello.selected_version() != hello.version {
+        layer = layer.field(
+            "tls.handshake.extensions.supported_version",
+            "Selected Version",
+            tls_version_name(hello.selected_version()),
+        );
+    }
+    if let Some(protocol) = hello.alpn() {
+        layer = layer.field(
+            "tls.handshake.extensions_alpn_str",
+            "ALPN Protocol",
+            protocol,
+        );
+    }
+    layer
+        .field(
+            "tls.handshake.ja3s_full",
+            "JA3S Fullstring",
+            &hello.ja3s_full,
+        )
+        .field("tls.handshake.ja3s", "JA3S", &hello.ja3s)
+}
+
+/// Fields both hellos start with
+fn tls_hello_fields<'a>(
+    layer: LayerBuilder<'a>,
+    version: u16,
+    random: &[u8],
+    session_id: &[u8],
+) -> LayerBuilder<'a> {
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() };
+    layer
+        .field(
+            "tls.handshake.version",
+            "Version",
+            format!("{} (0x{:04x})", tls_version_name(version), version),
+        )
+        .field("tls.handshake.random", "Random", hex(random))
+        .field(
+            "tls.handshake.session_id",
+            "Session ID",
+            if session_id.is_empty() {
+                "empty".to_string()
+            } else {
+                hex(session_id)
+            },
+        )
+}
+
+fn tls_extension_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    extensions: &[SerializableTlsExtension],
+) -> LayerBuilder<'a> {
+    for extension in extensions {
+        let mut value = format!(
+            "{} ({})",
+            tls_extension_name(extension.extension_type),
+            extension.extension_type
+        );
+        let data = extension.data.to_string();
+        if !data.is_empty() {
+            value += &format!(": {}", data);
+        }
+        layer = layer.field("tls.handshake.extension.type", "Extension", value);
+    }
+    layer
+}
+
+fn tls_certificate_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    certificate: &SerializableTlsCertificate,
+) -> LayerBuilder<'a> {
+    layer = layer
+        .field(
+            "tls.handshake.certificate.subject",
+            "Certificate Subject",
+            &certificate.subject,
+        )
+        .field(
+            "tls.handshake.certificate.issuer",
+            "Issuer",
+            &certificate.issuer,
+        )
+        .field(
+            "tls.handshake.certificate.serial",
+            "Serial Number",
+            &certificate.serial,
+        )
+        .field(
+            "tls.handshake.certificate.not_before",
+            "Not Before",
+            &certificate.not_before,
+        )
+        .field(
+            "tls.handshake.certificate.not_after",
+            "Not After",
+            &certificate.not_after,
+        );
+    if !certificate.alt_names.is_empty() {
+        layer = layer.field(
+            "tls.handshake.certificate.alt_names",
+            "Subject Alt Names",
+            certificate.alt_names.join(", "),
+        );
+    }
+    layer
+}
+
+fn icmp_details(packet: &ParsedPacket, icmp: &SerializableIcmpPacket) -> LayerDetails {
+    let icmp_type = icmp.type_number();
+    let mut layer = LayerBuilder::new(
+        packet,
+        "icmp",
+        "Internet Control Message Protocol".to_string(),
+    )
+    .field(
+        "icmp.type",
+        "Type",
+        format!("{} ({})", icmp_type, icmp_type_name(icmp_type)),
+    )
+    .field(
+        "icmp.code",
+        "Code",
+        match icmp.code_description() {
+            Some(description) => format!("{} ({})", icmp.icmp_code, description),
+            None => icmp.icmp_code.to_string(),
+        },
+    )
+    .field(
+        "icmp.checksum",
+        "Checksum",
+        format!("0x{:04x}", icmp.checksum),
+    );
+    layer = match &icmp.body {
+        SerializableIcmpBody::DestinationUnreachable {
+            next_hop_mtu: Some(mtu),
+            ..
+        } => layer.field("icmp.mtu", "MTU of Next Hop", mtu),
+        SerializableIcmpBody::Redirect { gateway, .. } => {
+            layer.field("icmp.redir_gw", "Gateway Address", gateway)
+        }
+        SerializableIcmpBody::ParameterProblem { pointer, .. } => {
+            layer.field("icmp.pointer", "Pointer", pointer)
+        }
+        SerializableIcmpBody::RouterAdvertisement { lifetime, entries } => {
+            layer = layer
+                .field("icmp.num_addrs", "Number of Addresses", entries.len())
+                .field(
+                    "icmp.lifetime",
+                    "Lifetime",
+                    format_seconds(*lifetime as u32),
+                );
+            for (i, entry) in entries.iter().enumerate() {
+                layer = layer
+                    .nth_field("icmp.router_address", i, "Router Address", entry.address)
+                    .nth_field("icmp.pref_level", i, "Preference Level", entry.preference);
+            }
+            layer
+        }
+        SerializableIcmpBody::Timestamp {
+            identifier,
+            sequence_number,
+            originate,
+            receive,
+            transmit,
+        } => layer
+            .field(
+                "icmp.ident",
+                "Identifier",
+                format!("0x{:04x} ({})", identifier, identifier),
+            )
+            .field("icmp.seq", "Sequence Number", sequence_number)
+            .field(
+                "icmp.originate_timestamp",
+                "Originate Timestamp",
+                format_icmp_timestamp(*originate),
+            )
+            .field(
+                "icmp.receive_timestamp",
+                "Receive Timestamp",
+                format_icmp_timestamp(*receive),
+            )
+            .field(
+                "icmp.transmit_timestamp",
+                "Transmit Timestamp",
+                format_icmp_timestamp(*transmit),
+            ),
+        SerializableIcmpBody::AddressMask {
+            identifier,
+            sequence_number,
+            mask,
+        } => layer
+            .field(
+                "icmp.ident",
+                "Identifier",
+                format!("0x{:04x} ({})", identifier, identifier),
+            )
+            .field("icmp.seq", "Sequence Number", sequence_number)
+            .field("icmp.address_mask", "Address Mask", mask),
+        _ => layer,
+    };
+    if let Some(id) = icmp.original().and_then(|original| original.original_in) {
+        layer = layer.field(
+            "icmp.original_in",
+            "Original Datagram In",
+            format!("#{}", id),
+        );
+    }
+    layer
+        .field("icmp.payload", "Payload", format!("{} bytes", icmp.length))
+        .build()
+}
+
+/// Headers of the datagram quoted by an ICMP error, their ranges are looked up by the caller
+fn quote_details(packet: &ParsedPacket, quote: &SerializableIcmpQuote) -> Vec<LayerDetails> {
+    let mut layers = vec![ipv4_header(packet, &quote.ipv4).build()];
+    match &quote.transport {
+        Some(SerializableIcmpQuotedTransport::Tcp {
+            source,
+            destination,
+            sequence,
+        }) => layers.push(
+            LayerBuilder::new(
+                packet,
+                "tcp",
+                format!(
+                    "Transmission Control Protocol, Src Port: {}, Dst Port: {}, Seq: {}",
+                    source, destination, sequence
+                ),
+            )
+            .field("tcp.srcport", "Source Port", source)
+            .field("tcp.dstport", "Destination Port", destination)
+            .field("tcp.seq", "Sequence Number", sequence)
+            .build(),
+        ),
+        Some(SerializableIcmpQuotedTransport::Udp(udp)) => layers.push(udp_details(packet, udp)),
+        Some(SerializableIcmpQuotedTransport::Echo {
+            icmp_type,
+            icmp_code,
+            identifier,
+            sequence_number,
+        }) => layers.push(
+            LayerBuilder::new(
+                packet,
+                "icmp",
+                "Internet Control Message Protocol".to_string(),
+            )
+            .field(
+                "icmp.type",
+                "Type",
+                format!("{} ({})", icmp_type, icmp_type_name(*icmp_type)),
+            )
+            .field("icmp.code", "Code", icmp_code)
+            .field(
+                "icmp.ident",
+                "Identifier",
+                format!("0x{:04x} ({})", identifier, identifier),
+            )
+            .field("icmp.seq", "Sequence Number", sequence_number)
+            .build(),
+        ),
+        None => {}
+    }
+    for layer in &mut layers {
+        layer.title.insert_str(0, "[Quoted] ");
+    }
+    layers
+}
+
+fn echo_request_details(
+    packet: &ParsedPacket,
+    echo: &SerializableEchoRequestPacket,
+) -> LayerDetails {
+    let mut layer = LayerBuilder::new(
+        packet,
+        "icmp",
+        "Internet Control Message Protocol".to_string(),
+    )
+    .field(
+        "icmp.type",
+        "Type",
+        format!("{} (Echo (ping) request)", echo.icmp_type),
+    )
+    .field("icmp.code", "Code", echo.icmp_code)
+    .field(
+        "icmp.checksum",
+        "Checksum",
+        format!("0x{:04x}", echo.checksum),
+    )
+    .field(
+        "icmp.ident",
+        "Identifier",
+        format!("0x{:04x} ({})", echo.identifier, echo.identifier),
+    )
+    .field("icmp.seq", "Sequence Number", echo.sequence_number)
+    .field("icmp.payload", "Payload", format!("{} bytes", echo.length));
+    if echo.unanswered {
+        layer = layer.field("icmp.no_resp", "Response", "None seen");
+    }
+    layer.build()
+}
+
+fn echo_reply_details(packet: &ParsedPacket, echo: &SerializableEchoReplyPacket) -> LayerDetails {
+    let layer = LayerBuilder::new(
+        packet,
+        "icmp",
+        "Internet Control Message Protocol".to_string(),
+    )
+    .field(
+        "icmp.type",
+        "Type",
+        format!("{} (Echo (ping) reply)", echo.icmp_type),
+    )
+    .field("icmp.code", "Code", echo.icmp_code)
+    .field(
+        "icmp.checksum",
+        "Checksum",
+        format!("0x{:04x}", echo.checksum),
+    )
+    .field(
+        "icmp.ident",
+        "Identifier",
+        format!("0x{:04x} ({})", echo.identifier, echo.identifier),
+    )
+    .field("icmp.seq", "Sequence Number", echo.sequence_number)
+    .field("icmp.payload", "Payload", format!("{} bytes", echo.length));
+    echo_reply_fields(layer, "icmp", echo.response_to, echo.time, echo.duplicate).build()
+}
+
+/// Add the request an echo reply answers, `protocol` is `icmp` or `icmpv6`
+fn echo_reply_fields<'a>(
+    mut layer: LayerBuilder<'a>,
+    protocol: &'static str,
+    response_to: Option<usize>,
+    time: Option<Duration>,
+    duplicate: bool,
+) -> LayerBuilder<'a> {
+    let (response_key, time_key, duplicate_key) = match protocol {
+        "icmp" => ("icmp.resp_to", "icmp.resptime", "icmp.duplicate"),
+        _ => ("icmpv6.resp_to", "icmpv6.resptime", "icmpv6.duplicate"),
+    };
+    if let Some(request) = response_to {
+        layer = layer.field(response_key, "Response To", format!("#{}", request));
+    }
+    if let Some(time) = time {
+        layer = layer.field(
+            time_key,
+            "Response Time",
+            format!("{:.3} ms", time.as_secs_f64() * 1000.0),
+        );
+    }
+    if duplicate {
+        layer = layer.field(duplicate_key, "Duplicate", "Request answered already");
+    }
+    layer
+}
+
+fn icmpv6_details(packet: &ParsedPacket, icmpv6: &SerializableIcmpv6Packet) -> LayerDetails {
+    let icmpv6_type = icmpv6.type_number();
+    let mut layer = LayerBuilder::new(
+        packet,
+        "icmpv6",
+        "Internet Control Message Protocol v6".to_string(),
+    )
+    .field(
+        "icmpv6.type",
+        "Type",
+        format!("{} ({})", icmpv6_type, icmpv6_type_name(icmpv6_type)),
+    )
+    .field("icmpv6.code", "Code", icmpv6.icmpv6_code)
+    .field(
+        "icmpv6.checksum",
+        "Checksum",
+        format!("0x{:04x}", icmpv6.checksum),
+    );
+    let flags = |flags: u8| {
+        let names = ndp_flag_names(icmpv6_type, flags);
+        if names.is_empty() {
+            format!("0x{:02x}", flags)
+        } else {
+            format!("0x{:02x} ({})", flags, names.join(", "))
+        }
+    };
+    layer = match &icmpv6.body {
+        SerializableIcmpv6Body::RouterAdvertisement {
+            hop_limit,
+            flags: router_flags,
+            lifetime,
+            reachable_time,
+            retransmit_timer,
+            ..
+        } => {
+            let preference = match router_flags >> 3 & 0x03 {
+                0 => "Medium",
+                1 => "High",
+                3 => "Low",
+                _ => "Reserved",
+            };
+            layer
+                .field("icmpv6.nd.ra.cur_hop_limit", "Cur hop limit", hop_limit)
+                .field(
+                    "icmpv6.nd.ra.flag",
+                    "Flags",
+                    format!("{}, Preference: {}", flags(*router_flags), preference),
+                )
+                .field(
+                    "icmpv6.nd.ra.router_lifetime",
+                    "Router lifetime",
+                    format_seconds(*lifetime as u32),
+                )
+                .field(
+                    "icmpv6.nd.ra.reachable_time",
+                    "Reachable time (ms)",
+                    reachable_time,
+                )
+                .field(
+                    "icmpv6.nd.ra.retrans_timer",
+                    "Retrans timer (ms)",
+                    retransmit_timer,
+                )
+        }
+        SerializableIcmpv6Body::EchoRequest {
+            identifier,
+            sequence_number,
+            unanswered,
+        } => {
+            layer = layer
+                .field(
+                    "icmpv6.echo.identifier",
+                    "Identifier",
+                    format!("0x{:04x} ({})", identifier, identifier),
+                )
+                .field("icmpv6.echo.sequence_number", "Sequence", sequence_number)
+                .field(
+                    "icmpv6.payload",
+                    "Payload",
+                    format!("{} bytes", icmpv6.length.saturating_sub(4)),
+                );
+            if *unanswered {
+                layer = layer.field("icmpv6.no_resp", "Response", "None seen");
+            }
+            layer
+        }
+        SerializableIcmpv6Body::EchoReply {
+            identifier,
+            sequence_number,
+            response_to,
+            time,
+            duplicate,
+        } => {
+            layer = layer
+                .field(
+                    "icmpv6.echo.identifier",
+                    "Identifier",
+                    format!("0x{:04x} ({})", identifier, identifier),
+                )
+                .field("icmpv6.echo.sequence_number", "Sequence", sequence_number)
+                .field(
+                    "icmpv6.payload",
+                    "Payload",
+                    format!("{} bytes", icmpv6.length.saturating_sub(4)),
+                );
+            echo_reply_fields(layer, "icmpv6", *response_to, *time, *duplicate)
+        }
+        SerializableIcmpv6Body::NeighborSolicitation { target, .. } => {
+            layer.field("icmpv6.nd.ns.target_address", "Target Address", target)
+        }
+        SerializableIcmpv6Body::NeighborAdvertisement {
+            flags: neighbor_flags,
+            target,
+            ..
+        } => layer
+            .field("icmpv6.nd.na.flag", "Flags", flags(*neighbor_flags))
+            .field("icmpv6.nd.na.target_address", "Target Address", target),
+        SerializableIcmpv6Body::Redirect {
+            target,
+            destination,
+            ..
+        } => layer
+            .field("icmpv6.nd.rd.target_address", "Target Address", target)
+            .field(
+                "icmpv6.nd.rd.destination_address",
+                "Destination Address",
+                destination,
+            ),
+        SerializableIcmpv6Body::MulticastListenerQuery {
+            maximum_response_delay,
+            address,
+            v2,
+        } => {
+            layer = layer
+                .field(
+                    "icmpv6.mld.maximum_response_delay",
+                    "Maximum Response Delay (ms)",
+                    maximum_response_delay,
+                )
+                .field("icmpv6.mld.multicast_address", "Multicast Address", address);
+            if let Some(v2) = v2 {
+                layer = layer
+                    .field(
+                        "icmpv6.mld.flag",
+                        "Flags",
+                        format!("S={}, QRV={}", u8::from(v2.suppress), v2.robustness),
+                    )
+                    .field(
+                        "icmpv6.mld.qqi",
+                        "Querier's Query Interval",
+                        format_seconds(v2.interval),
+                    )
+                    .field(
+                        "icmpv6.mld.nb_sources",
+                        "Number of Sources",
+                        v2.sources.len(),
+                    );
+                for (n, source) in v2.sources.iter().enumerate() {
+                    layer =
+                        layer.nth_field("icmpv6.mld.source_address", n, "Source Address", source);
+                }
+            }
+            layer
+        }
+        SerializableIcmpv6Body::MulticastListenerReport { address }
+        | SerializableIcmpv6Body::MulticastListenerDone { address } => {
+            layer.field("icmpv6.mld.multicast_address", "Multicast Address", address)
+        }
+        SerializableIcmpv6Body::MulticastListenerReportV2 { records } => {
+            layer = layer.field(
+                "icmpv6.mldr.nb_mcast_records",
+                "Number of Multicast Address Records",
+                records.len(),
+            );
+            for (n, record) in records.iter().enumerate() {
+                let mut value = format!(
+                    "{}, {}",
+                    mld_record_type_name(record.record_type),
+                    record.address
+                );
+                if !record.sources.is_empty() {
+                    let sources: Vec<String> = record
+                        .sources
+                        .iter()
+                        .map(|source| source.to_string())
+                        .collect();
+                    value += &format!(", sources {}", sources.join(", "));
+                }
+                layer = layer.nth_field("icmpv6.mldr.mar", n, "Multicast Address Record", value);
+            }
+            layer
+        }
+        _ => layer,
+    };
+    for (n, option) in icmpv6.options().iter().enumerate() {
+        layer = layer.nth_field(
+            "icmpv6.opt",
+            n,
+            "Option",
+            format!(
+                "({}) {}: {}",
+                option.option_type,
+                ndp_option_name(option.option_type),
+                option.data
+            ),
+        );
+    }
+    if matches!(icmpv6.body, SerializableIcmpv6Body::Other) {
+        layer = layer.field(
+            "icmpv6.payload",
+            "Payload",
+            format!("{} bytes", icmpv6.length),
+        );
+    }
+    layer.build()
+}
@@ -0,0 +1,23 @@
+//! Raw bytes are serialized as lowercase hex strings, which keeps JSON output compact
+
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    serializer.serialize_str(&hex)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    if hex.len() % 2 != 0 {
+        return Err(D::Error::custom("hex string has an odd length"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| D::Error::custom(format!("invalid hex byte at {}", i)))
+        })
+        .collect()
+}
@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use super::*;
+
+/// Where a layer sits in the protocol stack of its packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Level {
+    Link,
+    /// VLAN tags and MPLS labels between the link and network layers
+    Encapsulation,
+    Network,
+    Transport,
+    Application,
+}
+
+/// Protocol a layer was decoded as
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Protocol {
+    Ethernet,
+    Vlan,
+    Mpls,
+    Arp,
+    Ipv4,
+    Ipv6,
+    Icmp,
+    Icmpv6,
+    Tcp,
+    Udp,
+    Gre,
+    Vxlan,
+    Geneve,
+    Dns,
+    Dhcp,
+    Dhcpv6,
+    Http,
+    Tls,
+    /// Payload of a protocol that is not decoded
+    Data,
+    Malformed,
+}
+
+impl Protocol {
+    /// Get the protocol's display filter name, which its header's byte range is recorded under
+    pub fn filter_name(self) -> &'static str {
+        match self {
+            Protocol::Ethernet => "eth",
+            Protocol::Vlan => "vlan",
+            Protocol::Mpls => "mpls",
+            Protocol::Arp => "arp",
+            Protocol::Ipv4 => "ip",
+            Protocol::Ipv6 => "ipv6",
+            Protocol::Icmp => "icmp",
+            Protocol::Icmpv6 => "icmpv6",
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Gre => "gre",
+            Protocol::Vxlan => "vxlan",
+            Protocol::Geneve => "geneve",
+            Protocol::Dns => "dns",
+            Protocol::Dhcp => "dhcp",
+            Protocol::Dhcpv6 => "dhcpv6",
+            Protocol::Http => "http",
+            Protocol::Tls => "tls",
+            Protocol::Data => "data",
+            Protocol::Malformed => "malformed",
+        }
+    }
+}
+
+impl SerializablePacket {
+    /// Get the protocol the packet was decoded as
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            SerializablePacket::EthernetPacket(_) => Protocol::Ethernet,
+            SerializablePacket::VlanPacket(_) => Protocol::Vlan,
+            SerializablePacket::MplsPacket(_) => Protocol::Mpls,
+            SerializablePacket::ArpPacket(_) => Protocol::Arp,
+            SerializablePacket::Ipv4Packet(_) => Protocol::Ipv4,
+            SerializablePacket::Ipv6Packet(_) => Protocol::Ipv6,
+            SerializablePacket::EchoReplyPacket(_)
+            | SerializablePacket::EchoRequestPacket(_)
+            | SerializablePacket::IcmpPacket(_) => Protocol::Icmp,
+            SerializablePacket::Icmpv6Packet(_) => Protocol::Icmpv6,
+            SerializablePacket::TcpPacket(_) => Protocol::Tcp,
+            SerializablePacket::UdpPacket(_) => Protocol::Udp,
+            SerializablePacket::GrePacket(_) => Protocol::Gre,
+            SerializablePacket::VxlanPacket(_) => Protocol::Vxlan,
+            SerializablePacket::GenevePacket(_) => Protocol::Geneve,
+            SerializablePacket::DnsPacket(_) => Protocol::Dns,
+            SerializablePacket::DhcpPacket(_) => Protocol::Dhcp,
+            SerializablePacket::Dhcpv6Packet(_) => Protocol::Dhcpv6,
+            SerializablePacket::HttpRequestPacket(_)
+            | SerializablePacket::HttpResponsePacket(_) => Protocol::Http,
+            SerializablePacket::TlsPacket(_) => Protocol::Tls,
+            SerializablePacket::UnknownPacket(_) => Protocol::Data,
+            SerializablePacket::MalformedPacket(_) => Protocol::Malformed,
+        }
+    }
+}
+
+/// A decoded header in a packet's layer stack
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Layer {
+    packet: SerializablePacket,
+    level: Level,
+    /// How many tunnels the layer was carried in
+    depth: usize,
+    range: Option<Range<usize>>,
+}
+
+impl Layer {
+    pub fn new(packet: SerializablePacket, level: Level, depth: usize) -> Self {
+        Layer {
+            packet,
+            level,
+            depth,
+            range: None,
+        }
+    }
+
+    /// Get the decoded header
+    pub fn get_packet(&self) -> &SerializablePacket {
+        &self.packet
+    }
+
+    /// Get the decoded header for updating it
+    pub(super) fn get_packet_mut(&mut self) -> &mut SerializablePacket {
+        &mut self.packet
+    }
+
+    /// Get the protocol the header was decoded as
+    pub fn get_protocol(&self) -> Protocol {
+        self.packet.protocol()
+    }
+
+    /// Get where the layer sits in the protocol stack
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    /// Get how many tunnels the layer was carried in, 0 for the captured packet's own headers
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get bytes of the layer's header in the frame, `None` for headers decoded from
+    /// reassembled data or not recorded
+    pub fn get_range(&self) -> Option<Range<usize>> {
+        self.range.clone()
+    }
+
+    /// Set bytes of the layer's header in the frame
+    pub fn set_range(&mut self, range: Option<Range<usize>>) {
+        self.range = range;
+    }
+}
+
+/// Headers that can be looked up in a layer stack by type, see `ParsedPacket::find`
+pub trait LayerData: 'static {
+    /// Get the header if `packet` is one of this type
+    fn from_packet(packet: &SerializablePacket) -> Option<&Self>;
+
+    /// Get the header for updating it if `packet` is one of this type
+    fn from_packet_mut(packet: &mut SerializablePacket) -> Option<&mut Self>;
+}
+
+macro_rules! layer_data {
+    ($($variant:ident => $type:ty),* $(,)?) => {
+        $(
+            impl LayerData for $type {
+                fn from_packet(packet: &SerializablePacket) -> Option<&Self> {
+                    match packet {
+                        SerializablePacket::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+
+                fn from_packet_mut(packet: &mut SerializablePacket) -> Option<&mut Self> {
+                    match packet {
+                        SerializablePacket::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+layer_data! {
+    EthernetPacket => SerializableEthernetPacket,
+    VlanPacket => SerializableVlanPacket,
+    MplsPacket => SerializableMplsPacket,
+    ArpPacket => SerializableArpPacket,
+    Ipv4Packet => SerializableIpv4Packet,
+    Ipv6Packet => SerializableIpv6Packet,
+    EchoReplyPacket => SerializableEchoReplyPacket,
+    EchoRequestPacket => SerializableEchoRequestPacket,
+    IcmpPacket => SerializableIcmpPacket,
+    Icmpv6Packet => SerializableIcmpv6Packet,
+    TcpPacket => SerializableTcpPacket,
+    UdpPacket => SerializableUdpPacket,
+    GrePacket => SerializableGrePacket,
+    VxlanPacket => SerializableVxlanPacket,
+    GenevePacket => SerializableGenevePacket,
+    DnsPacket => SerializableDnsPacket,
+    DhcpPacket => SerializableDhcpPacket,
+    Dhcpv6Packet => SerializableDhcpv6Packet,
+    HttpRequestPacket => SerializableHttpRequestPacket,
+    HttpResponsePacket => SerializableHttpResponsePacket,
+    TlsPacket => SerializableTlsPacket,
+    UnknownPacket => SerializableUnknownPacket,
+}
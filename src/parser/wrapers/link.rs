@@ -0,0 +1,46 @@
+use pnet::packet::{ethernet::EtherType, vlan::VlanPacket};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableVlanPacket {
+    /// Tag protocol identifier, 0x8100 for 802.1Q and 0x88a8 for 802.1ad service tags
+    pub tpid: u16,
+    pub priority: u8,
+    pub drop_eligible: bool,
+    pub vlan_id: u16,
+    pub ethertype: String,
+}
+
+impl SerializableVlanPacket {
+    pub fn new(tpid: EtherType, packet: &VlanPacket) -> Self {
+        SerializableVlanPacket {
+            tpid: tpid.0,
+            priority: packet.get_priority_code_point().0,
+            drop_eligible: packet.get_drop_eligible_indicator() != 0,
+            vlan_id: packet.get_vlan_identifier(),
+            ethertype: packet.get_ethertype().to_string(),
+        }
+    }
+}
+
+/// One entry of an MPLS label stack
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableMplsPacket {
+    pub label: u32,
+    pub traffic_class: u8,
+    /// Whether this is the last entry of the stack
+    pub bottom_of_stack: bool,
+    pub ttl: u8,
+}
+
+impl SerializableMplsPacket {
+    /// Decode a 4-byte label stack entry
+    pub fn new(entry: [u8; 4]) -> Self {
+        let entry = u32::from_be_bytes(entry);
+        SerializableMplsPacket {
+            label: entry >> 12,
+            traffic_class: ((entry >> 9) & 0b111) as u8,
+            bottom_of_stack: entry & 0x100 != 0,
+            ttl: entry as u8,
+        }
+    }
+}
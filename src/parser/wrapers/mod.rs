@@ -1,29 +1,66 @@
+mod link;
+mod layer;
 mod network;
 mod transport;
+mod tunnel;
 mod application;
+mod details;
+#[cfg(feature = "serde")]
+mod hex;
+mod summary;
+pub use link::*;
+pub use layer::*;
 pub use network::*;
 pub use transport::*;
-// use application::*;
+pub use tunnel::*;
+pub use details::*;
+pub use summary::*;
+pub use application::*;
+
+use std::{borrow::Cow, ops::Range, time::Duration};
 
 use pnet::{util::MacAddr, packet::{ethernet::EthernetPacket, Packet}};
 
 
+/// Where a decoded field or header sits in the frame, keyed by its display filter name
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldRange {
+    pub field: Cow<'static, str>,
+    pub range: Range<usize>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParsedPacket {
     id: usize,
-    link_layer_packet: Option<SerializablePacket>,
-    network_layer_packet: Option<SerializablePacket>,
-    transport_layer_packet: Option<SerializablePacket>,
-    application_layer_packet: Option<SerializablePacket>,
+    timestamp: Duration,
+    length: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    data: Vec<u8>,
+    field_ranges: Vec<FieldRange>,
+    fragments: Vec<usize>,
+    interface_name: Option<String>,
+    comments: Vec<String>,
+    /// Decoded headers, outermost first and including those of the tunnels the packet was
+    /// carried in
+    layers: Vec<Layer>,
+    /// How many tunnels were entered, new layers belong to the innermost packet
+    tunnels: usize,
 }
 
 impl ParsedPacket {
     pub fn new(id: usize) -> Self {
         ParsedPacket {
             id,
-            link_layer_packet: None,
-            network_layer_packet: None,
-            transport_layer_packet: None,
-            application_layer_packet: None,
+            timestamp: Duration::ZERO,
+            length: 0,
+            data: Vec::new(),
+            field_ranges: Vec::new(),
+            fragments: Vec::new(),
+            interface_name: None,
+            comments: Vec::new(),
+            layers: Vec::new(),
+            tunnels: 0,
         }
     }
 
@@ -32,34 +69,177 @@ impl ParsedPacket {
         self.id
     }
 
-    /// Get link layer packet representation
+    /// Get capture time since the UNIX epoch
+    pub fn get_timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// Get length of the packet on the wire
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
+
+    /// Get captured bytes of the frame
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get byte ranges of the fields recorded while decoding
+    pub fn get_field_ranges(&self) -> &[FieldRange] {
+        &self.field_ranges
+    }
+
+    /// Get byte range of `field`, e.g. `ip.ttl`, if it was decoded
+    pub fn get_field_range(&self, field: &str) -> Option<Range<usize>> {
+        self.get_nth_field_range(field, 0)
+    }
+
+    /// Get byte range of the `n`th occurrence of `field`, for headers repeated in a packet
+    /// such as stacked VLAN tags
+    pub fn get_nth_field_range(&self, field: &str, n: usize) -> Option<Range<usize>> {
+        self.field_ranges
+            .iter()
+            .filter(|range| range.field == field)
+            .nth(n)
+            .map(|range| range.range.clone())
+    }
+
+    /// Get ids of the frames whose fragments were reassembled into this packet, empty when it was
+    /// not fragmented
+    pub fn get_fragments(&self) -> &[usize] {
+        &self.fragments
+    }
+
+    /// Get name of the interface the packet was captured on
+    pub fn get_interface_name(&self) -> Option<&str> {
+        self.interface_name.as_deref()
+    }
+
+    /// Get comments attached to the packet in its capture file
+    pub fn get_comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Set capture time since the UNIX epoch
+    pub fn set_timestamp(&mut self, timestamp: Duration) {
+        self.timestamp = timestamp;
+    }
+
+    /// Set length of the packet on the wire
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+    }
+
+    /// Set captured bytes of the frame
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+    }
+
+    /// Record where fields of a header starting at `offset` sit, given as `(field, start, length)`
+    /// relative to the header and clipped to the `available` captured bytes
+    pub fn add_field_ranges(
+        &mut self,
+        offset: usize,
+        available: usize,
+        fields: &[(&'static str, usize, usize)],
+    ) {
+        for &(field, start, length) in fields {
+            let end = (start + length).min(available);
+            if start < end {
+                self.field_ranges.push(FieldRange {
+                    field: Cow::Borrowed(field),
+                    range: offset + start..offset + end,
+                });
+            }
+        }
+    }
+
+    /// Forget the ranges recorded after the first `len`, for headers decoded from reassembled
+    /// bytes that are not where the ranges say in this frame
+    pub fn truncate_field_ranges(&mut self, len: usize) {
+        self.field_ranges.truncate(len);
+    }
+
+    /// Set ids of the frames whose fragments were reassembled into this packet
+    pub fn set_fragments(&mut self, fragments: Vec<usize>) {
+        self.fragments = fragments;
+    }
+
+    /// Set name of the interface the packet was captured on
+    pub fn set_interface_name(&mut self, interface_name: Option<String>) {
+        self.interface_name = interface_name;
+    }
+
+    /// Set comments attached to the packet in its capture file
+    pub fn set_comments(&mut self, comments: Vec<String>) {
+        self.comments = comments;
+    }
+
+    /// Get every decoded layer, outermost first
+    pub fn layers(&self) -> impl DoubleEndedIterator<Item = &Layer> {
+        self.layers.iter()
+    }
+
+    /// Get the innermost header of type `T`, e.g. `packet.find::<SerializableTcpPacket>()`
+    pub fn find<T: LayerData>(&self) -> Option<&T> {
+        self.find_all().next_back()
+    }
+
+    /// Get the innermost header of type `T` for updating it, e.g. by `dns::DnsTracker`
+    pub fn find_mut<T: LayerData>(&mut self) -> Option<&mut T> {
+        self.layers
+            .iter_mut()
+            .rev()
+            .find_map(|layer| T::from_packet_mut(layer.get_packet_mut()))
+    }
+
+    /// Get every header of type `T`, outermost first
+    pub fn find_all<T: LayerData>(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.layers
+            .iter()
+            .filter_map(|layer| T::from_packet(layer.get_packet()))
+    }
+
+    /// Get how many tunnels the packet was carried in
+    pub fn get_tunnel_depth(&self) -> usize {
+        self.tunnels
+    }
+
+    /// Get VLAN IDs of the frame's tags, outermost first and including those of tunnels
+    pub fn get_vlan_ids(&self) -> Vec<u16> {
+        self.find_all::<SerializableVlanPacket>()
+            .map(|vlan| vlan.vlan_id)
+            .collect()
+    }
+
+    /// Get link layer packet representation, of the innermost packet for tunneled traffic
     pub fn get_link_layer_packet(&self) -> Option<&SerializablePacket> {
-        self.link_layer_packet.as_ref()
+        self.get_layer_packet(Level::Link)
     }
 
     /// Get network layer packet representation
     pub fn get_network_layer_packet(&self) -> Option<&SerializablePacket> {
-        self.network_layer_packet.as_ref()
+        self.get_layer_packet(Level::Network)
     }
 
     /// Get transport layer packet representation
     pub fn get_transport_layer_packet(&self) -> Option<&SerializablePacket> {
-        self.transport_layer_packet.as_ref()
+        self.get_layer_packet(Level::Transport)
     }
 
     /// Get application layer packet representation
     pub fn get_application_layer_packet(&self) -> Option<&SerializablePacket> {
-        self.application_layer_packet.as_ref()
+        self.get_layer_packet(Level::Application)
     }
 
     /// Set link layer packet representation
     pub fn set_link_layer_packet(&mut self, link_layer_packet: Option<SerializablePacket>) {
-        self.link_layer_packet = link_layer_packet;
+        self.set_layer_packet(Level::Link, link_layer_packet);
     }
 
     /// Set network layer packet representation
     pub fn set_network_layer_packet(&mut self, network_layer_packet: Option<SerializablePacket>) {
-        self.network_layer_packet = network_layer_packet;
+        self.set_layer_packet(Level::Network, network_layer_packet);
     }
 
     /// Set transport layer packet representation
@@ -67,7 +247,7 @@ impl ParsedPacket {
         &mut self,
         transport_layer_packet: Option<SerializablePacket>,
     ) {
-        self.transport_layer_packet = transport_layer_packet;
+        self.set_layer_packet(Level::Transport, transport_layer_packet);
     }
 
     /// Set application layer packet representation
@@ -75,12 +255,76 @@ impl ParsedPacket {
         &mut self,
         application_layer_packet: Option<SerializablePacket>,
     ) {
-        self.application_layer_packet = application_layer_packet;
+        self.set_layer_packet(Level::Application, application_layer_packet);
+    }
+
+    /// Add a layer on top of the innermost packet, for levels holding several headers such
+    /// as stacked VLAN tags
+    pub fn push_layer(&mut self, level: Level, packet: SerializablePacket) {
+        self.layers.push(Layer::new(packet, level, self.tunnels));
+    }
+
+    /// Start a tunnel's packet, layers set from now on belong to it and the getters show it
+    pub fn enter_tunnel(&mut self) {
+        self.tunnels += 1;
+    }
+
+    /// Look up each layer's header range, once every field range was recorded
+    pub fn resolve_layer_ranges(&mut self) {
+        let mut seen: Vec<Protocol> = Vec::new();
+        for i in 0..self.layers.len() {
+            let protocol = self.layers[i].get_protocol();
+            // Stacked tags and tunneled headers repeat a protocol, each takes the next range
+            let n = seen.iter().filter(|&&other| other == protocol).count();
+            seen.push(protocol);
+            let range = self.get_nth_field_range(protocol.filter_name(), n);
+            self.layers[i].set_range(range);
+        }
+    }
+
+    /// Get the innermost packet's layer at `level`
+    fn get_layer_packet(&self, level: Level) -> Option<&SerializablePacket> {
+        self.layers
+            .iter()
+            .rev()
+            .take_while(|layer| layer.get_depth() == self.tunnels)
+            .find(|layer| layer.get_level() == level)
+            .map(Layer::get_packet)
+    }
+
+    /// Replace the innermost packet's layer at `level`, adding it if there is none yet
+    fn set_layer_packet(&mut self, level: Level, packet: Option<SerializablePacket>) {
+        let tunnels = self.tunnels;
+        let index = self
+            .layers
+            .iter()
+            .rposition(|layer| layer.get_depth() == tunnels && layer.get_level() == level);
+        match (index, packet) {
+            (Some(index), Some(packet)) => self.layers[index] = Layer::new(packet, level, tunnels),
+            (Some(index), None) => {
+                self.layers.remove(index);
+            }
+            (None, Some(packet)) => {
+                // Dissectors may set an inner layer before its outer one, keep the stack ordered
+                let index = self
+                    .layers
+                    .iter()
+                    .position(|layer| layer.get_depth() == tunnels && layer.get_level() > level)
+                    .unwrap_or(self.layers.len());
+                self.layers
+                    .insert(index, Layer::new(packet, level, tunnels));
+            }
+            (None, None) => {}
+        }
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum SerializablePacket {
     EthernetPacket(SerializableEthernetPacket),
+    VlanPacket(SerializableVlanPacket),
+    MplsPacket(SerializableMplsPacket),
     ArpPacket(SerializableArpPacket),
     Ipv4Packet(SerializableIpv4Packet),
     Ipv6Packet(SerializableIpv6Packet),
@@ -90,19 +334,26 @@ pub enum SerializablePacket {
     Icmpv6Packet(SerializableIcmpv6Packet),
     TcpPacket(SerializableTcpPacket),
     UdpPacket(SerializableUdpPacket),
-    // HttpRequestPacket(SerializableHttpRequestPacket),
-    // HttpResponsePacket(SerializableHttpResponsePacket),
-    // TlsPacket(SerializableTlsPacket),
-    // DnsPacket(SerializableDnsPacket),
+    GrePacket(SerializableGrePacket),
+    VxlanPacket(SerializableVxlanPacket),
+    GenevePacket(SerializableGenevePacket),
+    HttpRequestPacket(SerializableHttpRequestPacket),
+    HttpResponsePacket(SerializableHttpResponsePacket),
+    TlsPacket(SerializableTlsPacket),
+    DnsPacket(SerializableDnsPacket),
+    DhcpPacket(SerializableDhcpPacket),
+    Dhcpv6Packet(SerializableDhcpv6Packet),
 
     MalformedPacket(String),
     UnknownPacket(SerializableUnknownPacket),
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableEthernetPacket {
     pub destination: MacAddr,
     pub source: MacAddr,
     pub ethertype: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
     pub payload: Vec<u8>,
 }
 
@@ -118,10 +369,13 @@ impl<'a> From<&EthernetPacket<'a>> for SerializableEthernetPacket {
     }
 }
 
+/// Payload of an Ethernet frame whose EtherType is not decoded
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableUnknownPacket {
     pub destination: MacAddr,
     pub source: MacAddr,
     pub ethertype: String,
+    /// Bytes after the Ethernet header
     pub length: usize,
 }
 
@@ -131,7 +385,7 @@ impl<'a> From<&EthernetPacket<'a>> for SerializableUnknownPacket {
             destination: packet.get_destination(),
             source: packet.get_source(),
             ethertype: packet.get_ethertype().to_string(),
-            length: packet.packet().len(),
+            length: packet.payload().len(),
         }
     }
 }
\ No newline at end of file
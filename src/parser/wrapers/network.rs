@@ -2,6 +2,7 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 
 use pnet::{util::MacAddr, packet::{arp::{ArpPacket, ArpOperations}, Packet, ipv6::Ipv6Packet, ipv4::Ipv4Packet}};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableArpPacket {
     pub hardware_type: String,
     pub protocol_type: u16,
@@ -37,6 +38,7 @@ impl<'a> From<&ArpPacket<'a>> for SerializableArpPacket {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableIpv6Packet {
     pub version: u8,
     pub traffic_class: u8,
@@ -47,6 +49,8 @@ pub struct SerializableIpv6Packet {
     pub source: Ipv6Addr,
     pub destination: Ipv6Addr,
     pub length: usize,
+    /// Extension headers between the fixed header and the upper-layer protocol, in chain order
+    pub extension_headers: Vec<SerializableIpv6ExtensionHeader>,
 }
 
 impl<'a> From<&Ipv6Packet<'a>> for SerializableIpv6Packet {
@@ -65,10 +69,68 @@ impl<'a> From<&Ipv6Packet<'a>> for SerializableIpv6Packet {
             source: packet.get_source(),
             destination: packet.get_destination(),
             length: packet.payload().len(),
+            extension_headers: Vec::new(),
         }
     }
 }
 
+/// An IPv6 extension header
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableIpv6ExtensionHeader {
+    /// Protocol number identifying the header, e.g. 44 for Fragment
+    pub header_type: u8,
+    /// Header following this one, `None` when it is encrypted by ESP
+    pub next_header: Option<String>,
+    /// Length of the header in bytes
+    pub length: usize,
+    pub data: SerializableIpv6ExtensionData,
+}
+
+impl SerializableIpv6ExtensionHeader {
+    /// Get the header's name, e.g. `"Hop-by-Hop Options"`
+    pub fn name(&self) -> &'static str {
+        match self.header_type {
+            0 => "Hop-by-Hop Options",
+            43 => "Routing",
+            44 => "Fragment",
+            50 => "Encapsulating Security Payload",
+            51 => "Authentication Header",
+            60 => "Destination Options",
+            _ => "Extension Header",
+        }
+    }
+}
+
+/// Fields specific to each kind of extension header
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableIpv6ExtensionData {
+    /// Hop-by-Hop and Destination Options, as raw type-length-value options
+    Options(
+        #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+        Vec<u8>,
+    ),
+    Routing {
+        routing_type: u8,
+        segments_left: u8,
+    },
+    Fragment {
+        /// Offset of the fragment's data in the datagram, in bytes
+        offset: u16,
+        more_fragments: bool,
+        identification: u32,
+    },
+    Authentication {
+        spi: u32,
+        sequence: u32,
+    },
+    Esp {
+        spi: u32,
+        sequence: u32,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableIpv4Packet {
     pub version: u8,
     pub header_length: u8,
@@ -0,0 +1,607 @@
+use std::time::Duration;
+
+use pnet::packet::ethernet::EtherType;
+
+use super::{
+    dhcp_message_type_name, dhcpv6_message_type_name, dhcpv6_status_name, dns_opcode_name,
+    dns_rcode_name, dns_type_name, format_duid, format_size, icmp_type_name, icmpv6_type_name,
+    mld_record_type_name, ndp_flag_names, tls_alert_name, tls_handshake_type_name,
+    tls_version_name, DnsService, Layer, ParsedPacket, SerializableDhcpPacket,
+    SerializableDhcpv6Packet, SerializableDnsPacket, SerializableHttpResponsePacket,
+    SerializableIcmpBody, SerializableIcmpPacket, SerializableIcmpQuote,
+    SerializableIcmpQuotedTransport, SerializableIcmpv6Body, SerializableIcmpv6Packet,
+    SerializableIpv6ExtensionData, SerializableNdpOptionData, SerializablePacket,
+    SerializableTlsContent, SerializableTlsHandshake, SerializableTlsPacket,
+};
+
+/// One-line description of a packet, taken from its deepest decoded layer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PacketSummary {
+    pub source: String,
+    pub destination: String,
+    pub protocol: String,
+    pub info: String,
+    /// VLAN IDs outermost first, e.g. `100/200` for QinQ, empty for untagged frames
+    pub vlan: String,
+}
+
+/// Strip the numeric suffix of names such as `"EchoReply (0)"`
+fn name(value: &str) -> &str {
+    value.split(" (").next().unwrap_or(value)
+}
+
+impl ParsedPacket {
+    /// Get source, destination, protocol and info columns for packet lists
+    pub fn summary(&self) -> PacketSummary {
+        let mut summary = PacketSummary::default();
+        for layer in self.layers().map(Layer::get_packet) {
+            match layer {
+                SerializablePacket::EthernetPacket(ethernet) => {
+                    summary.source = ethernet.source.to_string();
+                    summary.destination = ethernet.destination.to_string();
+                    summary.protocol = "Ethernet".to_string();
+                    summary.info = format!("Ethertype {}", ethernet.ethertype);
+                }
+                SerializablePacket::VlanPacket(vlan) => {
+                    if !summary.vlan.is_empty() {
+                        summary.vlan.push('/');
+                    }
+                    summary.vlan += &vlan.vlan_id.to_string();
+                    summary.protocol = "VLAN".to_string();
+                    summary.info = format!(
+                        "PRI: {} ID: {} Type: {}",
+                        vlan.priority, vlan.vlan_id, vlan.ethertype
+                    );
+                }
+                SerializablePacket::MplsPacket(mpls) => {
+                    summary.protocol = "MPLS".to_string();
+                    summary.info = format!(
+                        "Label: {} Exp: {} S: {} TTL: {}",
+                        mpls.label, mpls.traffic_class, mpls.bottom_of_stack as u8, mpls.ttl
+                    );
+                }
+                SerializablePacket::GrePacket(gre) => {
+                    summary.protocol = "GRE".to_string();
+                    summary.info = format!("Encapsulated {}", EtherType(gre.protocol_type));
+                    if let Some(key) = gre.key {
+                        summary.info += &format!(" Key=0x{:08x}", key);
+                    }
+                }
+                SerializablePacket::VxlanPacket(vxlan) => {
+                    summary.protocol = "VXLAN".to_string();
+                    summary.info = format!("VNI={}", vxlan.vni);
+                }
+                SerializablePacket::GenevePacket(geneve) => {
+                    summary.protocol = "Geneve".to_string();
+                    summary.info = format!("VNI={} Options={}", geneve.vni, geneve.options.len());
+                }
+                SerializablePacket::DnsPacket(dns) => {
+                    summary.protocol = match dns.service {
+                        DnsService::Dns => "DNS",
+                        DnsService::Mdns => "MDNS",
+                        DnsService::Llmnr => "LLMNR",
+                    }
+                    .to_string();
+                    summary.info = dns_info(dns);
+                }
+                SerializablePacket::DhcpPacket(dhcp) => {
+                    summary.protocol = match dhcp.message_type() {
+                        Some(_) => "DHCP",
+                        None => "BOOTP",
+                    }
+                    .to_string();
+                    summary.info = dhcp_info(dhcp);
+                }
+                SerializablePacket::Dhcpv6Packet(dhcpv6) => {
+                    summary.protocol = "DHCPv6".to_string();
+                    summary.info = dhcpv6_info(dhcpv6);
+                }
+                SerializablePacket::HttpRequestPacket(request) => {
+                    let info = format!("{} {} {}", request.method, request.uri, request.version);
+                    http_info(&mut summary, info);
+                }
+                SerializablePacket::HttpResponsePacket(response) => {
+                    http_info(&mut summary, response_info(response));
+                }
+                SerializablePacket::TlsPacket(tls) => {
+                    summary.protocol = tls_version_name(tls.version);
+                    summary.info = tls_info(tls);
+                }
+                SerializablePacket::ArpPacket(arp) => {
+                    summary.protocol = "ARP".to_string();
+                    summary.info = if arp.operation.starts_with("ARP Request") {
+                        format!(
+                            "Who has {}? Tell {}",
+                            arp.target_proto_addr, arp.sender_proto_addr
+                        )
+                    } else if arp.operation.starts_with("ARP Reply") {
+                        format!("{} is at {}", arp.sender_proto_addr, arp.sender_hw_addr)
+                    } else {
+                        arp.operation.clone()
+                    };
+                }
+                SerializablePacket::Ipv4Packet(ipv4) => {
+                    summary.source = ipv4.source.to_string();
+                    summary.destination = ipv4.destination.to_string();
+                    summary.protocol = "IPv4".to_string();
+                    summary.info = if ipv4.flags & 0b001 != 0 || ipv4.fragment_offset != 0 {
+                        format!(
+                            "Fragmented IP protocol (proto={}, off={}, ID={:04x})",
+                            ipv4.next_level_protocol,
+                            ipv4.fragment_offset as usize * 8,
+                            ipv4.identification
+                        )
+                    } else {
+                        format!(
+                            "{} TTL={} ID={}",
+                            name(&ipv4.next_level_protocol),
+                            ipv4.ttl,
+                            ipv4.identification
+                        )
+                    };
+                }
+                SerializablePacket::Ipv6Packet(ipv6) => {
+                    summary.source = ipv6.source.to_string();
+                    summary.destination = ipv6.destination.to_string();
+                    summary.protocol = "IPv6".to_string();
+                    let fragment =
+                        ipv6.extension_headers
+                            .iter()
+                            .find_map(|header| match header.data {
+                                SerializableIpv6ExtensionData::Fragment {
+                                    offset,
+                                    identification,
+                                    ..
+                                } => Some((header, offset, identification)),
+                                _ => None,
+                            });
+                    let last = ipv6.extension_headers.last().map(|header| &header.data);
+                    summary.info = match (fragment, last) {
+                        (_, Some(SerializableIpv6ExtensionData::Esp { spi, sequence })) => {
+                            summary.protocol = "ESP".to_string();
+                            format!("ESP (SPI=0x{:08x}, Seq={})", spi, sequence)
+                        }
+                        (Some((header, offset, identification)), _) => format!(
+                            "IPv6 fragment (off={}, ID={:08x}, nxt={})",
+                            offset,
+                            identification,
+                            header.next_header.as_deref().unwrap_or_default()
+                        ),
+                        _ => format!("{} HopLimit={}", name(&ipv6.next_header), ipv6.hop_limit),
+                    };
+                }
+                SerializablePacket::TcpPacket(tcp) => {
+                    summary.protocol = "TCP".to_string();
+                    summary.info = format!(
+                        "{} \u{2192} {} [{}] Seq={}",
+                        tcp.source,
+                        tcp.destination,
+                        tcp.flag_names().join(", "),
+                        tcp.sequence
+                    );
+                    if tcp.flag_names().contains(&"ACK") {
+                        summary.info += &format!(" Ack={}", tcp.acknowledgement);
+                    }
+                    summary.info += &format!(" Win={} Len={}", tcp.window, tcp.length);
+                }
+                SerializablePacket::UdpPacket(udp) => {
+                    summary.protocol = "UDP".to_string();
+                    summary.info = format!(
+                        "{} \u{2192} {} Len={}",
+                        udp.source,
+                        udp.destination,
+                        udp.length.saturating_sub(8)
+                    );
+                }
+                SerializablePacket::EchoRequestPacket(echo) => {
+                    summary.protocol = "ICMP".to_string();
+                    summary.info = format!(
+                        "Echo (ping) request id=0x{:04x}, seq={}",
+                        echo.identifier, echo.sequence_number
+                    );
+                    if echo.unanswered {
+                        summary.info += " [no response]";
+                    }
+                }
+                SerializablePacket::EchoReplyPacket(echo) => {
+                    summary.protocol = "ICMP".to_string();
+                    summary.info = format!(
+                        "Echo (ping) reply id=0x{:04x}, seq={}",
+                        echo.identifier, echo.sequence_number
+                    );
+                    summary.info += &echo_reply_match(echo.response_to, echo.time, echo.duplicate);
+                }
+                SerializablePacket::IcmpPacket(icmp) => {
+                    summary.protocol = "ICMP".to_string();
+                    summary.info = icmp_info(icmp);
+                }
+                SerializablePacket::Icmpv6Packet(icmpv6) => {
+                    summary.protocol = "ICMPv6".to_string();
+                    summary.info = icmpv6_info(icmpv6);
+                }
+                SerializablePacket::MalformedPacket(reason) => {
+                    summary.info = format!("[Malformed: {}]", reason);
+                }
+                SerializablePacket::UnknownPacket(unknown) => {
+                    summary.source = unknown.source.to_string();
+                    summary.destination = unknown.destination.to_string();
+                    summary.protocol = "Ethernet".to_string();
+                    summary.info = format!("Unknown ethertype {}", unknown.ethertype);
+                }
+            }
+        }
+
+        if !self.get_fragments().is_empty() {
+            summary.info += &format!(
+                " [Reassembled from {} fragments]",
+                self.get_fragments().len()
+            );
+        }
+        summary
+    }
+}
+
+/// Describe an ICMP message, e.g. `Destination unreachable (Port unreachable), UDP
+/// 10.0.0.7:40000 → 10.0.0.53:53`
+fn icmp_info(icmp: &SerializableIcmpPacket) -> String {
+    let mut info = match icmp_type_name(icmp.type_number()) {
+        "Unknown" => format!("Unknown type {}", icmp.type_number()),
+        name => name.to_string(),
+    };
+    match icmp.code_description() {
+        Some(description) => info += &format!(" ({})", description),
+        None if icmp.icmp_code != 0 => info += &format!(" code={}", icmp.icmp_code),
+        None => {}
+    }
+    match &icmp.body {
+        SerializableIcmpBody::Redirect { gateway, .. } => {
+            info += &format!(", Gateway {}", gateway);
+        }
+        SerializableIcmpBody::ParameterProblem { pointer, .. } => {
+            info += &format!(", Pointer {}", pointer);
+        }
+        SerializableIcmpBody::RouterAdvertisement { lifetime, entries } => {
+            let routers: Vec<String> = entries
+                .iter()
+                .map(|entry| entry.address.to_string())
+                .collect();
+            info += &format!(", Routers [{}] Lifetime={}", routers.join(", "), lifetime);
+        }
+        SerializableIcmpBody::Timestamp {
+            identifier,
+            sequence_number,
+            ..
+        } => info += &format!(" id=0x{:04x}, seq={}", identifier, sequence_number),
+        SerializableIcmpBody::AddressMask {
+            identifier,
+            sequence_number,
+            mask,
+        } => {
+            info += &format!(" id=0x{:04x}, seq={}", identifier, sequence_number);
+            if icmp.type_number() == 18 {
+                info += &format!(", Mask {}", mask);
+            }
+        }
+        _ => {}
+    }
+    if let Some(original) = icmp.original() {
+        info += ", ";
+        info += &quoted_flow(original);
+    }
+    info
+}
+
+/// Describe an ICMPv6 message, e.g. `Neighbor Advertisement fe80::1 (Router, Solicited) is at
+/// 00:11:22:33:44:55`
+fn icmpv6_info(icmpv6: &SerializableIcmpv6Packet) -> String {
+    let mut info = match &icmpv6.body {
+        SerializableIcmpv6Body::Other => {
+            return format!("{} code={}", name(&icmpv6.icmpv6_type), icmpv6.icmpv6_code)
+        }
+        SerializableIcmpv6Body::EchoRequest {
+            identifier,
+            sequence_number,
+            unanswered,
+        } => {
+            let mut info = format!(
+                "Echo (ping) request id=0x{:04x}, seq={}",
+                identifier, sequence_number
+            );
+            if *unanswered {
+                info += " [no response]";
+            }
+            return info;
+        }
+        SerializableIcmpv6Body::EchoReply {
+            identifier,
+            sequence_number,
+            response_to,
+            time,
+            duplicate,
+        } => {
+            return format!(
+                "Echo (ping) reply id=0x{:04x}, seq={}{}",
+                identifier,
+                sequence_number,
+                echo_reply_match(*response_to, *time, *duplicate)
+            )
+        }
+        _ => icmpv6_type_name(icmpv6.type_number()).to_string(),
+    };
+    let from = icmpv6
+        .link_layer_address()
+        .map(|address| format!(" from {}", address))
+        .unwrap_or_default();
+    match &icmpv6.body {
+        SerializableIcmpv6Body::RouterSolicitation { .. } => info += &from,
+        SerializableIcmpv6Body::RouterAdvertisement { options, .. } => {
+            info += &from;
+            for option in options {
+                if let SerializableNdpOptionData::PrefixInformation {
+                    prefix_length,
+                    prefix,
+                    ..
+                } = option.data
+                {
+                    info += &format!(", Prefix {}/{}", prefix, prefix_length);
+                }
+            }
+        }
+        SerializableIcmpv6Body::NeighborSolicitation { target, .. } => {
+            info += &format!(" for {}{}", target, from);
+        }
+        SerializableIcmpv6Body::NeighborAdvertisement { flags, target, .. } => {
+            info += &format!(" {}", target);
+            let flags = ndp_flag_names(136, *flags);
+            if !flags.is_empty() {
+                info += &format!(" ({})", flags.join(", "));
+            }
+            if let Some(address) = icmpv6.link_layer_address() {
+                info += &format!(" is at {}", address);
+            }
+        }
+        SerializableIcmpv6Body::Redirect {
+            target,
+            destination,
+            ..
+        } => info += &format!(" {} via {}", destination, target),
+        SerializableIcmpv6Body::MulticastListenerQuery { address, v2, .. } => {
+            if v2.is_some() {
+                info += " v2";
+            }
+            if !address.is_unspecified() {
+                info += &format!(" for {}", address);
+            }
+        }
+        SerializableIcmpv6Body::MulticastListenerReport { address }
+        | SerializableIcmpv6Body::MulticastListenerDone { address } => {
+            info += &format!(" {}", address);
+        }
+        SerializableIcmpv6Body::MulticastListenerReportV2 { records } => {
+            let records: Vec<String> = records
+                .iter()
+                .map(|record| {
+                    format!(
+                        "{}: {}",
+                        mld_record_type_name(record.record_type),
+                        record.address
+                    )
+                })
+                .collect();
+            info += &format!(" [{}]", records.join(", "));
+        }
+        _ => {}
+    }
+    info
+}
+
+/// Describe the request an echo reply answers, e.g. ` (request in #3, 12.3 ms)`
+fn echo_reply_match(response_to: Option<usize>, time: Option<Duration>, duplicate: bool) -> String {
+    let mut info = String::new();
+    if let (Some(request), Some(time)) = (response_to, time) {
+        info += &format!(
+            " (request in #{}, {:.1} ms)",
+            request,
+            time.as_secs_f64() * 1000.0
+        );
+    }
+    if duplicate {
+        info += " [duplicate]";
+    }
+    info
+}
+
+/// Describe the datagram quoted by an ICMP error, e.g. `TCP 10.0.0.7:40000 → 10.0.0.1:80`
+fn quoted_flow(original: &SerializableIcmpQuote) -> String {
+    let ipv4 = &original.ipv4;
+    match &original.transport {
+        Some(SerializableIcmpQuotedTransport::Tcp {
+            source,
+            destination,
+            ..
+        }) => format!(
+            "TCP {}:{} \u{2192} {}:{}",
+            ipv4.source, source, ipv4.destination, destination
+        ),
+        Some(SerializableIcmpQuotedTransport::Udp(udp)) => format!(
+            "UDP {}:{} \u{2192} {}:{}",
+            ipv4.source, udp.source, ipv4.destination, udp.destination
+        ),
+        Some(SerializableIcmpQuotedTransport::Echo {
+            identifier,
+            sequence_number,
+            ..
+        }) => format!(
+            "ICMP {} \u{2192} {} id=0x{:04x}, seq={}",
+            ipv4.source, ipv4.destination, identifier, sequence_number
+        ),
+        None => format!(
+            "{} {} \u{2192} {}",
+            name(&ipv4.next_level_protocol),
+            ipv4.source,
+            ipv4.destination
+        ),
+    }
+}
+
+/// Set the info column to an HTTP message, joined to the messages before it in the packet
+fn http_info(summary: &mut PacketSummary, info: String) {
+    if summary.protocol == "HTTP" {
+        summary.info += ", ";
+        summary.info += &info;
+    } else {
+        summary.protocol = "HTTP".to_string();
+        summary.info = info;
+    }
+}
+
+/// Info column of a response, e.g. `GET /api/v1/users → 200 (12 ms, 3.4 KB)`
+fn response_info(response: &SerializableHttpResponsePacket) -> String {
+    match &response.request {
+        Some(request) => format!(
+            "{} {} \u{2192} {} ({} ms, {})",
+            request.method,
+            request.uri,
+            response.status,
+            request.time.as_millis(),
+            format_size(response.body.length)
+        ),
+        None => format!(
+            "{} {} {} ({})",
+            response.version,
+            response.status,
+            response.reason,
+            format_size(response.body.length)
+        ),
+    }
+}
+
+/// Info column of TLS records, e.g. `Client Hello (SNI=example.com, JA4=t13d1516h2_...)`
+fn tls_info(tls: &SerializableTlsPacket) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for record in &tls.records {
+        let messages = match &record.content {
+            SerializableTlsContent::ChangeCipherSpec => vec!["Change Cipher Spec".to_string()],
+            SerializableTlsContent::Alert { level, description } => vec![format!(
+                "Alert (Level: {}, Description: {})",
+                if *level == 2 { "Fatal" } else { "Warning" },
+                tls_alert_name(*description)
+            )],
+            SerializableTlsContent::Handshake(messages) => messages
+                .iter()
+                .map(|message| match message {
+                    SerializableTlsHandshake::ClientHello(hello) => {
+                        let mut details = Vec::new();
+                        if let Some(name) = hello.server_name() {
+                            details.push(format!("SNI={}", name));
+                        }
+                        details.push(format!("JA4={}", hello.ja4));
+                        format!("Client Hello ({})", details.join(", "))
+                    }
+                    SerializableTlsHandshake::ServerHello(hello) => {
+                        format!("Server Hello (JA3S={})", hello.ja3s)
+                    }
+                    SerializableTlsHandshake::Certificate(_) => "Certificate".to_string(),
+                    SerializableTlsHandshake::Other { handshake_type, .. } => {
+                        tls_handshake_type_name(*handshake_type)
+                    }
+                })
+                .collect(),
+            SerializableTlsContent::ApplicationData => vec!["Application Data".to_string()],
+            SerializableTlsContent::Heartbeat => vec!["Heartbeat".to_string()],
+            SerializableTlsContent::Encrypted if record.content_type == 21 => {
+                vec!["Encrypted Alert".to_string()]
+            }
+            SerializableTlsContent::Encrypted => vec!["Encrypted Handshake Message".to_string()],
+        };
+        for message in messages {
+            // Bulk data comes in many records, once is enough
+            if parts.last() != Some(&message) {
+                parts.push(message);
+            }
+        }
+    }
+    if parts.is_empty() {
+        return "Handshake Fragment".to_string();
+    }
+    parts.join(", ")
+}
+
+/// Info column of a DHCP message, e.g. `DHCP ACK - Transaction ID 0x3903f326 (192.0.2.10)`
+fn dhcp_info(dhcp: &SerializableDhcpPacket) -> String {
+    let Some(message_type) = dhcp.message_type() else {
+        return match dhcp.op {
+            1 => "Boot Request".to_string(),
+            _ => "Boot Reply".to_string(),
+        };
+    };
+    let mut info = format!(
+        "DHCP {} - Transaction ID 0x{:08x}",
+        dhcp_message_type_name(message_type),
+        dhcp.transaction_id
+    );
+    let address = match message_type {
+        2 | 5 => Some(dhcp.your_address).filter(|address| !address.is_unspecified()),
+        1 | 3 => dhcp
+            .address_option(50)
+            .or(Some(dhcp.client_address).filter(|address| !address.is_unspecified())),
+        _ => None,
+    };
+    if let Some(address) = address {
+        info += &format!(" ({})", address);
+    }
+    info
+}
+
+/// Info column of a DHCPv6 message, e.g. `Reply XID: 0x1a2b3c CID: DUID-LL 00:11:22:33:44:55
+/// IAA: 2001:db8::10`, relay messages show the message they carry
+fn dhcpv6_info(dhcpv6: &SerializableDhcpv6Packet) -> String {
+    let mut info = dhcpv6_message_type_name(dhcpv6.message_type);
+    if let Some(relay) = &dhcpv6.relay {
+        info += &format!(" L: {} P: {}", relay.link_address, relay.peer_address);
+        let relayed = dhcpv6.relayed();
+        if relayed.relay.is_some() {
+            return info;
+        }
+        return format!("{}, {}", info, dhcpv6_info(relayed));
+    }
+    info += &format!(" XID: 0x{:06x}", dhcpv6.transaction_id);
+    if let Some(duid) = dhcpv6.client_id() {
+        info += &format!(" CID: {}", format_duid(duid));
+    }
+    for (address, prefix_length, _) in dhcpv6.leases() {
+        match prefix_length {
+            128 => info += &format!(" IAA: {}", address),
+            _ => info += &format!(" IAPD: {}/{}", address, prefix_length),
+        }
+    }
+    if let Some(status) = dhcpv6.error_status() {
+        info += &format!(" {}", dhcpv6_status_name(status));
+    }
+    info
+}
+
+/// Info column of a DNS message, e.g. `Standard query response 0x1a2b A example.com A 192.0.2.1`
+fn dns_info(dns: &SerializableDnsPacket) -> String {
+    let mut info = dns_opcode_name(dns.opcode());
+    if dns.is_response() {
+        info += " response";
+    }
+    info += &format!(" 0x{:04x}", dns.id);
+    if dns.is_response() && dns.rcode() != 0 {
+        info += &format!(" {}", dns_rcode_name(dns.rcode()));
+    }
+    for question in &dns.questions {
+        info += &format!(" {} {}", dns_type_name(question.record_type), question.name);
+    }
+    for answer in &dns.answers {
+        info += &format!(" {} {}", dns_type_name(answer.record_type), answer.data);
+    }
+    if let Some(time) = dns.time {
+        info += &format!(" ({:.1} ms)", time.as_secs_f64() * 1000.0);
+    }
+    if dns.unanswered {
+        info += " [no response]";
+    }
+    info
+}
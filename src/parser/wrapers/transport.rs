@@ -1,10 +1,15 @@
-use pnet::packet::{
-    icmpv6::{Icmpv6Packet, Icmpv6Type, Icmpv6Types},
+use std::{fmt, net::{Ipv4Addr, Ipv6Addr}, time::Duration};
+
+use pnet::{packet::{
+    icmpv6::{Icmpv6Type, Icmpv6Types},
     tcp::TcpPacket,
     udp::UdpPacket,
-    Packet, icmp::{IcmpTypes, IcmpType, IcmpPacket, echo_reply::EchoReplyPacket, echo_request::EchoRequestPacket},
-};
+    Packet, icmp::{IcmpTypes, IcmpType, echo_reply::EchoReplyPacket, echo_request::EchoRequestPacket},
+}, util::MacAddr};
+
+use super::{format_seconds, SerializableIpv4Packet};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableTcpPacket {
     pub source: u16,
     pub destination: u16,
@@ -16,8 +21,24 @@ pub struct SerializableTcpPacket {
     pub window: u16,
     pub checksum: u16,
     pub urgent_ptr: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
     pub options: Vec<u8>,
     pub length: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub payload: Vec<u8>,
+}
+
+impl SerializableTcpPacket {
+    /// Get names of the flags set, e.g. `["SYN", "ACK"]`
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        const NAMES: [&str; 8] = ["FIN", "SYN", "RST", "PSH", "ACK", "URG", "ECE", "CWR"];
+        NAMES
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| self.flags & (1 << bit) != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
 }
 
 impl<'a> From<&TcpPacket<'a>> for SerializableTcpPacket {
@@ -35,10 +56,12 @@ impl<'a> From<&TcpPacket<'a>> for SerializableTcpPacket {
             urgent_ptr: packet.get_urgent_ptr(),
             options: packet.get_options_raw().to_vec(),
             length: packet.payload().len(),
+            payload: packet.payload().to_vec(),
         }
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableUdpPacket {
     pub source: u16,
     pub destination: u16,
@@ -57,17 +80,20 @@ impl<'a> From<&UdpPacket<'a>> for SerializableUdpPacket {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableIcmpv6Packet {
     pub icmpv6_type: String,
     pub icmpv6_code: u8,
     pub checksum: u16,
     pub length: usize,
+    /// Fields of the message type, after the type, code and checksum
+    pub body: SerializableIcmpv6Body,
 }
 
 impl SerializableIcmpv6Packet {
     /// Get ICMPv6 Message Type
     pub fn icmpv6_type_to_string(icmp_type: Icmpv6Type) -> String {
-        return match icmp_type {
+        match icmp_type {
             Icmpv6Types::DestinationUnreachable => {
                 format!("DestinationUnreachable ({})", icmp_type.0)
             }
@@ -81,32 +107,300 @@ impl SerializableIcmpv6Packet {
             Icmpv6Types::NeighborSolicit => format!("NeighborSolicit ({})", icmp_type.0),
             Icmpv6Types::NeighborAdvert => format!("NeighborAdvert ({})", icmp_type.0),
             Icmpv6Types::Redirect => format!("Redirect ({})", icmp_type.0),
+            Icmpv6Type(130) => format!("MulticastListenerQuery ({})", icmp_type.0),
+            Icmpv6Type(131) => format!("MulticastListenerReport ({})", icmp_type.0),
+            Icmpv6Type(132) => format!("MulticastListenerDone ({})", icmp_type.0),
+            Icmpv6Type(143) => format!("MulticastListenerReportV2 ({})", icmp_type.0),
             _ => format!("Unknown ({})", icmp_type.0),
-        };
+        }
+    }
+
+    /// Get the type number
+    pub fn type_number(&self) -> u8 {
+        self.icmpv6_type
+            .rsplit_once('(')
+            .and_then(|(_, number)| number.trim_end_matches(')').parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Get the Neighbor Discovery options, empty for other messages
+    pub fn options(&self) -> &[SerializableNdpOption] {
+        match &self.body {
+            SerializableIcmpv6Body::RouterSolicitation { options }
+            | SerializableIcmpv6Body::RouterAdvertisement { options, .. }
+            | SerializableIcmpv6Body::NeighborSolicitation { options, .. }
+            | SerializableIcmpv6Body::NeighborAdvertisement { options, .. }
+            | SerializableIcmpv6Body::Redirect { options, .. } => options,
+            _ => &[],
+        }
+    }
+
+    /// Get the link-layer address of the sender, or of the target of a neighbor advertisement
+    /// or redirect
+    pub fn link_layer_address(&self) -> Option<MacAddr> {
+        self.options().iter().find_map(|option| match option.data {
+            SerializableNdpOptionData::LinkLayerAddress(address) => Some(address),
+            _ => None,
+        })
     }
 }
 
-impl<'a> From<&Icmpv6Packet<'a>> for SerializableIcmpv6Packet {
-    fn from(packet: &Icmpv6Packet<'a>) -> Self {
-        SerializableIcmpv6Packet {
-            icmpv6_type: SerializableIcmpv6Packet::icmpv6_type_to_string(packet.get_icmpv6_type()),
-            icmpv6_code: packet.get_icmpv6_code().0,
-            checksum: packet.get_checksum(),
-            length: packet.payload().len(),
+/// Fields following the ICMPv6 type, code and checksum, Neighbor Discovery messages of
+/// RFC 4861 and Multicast Listener Discovery ones of RFC 2710 and 3810
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableIcmpv6Body {
+    EchoRequest {
+        identifier: u16,
+        sequence_number: u16,
+        /// Whether no reply to the request was seen in time
+        unanswered: bool,
+    },
+    EchoReply {
+        identifier: u16,
+        sequence_number: u16,
+        /// Id of the packet with the request answered, filled in by `ping::PingTracker`
+        response_to: Option<usize>,
+        /// Round-trip time since the request
+        time: Option<Duration>,
+        /// Whether the request was answered already
+        duplicate: bool,
+    },
+    RouterSolicitation {
+        options: Vec<SerializableNdpOption>,
+    },
+    RouterAdvertisement {
+        hop_limit: u8,
+        /// Managed (0x80), other configuration (0x40), home agent (0x20), router
+        /// preference (0x18) and proxy (0x04) flags
+        flags: u8,
+        /// Seconds the router is a default router for, 0 when it is not one
+        lifetime: u16,
+        /// Milliseconds a neighbor is considered reachable for
+        reachable_time: u32,
+        /// Milliseconds between retransmitted neighbor solicitations
+        retransmit_timer: u32,
+        options: Vec<SerializableNdpOption>,
+    },
+    NeighborSolicitation {
+        target: Ipv6Addr,
+        options: Vec<SerializableNdpOption>,
+    },
+    NeighborAdvertisement {
+        /// Router (0x80), solicited (0x40) and override (0x20) flags
+        flags: u8,
+        target: Ipv6Addr,
+        options: Vec<SerializableNdpOption>,
+    },
+    Redirect {
+        target: Ipv6Addr,
+        destination: Ipv6Addr,
+        options: Vec<SerializableNdpOption>,
+    },
+    MulticastListenerQuery {
+        /// Milliseconds
+        maximum_response_delay: u32,
+        /// Unspecified for general queries
+        address: Ipv6Addr,
+        /// Fields added by MLDv2
+        v2: Option<SerializableMldv2Query>,
+    },
+    MulticastListenerReport {
+        address: Ipv6Addr,
+    },
+    MulticastListenerDone {
+        address: Ipv6Addr,
+    },
+    MulticastListenerReportV2 {
+        records: Vec<SerializableMldRecord>,
+    },
+    Other,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableMldv2Query {
+    /// Whether routers must not update their timers on receipt
+    pub suppress: bool,
+    pub robustness: u8,
+    /// Seconds between general queries
+    pub interval: u32,
+    pub sources: Vec<Ipv6Addr>,
+}
+
+/// Multicast address record of an MLDv2 report
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableMldRecord {
+    pub record_type: u8,
+    pub address: Ipv6Addr,
+    pub sources: Vec<Ipv6Addr>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableNdpOption {
+    pub option_type: u8,
+    pub data: SerializableNdpOptionData,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableNdpOptionData {
+    /// Source or target link-layer address
+    LinkLayerAddress(MacAddr),
+    PrefixInformation {
+        prefix_length: u8,
+        /// On-link (0x80) and autonomous address-configuration (0x40) flags
+        flags: u8,
+        /// Seconds
+        valid_lifetime: u32,
+        /// Seconds
+        preferred_lifetime: u32,
+        prefix: Ipv6Addr,
+    },
+    Mtu(u32),
+    /// Recursive DNS servers, RFC 8106
+    Rdnss {
+        lifetime: u32,
+        servers: Vec<Ipv6Addr>,
+    },
+    /// DNS search list, RFC 8106
+    Dnssl {
+        lifetime: u32,
+        domains: Vec<String>,
+    },
+    Unknown(#[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))] Vec<u8>),
+}
+
+impl fmt::Display for SerializableNdpOptionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializableNdpOptionData::LinkLayerAddress(address) => write!(f, "{}", address),
+            SerializableNdpOptionData::PrefixInformation {
+                prefix_length,
+                flags,
+                valid_lifetime,
+                preferred_lifetime,
+                prefix,
+            } => {
+                write!(f, "{}/{}", prefix, prefix_length)?;
+                if flags & 0x80 != 0 {
+                    write!(f, ", on-link")?;
+                }
+                if flags & 0x40 != 0 {
+                    write!(f, ", autonomous")?;
+                }
+                write!(
+                    f,
+                    ", valid {}, preferred {}",
+                    format_seconds(*valid_lifetime),
+                    format_seconds(*preferred_lifetime)
+                )
+            }
+            SerializableNdpOptionData::Mtu(mtu) => write!(f, "{}", mtu),
+            SerializableNdpOptionData::Rdnss { lifetime, servers } => {
+                let servers: Vec<String> = servers.iter().map(Ipv6Addr::to_string).collect();
+                write!(
+                    f,
+                    "{}, lifetime {}",
+                    servers.join(", "),
+                    format_seconds(*lifetime)
+                )
+            }
+            SerializableNdpOptionData::Dnssl { lifetime, domains } => {
+                write!(
+                    f,
+                    "{}, lifetime {}",
+                    domains.join(", "),
+                    format_seconds(*lifetime)
+                )
+            }
+            SerializableNdpOptionData::Unknown(data) => write!(f, "{} bytes", data.len()),
         }
     }
 }
 
+/// Get a readable name of an ICMPv6 type, as in the IANA registry
+pub fn icmpv6_type_name(icmpv6_type: u8) -> &'static str {
+    match icmpv6_type {
+        1 => "Destination Unreachable",
+        2 => "Packet Too Big",
+        3 => "Time Exceeded",
+        4 => "Parameter Problem",
+        128 => "Echo (ping) request",
+        129 => "Echo (ping) reply",
+        130 => "Multicast Listener Query",
+        131 => "Multicast Listener Report",
+        132 => "Multicast Listener Done",
+        133 => "Router Solicitation",
+        134 => "Router Advertisement",
+        135 => "Neighbor Solicitation",
+        136 => "Neighbor Advertisement",
+        137 => "Redirect",
+        143 => "Multicast Listener Report Message v2",
+        _ => "Unknown",
+    }
+}
+
+/// Get names of the flags set in a router or neighbor advertisement
+pub fn ndp_flag_names(icmpv6_type: u8, flags: u8) -> Vec<&'static str> {
+    let names: &[(u8, &str)] = match icmpv6_type {
+        134 => &[
+            (0x80, "Managed"),
+            (0x40, "Other"),
+            (0x20, "Home Agent"),
+            (0x04, "Proxy"),
+        ],
+        136 => &[(0x80, "Router"), (0x40, "Solicited"), (0x20, "Override")],
+        _ => &[],
+    };
+    names
+        .iter()
+        .filter(|(mask, _)| flags & mask != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+/// Get a readable name of a Neighbor Discovery option type
+pub fn ndp_option_name(option_type: u8) -> &'static str {
+    match option_type {
+        1 => "Source link-layer address",
+        2 => "Target link-layer address",
+        3 => "Prefix information",
+        4 => "Redirected header",
+        5 => "MTU",
+        14 => "Nonce",
+        24 => "Route Information",
+        25 => "Recursive DNS Server",
+        31 => "DNS Search List",
+        _ => "Unknown",
+    }
+}
+
+/// Get a readable name of an MLDv2 multicast address record type, RFC 3810 section 5.2.12
+pub fn mld_record_type_name(record_type: u8) -> &'static str {
+    match record_type {
+        1 => "Mode is include",
+        2 => "Mode is exclude",
+        3 => "Change to include mode",
+        4 => "Change to exclude mode",
+        5 => "Allow new sources",
+        6 => "Block old sources",
+        _ => "Unknown",
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableIcmpPacket {
     pub icmp_type: String,
     pub icmp_code: u8,
     pub checksum: u16,
     pub length: usize,
+    /// Fields of the message type, after the type, code and checksum
+    pub body: SerializableIcmpBody,
 }
 
 impl SerializableIcmpPacket {
     pub fn icmp_type_to_string(icmp_type: IcmpType) -> String {
-        return match icmp_type {
+        match icmp_type {
             IcmpTypes::EchoReply => format!("EchoReply ({})", icmp_type.0),
             IcmpTypes::DestinationUnreachable => {
                 format!("DestinationUnreachable ({})", icmp_type.0)
@@ -126,21 +420,236 @@ impl SerializableIcmpPacket {
             IcmpTypes::AddressMaskReply => format!("AddressMaskReply ({})", icmp_type.0),
             IcmpTypes::Traceroute => format!("Traceroute ({})", icmp_type.0),
             _ => format!("Unknown ({})", icmp_type.0),
-        };
+        }
     }
 }
 
-impl<'a> From<&IcmpPacket<'a>> for SerializableIcmpPacket {
-    fn from(packet: &IcmpPacket<'a>) -> Self {
-        SerializableIcmpPacket {
-            icmp_type: SerializableIcmpPacket::icmp_type_to_string(packet.get_icmp_type()),
-            icmp_code: packet.get_icmp_code().0,
-            checksum: packet.get_checksum(),
-            length: packet.payload().len(),
+impl SerializableIcmpPacket {
+    /// Get the type number
+    pub fn type_number(&self) -> u8 {
+        self.icmp_type
+            .rsplit_once('(')
+            .and_then(|(_, number)| number.trim_end_matches(')').parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Get meaning of the code, e.g. `Port unreachable` or `Fragmentation needed, MTU=1400`
+    pub fn code_description(&self) -> Option<String> {
+        let name = icmp_code_name(self.type_number(), self.icmp_code)?;
+        Some(match self.body {
+            SerializableIcmpBody::DestinationUnreachable {
+                next_hop_mtu: Some(mtu),
+                ..
+            } => format!("{}, MTU={}", name, mtu),
+            _ => name.to_string(),
+        })
+    }
+
+    /// Get the datagram an error message was sent about
+    pub fn original(&self) -> Option<&SerializableIcmpQuote> {
+        self.body.original()
+    }
+
+    /// Get the datagram an error message was sent about, for linking it to its packet
+    pub fn original_mut(&mut self) -> Option<&mut SerializableIcmpQuote> {
+        match &mut self.body {
+            SerializableIcmpBody::DestinationUnreachable { original, .. }
+            | SerializableIcmpBody::SourceQuench { original }
+            | SerializableIcmpBody::Redirect { original, .. }
+            | SerializableIcmpBody::TimeExceeded { original }
+            | SerializableIcmpBody::ParameterProblem { original, .. } => original.as_mut(),
+            _ => None,
         }
     }
 }
 
+/// Fields following the ICMP type, code and checksum, RFC 792, 950 and 1256
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableIcmpBody {
+    DestinationUnreachable {
+        /// MTU of the next hop when fragmentation was needed, RFC 1191
+        next_hop_mtu: Option<u16>,
+        original: Option<SerializableIcmpQuote>,
+    },
+    SourceQuench {
+        original: Option<SerializableIcmpQuote>,
+    },
+    Redirect {
+        gateway: Ipv4Addr,
+        original: Option<SerializableIcmpQuote>,
+    },
+    TimeExceeded {
+        original: Option<SerializableIcmpQuote>,
+    },
+    ParameterProblem {
+        /// Offset of the faulty byte in the original datagram
+        pointer: u8,
+        original: Option<SerializableIcmpQuote>,
+    },
+    RouterAdvertisement {
+        lifetime: u16,
+        entries: Vec<SerializableIcmpRouterEntry>,
+    },
+    /// Timestamp request and reply, times are milliseconds since midnight UT
+    Timestamp {
+        identifier: u16,
+        sequence_number: u16,
+        originate: u32,
+        receive: u32,
+        transmit: u32,
+    },
+    /// Address mask request and reply
+    AddressMask {
+        identifier: u16,
+        sequence_number: u16,
+        mask: Ipv4Addr,
+    },
+    Other,
+}
+
+impl SerializableIcmpBody {
+    /// Get the datagram an error message was sent about
+    pub fn original(&self) -> Option<&SerializableIcmpQuote> {
+        match self {
+            SerializableIcmpBody::DestinationUnreachable { original, .. }
+            | SerializableIcmpBody::SourceQuench { original }
+            | SerializableIcmpBody::Redirect { original, .. }
+            | SerializableIcmpBody::TimeExceeded { original }
+            | SerializableIcmpBody::ParameterProblem { original, .. } => original.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableIcmpRouterEntry {
+    pub address: Ipv4Addr,
+    /// Higher is preferred, `0x80000000` means the address must not be used as default router
+    pub preference: i32,
+}
+
+/// Start of the datagram an ICMP error was sent about, its IP header and at least 8 bytes
+/// of what followed
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableIcmpQuote {
+    pub ipv4: SerializableIpv4Packet,
+    pub transport: Option<SerializableIcmpQuotedTransport>,
+    /// Packet carrying the original datagram, filled in by `icmp::IcmpTracker`
+    pub original_in: Option<usize>,
+}
+
+impl SerializableIcmpQuote {
+    /// Get source and destination ports of quoted TCP and UDP headers
+    pub fn ports(&self) -> Option<(u16, u16)> {
+        match self.transport.as_ref()? {
+            SerializableIcmpQuotedTransport::Tcp {
+                source,
+                destination,
+                ..
+            } => Some((*source, *destination)),
+            SerializableIcmpQuotedTransport::Udp(udp) => Some((udp.source, udp.destination)),
+            SerializableIcmpQuotedTransport::Echo { .. } => None,
+        }
+    }
+}
+
+/// First 8 bytes of the quoted transport header, all of a UDP one
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SerializableIcmpQuotedTransport {
+    Tcp {
+        source: u16,
+        destination: u16,
+        sequence: u32,
+    },
+    Udp(SerializableUdpPacket),
+    /// ICMP echo request or reply
+    Echo {
+        icmp_type: u8,
+        icmp_code: u8,
+        identifier: u16,
+        sequence_number: u16,
+    },
+}
+
+/// Get a readable name of an ICMP type, as in the IANA registry
+pub fn icmp_type_name(icmp_type: u8) -> &'static str {
+    match icmp_type {
+        0 => "Echo (ping) reply",
+        3 => "Destination unreachable",
+        4 => "Source quench",
+        5 => "Redirect",
+        8 => "Echo (ping) request",
+        9 => "Router advertisement",
+        10 => "Router solicitation",
+        11 => "Time-to-live exceeded",
+        12 => "Parameter problem",
+        13 => "Timestamp request",
+        14 => "Timestamp reply",
+        15 => "Information request",
+        16 => "Information reply",
+        17 => "Address mask request",
+        18 => "Address mask reply",
+        30 => "Traceroute",
+        _ => "Unknown",
+    }
+}
+
+/// Get a readable name of an ICMP code, `None` for types without named codes
+pub fn icmp_code_name(icmp_type: u8, code: u8) -> Option<&'static str> {
+    let name = match (icmp_type, code) {
+        (3, 0) => "Network unreachable",
+        (3, 1) => "Host unreachable",
+        (3, 2) => "Protocol unreachable",
+        (3, 3) => "Port unreachable",
+        (3, 4) => "Fragmentation needed",
+        (3, 5) => "Source route failed",
+        (3, 6) => "Destination network unknown",
+        (3, 7) => "Destination host unknown",
+        (3, 8) => "Source host isolated",
+        (3, 9) => "Network administratively prohibited",
+        (3, 10) => "Host administratively prohibited",
+        (3, 11) => "Network unreachable for TOS",
+        (3, 12) => "Host unreachable for TOS",
+        (3, 13) => "Communication administratively filtered",
+        (3, 14) => "Host precedence violation",
+        (3, 15) => "Precedence cutoff in effect",
+        (5, 0) => "Redirect for network",
+        (5, 1) => "Redirect for host",
+        (5, 2) => "Redirect for TOS and network",
+        (5, 3) => "Redirect for TOS and host",
+        (9, 0) => "Normal router advertisement",
+        (9, 16) => "Does not route common traffic",
+        (11, 0) => "Time to live exceeded in transit",
+        (11, 1) => "Fragment reassembly time exceeded",
+        (12, 0) => "Pointer indicates the error",
+        (12, 1) => "Missing a required option",
+        (12, 2) => "Bad length",
+        (3 | 5 | 9 | 11 | 12, _) => "Unknown code",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Format an ICMP timestamp, milliseconds since midnight UT, e.g. `12:30:05.250 UTC`
+pub fn format_icmp_timestamp(timestamp: u32) -> String {
+    // The high bit flags a non-standard time, RFC 792
+    if timestamp & 0x8000_0000 != 0 || timestamp >= 86_400_000 {
+        return format!("{} (non-standard)", timestamp & 0x7fff_ffff);
+    }
+    let seconds = timestamp / 1000;
+    format!(
+        "{} ({:02}:{:02}:{:02}.{:03} UTC)",
+        timestamp,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        timestamp % 1000
+    )
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableEchoReplyPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
@@ -148,6 +657,12 @@ pub struct SerializableEchoReplyPacket {
     pub identifier: u16,
     pub sequence_number: u16,
     pub length: usize,
+    /// Id of the packet with the request answered, filled in by `ping::PingTracker`
+    pub response_to: Option<usize>,
+    /// Round-trip time since the request
+    pub time: Option<Duration>,
+    /// Whether the request was answered already
+    pub duplicate: bool,
 }
 
 impl<'a> From<&EchoReplyPacket<'a>> for SerializableEchoReplyPacket {
@@ -156,14 +671,18 @@ impl<'a> From<&EchoReplyPacket<'a>> for SerializableEchoReplyPacket {
             icmp_type: packet.get_icmp_type().0,
             icmp_code: packet.get_icmp_code().0,
             checksum: packet.get_checksum(),
-            identifier: packet.get_checksum(),
+            identifier: packet.get_identifier(),
             sequence_number: packet.get_sequence_number(),
             length: packet.payload().len(),
+            response_to: None,
+            time: None,
+            duplicate: false,
         }
     }
 }
 
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerializableEchoRequestPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
@@ -171,6 +690,8 @@ pub struct SerializableEchoRequestPacket {
     pub identifier: u16,
     pub sequence_number: u16,
     pub length: usize,
+    /// Whether no reply to the request was seen in time
+    pub unanswered: bool,
 }
 
 impl<'a> From<&EchoRequestPacket<'a>> for SerializableEchoRequestPacket {
@@ -182,6 +703,7 @@ impl<'a> From<&EchoRequestPacket<'a>> for SerializableEchoRequestPacket {
             identifier: packet.get_identifier(),
             sequence_number: packet.get_sequence_number(),
             length: packet.payload().len(),
+            unanswered: false,
         }
     }
 }
\ No newline at end of file
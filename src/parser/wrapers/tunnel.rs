@@ -0,0 +1,46 @@
+/// Generic Routing Encapsulation header, RFC 2784 with the RFC 2890 key and sequence number
+/// and the RFC 2637 (PPTP) version 1 acknowledgement
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableGrePacket {
+    pub version: u8,
+    /// EtherType of the payload
+    pub protocol_type: u16,
+    pub checksum: Option<u16>,
+    pub key: Option<u32>,
+    pub sequence: Option<u32>,
+    pub acknowledgement: Option<u32>,
+}
+
+/// Virtual eXtensible LAN header, RFC 7348
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableVxlanPacket {
+    pub flags: u8,
+    /// VXLAN network identifier
+    pub vni: u32,
+}
+
+/// Generic Network Virtualization Encapsulation header, RFC 8926
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableGenevePacket {
+    pub version: u8,
+    /// Whether the packet carries control messages instead of tenant traffic
+    pub oam: bool,
+    /// Whether some options must be understood by the receiver
+    pub critical: bool,
+    /// EtherType of the payload
+    pub protocol_type: u16,
+    /// Virtual network identifier
+    pub vni: u32,
+    pub options: Vec<SerializableGeneveOption>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableGeneveOption {
+    pub class: u16,
+    pub option_type: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::parser::wrapers::hex"))]
+    pub data: Vec<u8>,
+}
+
+/// EtherType 0x6558 carries whole Ethernet frames, as in NVGRE and Geneve
+pub const TRANSPARENT_ETHERNET_BRIDGING: u16 = 0x6558;
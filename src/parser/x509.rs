@@ -0,0 +1,345 @@
+//! Just enough DER to show who a certificate was issued to, by whom, and for how long
+
+use super::wrapers::SerializableTlsCertificate;
+
+/// Subject alternative name extension, 2.5.29.17
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const INTEGER: u8 = 0x02;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const OCTET_STRING: u8 = 0x04;
+const BOOLEAN: u8 = 0x01;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const BMP_STRING: u8 = 0x1e;
+/// `[0]` holding the version of a TBSCertificate
+const VERSION: u8 = 0xa0;
+/// `[3]` holding the extensions of a TBSCertificate
+const EXTENSIONS: u8 = 0xa3;
+/// `[2]` dNSName of a GeneralName
+const DNS_NAME: u8 = 0x82;
+
+/// Reads DER elements one after another, RFC 5280 section 4.1
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element, giving its tag and contents
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.data.split_first()?;
+        // High tag numbers never show up in certificates
+        if tag & 0x1f == 0x1f {
+            return None;
+        }
+        let (&first, rest) = rest.split_first()?;
+        let (length, rest) = match first {
+            0x00..=0x7f => (first as usize, rest),
+            0x81..=0x84 => {
+                let count = (first & 0x7f) as usize;
+                let bytes = rest.get(..count)?;
+                let length = bytes
+                    .iter()
+                    .fold(0, |length, &byte| length << 8 | byte as usize);
+                (length, &rest[count..])
+            }
+            _ => return None,
+        };
+        let contents = rest.get(..length)?;
+        self.data = &rest[length..];
+        Some((tag, contents))
+    }
+
+    /// Read the next element, which must have tag `tag`
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.next()? {
+            (found, contents) if found == tag => Some(contents),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a DER encoded certificate, `None` if it does not look like one
+pub fn parse_certificate(der: &[u8]) -> Option<SerializableTlsCertificate> {
+    let certificate = Der { data: der }.expect(SEQUENCE)?;
+    let mut tbs = Der {
+        data: Der { data: certificate }.expect(SEQUENCE)?,
+    };
+    if tbs.peek() == Some(VERSION) {
+        tbs.next()?;
+    }
+    let serial = tbs.expect(INTEGER)?;
+    // Signature algorithm
+    tbs.expect(SEQUENCE)?;
+    let issuer = tbs.expect(SEQUENCE)?;
+    let mut validity = Der {
+        data: tbs.expect(SEQUENCE)?,
+    };
+    let subject = tbs.expect(SEQUENCE)?;
+    // Subject public key info
+    tbs.expect(SEQUENCE)?;
+
+    let mut alt_names = Vec::new();
+    while let Some((tag, contents)) = tbs.next() {
+        if tag == EXTENSIONS {
+            alt_names = subject_alt_names(contents)?;
+        }
+    }
+    Some(SerializableTlsCertificate {
+        subject: name(subject)?,
+        issuer: name(issuer)?,
+        serial: serial.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        not_before: time(validity.next()?)?,
+        not_after: time(validity.next()?)?,
+        alt_names,
+    })
+}
+
+/// Get the DNS names of the subject alternative name extension, if there is one
+fn subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let mut extensions = Der {
+        data: Der { data: extensions }.expect(SEQUENCE)?,
+    };
+    while let Some(extension) = extensions.next() {
+        let mut extension = Der { data: extension.1 };
+        if extension.expect(OBJECT_IDENTIFIER)? != SUBJECT_ALT_NAME {
+            continue;
+        }
+        if extension.peek() == Some(BOOLEAN) {
+            extension.next()?;
+        }
+        let value = extension.expect(OCTET_STRING)?;
+        let mut names = Der {
+            data: Der { data: value }.expect(SEQUENCE)?,
+        };
+        let mut dns_names = Vec::new();
+        while let Some((tag, contents)) = names.next() {
+            if tag == DNS_NAME {
+                dns_names.push(String::from_utf8_lossy(contents).into_owned());
+            }
+        }
+        return Some(dns_names);
+    }
+    Some(Vec::new())
+}
+
+/// Format a distinguished name as `C=US, O=Example, CN=example.com`, in encoding order
+fn name(name: &[u8]) -> Option<String> {
+    let mut sets = Der { data: name };
+    let mut attributes = Vec::new();
+    while sets.peek().is_some() {
+        let mut set = Der {
+            data: sets.expect(SET)?,
+        };
+        while set.peek().is_some() {
+            let mut attribute = Der {
+                data: set.expect(SEQUENCE)?,
+            };
+            let key = attribute_name(attribute.expect(OBJECT_IDENTIFIER)?);
+            let (tag, value) = attribute.next()?;
+            let value = match tag {
+                BMP_STRING => {
+                    let units: Vec<u16> = value
+                        .chunks_exact(2)
+                        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                        .collect();
+                    String::from_utf16_lossy(&units)
+                }
+                _ => String::from_utf8_lossy(value).into_owned(),
+            };
+            attributes.push(format!("{}={}", key, value));
+        }
+    }
+    Some(attributes.join(", "))
+}
+
+/// Short name of an attribute type, RFC 4514 section 3, or its dotted object identifier
+fn attribute_name(oid: &[u8]) -> String {
+    let name = match oid {
+        [0x55, 0x04, 0x03] => "CN",
+        [0x55, 0x04, 0x05] => "serialNumber",
+        [0x55, 0x04, 0x06] => "C",
+        [0x55, 0x04, 0x07] => "L",
+        [0x55, 0x04, 0x08] => "ST",
+        [0x55, 0x04, 0x09] => "STREET",
+        [0x55, 0x04, 0x0a] => "O",
+        [0x55, 0x04, 0x0b] => "OU",
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC",
+        _ => return dotted(oid),
+    };
+    name.to_string()
+}
+
+/// Format an object identifier as `1.2.840.113549`
+fn dotted(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut arc: u64 = 0;
+    for &byte in oid {
+        arc = arc << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    arcs.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Format a UTCTime or GeneralizedTime as `2024-01-31 12:00:00 UTC`
+fn time((tag, value): (u8, &[u8])) -> Option<String> {
+    let value = std::str::from_utf8(value).ok()?;
+    let digits = value.strip_suffix('Z').unwrap_or(value);
+    let full = match (tag, digits.get(..2)?.parse::<u32>().ok()?) {
+        (UTC_TIME, year) if year >= 50 => format!("19{}", digits),
+        (UTC_TIME, _) => format!("20{}", digits),
+        (GENERALIZED_TIME, _) => digits.to_string(),
+        _ => return None,
+    };
+    if full.len() < 14 || !full.bytes().take(14).all(|byte| byte.is_ascii_digit()) {
+        return Some(value.to_string());
+    }
+    Some(format!(
+        "{}-{}-{} {}:{}:{} UTC",
+        &full[..4],
+        &full[4..6],
+        &full[6..8],
+        &full[8..10],
+        &full[10..12],
+        &full[12..14]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a DER element, with a long form length when needed
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            length @ 0..=0x7f => element.push(length as u8),
+            length @ 0x80..=0xff => element.extend([0x81, length as u8]),
+            length => {
+                element.push(0x82);
+                element.extend((length as u16).to_be_bytes());
+            }
+        }
+        element.extend(contents);
+        element
+    }
+
+    /// A name of `(attribute type, string tag, value)`, each in a set of its own
+    fn distinguished_name(attributes: &[(&[u8], u8, &[u8])]) -> Vec<u8> {
+        let sets: Vec<u8> = attributes
+            .iter()
+            .flat_map(|&(oid, tag, value)| {
+                let attribute = [der(OBJECT_IDENTIFIER, oid), der(tag, value)].concat();
+                der(SET, &der(SEQUENCE, &attribute))
+            })
+            .collect();
+        der(SEQUENCE, &sets)
+    }
+
+    fn encoded(not_before: &[u8]) -> Vec<u8> {
+        let algorithm = der(
+            SEQUENCE,
+            &der(
+                OBJECT_IDENTIFIER,
+                &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02],
+            ),
+        );
+        let validity = [
+            der(UTC_TIME, not_before),
+            der(GENERALIZED_TIME, b"20490101000000Z"),
+        ];
+        let names = [
+            der(DNS_NAME, b"example.com"),
+            // An email address, not a DNS name
+            der(0x81, b"admin@example.com"),
+            der(DNS_NAME, b"www.example.com"),
+        ];
+        let alt_names = [
+            der(OBJECT_IDENTIFIER, SUBJECT_ALT_NAME),
+            der(BOOLEAN, &[0]),
+            der(OCTET_STRING, &der(SEQUENCE, &names.concat())),
+        ];
+        let extensions = der(SEQUENCE, &der(SEQUENCE, &alt_names.concat()));
+        let tbs = [
+            der(VERSION, &der(INTEGER, &[2])),
+            der(INTEGER, &[0x01, 0xab]),
+            algorithm.clone(),
+            distinguished_name(&[
+                (&[0x55, 0x04, 0x06], 0x13, b"US"),
+                (&[0x55, 0x04, 0x03], 0x0c, b"Example CA"),
+            ]),
+            der(SEQUENCE, &validity.concat()),
+            distinguished_name(&[
+                (&[0x55, 0x04, 0x03], 0x0c, b"example.com"),
+                // "Ex" as UTF-16
+                (&[0x55, 0x04, 0x0a], BMP_STRING, &[0, b'E', 0, b'x']),
+                (&[0x55, 0x04, 0x63], 0x0c, b"other"),
+            ]),
+            der(SEQUENCE, &[algorithm.clone(), der(0x03, &[0; 33])].concat()),
+            der(EXTENSIONS, &extensions),
+        ];
+        let signature = der(0x03, &[0; 65]);
+        der(
+            SEQUENCE,
+            &[der(SEQUENCE, &tbs.concat()), algorithm, signature].concat(),
+        )
+    }
+
+    #[test]
+    fn parses_certificates() {
+        let certificate = parse_certificate(&encoded(b"240131120000Z")).unwrap();
+        assert_eq!(certificate.subject, "CN=example.com, O=Ex, 2.5.4.99=other");
+        assert_eq!(certificate.issuer, "C=US, CN=Example CA");
+        assert_eq!(certificate.serial, "01ab");
+        assert_eq!(certificate.not_before, "2024-01-31 12:00:00 UTC");
+        assert_eq!(certificate.not_after, "2049-01-01 00:00:00 UTC");
+        assert_eq!(certificate.alt_names, ["example.com", "www.example.com"]);
+
+        // Two-digit years from 50 on are in the 20th century
+        let certificate = parse_certificate(&encoded(b"500101000000Z")).unwrap();
+        assert_eq!(certificate.not_before, "1950-01-01 00:00:00 UTC");
+        // Times that are not digits are shown as they are
+        let certificate = parse_certificate(&encoded(b"24013112ZZZZZ")).unwrap();
+        assert_eq!(certificate.not_before, "24013112ZZZZZ");
+    }
+
+    #[test]
+    fn rejects_truncated_certificates() {
+        let der = encoded(b"240131120000Z");
+        for length in 0..der.len() {
+            assert!(parse_certificate(&der[..length]).is_none(), "{}", length);
+        }
+        // A length longer than four bytes
+        assert!(parse_certificate(&[SEQUENCE, 0x85, 0, 0, 0, 0, 1, 0]).is_none());
+    }
+
+    #[test]
+    fn formats_object_identifiers() {
+        assert_eq!(
+            dotted(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]),
+            "1.2.840.113549"
+        );
+        assert_eq!(dotted(&[0x55, 0x1d, 0x11]), "2.5.29.17");
+        // The first two arcs are encoded together, the second one is unbounded under arc 2
+        assert_eq!(dotted(&[0x88, 0x37, 0x03]), "2.999.3");
+    }
+}
@@ -0,0 +1,228 @@
+//! Startup screen for choosing the interface to capture from
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyCode};
+use netui::parser::Parser;
+use pnet::datalink::NetworkInterface;
+use tui::{
+    backend::Backend,
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame, Terminal,
+};
+
+/// How often the packets/s preview is refreshed
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts the frames read from an interface on a background thread, without dissecting them
+struct FrameCounter {
+    frames: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FrameCounter {
+    fn spawn(mut parser: Parser) -> Self {
+        let frames = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (frames, stop) = (Arc::clone(&frames), Arc::clone(&stop));
+            // Live reads time out on idle links, so `stop` is checked regularly
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Ok(Some(_)) = parser.next() {
+                        frames.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            })
+        };
+
+        FrameCounter {
+            frames,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for FrameCounter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// An interface with a short capture running to preview its traffic
+struct Candidate {
+    interface: NetworkInterface,
+    /// Capture counting the interface's frames, or why it could not be opened
+    preview: Result<FrameCounter, String>,
+    last_count: u64,
+    rate: Option<f64>,
+}
+
+impl Candidate {
+    fn new(interface: NetworkInterface) -> Self {
+        let preview = Parser::new(interface.name.clone())
+            .map(FrameCounter::spawn)
+            .map_err(|err| err.to_string());
+
+        Candidate {
+            interface,
+            preview,
+            last_count: 0,
+            rate: None,
+        }
+    }
+
+    fn update_rate(&mut self, elapsed: Duration) {
+        if let Ok(preview) = &self.preview {
+            let count = preview.frames();
+            self.rate = Some((count - self.last_count) as f64 / elapsed.as_secs_f64());
+            self.last_count = count;
+        }
+    }
+}
+
+/// Get the interface's flags as `UP`, `LOOPBACK` and `RUNNING` words
+pub fn flags(interface: &NetworkInterface) -> String {
+    let mut flags = Vec::new();
+    if interface.is_up() {
+        flags.push("UP");
+    }
+    if interface.is_loopback() {
+        flags.push("LOOPBACK");
+    }
+    #[cfg(unix)]
+    if interface.is_running() {
+        flags.push("RUNNING");
+    }
+    flags.join(",")
+}
+
+/// Get the interface's MAC address, `-` when it has none
+pub fn mac(interface: &NetworkInterface) -> String {
+    interface
+        .mac
+        .map_or_else(|| "-".to_string(), |mac| mac.to_string())
+}
+
+/// Get the interface's addresses with their prefix lengths
+pub fn addresses(interface: &NetworkInterface) -> String {
+    interface
+        .ips
+        .iter()
+        .map(|ip| ip.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Show every interface until one is chosen with Enter, `None` when the user quits
+pub fn pick_interface<B: Backend>(
+    terminal: &mut Terminal<B>,
+) -> io::Result<Option<NetworkInterface>> {
+    let mut candidates: Vec<Candidate> = Parser::interfaces()
+        .into_iter()
+        .map(Candidate::new)
+        .collect();
+    let mut state = TableState::default();
+    if !candidates.is_empty() {
+        state.select(Some(0));
+    }
+
+    let mut last_rate = Instant::now();
+    loop {
+        terminal.draw(|f| ui(f, &candidates, &mut state))?;
+
+        if crossterm::event::poll(Duration::from_millis(250))? {
+            if let Event::Key(key) = event::read()? {
+                let selected = state.selected().unwrap_or(0);
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    KeyCode::Down if !candidates.is_empty() => {
+                        state.select(Some((selected + 1) % candidates.len()))
+                    }
+                    KeyCode::Up if !candidates.is_empty() => {
+                        state.select(Some((selected + candidates.len() - 1) % candidates.len()))
+                    }
+                    KeyCode::Enter if !candidates.is_empty() => {
+                        // The previews are stopped before the chosen interface is opened again
+                        return Ok(Some(candidates.swap_remove(selected).interface));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if last_rate.elapsed() >= RATE_INTERVAL {
+            let elapsed = last_rate.elapsed();
+            for candidate in candidates.iter_mut() {
+                candidate.update_rate(elapsed);
+            }
+            last_rate = Instant::now();
+        }
+    }
+}
+
+fn ui<B: Backend>(f: &mut Frame<B>, candidates: &[Candidate], state: &mut TableState) {
+    let header = Row::new(vec!["Interface", "MAC", "Addresses", "Flags", "Packets/s"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = candidates
+        .iter()
+        .map(|candidate| {
+            let interface = &candidate.interface;
+            let rate = match (&candidate.preview, candidate.rate) {
+                (Err(err), _) => Cell::from(err.as_str()).style(Style::default().fg(Color::Red)),
+                (Ok(_), None) => Cell::from("..."),
+                (Ok(_), Some(rate)) => Cell::from(format!("{:.0}", rate)),
+            };
+            Row::new(vec![
+                Cell::from(interface.name.as_str()),
+                Cell::from(mac(interface)),
+                Cell::from(addresses(interface)),
+                Cell::from(flags(interface)),
+                rate,
+            ])
+        })
+        .collect();
+
+    let table = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Choose an interface (Enter to capture, q to quit)"),
+        )
+        .widths(&[
+            Constraint::Length(16),
+            Constraint::Length(18),
+            Constraint::Percentage(40),
+            Constraint::Length(22),
+            Constraint::Min(10),
+        ])
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(table, f.size(), state);
+}
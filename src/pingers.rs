@@ -0,0 +1,166 @@
+//! Pinger view, one row per source pinging a target with ICMP or ICMPv6 echo requests
+
+use std::{net::IpAddr, time::Duration};
+
+use netui::parser::ping::EchoSession;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+/// Source, target and identifier of a session
+type SessionKey = (IpAddr, IpAddr, u16);
+
+#[derive(Default)]
+pub struct PingerView {
+    state: TableState,
+    /// Session selected, followed by its key as sessions before it are pruned
+    selected: Option<SessionKey>,
+}
+
+impl PingerView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get index of the selected session in `sessions`
+    pub fn selected(&self, sessions: &[EchoSession]) -> Option<usize> {
+        let selected = self.selected?;
+        sessions.iter().position(|session| key(session) == selected)
+    }
+
+    pub fn next(&mut self, sessions: &[EchoSession]) {
+        self.follow(sessions);
+        if !sessions.is_empty() {
+            let next = self
+                .state
+                .selected()
+                .map_or(0, |i| (i + 1).min(sessions.len() - 1));
+            self.select(Some(next), sessions);
+        }
+    }
+
+    pub fn previous(&mut self, sessions: &[EchoSession]) {
+        self.follow(sessions);
+        if !sessions.is_empty() {
+            let previous = self.state.selected().map_or(0, |i| i.saturating_sub(1));
+            self.select(Some(previous), sessions);
+        }
+    }
+
+    /// Find the selected session's row again after `PingTracker::prune`, or select the row
+    /// taking its place if it was pruned itself
+    fn follow(&mut self, sessions: &[EchoSession]) {
+        if let Some(row) = self.state.selected() {
+            let row = self.selected(sessions).or_else(|| {
+                let last = sessions.len().checked_sub(1)?;
+                Some(row.min(last))
+            });
+            self.select(row, sessions);
+        }
+    }
+
+    fn select(&mut self, row: Option<usize>, sessions: &[EchoSession]) {
+        self.state.select(row);
+        self.selected = row.map(|row| key(&sessions[row]));
+    }
+
+    /// Sessions with a packet less than `ping::ECHO_TIMEOUT` before `now` are active
+    pub fn render<B: Backend>(
+        &mut self,
+        f: &mut Frame<B>,
+        area: Rect,
+        sessions: &[EchoSession],
+        first_timestamp: Duration,
+        now: Duration,
+    ) {
+        self.follow(sessions);
+        let rows: Vec<Row> = sessions
+            .iter()
+            .map(|session| {
+                let started = session.started.saturating_sub(first_timestamp);
+                let loss = session
+                    .loss()
+                    .map(|loss| format!("{:.1}%", loss))
+                    .unwrap_or_default();
+                Row::new(vec![
+                    Cell::from(session.source.to_string()),
+                    Cell::from(session.target.to_string()),
+                    Cell::from(format!("0x{:04x}", session.identifier)),
+                    Cell::from(session.sent.to_string()),
+                    Cell::from(session.received.to_string()),
+                    Cell::from(session.lost.to_string()),
+                    Cell::from(loss),
+                    Cell::from(session.duplicates.to_string()),
+                    Cell::from(milliseconds(session.min)),
+                    Cell::from(milliseconds(session.average())),
+                    Cell::from(milliseconds(session.max)),
+                    Cell::from(milliseconds(session.jitter())),
+                    Cell::from(format!("{:.6}", started.as_secs_f64())),
+                    Cell::from(format!("#{}", session.last_packet)),
+                ])
+                .style(style(session, now))
+            })
+            .collect();
+
+        let header = Row::new(vec![
+            "Source", "Target", "Id", "Sent", "Recv", "Lost", "Loss", "Dup", "Min", "Avg", "Max",
+            "Jitter", "Started", "Last",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let active = sessions
+            .iter()
+            .filter(|session| session.is_active(now))
+            .count();
+        let title = format!(
+            "Pingers [{} sessions, {} active] (Enter go to packet, Esc close)",
+            sessions.len(),
+            active
+        );
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&[
+                Constraint::Length(26),
+                Constraint::Length(26),
+                Constraint::Length(7),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Length(5),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Percentage(100),
+            ])
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+fn key(session: &EchoSession) -> SessionKey {
+    (session.source, session.target, session.identifier)
+}
+
+/// Sessions losing requests in red, other active ones in green
+fn style(session: &EchoSession, now: Duration) -> Style {
+    if session.lost > 0 {
+        Style::default().fg(Color::Red)
+    } else if session.is_active(now) {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    }
+}
+
+fn milliseconds(time: Option<Duration>) -> String {
+    time.map(|time| format!("{:.1} ms", time.as_secs_f64() * 1000.0))
+        .unwrap_or_default()
+}
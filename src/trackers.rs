@@ -0,0 +1,68 @@
+//! Following conversations across packets, the same way with or without the interface
+
+use std::time::Duration;
+
+use netui::parser::{
+    dhcp::DhcpTracker, dns::DnsTracker, http::HttpTracker, icmp::IcmpTracker, ping::PingTracker,
+    reassembly::TcpReassembler, tls::TlsTracker, wrapers::ParsedPacket,
+};
+
+/// Every tracker, fed with all packets whatever the display filter
+#[derive(Default)]
+pub struct Trackers {
+    /// Every TCP connection
+    pub streams: TcpReassembler,
+    /// HTTP messages decoded from `streams`
+    pub http: HttpTracker,
+    /// TLS records decoded from `streams`
+    pub tls: TlsTracker,
+    /// DNS queries waiting for their response
+    pub dns: DnsTracker,
+    /// Recent datagrams, for linking the ICMP errors they trigger
+    pub icmp: IcmpTracker,
+    /// Echo sessions per pinger, with their round-trip times
+    pub ping: PingTracker,
+    /// DHCP and DHCPv6 exchanges per client
+    pub dhcp: DhcpTracker,
+}
+
+/// Packets whose request went unanswered, by id
+pub struct Unanswered {
+    pub dns: Vec<usize>,
+    pub ping: Vec<usize>,
+}
+
+impl Trackers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a packet, in capture order, adding what the trackers learned from it
+    pub fn process(&mut self, packet: &mut ParsedPacket) {
+        if let Some(segment) = self.streams.process(packet) {
+            self.http.process(packet, &segment);
+            self.tls.process(packet, &segment);
+        }
+        self.dns.process(packet);
+        self.icmp.process(packet);
+        self.ping.process(packet);
+        self.dhcp.process(packet);
+    }
+
+    /// Give up on the DNS queries and echo requests left unanswered at `now`, returning them
+    pub fn expire(&mut self, now: Duration) -> Unanswered {
+        Unanswered {
+            dns: self.dns.expire(now),
+            ping: self.ping.expire(now),
+        }
+    }
+
+    /// Forget echo sessions and TCP streams gone idle at `now`, in capture time
+    pub fn prune(&mut self, now: Duration) {
+        self.ping.prune(now);
+        for stream in self.streams.prune(now) {
+            self.http.forget(stream);
+            self.tls.forget(stream);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Collapsible protocol tree of the selected packet
+
+use std::{collections::HashSet, ops::Range};
+
+use netui::parser::wrapers::LayerDetails;
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+/// A line of the tree, either a layer header or one of its fields
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Line {
+    layer: usize,
+    field: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct DetailTree {
+    /// Protocols whose layer is expanded, kept while moving between packets
+    expanded: HashSet<&'static str>,
+    /// Selected line
+    selected: usize,
+}
+
+impl DetailTree {
+    fn lines(&self, layers: &[LayerDetails]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (i, layer) in layers.iter().enumerate() {
+            lines.push(Line {
+                layer: i,
+                field: None,
+            });
+            if self.expanded.contains(layer.protocol) {
+                lines.extend((0..layer.fields.len()).map(|field| Line {
+                    layer: i,
+                    field: Some(field),
+                }));
+            }
+        }
+        lines
+    }
+
+    fn selected_line(&self, layers: &[LayerDetails]) -> Option<Line> {
+        let lines = self.lines(layers);
+        lines
+            .get(self.selected.min(lines.len().saturating_sub(1)))
+            .copied()
+    }
+
+    /// Get the bytes of the selected field, or of the whole layer on a header line
+    pub fn selected_range(&self, layers: &[LayerDetails]) -> Option<Range<usize>> {
+        let line = self.selected_line(layers)?;
+        let layer = &layers[line.layer];
+        match line.field {
+            Some(field) => layer.fields[field].range.clone(),
+            None => layer.range.clone(),
+        }
+    }
+
+    pub fn next(&mut self, layers: &[LayerDetails]) {
+        let count = self.lines(layers).len();
+        if count > 0 {
+            self.selected = (self.selected + 1).min(count - 1);
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Expand the layer of the selected line
+    pub fn expand(&mut self, layers: &[LayerDetails]) {
+        if let Some(line) = self.selected_line(layers) {
+            self.expanded.insert(layers[line.layer].protocol);
+        }
+    }
+
+    /// Collapse the selected layer, or go from a field up to its layer
+    pub fn collapse(&mut self, layers: &[LayerDetails]) {
+        if let Some(line) = self.selected_line(layers) {
+            if line.field.is_some() {
+                self.select_layer(layers, line.layer);
+            } else {
+                self.expanded.remove(layers[line.layer].protocol);
+            }
+        }
+    }
+
+    /// Expand a collapsed layer or collapse an expanded one
+    pub fn toggle(&mut self, layers: &[LayerDetails]) {
+        if let Some(line) = self.selected_line(layers) {
+            let protocol = layers[line.layer].protocol;
+            if !self.expanded.remove(protocol) {
+                self.expanded.insert(protocol);
+            }
+            self.select_layer(layers, line.layer);
+        }
+    }
+
+    fn select_layer(&mut self, layers: &[LayerDetails], layer: usize) {
+        let lines = self.lines(layers);
+        if let Some(i) = lines
+            .iter()
+            .position(|line| line.layer == layer && line.field.is_none())
+        {
+            self.selected = i;
+        }
+    }
+
+    pub fn render<B: Backend>(
+        &self,
+        f: &mut Frame<B>,
+        area: Rect,
+        layers: &[LayerDetails],
+        focused: bool,
+    ) {
+        let items: Vec<ListItem> = self
+            .lines(layers)
+            .into_iter()
+            .map(|line| {
+                let layer = &layers[line.layer];
+                let text = match line.field {
+                    None => {
+                        let marker = if self.expanded.contains(layer.protocol) {
+                            "\u{25be}"
+                        } else {
+                            "\u{25b8}"
+                        };
+                        Spans::from(vec![
+                            Span::raw(format!("{} ", marker)),
+                            Span::styled(
+                                layer.title.clone(),
+                                Style::default().add_modifier(Modifier::BOLD),
+                            ),
+                        ])
+                    }
+                    Some(field) => {
+                        let field = &layer.fields[field];
+                        Spans::from(vec![
+                            Span::raw("    "),
+                            Span::styled(
+                                format!("{}: ", field.name),
+                                Style::default().fg(Color::Cyan),
+                            ),
+                            Span::raw(field.value.clone()),
+                        ])
+                    }
+                };
+                ListItem::new(text)
+            })
+            .collect();
+
+        let border = if focused {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title("Details (Tab to focus, \u{2190}/\u{2192} collapse/expand)"),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        let mut state = ListState::default();
+        if focused && !layers.is_empty() {
+            state.select(Some(self.selected.min(self.lines(layers).len() - 1)));
+        }
+        f.render_stateful_widget(list, area, &mut state);
+    }
+}